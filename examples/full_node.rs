@@ -0,0 +1,103 @@
+// Embeds a complete node in this process and walks one holder through issuance, conversion and redemption over
+// its HTTP API, the way a client integration would. Run with `cargo run --example full_node`; state goes to a
+// temporary directory, and the node is stopped and its state saved at the end.
+use ed25519_dalek::{Signer, SigningKey};
+use pi_supernode::api::redemption::RedemptionRequest;
+use pi_supernode::config::NodeConfig;
+use pi_supernode::{logging, node};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+// A fixed PI -> USDC rate instead of the price oracle, and one key per role
+const CONFIG: &str = r#"
+node_name: full-node-example
+network_id: testnet
+server: { address: 127.0.0.1, port: 3099, drain_timeout_secs: 1 }
+logging: { filter: "info,warp=warn" }
+clock: { ntp_servers: [] }
+metrics_history: { enabled: false }
+auth:
+  api_keys:
+    k-admin: { subject: ops, scopes: [admin] }
+    k-minter: { subject: minter, scopes: [issue] }
+    k-alice: { subject: alice, scopes: [convert, redeem] }
+validation:
+  assets:
+    PI: { min_amount: 1, max_amount: 1000000000000 }
+    USDC: { min_amount: 1, max_amount: 1000000000000 }
+assets:
+  PI: { decimals: 7 }
+  USDC: { decimals: 6, stablecoin: true }
+converter:
+  rates: [{ from: PI, to: USDC, numerator: 314159, denominator: 10000000 }]
+bootstrap: { state_path: "{data}/state.json.zst", journal_path: "{data}/journal.jsonl" }
+model: { checkpoint_path: "{data}/model.json" }
+p2p: { address_book_path: "{data}/peers.json" }
+audit_log: { enabled: true, dir: "{data}/audit" }
+event_log: { schemas_path: "{data}/event_schemas.json" }
+jobs: { path: "{data}/jobs.json" }
+upgrade: { manifest_path: "{data}/manifest.json" }
+key_compromise: { key_dir: "{data}/keys" }
+"#;
+
+const BASE: &str = "http://127.0.0.1:3099";
+
+async fn call(client: &Client, method: reqwest::Method, path: &str, key: &str, body: Value) -> Result<Value, String> {
+    let response = client.request(method, format!("{}{}", BASE, path)).header("x-api-key", key).json(&body).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("{} {}: {}", status, path, body));
+    }
+    println!("{} -> {}", path, body);
+    Ok(body)
+}
+
+async fn walkthrough(client: &Client) -> Result<(), String> {
+    call(client, reqwest::Method::POST, "/v1/issuance", "k-minter", json!({ "asset": "PI", "amount": "10000000", "recipient": "alice" })).await?;
+
+    // The holder registers the key that signs its burn authorizations
+    let holder = SigningKey::from_bytes(&[7; 32]);
+    let key = json!({ "public_key": hex::encode(holder.verifying_key().to_bytes()) });
+    call(client, reqwest::Method::PUT, "/v1/accounts/alice/key", "k-alice", key).await?;
+
+    call(client, reqwest::Method::POST, "/convert", "k-alice", json!({ "asset": "PI", "to_asset": "USDC", "amount": "4000000" })).await?;
+
+    let mut burn = RedemptionRequest { account: "alice".to_string(), asset: "PI".to_string(), amount: 1_000_000, nonce: 0, signature: String::new() };
+    burn.signature = hex::encode(holder.sign(&burn.signed_bytes()).to_bytes());
+    let body = json!({ "account": burn.account, "asset": burn.asset, "amount": burn.amount.to_string(), "nonce": burn.nonce, "signature": burn.signature });
+    call(client, reqwest::Method::POST, "/v1/redemption", "k-alice", body).await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    // A fresh ledger each run, so the holder's key and nonce start over
+    let data = std::env::temp_dir().join("pi-supernode-full-node-example");
+    let _ = std::fs::remove_dir_all(&data);
+    let config: NodeConfig = serde_yaml::from_str(&CONFIG.replace("{data}", &data.display().to_string())).map_err(|e| e.to_string())?;
+    logging::init(&config.logging, &config.telemetry)?;
+
+    let shutdown = CancellationToken::new();
+    let served = tokio::spawn(node::serve(config, shutdown.clone()));
+    let client = Client::new();
+    let mut listening = false;
+    for _ in 0..50 {
+        if client.get(format!("{}/healthz", BASE)).send().await.is_ok() {
+            listening = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let result = if listening { walkthrough(&client).await } else { Err("node did not start listening".to_string()) };
+
+    // Close the client's idle connection so the node does not wait out its drain timeout
+    drop(client);
+    shutdown.cancel();
+    served.await.map_err(|e| e.to_string())??;
+    println!("state saved under {}", data.display());
+    result
+}
@@ -0,0 +1,111 @@
+use crate::ai::engine::{AIEngine, Rule, Source};
+use crate::ai::self_heal::heal_once;
+use crate::api::auth::{Auth, Principal};
+use crate::api::response_cache::ResponseCache;
+use crate::events::bus::{Event, EventBus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Serialize)]
+struct RulesView {
+    rules: Vec<Rule>,
+    threshold: f32,
+}
+
+// Partial override; omitted fields are left alone
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesUpdate {
+    #[serde(default)]
+    pub weights: HashMap<String, f32>,
+    #[serde(default)]
+    pub threshold: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SelfHealRequest {
+    pub source: Source,
+
+    // Evolve even if the module's threat log is below self_heal.log_threshold
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize)]
+struct SelfHealOutcome {
+    source: Source,
+    evolved: Option<Rule>,
+    threshold: f32,
+}
+
+fn rules_view(engine: &AIEngine) -> RulesView {
+    RulesView { rules: engine.rules(), threshold: engine.threshold() }
+}
+
+// GET/PUT /admin/rules, GET /admin/model and POST /admin/self-heal; GET /admin/rules is served through `cache`
+pub fn routes(
+    engine: AIEngine,
+    bus: EventBus,
+    log_threshold: usize,
+    auth: &Auth,
+    cache: &ResponseCache,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let ai = engine.clone();
+    let get_rules =
+        warp::path!("admin" / "rules").and(warp::get()).and(auth.authorized()).map(move |_| warp::reply::json(&rules_view(&ai)));
+
+    let ai = engine.clone();
+    let put_rules = warp::path!("admin" / "rules").and(warp::put()).and(auth.authorized()).and(warp::body::json()).map(
+        move |principal: Principal, update: RulesUpdate| {
+            // Validate both parts before applying either
+            if let Some(threshold) = update.threshold {
+                if !(0.01..=0.99).contains(&threshold) {
+                    let e = format!("threshold {} is outside 0.01..=0.99", threshold);
+                    return warp::reply::with_status(warp::reply::json(&e), StatusCode::UNPROCESSABLE_ENTITY);
+                }
+            }
+            if let Err(e) = ai.set_rule_weights(&update.weights) {
+                return warp::reply::with_status(warp::reply::json(&e), StatusCode::UNPROCESSABLE_ENTITY);
+            }
+            if let Some(threshold) = update.threshold {
+                let _ = ai.set_threshold(threshold);
+            }
+            info!(subject = %principal.subject, weights = ?update.weights, threshold = ?update.threshold, "AI rules overridden");
+            warp::reply::with_status(warp::reply::json(&rules_view(&ai)), StatusCode::OK)
+        },
+    );
+
+    let ai = engine.clone();
+    let model =
+        warp::path!("admin" / "model").and(warp::get()).and(auth.authorized()).map(move |_| warp::reply::json(&ai.model_info()));
+
+    let rules = cache.cached("rules", get_rules.or(put_rules));
+
+    let cache = cache.clone();
+    let self_heal = warp::path!("admin" / "self-heal").and(warp::post()).and(auth.authorized()).and(warp::body::json()).map(
+        move |principal: Principal, request: SelfHealRequest| {
+            let evolved = if request.force {
+                let rule = engine.evolve(request.source);
+                if let Some(rule) = &rule {
+                    bus.publish(Event::SelfHealTriggered { source: request.source.label().to_string(), rule: rule.name.clone() });
+                }
+                rule
+            } else {
+                let before = engine.threat_log_len(request.source);
+                heal_once(&engine, &bus, request.source, log_threshold);
+                (engine.threat_log_len(request.source) < before).then(|| engine.best_rule()).flatten()
+            };
+            if evolved.is_some() {
+                cache.invalidate("rules");
+            }
+            info!(subject = %principal.subject, source = ?request.source, force = request.force, evolved = evolved.is_some(), "manual self-heal");
+            warp::reply::json(&SelfHealOutcome { source: request.source, evolved, threshold: engine.threshold() })
+        },
+    );
+
+    rules.or(model).or(self_heal)
+}
@@ -0,0 +1,249 @@
+use crate::api::auth::Auth;
+use crate::job_queue::{Job, JobKind, JobQueue, JobStatus};
+use crate::runtime::scheduler::Scheduler;
+use crate::sessions::SessionManager;
+use crate::storage::entities::EntityStore;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration as StdDuration;
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// Bulk action over a set of admin entities, e.g. rotate all webhook keys
+pub trait BulkOperation: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Entities the operation would touch right now
+    fn select(&self) -> Vec<String>;
+
+    fn apply(&self, target: &str) -> Result<(), String>;
+}
+
+#[derive(Clone, Serialize)]
+pub struct Preview {
+    pub operation: String,
+    pub targets: Vec<String>,
+    pub confirmation_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Progress of a confirmed run, persisted so a restarted job resumes where it stopped
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BulkRun {
+    pub operation: String,
+    pub targets: Vec<String>,
+    pub completed: usize,
+    pub failures: Vec<(String, String)>,
+    pub finished: bool,
+}
+
+// Previews must be confirmed within this window
+const CONFIRM_WINDOW_MINUTES: i64 = 15;
+
+// How often confirmed runs are picked up from the job queue
+const WORKER_INTERVAL: StdDuration = StdDuration::from_secs(5);
+
+// Log every admin UI user out, e.g. after a credential leak
+pub struct RevokeSessions(pub SessionManager);
+
+impl BulkOperation for RevokeSessions {
+    fn name(&self) -> &str {
+        "revoke_sessions"
+    }
+
+    fn select(&self) -> Vec<String> {
+        self.0.list().into_iter().filter(|(_, session)| session.revoked_at.is_none()).map(|(id, _)| id).collect()
+    }
+
+    fn apply(&self, target: &str) -> Result<(), String> {
+        self.0.revoke(target).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+// Put every failed job back in the queue once the cause is fixed
+pub struct RequeueFailedJobs(pub JobQueue);
+
+impl BulkOperation for RequeueFailedJobs {
+    fn name(&self) -> &str {
+        "requeue_failed_jobs"
+    }
+
+    fn select(&self) -> Vec<String> {
+        self.0.jobs().into_iter().filter(|job| job.status == JobStatus::Failed).map(|job| job.id.to_string()).collect()
+    }
+
+    fn apply(&self, target: &str) -> Result<(), String> {
+        let id = target.parse().map_err(|_| format!("{} is not a job id", target))?;
+        match self.0.requeue(id) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("job no longer exists".to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BulkOps {
+    operations: Arc<HashMap<String, Arc<dyn BulkOperation>>>,
+    previews: Arc<Mutex<HashMap<String, Preview>>>,
+    runs: EntityStore<BulkRun>,
+    queue: JobQueue,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmRequest {
+    pub confirmation_token: String,
+}
+
+impl BulkOps {
+    pub fn new(operations: Vec<Arc<dyn BulkOperation>>, runs: EntityStore<BulkRun>, queue: JobQueue) -> Self {
+        let operations = operations.into_iter().map(|op| (op.name().to_string(), op)).collect();
+        BulkOps { operations: Arc::new(operations), previews: Arc::default(), runs, queue }
+    }
+
+    pub fn preview(&self, operation: &str) -> Option<Preview> {
+        let op = self.operations.get(operation)?;
+        let mut token = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut token);
+        let preview = Preview {
+            operation: operation.to_string(),
+            targets: op.select(),
+            confirmation_token: hex::encode(token),
+            expires_at: Utc::now() + Duration::minutes(CONFIRM_WINDOW_MINUTES),
+        };
+        self.previews.lock().unwrap().insert(preview.confirmation_token.clone(), preview.clone());
+        Some(preview)
+    }
+
+    // Turn a confirmed preview into a queued run over exactly the previewed targets
+    pub fn confirm(&self, token: &str) -> Result<String, String> {
+        let preview = self.previews.lock().unwrap().remove(token).ok_or("unknown or used confirmation token")?;
+        if preview.expires_at < Utc::now() {
+            return Err("confirmation token expired, request a new preview".to_string());
+        }
+        let run = BulkRun { operation: preview.operation, targets: preview.targets, completed: 0, failures: Vec::new(), finished: false };
+        let job_id = self.queue.enqueue(JobKind::BulkOperation, String::new(), 5, 0).map_err(|e| e.to_string())?;
+        self.runs.create(&job_id.to_string(), run).map_err(|e| e.to_string())?;
+        Ok(job_id.to_string())
+    }
+
+    // Job worker: continue the run from its persisted cursor
+    pub fn run_job(&self, job: &Job) -> Result<(), String> {
+        let id = job.id.to_string();
+        let mut entity = self.runs.get(&id).map_err(|e| e.to_string())?;
+        let op = self.operations.get(&entity.value.operation).ok_or("operation no longer registered")?.clone();
+        while entity.value.completed < entity.value.targets.len() {
+            let target = entity.value.targets[entity.value.completed].clone();
+            if let Err(e) = op.apply(&target) {
+                warn!(operation = op.name(), %target, error = %e, "bulk operation item failed");
+                entity.value.failures.push((target, e));
+            }
+            entity.value.completed += 1;
+            entity = self.runs.update(&id, entity.version, entity.value).map_err(|e| e.to_string())?;
+        }
+        entity.value.finished = true;
+        self.runs.update(&id, entity.version, entity.value).map_err(|e| e.to_string())?;
+        info!(operation = op.name(), run = %id, "bulk operation finished");
+        self.queue.ack(job.id).map_err(|e| e.to_string())
+    }
+
+    // Run every confirmed operation that is waiting; a failed run stays leased and is retried after the visibility timeout
+    pub fn work(&self) {
+        while let Ok(Some(job)) = self.queue.lease_kind(JobKind::BulkOperation) {
+            if let Err(e) = self.run_job(&job) {
+                warn!(run = %job.id, error = %e, "bulk operation run failed");
+                break;
+            }
+        }
+    }
+
+    // POST /admin/bulk/{op}/preview, POST /admin/bulk/{op}/execute, GET /admin/bulk/runs/{id}
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let ops = self.clone();
+        let preview = warp::path!("admin" / "bulk" / String / "preview")
+            .and(warp::post())
+            .and(auth.authorized())
+            .map(move |operation: String, _| match ops.preview(&operation) {
+                Some(preview) => warp::reply::with_status(warp::reply::json(&preview), StatusCode::OK),
+                None => warp::reply::with_status(warp::reply::json(&"unknown operation"), StatusCode::NOT_FOUND),
+            });
+        let ops = self.clone();
+        let execute = warp::path!("admin" / "bulk" / String / "execute")
+            .and(warp::post())
+            .and(auth.authorized())
+            .and(warp::body::json())
+            .map(move |_operation: String, _, req: ConfirmRequest| match ops.confirm(&req.confirmation_token) {
+                Ok(run_id) => warp::reply::with_status(warp::reply::json(&run_id), StatusCode::ACCEPTED),
+                Err(e) => warp::reply::with_status(warp::reply::json(&e), StatusCode::CONFLICT),
+            });
+        let ops = self.clone();
+        let status = warp::path!("admin" / "bulk" / "runs" / String)
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |id: String, _| match ops.runs.get(&id) {
+                Ok(run) => warp::reply::with_status(warp::reply::json(&run.value), StatusCode::OK),
+                Err(_) => warp::reply::with_status(warp::reply::json(&"unknown run"), StatusCode::NOT_FOUND),
+            });
+        preview.or(execute).or(status)
+    }
+}
+
+// Pick up confirmed runs from the job queue
+pub fn register_worker(scheduler: &Scheduler, ops: BulkOps) {
+    scheduler.register(
+        "bulk:worker",
+        WORKER_INTERVAL,
+        StdDuration::ZERO,
+        Arc::new(move || {
+            let ops = ops.clone();
+            Box::pin(async move {
+                let _ = tokio::task::spawn_blocking(move || ops.work()).await;
+            })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use crate::storage::mvcc::Store;
+
+    #[tokio::test]
+    async fn confirmed_previews_run_through_the_job_queue() {
+        let path = std::env::temp_dir().join(format!("bulk-jobs-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let queue = JobQueue::open(path.clone(), 60).unwrap();
+        for _ in 0..2 {
+            let id = queue.enqueue(JobKind::Export, "ledger".to_string(), 1, 0).unwrap();
+            queue.lease().unwrap();
+            queue.fail(id).unwrap();
+        }
+        let ops = BulkOps::new(vec![Arc::new(RequeueFailedJobs(queue.clone()))], EntityStore::new(Store::new(), "bulk_runs"), queue.clone());
+
+        let api_keys = HashMap::from([("k-admin".to_string(), ApiKeyConfig { subject: "ops".to_string(), scopes: vec![Scope::Admin], tenant: None })]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let api = warp::any().and(Router::new().mount("bulk", ops.routes(&auth)).build());
+        let admin = |method: &str, path: &str| warp::test::request().method(method).path(path).header("x-api-key", "k-admin");
+
+        let preview = admin("POST", "/admin/bulk/requeue_failed_jobs/preview").reply(&api).await;
+        let preview: serde_json::Value = serde_json::from_slice(preview.body()).unwrap();
+        assert_eq!(preview["targets"].as_array().unwrap().len(), 2);
+        let confirm = serde_json::json!({ "confirmation_token": preview["confirmation_token"] });
+        let accepted = admin("POST", "/admin/bulk/requeue_failed_jobs/execute").json(&confirm).reply(&api).await;
+        assert_eq!(accepted.status(), StatusCode::ACCEPTED);
+        let run_id: String = serde_json::from_slice(accepted.body()).unwrap();
+
+        ops.work();
+        let status = admin("GET", &format!("/admin/bulk/runs/{}", run_id)).reply(&api).await;
+        let run: serde_json::Value = serde_json::from_slice(status.body()).unwrap();
+        assert_eq!((run["completed"].as_u64(), run["finished"].as_bool()), (Some(2), Some(true)));
+        assert!(queue.jobs().iter().all(|job| job.status == JobStatus::Ready));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::admin::policy_params::{PolicyParamStore, PolicyParams};
+use crate::api::auth::Auth;
+use crate::api::fee_estimate::{estimate, FeeEstimate, FeeEstimateRequest, Operation};
+use crate::fees::{FeeError, FeeSchedule};
+use crate::p2p::policy_gossip::{PolicyApplier, PolicyBundle};
+use crate::storage::ledger_history::{LedgerHistory, TransactionRecord};
+use crate::storage::mvcc::{Store, WriteBatch};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+const PREFIX: &str = "policy/versions/";
+
+// Where a policy version came from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PolicySource {
+    // Live parameters written through `PolicyParamStore`, at their entity version
+    Params { version: u64, by: String },
+
+    // Gossiped bundle applied from a trusted publisher
+    Bundle { version: u64, publisher: String },
+}
+
+// One policy as it was in force from `effective_from` until the next version
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PolicyVersion {
+    pub seq: u64,
+    pub effective_from: DateTime<Utc>,
+    pub source: PolicySource,
+    pub params: PolicyParams,
+}
+
+// Every policy the node has applied, kept forever under `policy/versions/` so past decisions can be re-derived
+#[derive(Clone)]
+pub struct PolicyHistory {
+    store: Store,
+
+    // Serializes sequence allocation
+    writing: Arc<Mutex<()>>,
+}
+
+impl PolicyHistory {
+    pub fn new(store: Store) -> Self {
+        PolicyHistory { store, writing: Arc::default() }
+    }
+
+    pub fn record(&self, effective_from: DateTime<Utc>, source: PolicySource, params: PolicyParams) -> Result<PolicyVersion, String> {
+        let _writing = self.writing.lock().unwrap();
+        let seq = self.latest().map_or(1, |v| v.seq + 1);
+        let version = PolicyVersion { seq, effective_from, source, params };
+        let mut batch = WriteBatch::default();
+        batch.put(format!("{}{:020}", PREFIX, seq), serde_json::to_vec(&version).map_err(|e| e.to_string())?);
+        self.store.try_commit(batch).map_err(|e| e.to_string())?;
+        info!(seq, %effective_from, source = ?version.source, "policy version recorded");
+        Ok(version)
+    }
+
+    // Oldest first
+    pub fn versions(&self) -> Vec<PolicyVersion> {
+        self.store.read_txn().scan_prefix(PREFIX).into_iter().filter_map(|(_, bytes)| serde_json::from_slice(&bytes).ok()).collect()
+    }
+
+    pub fn latest(&self) -> Option<PolicyVersion> {
+        self.versions().pop()
+    }
+
+    // The version in force at `at`: the latest to take effect at or before it
+    pub fn active_at(&self, at: DateTime<Utc>) -> Option<PolicyVersion> {
+        self.versions().into_iter().filter(|v| v.effective_from <= at).max_by_key(|v| (v.effective_from, v.seq))
+    }
+
+    // Wrap a gossip applier so each bundle it applies is recorded; bundle payloads are JSON `PolicyParams`
+    pub fn recording(&self, inner: Box<dyn PolicyApplier>) -> Box<dyn PolicyApplier> {
+        Box::new(Recording { history: self.clone(), inner })
+    }
+
+    pub fn reevaluate(&self, live: &PolicyParamStore, fees: &FeeSchedule, tx: TransactionRecord, against: Against) -> Result<Reevaluation, ReevaluateError> {
+        let request = fee_request(&tx)?;
+        let policy = match against {
+            Against::Historical => self.active_at(tx.at).ok_or(ReevaluateError::NoPolicy(tx.at))?,
+            // Nothing recorded yet: the live parameters, with no sequence number
+            Against::Current => self.latest().unwrap_or_else(|| {
+                let current = live.current();
+                PolicyVersion {
+                    seq: 0,
+                    effective_from: Utc::now(),
+                    source: PolicySource::Params { version: current.version, by: String::new() },
+                    params: current.value,
+                }
+            }),
+        };
+        let estimate = estimate(fees, &policy.params, &request).map_err(ReevaluateError::Fee)?;
+        Ok(Reevaluation { transaction: tx, against, policy, estimate })
+    }
+
+    // GET /admin/policy/versions[?at=], GET /admin/transactions/{tx_id}/reevaluate?against=historical|current
+    pub fn routes(&self, live: PolicyParamStore, fees: FeeSchedule, ledger: LedgerHistory, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let history = self.clone();
+        let versions = warp::path!("admin" / "policy" / "versions")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and(warp::query::<VersionsQuery>())
+            .map(move |_, query: VersionsQuery| match query.at {
+                None => warp::reply::with_status(warp::reply::json(&history.versions()), StatusCode::OK),
+                Some(at) => match history.active_at(at) {
+                    Some(version) => warp::reply::with_status(warp::reply::json(&version), StatusCode::OK),
+                    None => warp::reply::with_status(warp::reply::json(&ReevaluateError::NoPolicy(at).to_string()), StatusCode::NOT_FOUND),
+                },
+            });
+
+        let history = self.clone();
+        let reevaluate = warp::path!("admin" / "transactions" / String / "reevaluate")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and(warp::query::<ReevaluateQuery>())
+            .map(move |tx_id: String, _, query: ReevaluateQuery| {
+                let result = ledger
+                    .transaction(&tx_id)
+                    .ok_or(ReevaluateError::UnknownTransaction(tx_id))
+                    .and_then(|tx| history.reevaluate(&live, &fees, tx, query.against));
+                match result {
+                    Ok(reevaluation) => warp::reply::with_status(warp::reply::json(&reevaluation), StatusCode::OK),
+                    Err(e) => {
+                        let status = match e {
+                            ReevaluateError::UnknownTransaction(_) | ReevaluateError::NoPolicy(_) => StatusCode::NOT_FOUND,
+                            ReevaluateError::Unsupported(_) | ReevaluateError::Fee(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                        };
+                        warp::reply::with_status(warp::reply::json(&e.to_string()), status)
+                    }
+                }
+            });
+
+        versions.or(reevaluate)
+    }
+}
+
+struct Recording {
+    history: PolicyHistory,
+    inner: Box<dyn PolicyApplier>,
+}
+
+impl PolicyApplier for Recording {
+    fn apply(&self, bundle: &PolicyBundle) -> Result<(), String> {
+        self.inner.apply(bundle)?;
+        let source = PolicySource::Bundle { version: bundle.version, publisher: hex::encode(bundle.publisher) };
+        match serde_json::from_slice::<PolicyParams>(&bundle.payload) {
+            Ok(params) => {
+                if let Err(e) = self.history.record(Utc::now(), source, params) {
+                    warn!(version = bundle.version, error = %e, "applied policy bundle not recorded");
+                }
+            }
+            Err(e) => warn!(version = bundle.version, error = %e, "policy bundle payload is not policy parameters, not recorded"),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Against {
+    // The policy in force when the transaction happened
+    #[default]
+    Historical,
+    Current,
+}
+
+#[derive(Deserialize)]
+struct VersionsQuery {
+    at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct ReevaluateQuery {
+    #[serde(default)]
+    against: Against,
+}
+
+// A past transaction priced again under a chosen policy; nothing is charged or changed
+#[derive(Clone, Debug, Serialize)]
+pub struct Reevaluation {
+    pub transaction: TransactionRecord,
+    pub against: Against,
+    pub policy: PolicyVersion,
+    pub estimate: FeeEstimate,
+}
+
+#[derive(Debug)]
+pub enum ReevaluateError {
+    UnknownTransaction(String),
+    NoPolicy(DateTime<Utc>),
+    Unsupported(String),
+    Fee(FeeError),
+}
+
+impl fmt::Display for ReevaluateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReevaluateError::UnknownTransaction(id) => write!(f, "no transaction {}", id),
+            ReevaluateError::NoPolicy(at) => write!(f, "no policy version was in force at {}", at.to_rfc3339()),
+            ReevaluateError::Unsupported(e) => write!(f, "{}", e),
+            ReevaluateError::Fee(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn fee_request(tx: &TransactionRecord) -> Result<FeeEstimateRequest, ReevaluateError> {
+    let operation = match tx.kind.as_str() {
+        "issuance" => Operation::Issue,
+        "redemption" => Operation::Redeem,
+        "conversion" => Operation::Convert,
+        other => return Err(ReevaluateError::Unsupported(format!("cannot re-evaluate a {} transaction", other))),
+    };
+    let amount = tx.amount.parse().map_err(|_| ReevaluateError::Unsupported(format!("amount {} is not an integer", tx.amount)))?;
+    Ok(FeeEstimateRequest { operation, asset: tx.asset.clone(), to_asset: tx.to_asset.clone(), amount })
+}
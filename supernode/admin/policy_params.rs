@@ -0,0 +1,257 @@
+use crate::admin::policy_history::{PolicyHistory, PolicySource};
+use crate::api::auth::{Auth, Principal};
+use crate::runtime::scheduler::Scheduler;
+use crate::storage::entities::{if_match, EntityError, EntityStore, Versioned};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// Tunable fees, thresholds and limits, by name
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PolicyParams {
+    pub values: BTreeMap<String, f64>,
+}
+
+// `policy_guard` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct PolicyGuardConfig {
+    // Largest relative change applied without a second approver, 0.2 = ±20%
+    pub max_relative_change: f64,
+
+    // Per-parameter overrides of the bound
+    pub per_param: BTreeMap<String, f64>,
+
+    // Earliest activation after approval for guarded changes
+    pub min_activation_delay_secs: u64,
+}
+
+impl Default for PolicyGuardConfig {
+    fn default() -> Self {
+        PolicyGuardConfig { max_relative_change: 0.2, per_param: BTreeMap::new(), min_activation_delay_secs: 3600 }
+    }
+}
+
+// Parameter that moved further than its bound
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Excess {
+    pub param: String,
+    pub from: Option<f64>,
+    pub to: Option<f64>,
+    pub bound: f64,
+}
+
+impl PolicyGuardConfig {
+    pub fn excesses(&self, current: &PolicyParams, proposed: &PolicyParams) -> Vec<Excess> {
+        let names: std::collections::BTreeSet<&String> = current.values.keys().chain(proposed.values.keys()).collect();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let bound = *self.per_param.get(name).unwrap_or(&self.max_relative_change);
+                let (from, to) = (current.values.get(name).copied(), proposed.values.get(name).copied());
+                let within = match (from, to) {
+                    (Some(a), Some(b)) if a == b => true,
+                    (Some(a), Some(b)) if a != 0.0 => ((b - a) / a).abs() <= bound,
+                    // Adding, removing or moving off zero is always a large change
+                    _ => false,
+                };
+                (!within).then(|| Excess { param: name.clone(), from, to, bound })
+            })
+            .collect()
+    }
+}
+
+// Out-of-bounds change waiting for a second admin and its activation time
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingChange {
+    pub proposed: PolicyParams,
+    pub excesses: Vec<Excess>,
+    pub proposed_by: String,
+    pub proposed_at: DateTime<Utc>,
+
+    // Version of the live parameters the change was computed against
+    pub base_version: u64,
+
+    pub approved_by: Option<String>,
+    pub activate_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct ApproveRequest {
+    pub activate_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum UpdateOutcome {
+    Applied { version: u64 },
+    PendingApproval { change_id: String, excesses: Vec<Excess> },
+}
+
+const LIVE: &str = "live";
+
+#[derive(Clone)]
+pub struct PolicyParamStore {
+    live: EntityStore<PolicyParams>,
+    pending: EntityStore<PendingChange>,
+    guard: Arc<PolicyGuardConfig>,
+
+    // Records every write that takes effect, see `PolicyHistory`
+    history: Option<PolicyHistory>,
+}
+
+impl PolicyParamStore {
+    pub fn new(live: EntityStore<PolicyParams>, pending: EntityStore<PendingChange>, guard: PolicyGuardConfig) -> Self {
+        PolicyParamStore { live, pending, guard: Arc::new(guard), history: None }
+    }
+
+    // An empty history starts from the live parameters as of now; earlier transactions have no recorded policy
+    pub fn with_history(mut self, history: PolicyHistory) -> Self {
+        let current = self.current();
+        if current.version > 0 && history.latest().is_none() {
+            let source = PolicySource::Params { version: current.version, by: "baseline".to_string() };
+            if let Err(e) = history.record(Utc::now(), source, current.value) {
+                warn!(error = %e, "baseline policy version not recorded");
+            }
+        }
+        self.history = Some(history);
+        self
+    }
+
+    pub fn current(&self) -> Versioned<PolicyParams> {
+        self.live.get(LIVE).unwrap_or(Versioned { version: 0, deleted_at: None, value: PolicyParams::default() })
+    }
+
+    fn write_live(&self, by: &str, expected_version: u64, params: PolicyParams) -> Result<Versioned<PolicyParams>, EntityError> {
+        let written = if expected_version == 0 { self.live.create(LIVE, params) } else { self.live.update(LIVE, expected_version, params) }?;
+        if let Some(history) = &self.history {
+            let source = PolicySource::Params { version: written.version, by: by.to_string() };
+            if let Err(e) = history.record(Utc::now(), source, written.value.clone()) {
+                warn!(version = written.version, error = %e, "policy version not recorded");
+            }
+        }
+        Ok(written)
+    }
+
+    // Apply small changes now; park large ones for a second approver
+    pub fn propose(&self, by: &str, expected_version: u64, proposed: PolicyParams) -> Result<UpdateOutcome, EntityError> {
+        let current = self.current();
+        if current.version != expected_version {
+            return Err(EntityError::Conflict { expected: expected_version, current: current.version });
+        }
+        let excesses = self.guard.excesses(&current.value, &proposed);
+        if excesses.is_empty() {
+            let written = self.write_live(by, expected_version, proposed)?;
+            info!(by, version = written.version, "policy parameters updated");
+            return Ok(UpdateOutcome::Applied { version: written.version });
+        }
+        let change_id = crate::ids::CaseId::new().to_string();
+        let change = PendingChange {
+            proposed,
+            excesses: excesses.clone(),
+            proposed_by: by.to_string(),
+            proposed_at: Utc::now(),
+            base_version: expected_version,
+            approved_by: None,
+            activate_at: None,
+        };
+        self.pending.create(&change_id, change)?;
+        warn!(by, %change_id, params = excesses.len(), "policy change exceeds rate-of-change bounds, approval required");
+        Ok(UpdateOutcome::PendingApproval { change_id, excesses })
+    }
+
+    pub fn approve(&self, change_id: &str, by: &str, activate_at: DateTime<Utc>) -> Result<PendingChange, String> {
+        let entity = self.pending.get(change_id).map_err(|e| e.to_string())?;
+        let mut change = entity.value;
+        if change.proposed_by == by {
+            return Err("the proposer cannot approve their own change".to_string());
+        }
+        if change.approved_by.is_some() {
+            return Err("change is already approved".to_string());
+        }
+        let earliest = Utc::now() + chrono::Duration::seconds(self.guard.min_activation_delay_secs as i64);
+        if activate_at < earliest {
+            return Err(format!("activation must be scheduled at or after {}", earliest.to_rfc3339()));
+        }
+        change.approved_by = Some(by.to_string());
+        change.activate_at = Some(activate_at);
+        self.pending.update(change_id, entity.version, change.clone()).map_err(|e| e.to_string())?;
+        info!(%change_id, by, %activate_at, "policy change approved");
+        Ok(change)
+    }
+
+    // Apply approved changes whose activation time has passed
+    pub fn activate_due(&self) {
+        let now = Utc::now();
+        for (id, change) in self.pending_changes() {
+            if change.activate_at.is_none_or(|at| at > now) {
+                continue;
+            }
+            match self.write_live(change.approved_by.as_deref().unwrap_or(&change.proposed_by), change.base_version, change.proposed.clone()) {
+                Ok(written) => info!(change_id = %id, version = written.version, "scheduled policy change activated"),
+                // Someone changed the parameters since; the stale change is dropped rather than overwriting theirs
+                Err(e) => warn!(change_id = %id, error = %e, "scheduled policy change discarded"),
+            }
+            if let Ok(entity) = self.pending.get(&id) {
+                let _ = self.pending.delete(&id, entity.version);
+            }
+        }
+    }
+
+    pub fn pending_changes(&self) -> Vec<(String, PendingChange)> {
+        self.pending.list().into_iter().map(|(id, e)| (id, e.value)).collect()
+    }
+
+    // GET/PUT /admin/policy/params, GET /admin/policy/changes, POST /admin/policy/changes/{id}/approve
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let store = self.clone();
+        let get = warp::path!("admin" / "policy" / "params").and(warp::get()).and(auth.authorized()).map(move |_| {
+            let current = store.current();
+            warp::reply::with_header(warp::reply::json(&current.value), "etag", current.etag())
+        });
+        let store = self.clone();
+        let put = warp::path!("admin" / "policy" / "params")
+            .and(warp::put())
+            .and(auth.authorized())
+            .and(if_match())
+            .and(warp::body::json())
+            .map(move |principal: Principal, version: u64, params: PolicyParams| match store.propose(&principal.subject, version, params) {
+                Ok(outcome @ UpdateOutcome::Applied { .. }) => warp::reply::with_status(warp::reply::json(&outcome), StatusCode::OK),
+                Ok(outcome) => warp::reply::with_status(warp::reply::json(&outcome), StatusCode::ACCEPTED),
+                Err(e) => warp::reply::with_status(warp::reply::json(&e.to_string()), StatusCode::PRECONDITION_FAILED),
+            });
+        let store = self.clone();
+        let list = warp::path!("admin" / "policy" / "changes")
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |_| warp::reply::json(&store.pending_changes().into_iter().collect::<BTreeMap<_, _>>()));
+        let store = self.clone();
+        let approve = warp::path!("admin" / "policy" / "changes" / String / "approve")
+            .and(warp::post())
+            .and(auth.authorized())
+            .and(warp::body::json())
+            .map(move |id: String, principal: Principal, req: ApproveRequest| match store.approve(&id, &principal.subject, req.activate_at) {
+                Ok(change) => warp::reply::with_status(warp::reply::json(&change), StatusCode::OK),
+                Err(e) => warp::reply::with_status(warp::reply::json(&e), StatusCode::CONFLICT),
+            });
+        get.or(put).or(list).or(approve)
+    }
+}
+
+// Check once a minute for approved changes that are due
+pub fn register_activation(scheduler: &Scheduler, store: PolicyParamStore) {
+    scheduler.register(
+        "policy_params:activate",
+        Duration::from_secs(60),
+        Duration::ZERO,
+        Arc::new(move || {
+            let store = store.clone();
+            Box::pin(async move { store.activate_due() })
+        }),
+    );
+}
@@ -0,0 +1,191 @@
+use crate::anomaly_model::{build_model, AnomalyModel, Features, ModelBackend};
+use crate::api::auth::Auth;
+use crate::storage::mvcc::{Store, WriteBatch};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::info;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// A past request with the outcome it actually received
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoricalRequest {
+    pub request_id: String,
+    pub source: String,
+    pub features: Vec<f32>,
+    pub amount: u128,
+    pub rejected: bool,
+    pub fee: u128,
+}
+
+// Requests kept for replay, stored under `backtest/` in time order of their ids
+#[derive(Clone)]
+pub struct RequestArchive {
+    store: Store,
+}
+
+const PREFIX: &str = "backtest/";
+
+impl RequestArchive {
+    pub fn new(store: Store) -> Self {
+        RequestArchive { store }
+    }
+
+    pub fn record(&self, request: &HistoricalRequest) {
+        if let Ok(bytes) = serde_json::to_vec(request) {
+            let mut batch = WriteBatch::default();
+            batch.put(format!("{}{}", PREFIX, request.request_id), bytes);
+            self.store.commit(batch);
+        }
+    }
+
+    // Consistent snapshot of the archive; writes during a backtest are not seen
+    pub fn snapshot(&self) -> Vec<HistoricalRequest> {
+        self.store
+            .read_txn()
+            .scan_prefix(PREFIX)
+            .into_iter()
+            .filter_map(|(_, bytes)| serde_json::from_slice(&bytes).ok())
+            .collect()
+    }
+}
+
+// Policy/model bundle under evaluation
+pub struct Candidate {
+    pub model: Box<dyn AnomalyModel>,
+    pub threshold: f32,
+
+    // Fee charged on accepted volume, in basis points
+    pub fee_bps: u32,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Totals {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub accepted_volume: u128,
+    pub fee_revenue: u128,
+}
+
+impl Totals {
+    fn add(&mut self, rejected: bool, amount: u128, fee: u128) {
+        if rejected {
+            self.rejected += 1;
+        } else {
+            self.accepted += 1;
+            self.accepted_volume += amount;
+            self.fee_revenue += fee;
+        }
+    }
+}
+
+// Request whose outcome the candidate would change
+#[derive(Clone, Debug, Serialize)]
+pub struct Flip {
+    pub request_id: String,
+    pub source: String,
+    pub score: f32,
+    pub was_rejected: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BacktestReport {
+    pub replayed: u64,
+    pub baseline: Totals,
+    pub candidate: Totals,
+
+    // Per source (crypto, api, converter)
+    pub by_source: BTreeMap<String, (Totals, Totals)>,
+
+    pub newly_rejected: u64,
+    pub newly_accepted: u64,
+
+    // First flips, for inspection
+    pub sample_flips: Vec<Flip>,
+}
+
+// Flips kept in the report
+const SAMPLE_FLIPS: usize = 100;
+
+// Replay history through the candidate without touching the live engine or its metrics
+// Stops at the first request the candidate cannot score
+pub fn run(history: &[HistoricalRequest], candidate: &Candidate) -> Result<BacktestReport, String> {
+    let mut report = BacktestReport {
+        replayed: 0,
+        baseline: Totals::default(),
+        candidate: Totals::default(),
+        by_source: BTreeMap::new(),
+        newly_rejected: 0,
+        newly_accepted: 0,
+        sample_flips: Vec::new(),
+    };
+    for request in history {
+        let score = candidate.model.score(&Features { values: request.features.clone() })?;
+        let rejected = score > candidate.threshold;
+        let fee = request.amount * candidate.fee_bps as u128 / 10_000;
+
+        report.replayed += 1;
+        report.baseline.add(request.rejected, request.amount, request.fee);
+        report.candidate.add(rejected, request.amount, fee);
+        let (baseline, replayed) = report.by_source.entry(request.source.clone()).or_default();
+        baseline.add(request.rejected, request.amount, request.fee);
+        replayed.add(rejected, request.amount, fee);
+
+        if rejected != request.rejected {
+            if rejected {
+                report.newly_rejected += 1;
+            } else {
+                report.newly_accepted += 1;
+            }
+            if report.sample_flips.len() < SAMPLE_FLIPS {
+                report.sample_flips.push(Flip {
+                    request_id: request.request_id.clone(),
+                    source: request.source.clone(),
+                    score,
+                    was_rejected: request.rejected,
+                });
+            }
+        }
+    }
+    info!(
+        replayed = report.replayed,
+        newly_rejected = report.newly_rejected,
+        newly_accepted = report.newly_accepted,
+        "backtest finished"
+    );
+    Ok(report)
+}
+
+// Candidate as submitted by an operator: an exported ONNX model plus policy settings
+#[derive(Deserialize)]
+pub struct BacktestRequest {
+    pub model_path: String,
+    pub threshold: f32,
+    pub fee_bps: u32,
+}
+
+// POST /admin/backtest
+pub fn routes(archive: RequestArchive, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("admin" / "backtest")
+        .and(warp::post())
+        .and(auth.authorized())
+        .and(warp::body::json())
+        .and_then(move |_, req: BacktestRequest| {
+            let archive = archive.clone();
+            async move {
+                // Model loading and replay are CPU bound
+                let result = tokio::task::spawn_blocking(move || {
+                    let model = build_model(&ModelBackend::Onnx { model_path: req.model_path })?;
+                    let candidate = Candidate { model, threshold: req.threshold, fee_bps: req.fee_bps };
+                    run(&archive.snapshot(), &candidate)
+                })
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|r| r);
+                Ok::<_, Rejection>(match result {
+                    Ok(report) => warp::reply::with_status(warp::reply::json(&report), StatusCode::OK),
+                    Err(e) => warp::reply::with_status(warp::reply::json(&e), StatusCode::UNPROCESSABLE_ENTITY),
+                })
+            }
+        })
+}
@@ -0,0 +1,321 @@
+use crate::ai::feedback::{FeedbackStats, Label};
+use crate::ai::persistence::ModelSnapshot;
+use crate::anomaly_model::{AnomalyModel, Features, Feedback};
+use crate::cache::{AdaptiveLru, CacheConfig};
+use crate::events::bus::{Event, EventBus, Step};
+use crate::metrics;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use tracing::{debug, instrument, warn};
+
+// Module a signal or decision originates from
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Crypto,
+    Api,
+    Converter,
+}
+
+impl Source {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Source::Crypto => "crypto",
+            Source::Api => "api",
+            Source::Converter => "converter",
+        }
+    }
+}
+
+// RL rule: an action the agent can take and its learned value
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    pub weight: f32,
+}
+
+// What operators see of the engine on GET /admin/model
+#[derive(Serialize)]
+pub struct ModelInfo {
+    pub backend: &'static str,
+    pub state_bytes: usize,
+    pub threshold: f32,
+    pub decisions: u64,
+    pub rules: Vec<Rule>,
+    pub threat_levels: HashMap<Source, f32>,
+    pub threat_log: HashMap<Source, usize>,
+    pub feedback: FeedbackStats,
+}
+
+// Outcome of a shared decision
+pub struct Decision {
+    pub score: f32,
+    pub rejected: bool,
+}
+
+struct EngineState {
+    model: Box<dyn AnomalyModel>,
+    rules: Vec<Rule>,
+    threshold: f32,
+
+    // Recent threat level reported by each module, decays on every decision
+    threat_levels: HashMap<Source, f32>,
+
+    // Decisions taken since the engine was created or restored
+    decisions: u64,
+
+    // Threat reports since the last evolution, per module
+    threat_log: HashMap<Source, usize>,
+
+    // Recent decisions kept so operators can label them by request id
+    recent: HashMap<String, (Vec<f32>, f32, bool)>,
+    recent_order: VecDeque<String>,
+
+    stats: FeedbackStats,
+}
+
+// Recent decisions retained for feedback
+const RECENT_CAPACITY: usize = 10_000;
+
+// Single AI/RL engine injected into crypto, API and converter modules
+#[derive(Clone)]
+pub struct AIEngine {
+    state: Arc<RwLock<EngineState>>,
+
+    // Model scores by exact feature bits; cleared whenever the model learns
+    scores: AdaptiveLru<Vec<u32>, f32>,
+
+    // Decisions on identified requests are published as transaction steps
+    events: Option<EventBus>,
+}
+
+impl AIEngine {
+    pub fn new(model: Box<dyn AnomalyModel>, threshold: f32) -> Self {
+        Self::with_cache(model, threshold, CacheConfig::default())
+    }
+
+    pub fn with_cache(model: Box<dyn AnomalyModel>, threshold: f32, cache: CacheConfig) -> Self {
+        let rules = ["cache_responses", "tighten_limits", "rotate_keys"]
+            .iter()
+            .map(|name| Rule { name: name.to_string(), weight: 0.5 })
+            .collect();
+        AIEngine {
+            state: Arc::new(RwLock::new(EngineState {
+                model,
+                rules,
+                threshold,
+                threat_levels: HashMap::new(),
+                decisions: 0,
+                threat_log: HashMap::new(),
+                recent: HashMap::new(),
+                recent_order: VecDeque::new(),
+                stats: FeedbackStats::default(),
+            })),
+            scores: AdaptiveLru::new("decisions", cache),
+            events: None,
+        }
+    }
+
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    // Report a threat observed by any module so the others become more cautious
+    #[instrument(skip(self))]
+    pub fn report_threat(&self, source: Source, severity: f32) {
+        warn!(?source, severity, "threat reported");
+        let mut state = self.state.write().unwrap();
+        let level = state.threat_levels.entry(source).or_insert(0.0);
+        *level = (*level + severity).min(1.0);
+        let entries = state.threat_log.entry(source).or_insert(0);
+        *entries += 1;
+        metrics::LOG_SIZES.with_label_values(&[&format!("threat_{}", source.label())]).set(*entries as i64);
+    }
+
+    pub fn threat_log_len(&self, source: Source) -> usize {
+        self.state.read().unwrap().threat_log.get(&source).copied().unwrap_or(0)
+    }
+
+    // Self-heal step: reinforce the best rule, tighten the threshold and clear the module's log
+    pub fn evolve(&self, source: Source) -> Option<Rule> {
+        let best = self.best_rule();
+        let mut state = self.state.write().unwrap();
+        if let Some(best) = &best {
+            if let Some(rule) = state.rules.iter_mut().find(|r| r.name == best.name) {
+                rule.weight = (rule.weight + 0.05).min(1.0);
+            }
+        }
+        state.threshold = (state.threshold * 0.98).max(0.01);
+        state.threat_log.insert(source, 0);
+        metrics::SELF_HEAL_RUNS.with_label_values(&[source.label()]).inc();
+        metrics::LOG_SIZES.with_label_values(&[&format!("threat_{}", source.label())]).set(0);
+        best
+    }
+
+    // Score a request, combining the model with threat intelligence from all modules; model failures are returned, never scored
+    pub fn evaluate(&self, source: Source, features: &Features) -> Result<Decision, String> {
+        let mut state = self.state.write().unwrap();
+        let key: Vec<u32> = features.values.iter().map(|v| v.to_bits()).collect();
+        let model_score = match self.scores.get(&key) {
+            Some(score) => score,
+            None => {
+                let score = state.model.score(features).inspect_err(|e| warn!(?source, error = %e, "model could not score"))?;
+                self.scores.insert(key, score);
+                score
+            }
+        };
+        let threat = state.threat_levels.values().copied().fold(0.0f32, f32::max);
+        for level in state.threat_levels.values_mut() {
+            *level *= 0.95;
+        }
+        state.decisions += 1;
+        let score = (model_score + 0.5 * threat).min(1.0);
+        let rejected = score > state.threshold;
+        debug!(?source, score, model_score, threat, rejected, "decision evaluated");
+        if rejected {
+            metrics::REJECTIONS.with_label_values(&[source.label()]).inc();
+        }
+        Ok(Decision { score, rejected })
+    }
+
+    // Evaluate and remember the decision so it can later be corrected by an operator
+    #[instrument(skip(self, features))]
+    pub fn evaluate_request(&self, request_id: &str, source: Source, features: &Features) -> Result<Decision, String> {
+        let decision = self.evaluate(source, features)?;
+        let mut state = self.state.write().unwrap();
+        if state.recent_order.len() >= RECENT_CAPACITY {
+            if let Some(oldest) = state.recent_order.pop_front() {
+                state.recent.remove(&oldest);
+            }
+        }
+        state.recent.insert(request_id.to_string(), (features.values.clone(), decision.score, decision.rejected));
+        state.recent_order.push_back(request_id.to_string());
+        drop(state);
+        if let Some(events) = &self.events {
+            events.publish(Event::TransactionStep {
+                tx_id: request_id.to_string(),
+                step: Step::Decision,
+                outcome: if decision.rejected { "rejected" } else { "approved" }.to_string(),
+                detail: Some(format!("{} score {:.3}", source.label(), decision.score)),
+                related: None,
+            });
+        }
+        Ok(decision)
+    }
+
+    // Record an operator label for a past decision and adapt the threshold online
+    pub fn submit_feedback(&self, request_id: &str, label: Label) -> Result<(), String> {
+        let mut state = self.state.write().unwrap();
+        let (values, score, rejected) = state
+            .recent
+            .get(request_id)
+            .cloned()
+            .ok_or_else(|| format!("no recent decision for request {}", request_id))?;
+        let anomalous = label == Label::Anomalous;
+        state.stats.record(rejected, anomalous);
+
+        // Nudge the threshold past the mislabeled score
+        if rejected && !anomalous {
+            state.threshold = (state.threshold + 0.5 * (score - state.threshold).max(0.01)).min(0.99);
+        } else if !rejected && anomalous {
+            state.threshold = (state.threshold - 0.5 * (state.threshold - score).max(0.01)).max(0.01);
+        }
+        state.model.update(&Feedback { features: Features { values }, anomalous });
+        self.scores.clear();
+        Ok(())
+    }
+
+    pub fn feedback_stats(&self) -> FeedbackStats {
+        self.state.read().unwrap().stats.clone()
+    }
+
+    pub fn learn(&self, feedback: &Feedback) {
+        self.state.write().unwrap().model.update(feedback);
+        self.scores.clear();
+    }
+
+    // Reinforce or penalize a rule based on the observed reward
+    pub fn reward(&self, rule: &str, reward: f32) {
+        let mut state = self.state.write().unwrap();
+        if let Some(r) = state.rules.iter_mut().find(|r| r.name == rule) {
+            r.weight = (r.weight + 0.1 * (reward - r.weight)).clamp(0.0, 1.0);
+        }
+    }
+
+    // Highest valued rule, used by self-heal to pick an action
+    pub fn best_rule(&self) -> Option<Rule> {
+        let state = self.state.read().unwrap();
+        state.rules.iter().cloned().max_by(|a, b| a.weight.total_cmp(&b.weight))
+    }
+
+    pub fn rules(&self) -> Vec<Rule> {
+        self.state.read().unwrap().rules.clone()
+    }
+
+    pub fn score_cache(&self) -> AdaptiveLru<Vec<u32>, f32> {
+        self.scores.clone()
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.state.read().unwrap().threshold
+    }
+
+    // Operator override of rule weights; only weights of existing rules change
+    pub fn set_rule_weights(&self, weights: &HashMap<String, f32>) -> Result<Vec<Rule>, String> {
+        let mut state = self.state.write().unwrap();
+        if let Some(unknown) = weights.keys().find(|name| !state.rules.iter().any(|r| &r.name == *name)) {
+            return Err(format!("unknown rule `{}`", unknown));
+        }
+        if let Some((name, weight)) = weights.iter().find(|(_, w)| !(0.0..=1.0).contains(*w)) {
+            return Err(format!("weight {} for `{}` is outside 0..=1", weight, name));
+        }
+        for rule in state.rules.iter_mut() {
+            if let Some(weight) = weights.get(&rule.name) {
+                rule.weight = *weight;
+            }
+        }
+        Ok(state.rules.clone())
+    }
+
+    pub fn set_threshold(&self, threshold: f32) -> Result<(), String> {
+        if !(0.01..=0.99).contains(&threshold) {
+            return Err(format!("threshold {} is outside 0.01..=0.99", threshold));
+        }
+        self.state.write().unwrap().threshold = threshold;
+        Ok(())
+    }
+
+    pub fn model_info(&self) -> ModelInfo {
+        let state = self.state.read().unwrap();
+        ModelInfo {
+            backend: state.model.name(),
+            state_bytes: state.model.export_state().len(),
+            threshold: state.threshold,
+            decisions: state.decisions,
+            rules: state.rules.clone(),
+            threat_levels: state.threat_levels.clone(),
+            threat_log: state.threat_log.clone(),
+            feedback: state.stats.clone(),
+        }
+    }
+
+    // Capture the learned state for a checkpoint
+    pub fn snapshot(&self) -> ModelSnapshot {
+        let state = self.state.read().unwrap();
+        ModelSnapshot::new(state.model.export_state(), state.rules.clone(), state.threshold, state.decisions)
+    }
+
+    // Warm-start from a checkpoint taken by a compatible version
+    pub fn restore(&self, snapshot: ModelSnapshot) -> Result<(), String> {
+        snapshot.check_version()?;
+        let mut state = self.state.write().unwrap();
+        state.model.import_state(&snapshot.model_state)?;
+        state.rules = snapshot.rules;
+        state.threshold = snapshot.threshold;
+        state.decisions = snapshot.decisions;
+        self.scores.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use crate::ai::engine::{AIEngine, Decision};
+use crate::anomaly_model::Features;
+use crate::api::auth::{Auth, Principal, Scope};
+use crate::api::problem::ApiError;
+use crate::api::versioning::{deprecated, Deprecation};
+use serde::Serialize;
+use utoipa::ToSchema;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use warp::{Filter, Rejection, Reply};
+
+// Feature whose value pushed the score towards rejection
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct MatchedFeature {
+    pub name: String,
+    pub value: f32,
+}
+
+// Why a transaction was blocked, returned to callers and kept for audit
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct RejectionReport {
+    pub decision_id: String,
+    pub score: f32,
+    pub threshold: f32,
+    pub triggered_rules: Vec<String>,
+    pub matched_features: Vec<MatchedFeature>,
+    pub remediation: String,
+}
+
+impl fmt::Display for RejectionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Rejected: score {:.2} above threshold {:.2} (rules: {})",
+            self.score,
+            self.threshold,
+            self.triggered_rules.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for RejectionReport {}
+
+// Values above this are reported as contributing features
+const FEATURE_MATCH_LEVEL: f32 = 0.5;
+
+// Explain a rejected decision in terms of rules, features and what to do next
+pub fn build_report(
+    engine: &AIEngine,
+    decision_id: &str,
+    feature_names: &[&str],
+    features: &Features,
+    decision: &Decision,
+) -> RejectionReport {
+    let threshold = engine.threshold();
+    let mut triggered_rules = vec!["anomaly_score_above_threshold".to_string()];
+    if decision.score >= 0.99 {
+        triggered_rules.push("score_saturated".to_string());
+    }
+    let matched_features: Vec<MatchedFeature> = feature_names
+        .iter()
+        .zip(features.values.iter())
+        .filter(|(_, v)| **v >= FEATURE_MATCH_LEVEL)
+        .map(|(name, v)| MatchedFeature { name: name.to_string(), value: *v })
+        .collect();
+    let remediation = if matched_features.is_empty() {
+        "Retry later; elevated network-wide threat level is raising scores".to_string()
+    } else {
+        format!(
+            "Review {} and resubmit, or file operator feedback if this is a false positive",
+            matched_features.iter().map(|m| m.name.as_str()).collect::<Vec<_>>().join(", ")
+        )
+    };
+    RejectionReport {
+        decision_id: decision_id.to_string(),
+        score: decision.score,
+        threshold,
+        triggered_rules,
+        matched_features,
+        remediation,
+    }
+}
+
+// Reports kept for audit; the oldest is dropped once this many are held
+const DECISION_CAPACITY: usize = 10_000;
+
+#[derive(Default)]
+struct Reports {
+    // Decision id -> the subject whose request was rejected, and why
+    by_id: HashMap<String, (String, RejectionReport)>,
+    order: VecDeque<String>,
+}
+
+// Recent rejection reports by decision id
+#[derive(Clone, Default)]
+pub struct DecisionStore {
+    reports: Arc<RwLock<Reports>>,
+}
+
+impl DecisionStore {
+    pub fn insert(&self, subject: &str, report: RejectionReport) {
+        let mut reports = self.reports.write().unwrap();
+        if reports.order.len() >= DECISION_CAPACITY {
+            if let Some(oldest) = reports.order.pop_front() {
+                reports.by_id.remove(&oldest);
+            }
+        }
+        reports.order.push_back(report.decision_id.clone());
+        reports.by_id.insert(report.decision_id.clone(), (subject.to_string(), report));
+    }
+
+    // Only the rejected caller and admins see a report; others are told it does not exist
+    pub fn get(&self, principal: &Principal, decision_id: &str) -> Option<RejectionReport> {
+        let reports = self.reports.read().unwrap();
+        let (subject, report) = reports.by_id.get(decision_id)?;
+        (*subject == principal.subject || principal.has_scope(Scope::Admin)).then(|| report.clone())
+    }
+
+    // GET /v1/decisions/{id}, and the deprecated unversioned /decisions/{id}
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let store = self.clone();
+        let lookup = move |id: String, principal: Principal| {
+            let report = store.get(&principal, &id).ok_or_else(|| ApiError::NotFound(format!("no decision {}", id)));
+            async move { report.map(|report| warp::reply::json(&report)).map_err(warp::reject::custom) }
+        };
+        let v1 = warp::path!("v1" / "decisions" / String).and(warp::get()).and(auth.authorized()).and_then(lookup.clone());
+        let legacy = deprecated(
+            warp::path!("decisions" / String).and(warp::get()).and(auth.authorized()).and_then(lookup),
+            Deprecation::unversioned("/v1/decisions/{id}"),
+        );
+        v1.or(legacy)
+    }
+}
@@ -0,0 +1,85 @@
+use crate::ai::engine::AIEngine;
+use crate::api::auth::{Auth, Principal};
+use crate::api::problem::ApiError;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// Ground truth supplied by an operator
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Label {
+    Legitimate,
+    Anomalous,
+}
+
+// Confusion matrix over labeled decisions
+#[derive(Clone, Default, Serialize, ToSchema)]
+pub struct FeedbackStats {
+    pub true_positives: u64,
+    pub false_positives: u64,
+    pub true_negatives: u64,
+    pub false_negatives: u64,
+}
+
+impl FeedbackStats {
+    pub fn record(&mut self, rejected: bool, anomalous: bool) {
+        match (rejected, anomalous) {
+            (true, true) => self.true_positives += 1,
+            (true, false) => self.false_positives += 1,
+            (false, false) => self.true_negatives += 1,
+            (false, true) => self.false_negatives += 1,
+        }
+    }
+
+    pub fn precision(&self) -> Option<f64> {
+        let predicted = self.true_positives + self.false_positives;
+        (predicted > 0).then(|| self.true_positives as f64 / predicted as f64)
+    }
+
+    pub fn recall(&self) -> Option<f64> {
+        let actual = self.true_positives + self.false_negatives;
+        (actual > 0).then(|| self.true_positives as f64 / actual as f64)
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct FeedbackRequest {
+    pub request_id: String,
+    pub label: Label,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StatsResponse {
+    pub counts: FeedbackStats,
+    pub precision: Option<f64>,
+    pub recall: Option<f64>,
+    pub threshold: f32,
+}
+
+// POST /v1/feedback and GET /v1/feedback/stats, for operators labelling decisions
+pub fn routes(engine: AIEngine, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let submit_engine = engine.clone();
+    let submit = warp::path!("v1" / "feedback").and(warp::post()).and(auth.authorized()).and(warp::body::json()).and_then(
+        move |_: Principal, req: FeedbackRequest| {
+            let recorded = submit_engine.submit_feedback(&req.request_id, req.label);
+            async move {
+                match recorded {
+                    Ok(()) => Ok(StatusCode::ACCEPTED),
+                    Err(e) => Err(warp::reject::custom(ApiError::NotFound(e))),
+                }
+            }
+        },
+    );
+    let stats = warp::path!("v1" / "feedback" / "stats").and(warp::get()).and(auth.authorized()).map(move |_: Principal| {
+        let counts = engine.feedback_stats();
+        warp::reply::json(&StatsResponse {
+            precision: counts.precision(),
+            recall: counts.recall(),
+            counts,
+            threshold: engine.threshold(),
+        })
+    });
+    submit.or(stats)
+}
@@ -0,0 +1,94 @@
+use crate::ai::engine::{AIEngine, Rule};
+use crate::runtime::scheduler::Scheduler;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
+
+// Bump whenever the snapshot layout changes incompatibly
+pub const MODEL_FORMAT_VERSION: u32 = 1;
+
+// Everything the engine has learned, written to disk between restarts
+#[derive(Serialize, Deserialize)]
+pub struct ModelSnapshot {
+    pub format_version: u32,
+    pub model_state: Vec<u8>,
+    pub rules: Vec<Rule>,
+    pub threshold: f32,
+    pub decisions: u64,
+}
+
+impl ModelSnapshot {
+    pub fn new(model_state: Vec<u8>, rules: Vec<Rule>, threshold: f32, decisions: u64) -> Self {
+        ModelSnapshot { format_version: MODEL_FORMAT_VERSION, model_state, rules, threshold, decisions }
+    }
+
+    pub fn check_version(&self) -> Result<(), String> {
+        if self.format_version != MODEL_FORMAT_VERSION {
+            return Err(format!(
+                "model snapshot format {} is incompatible with {}, starting from defaults",
+                self.format_version, MODEL_FORMAT_VERSION
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Write atomically so a crash mid-save never corrupts the last good checkpoint
+pub fn save(engine: &AIEngine, path: &Path) -> Result<(), String> {
+    let bytes = serde_json::to_vec(&engine.snapshot()).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+// Restore the engine if a checkpoint exists, returns whether it was warm-started
+pub fn load(engine: &AIEngine, path: &Path) -> Result<bool, String> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.to_string()),
+    };
+    let snapshot: ModelSnapshot = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+    engine.restore(snapshot)?;
+    Ok(true)
+}
+
+// Register the periodic checkpoint job, the final save happens on graceful shutdown
+pub fn register_checkpoint(scheduler: &Scheduler, engine: &AIEngine, path: PathBuf, interval: Duration) {
+    let engine = engine.clone();
+    scheduler.register(
+        "model-checkpoint",
+        interval,
+        Duration::ZERO,
+        Arc::new(move || {
+            let engine = engine.clone();
+            let path = path.clone();
+            Box::pin(async move {
+                if let Err(e) = save(&engine, &path) {
+                    error!(error = %e, "model checkpoint failed");
+                }
+            })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly_model::LinfaModel;
+
+    #[test]
+    fn restores_what_was_saved() {
+        let path = std::env::temp_dir().join(format!("model-{}.json", std::process::id()));
+        assert!(!load(&AIEngine::new(Box::new(LinfaModel::new(10)), 0.8), &path).unwrap());
+
+        save(&AIEngine::new(Box::new(LinfaModel::new(10)), 0.42), &path).unwrap();
+        let restored = AIEngine::new(Box::new(LinfaModel::new(10)), 0.8);
+        assert!(load(&restored, &path).unwrap());
+        assert_eq!(restored.snapshot().threshold, 0.42);
+        fs::remove_file(path).unwrap();
+    }
+}
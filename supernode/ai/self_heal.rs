@@ -0,0 +1,84 @@
+use crate::ai::engine::{AIEngine, Source};
+use crate::events::bus::{Event, EventBus};
+use crate::runtime::scheduler::Scheduler;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, instrument};
+
+// Per-module switch
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ModuleToggle {
+    pub enabled: bool,
+}
+
+impl Default for ModuleToggle {
+    fn default() -> Self {
+        ModuleToggle { enabled: true }
+    }
+}
+
+// `self_heal` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SelfHealConfig {
+    // How often each module checks whether to evolve
+    pub interval_secs: u64,
+
+    // Evolve once this many threats have been logged
+    pub log_threshold: usize,
+
+    pub crypto: ModuleToggle,
+    pub api: ModuleToggle,
+    pub converter: ModuleToggle,
+}
+
+impl Default for SelfHealConfig {
+    fn default() -> Self {
+        SelfHealConfig {
+            interval_secs: 3600,
+            log_threshold: 50,
+            crypto: ModuleToggle::default(),
+            api: ModuleToggle::default(),
+            converter: ModuleToggle::default(),
+        }
+    }
+}
+
+// One self-heal pass for a module
+#[instrument(skip(engine, bus))]
+pub fn heal_once(engine: &AIEngine, bus: &EventBus, source: Source, log_threshold: usize) {
+    if engine.threat_log_len(source) >= log_threshold {
+        if let Some(rule) = engine.evolve(source) {
+            info!(?source, rule = %rule.name, "self-healed");
+            bus.publish(Event::SelfHealTriggered { source: source.label().to_string(), rule: rule.name });
+        }
+    }
+}
+
+// Register the enabled crypto, API and converter self-heal jobs with the scheduler
+pub fn register(scheduler: &Scheduler, engine: &AIEngine, bus: &EventBus, config: &SelfHealConfig) {
+    for (name, source, toggle) in [
+        ("self-heal:crypto", Source::Crypto, &config.crypto),
+        ("self-heal:api", Source::Api, &config.api),
+        ("self-heal:converter", Source::Converter, &config.converter),
+    ] {
+        if !toggle.enabled {
+            continue;
+        }
+        let engine = engine.clone();
+        let bus = bus.clone();
+        let log_threshold = config.log_threshold;
+        scheduler.register(
+            name,
+            Duration::from_secs(config.interval_secs),
+            Duration::from_secs(60),
+            Arc::new(move || {
+                let engine = engine.clone();
+                let bus = bus.clone();
+                Box::pin(async move { heal_once(&engine, &bus, source, log_threshold) })
+            }),
+        );
+    }
+}
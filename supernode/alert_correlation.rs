@@ -0,0 +1,337 @@
+use crate::alerting::{Alert, AlertChannel, Alerter, Severity};
+use crate::api::auth::Auth;
+use crate::runtime::scheduler::Scheduler;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+use warp::{Filter, Rejection, Reply};
+
+// Resolved incidents kept for the admin API
+const RESOLVED_KEPT: usize = 100;
+
+// `alert_correlation` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CorrelationConfig {
+    // An alert joins an open incident when it arrives this soon after the incident's latest event
+    pub window_secs: u64,
+
+    // Incidents with no new event for this long are resolved
+    pub resolve_after_secs: u64,
+
+    // Child events kept per incident; later ones are only counted
+    pub max_children: usize,
+    pub components: Vec<ComponentConfig>,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        CorrelationConfig { window_secs: 120, resolve_after_secs: 600, max_children: 500, components: Vec::new() }
+    }
+}
+
+// Alert kinds a component raises and the components it needs to work
+#[derive(Clone, Debug, Deserialize)]
+pub struct ComponentConfig {
+    pub name: String,
+    #[serde(default)]
+    pub kinds: Vec<String>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+// Who hears about an incident, notified once when the incident first reaches `min_severity`
+pub struct EscalationPolicy {
+    pub name: String,
+    pub min_severity: Severity,
+    pub channel: Arc<dyn AlertChannel>,
+}
+
+// One policy per severity some channel of `notify` starts at, so each channel hears of an incident once it is severe
+// enough for it, and every channel again each time the incident escalates past another channel's threshold
+pub fn escalation_policies(notify: &Alerter) -> Vec<EscalationPolicy> {
+    let channel: Arc<dyn AlertChannel> = Arc::new(notify.clone());
+    notify
+        .min_severities()
+        .into_iter()
+        .map(|min_severity| EscalationPolicy { name: format!("{:?}", min_severity).to_lowercase(), min_severity, channel: channel.clone() })
+        .collect()
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Incident {
+    pub id: u64,
+    pub opened_at: DateTime<Utc>,
+    pub last_at: DateTime<Utc>,
+    pub severity: Severity,
+
+    // Most upstream failing component, i.e. none of its dependencies are failing too
+    pub root_cause: String,
+
+    // Components involved and when each first alerted
+    pub components: BTreeMap<String, DateTime<Utc>>,
+    pub children: Vec<Alert>,
+    pub dropped_children: u64,
+    pub notified: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl Incident {
+    fn events(&self) -> u64 {
+        self.children.len() as u64 + self.dropped_children
+    }
+
+    // One alert standing in for the whole incident; keeps the root cause's kind so runbooks still match
+    fn summary(&self, root: Option<&Alert>) -> Alert {
+        let components: Vec<&str> = self.components.keys().map(String::as_str).collect();
+        Alert {
+            kind: root.map_or_else(|| self.root_cause.clone(), |a| a.kind.clone()),
+            severity: self.severity,
+            message: format!(
+                "incident #{}: {} alerts from {} since {}; likely root cause: {}{}",
+                self.id,
+                self.events(),
+                components.join(", "),
+                self.opened_at.to_rfc3339(),
+                self.root_cause,
+                root.map_or_else(String::new, |a| format!(" ({})", a.message)),
+            ),
+            at: self.last_at,
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    open: Vec<Incident>,
+    resolved: VecDeque<Incident>,
+}
+
+// Alert channel that folds related alerts into incidents and forwards one notification per policy
+#[derive(Clone)]
+pub struct Correlator {
+    config: CorrelationConfig,
+    component_of: Arc<HashMap<String, String>>,
+
+    // Transitive dependencies of each component
+    upstream: Arc<HashMap<String, BTreeSet<String>>>,
+    policies: Arc<Vec<EscalationPolicy>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Correlator {
+    pub fn new(config: CorrelationConfig, policies: Vec<EscalationPolicy>) -> Self {
+        let component_of = config.components.iter().flat_map(|c| c.kinds.iter().map(|k| (k.clone(), c.name.clone()))).collect();
+        let direct: HashMap<&str, &[String]> = config.components.iter().map(|c| (c.name.as_str(), c.depends_on.as_slice())).collect();
+        let upstream = config
+            .components
+            .iter()
+            .map(|c| {
+                let mut seen = BTreeSet::new();
+                let mut stack: Vec<&String> = c.depends_on.iter().collect();
+                while let Some(dep) = stack.pop() {
+                    if seen.insert(dep.clone()) {
+                        stack.extend(direct.get(dep.as_str()).copied().unwrap_or_default());
+                    }
+                }
+                (c.name.clone(), seen)
+            })
+            .collect();
+        Correlator {
+            config,
+            component_of: Arc::new(component_of),
+            upstream: Arc::new(upstream),
+            policies: Arc::new(policies),
+            state: Arc::default(),
+        }
+    }
+
+    // Alerts of kinds no component claims are only correlated with the same kind
+    fn component(&self, alert: &Alert) -> String {
+        self.component_of.get(&alert.kind).cloned().unwrap_or_else(|| alert.kind.clone())
+    }
+
+    fn depends(&self, component: &str, on: &str) -> bool {
+        self.upstream.get(component).is_some_and(|deps| deps.contains(on))
+    }
+
+    fn related(&self, a: &str, b: &str) -> bool {
+        a == b || self.depends(a, b) || self.depends(b, a)
+    }
+
+    fn root_cause(&self, components: &BTreeMap<String, DateTime<Utc>>) -> String {
+        components
+            .iter()
+            .filter(|(name, _)| !components.keys().any(|other| self.depends(name, other)))
+            .min_by_key(|(_, first)| **first)
+            .map(|(name, _)| name.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn receive(&self, alert: Alert) {
+        let component = self.component(&alert);
+        let window = ChronoDuration::seconds(self.config.window_secs as i64);
+        let mut notify = Vec::new();
+        let summary = {
+            let mut state = self.state.lock().unwrap();
+            let joined = state
+                .open
+                .iter()
+                .position(|i| alert.at - i.last_at <= window && i.components.keys().any(|c| self.related(c, &component)));
+            let index = match joined {
+                Some(index) => index,
+                None => {
+                    state.next_id += 1;
+                    let id = state.next_id;
+                    state.open.push(Incident {
+                        id,
+                        opened_at: alert.at,
+                        last_at: alert.at,
+                        severity: alert.severity,
+                        root_cause: component.clone(),
+                        components: BTreeMap::new(),
+                        children: Vec::new(),
+                        dropped_children: 0,
+                        notified: Vec::new(),
+                        resolved_at: None,
+                    });
+                    info!(id, %component, "incident opened");
+                    state.open.len() - 1
+                }
+            };
+            let incident = &mut state.open[index];
+            incident.last_at = incident.last_at.max(alert.at);
+            incident.severity = incident.severity.max(alert.severity);
+            incident.components.entry(component).or_insert(alert.at);
+            incident.root_cause = self.root_cause(&incident.components);
+            if incident.children.len() < self.config.max_children {
+                incident.children.push(alert);
+            } else {
+                incident.dropped_children += 1;
+            }
+            for policy in self.policies.iter() {
+                if incident.severity >= policy.min_severity && !incident.notified.contains(&policy.name) {
+                    incident.notified.push(policy.name.clone());
+                    notify.push(policy.channel.clone());
+                }
+            }
+            if notify.is_empty() {
+                return;
+            }
+            let root = incident.children.iter().find(|a| self.component(a) == incident.root_cause);
+            incident.summary(root)
+        };
+        for channel in notify {
+            channel.send(std::slice::from_ref(&summary));
+        }
+    }
+
+    // Resolve incidents that have been quiet for `resolve_after_secs`
+    pub fn sweep(&self, now: DateTime<Utc>) {
+        let quiet = ChronoDuration::seconds(self.config.resolve_after_secs as i64);
+        let mut state = self.state.lock().unwrap();
+        let (done, open): (Vec<_>, Vec<_>) = std::mem::take(&mut state.open).into_iter().partition(|i| now - i.last_at >= quiet);
+        state.open = open;
+        for mut incident in done {
+            info!(id = incident.id, events = incident.events(), root_cause = %incident.root_cause, "incident resolved");
+            incident.resolved_at = Some(now);
+            state.resolved.push_front(incident);
+        }
+        state.resolved.truncate(RESOLVED_KEPT);
+    }
+
+    // Open incidents first, then the most recently resolved
+    pub fn incidents(&self) -> Vec<Incident> {
+        let state = self.state.lock().unwrap();
+        state.open.iter().rev().chain(state.resolved.iter()).cloned().collect()
+    }
+
+    // GET /admin/incidents
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let correlator = self.clone();
+        warp::path!("admin" / "incidents")
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |_| warp::reply::json(&correlator.incidents()))
+    }
+}
+
+impl AlertChannel for Correlator {
+    fn name(&self) -> &str {
+        "correlation"
+    }
+
+    fn send(&self, alerts: &[Alert]) {
+        for alert in alerts {
+            self.receive(alert.clone());
+        }
+    }
+}
+
+pub fn register(scheduler: &Scheduler, correlator: Correlator) {
+    scheduler.register(
+        "alert_correlation:sweep",
+        Duration::from_secs(correlator.config.window_secs.max(1)),
+        Duration::ZERO,
+        Arc::new(move || {
+            let correlator = correlator.clone();
+            Box::pin(async move { correlator.sweep(Utc::now()) })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::{Delivery, Route};
+
+    #[derive(Default)]
+    struct Capture(Mutex<Vec<Alert>>);
+
+    impl AlertChannel for Capture {
+        fn name(&self) -> &str {
+            "capture"
+        }
+
+        fn send(&self, alerts: &[Alert]) {
+            self.0.lock().unwrap().extend_from_slice(alerts);
+        }
+    }
+
+    fn alert(kind: &str, severity: Severity, second: i64) -> Alert {
+        Alert { kind: kind.to_string(), severity, message: kind.to_string(), at: DateTime::<Utc>::UNIX_EPOCH + ChronoDuration::seconds(second) }
+    }
+
+    #[test]
+    fn a_flood_reaches_each_channel_once_per_escalation() {
+        let config: CorrelationConfig = serde_yaml::from_str(
+            "components:\n  - { name: storage, kinds: [storage_unavailable] }\n  - { name: api, kinds: [api_error_rate], depends_on: [storage] }\n",
+        )
+        .unwrap();
+        let (pager, chat) = (Arc::new(Capture::default()), Arc::new(Capture::default()));
+        let route = |channel: Arc<Capture>, min_severity| Route { channel, min_severity, delivery: Delivery::Immediate, quiet_hours: None };
+        let notify = Alerter::new(vec![route(chat.clone(), Severity::Warning), route(pager.clone(), Severity::Critical)]);
+        let correlator = Correlator::new(config, escalation_policies(&notify));
+        let alerter = Alerter::new(vec![Route { channel: Arc::new(correlator.clone()), min_severity: Severity::Info, delivery: Delivery::Immediate, quiet_hours: None }]);
+
+        for second in 0..50 {
+            alerter.raise(alert("api_error_rate", Severity::Warning, second));
+        }
+        alerter.raise(alert("storage_unavailable", Severity::Warning, 50));
+        assert_eq!(chat.0.lock().unwrap().len(), 1);
+        assert!(pager.0.lock().unwrap().is_empty());
+
+        alerter.raise(alert("storage_unavailable", Severity::Critical, 60));
+        let paged = pager.0.lock().unwrap().clone();
+        assert_eq!(paged.len(), 1);
+        assert_eq!(paged[0].kind, "storage_unavailable");
+        assert!(paged[0].message.contains("52 alerts") && paged[0].message.contains("root cause: storage"), "{}", paged[0].message);
+        assert_eq!(chat.0.lock().unwrap().len(), 2);
+        assert_eq!(correlator.incidents().len(), 1);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::runtime::tasks::run_every;
+use crate::webhooks::sign;
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Alert {
+    pub kind: String,
+    pub severity: Severity,
+    pub message: String,
+    pub at: DateTime<Utc>,
+}
+
+// Destination for alerts (pager, chat, webhook, ...)
+pub trait AlertChannel: Send + Sync {
+    fn name(&self) -> &str;
+    fn send(&self, alerts: &[Alert]);
+}
+
+// How a channel receives alerts
+#[derive(Clone)]
+pub enum Delivery {
+    Immediate,
+
+    // Batch non-critical alerts and send them once per period
+    Digest(Duration),
+}
+
+// Window during which only critical alerts get through, may wrap midnight
+#[derive(Clone, Debug, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, t: NaiveTime) -> bool {
+        if self.start <= self.end {
+            t >= self.start && t < self.end
+        } else {
+            t >= self.start || t < self.end
+        }
+    }
+}
+
+// `alerting` section of the node config
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AlertingConfig {
+    pub channels: Vec<ChannelConfig>,
+}
+
+// One destination and how it is routed to
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChannelConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub sink: SinkConfig,
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+
+    // Batch non-critical alerts and send them this often; immediate when unset
+    #[serde(default)]
+    pub digest_secs: Option<u64>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Warning
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkConfig {
+    // The node's own log, at a level matching the severity
+    Log,
+
+    // POST a JSON array of alerts, signed like event webhooks when `secret` is set
+    Webhook {
+        url: String,
+        #[serde(default)]
+        secret: Option<String>,
+    },
+}
+
+impl AlertingConfig {
+    pub fn routes(&self) -> Result<Vec<Route>, String> {
+        self.channels
+            .iter()
+            .map(|c| {
+                let channel: Arc<dyn AlertChannel> = match &c.sink {
+                    SinkConfig::Log => Arc::new(LogChannel { name: c.name.clone() }),
+                    SinkConfig::Webhook { url, secret } => {
+                        reqwest::Url::parse(url).map_err(|e| format!("alerting channel {}: {}: {}", c.name, url, e))?;
+                        Arc::new(WebhookChannel::new(&c.name, url, secret.clone()))
+                    }
+                };
+                let delivery = match c.digest_secs {
+                    Some(0) => return Err(format!("alerting channel {}: digest_secs must be positive", c.name)),
+                    Some(secs) => Delivery::Digest(Duration::from_secs(secs)),
+                    None => Delivery::Immediate,
+                };
+                Ok(Route { channel, min_severity: c.min_severity, delivery, quiet_hours: c.quiet_hours.clone() })
+            })
+            .collect()
+    }
+}
+
+pub struct LogChannel {
+    name: String,
+}
+
+impl AlertChannel for LogChannel {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send(&self, alerts: &[Alert]) {
+        for alert in alerts {
+            match alert.severity {
+                Severity::Critical => error!(channel = %self.name, kind = %alert.kind, at = %alert.at, "{}", alert.message),
+                Severity::Warning => warn!(channel = %self.name, kind = %alert.kind, at = %alert.at, "{}", alert.message),
+                Severity::Info => info!(channel = %self.name, kind = %alert.kind, at = %alert.at, "{}", alert.message),
+            }
+        }
+    }
+}
+
+pub struct WebhookChannel {
+    name: String,
+    url: String,
+    secret: Option<String>,
+    client: reqwest::Client,
+}
+
+impl WebhookChannel {
+    pub fn new(name: &str, url: &str, secret: Option<String>) -> Self {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default();
+        WebhookChannel { name: name.to_string(), url: url.to_string(), secret, client }
+    }
+}
+
+impl AlertChannel for WebhookChannel {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Delivered in the background, so raising an alert never waits on the receiver
+    fn send(&self, alerts: &[Alert]) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!(channel = %self.name, alerts = alerts.len(), "alerts dropped, no runtime to deliver them on");
+            return;
+        };
+        let body = match serde_json::to_string(alerts) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(channel = %self.name, error = %e, "alerts not serializable");
+                return;
+            }
+        };
+        let mut request = self.client.post(&self.url).header("content-type", "application/json");
+        if let Some(secret) = &self.secret {
+            let timestamp = Utc::now().timestamp();
+            request = request.header("x-pi-timestamp", timestamp.to_string()).header("x-pi-signature", sign(secret, timestamp, body.as_bytes()));
+        }
+        let name = self.name.clone();
+        runtime.spawn(async move {
+            if let Err(e) = request.body(body).send().await.and_then(|r| r.error_for_status()) {
+                warn!(channel = %name, error = %e, "alert delivery failed");
+            }
+        });
+    }
+}
+
+pub struct Route {
+    pub channel: Arc<dyn AlertChannel>,
+    pub min_severity: Severity,
+    pub delivery: Delivery,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+#[derive(Clone)]
+pub struct Alerter {
+    routes: Arc<Vec<Route>>,
+
+    // Pending digest alerts per channel
+    pending: Arc<Mutex<HashMap<String, Vec<Alert>>>>,
+}
+
+impl Alerter {
+    pub fn new(routes: Vec<Route>) -> Self {
+        Alerter { routes: Arc::new(routes), pending: Arc::default() }
+    }
+
+    // Lowest severity each route accepts, lowest first and without repeats
+    pub fn min_severities(&self) -> Vec<Severity> {
+        let mut severities: Vec<Severity> = self.routes.iter().map(|r| r.min_severity).collect();
+        severities.sort();
+        severities.dedup();
+        severities
+    }
+
+    // Route an alert by severity, deferring it when digesting or in quiet hours
+    pub fn raise(&self, alert: Alert) {
+        for route in self.routes.iter() {
+            if alert.severity < route.min_severity {
+                continue;
+            }
+            let critical = alert.severity == Severity::Critical;
+            let quiet = route.quiet_hours.as_ref().is_some_and(|q| q.contains(alert.at.time()));
+            let digest = matches!(route.delivery, Delivery::Digest(_));
+            if critical || (!quiet && !digest) {
+                route.channel.send(std::slice::from_ref(&alert));
+            } else {
+                self.pending.lock().unwrap().entry(route.channel.name().to_string()).or_default().push(alert.clone());
+            }
+        }
+    }
+
+    // Send accumulated digests for every channel that is outside quiet hours
+    pub fn flush(&self, now: DateTime<Utc>) {
+        let mut pending = self.pending.lock().unwrap();
+        for route in self.routes.iter() {
+            if route.quiet_hours.as_ref().is_some_and(|q| q.contains(now.time())) {
+                continue;
+            }
+            if let Some(alerts) = pending.remove(route.channel.name()) {
+                if !alerts.is_empty() {
+                    route.channel.send(&alerts);
+                }
+            }
+        }
+    }
+
+    // Flush digests at the shortest configured digest period until cancelled
+    pub async fn digest_loop(self, token: CancellationToken) {
+        let period = self
+            .routes
+            .iter()
+            .filter_map(|r| match r.delivery {
+                Delivery::Digest(p) => Some(p),
+                Delivery::Immediate => None,
+            })
+            .min()
+            .unwrap_or(Duration::from_secs(3600));
+        run_every(token, period, || async { self.flush(Utc::now()) }).await;
+        self.flush(Utc::now());
+    }
+}
+
+// So a correlator can hand its incident summaries on to the configured routes
+impl AlertChannel for Alerter {
+    fn name(&self) -> &str {
+        "alerting"
+    }
+
+    fn send(&self, alerts: &[Alert]) {
+        for alert in alerts {
+            self.raise(alert.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::tests::spawn;
+    use tokio::sync::mpsc;
+    use warp::Filter;
+
+    fn alert(kind: &str, severity: Severity, at: &str) -> Alert {
+        Alert { kind: kind.to_string(), severity, message: kind.to_string(), at: DateTime::parse_from_rfc3339(at).unwrap().with_timezone(&Utc) }
+    }
+
+    #[tokio::test]
+    async fn webhook_channel_gets_critical_alerts_now_and_the_rest_as_a_digest() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let receiver = warp::path!("pager").and(warp::header::<String>("x-pi-signature")).and(warp::body::json()).map(move |_: String, alerts: Vec<serde_json::Value>| {
+            let _ = tx.send(alerts.iter().map(|a| a["kind"].as_str().unwrap_or_default().to_string()).collect::<Vec<_>>());
+            warp::reply()
+        });
+        let (addr, shutdown) = spawn(receiver).await;
+
+        let config: AlertingConfig = serde_yaml::from_str(&format!(
+            "channels:\n  - name: oncall\n    kind: webhook\n    url: http://{}/pager\n    secret: s3cret\n    min_severity: warning\n    \
+             digest_secs: 3600\n    quiet_hours: {{ start: \"22:00:00\", end: \"07:00:00\" }}\n",
+            addr
+        ))
+        .unwrap();
+        let alerter = Alerter::new(config.routes().unwrap());
+
+        alerter.raise(alert("disk_low", Severity::Info, "2026-01-01T12:00:00Z"));
+        alerter.raise(alert("oracle_stale", Severity::Warning, "2026-01-01T23:00:00Z"));
+        alerter.raise(alert("key_compromised", Severity::Critical, "2026-01-01T23:05:00Z"));
+        let received = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(received, ["key_compromised"]);
+
+        // Held through quiet hours, then sent as one digest
+        alerter.flush(DateTime::parse_from_rfc3339("2026-01-02T03:00:00Z").unwrap().with_timezone(&Utc));
+        assert!(tokio::time::timeout(Duration::from_millis(200), rx.recv()).await.is_err());
+        alerter.flush(DateTime::parse_from_rfc3339("2026-01-02T08:00:00Z").unwrap().with_timezone(&Utc));
+        let received = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(received, ["oracle_stale"]);
+        shutdown.cancel();
+    }
+
+    #[test]
+    fn rejects_unusable_channels() {
+        let config: AlertingConfig = serde_yaml::from_str("channels:\n  - { name: log, kind: log, digest_secs: 0 }\n").unwrap();
+        assert!(config.routes().err().unwrap().contains("digest_secs"));
+        let config: AlertingConfig = serde_yaml::from_str("channels:\n  - { name: hook, kind: webhook, url: not a url }\n").unwrap();
+        assert!(config.routes().err().unwrap().contains("hook"));
+    }
+}
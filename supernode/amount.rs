@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmountError {
+    AssetMismatch { left: String, right: String },
+    Overflow,
+    Underflow,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmountError::AssetMismatch { left, right } => write!(f, "cannot combine {} with {}", left, right),
+            AmountError::Overflow => write!(f, "amount overflow"),
+            AmountError::Underflow => write!(f, "amount would go negative"),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+// How a result that falls between two smallest units is settled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    // Toward zero; never credits more than the exact result
+    #[default]
+    Down,
+
+    // Away from zero; never charges less than the exact result
+    Up,
+
+    // To the nearest unit, ties to the even one
+    HalfEven,
+}
+
+impl Rounding {
+    // `value * numerator / denominator` in integers; `None` on overflow or a zero denominator
+    pub fn mul_div(self, value: u128, numerator: u128, denominator: u128) -> Option<u128> {
+        if denominator == 0 {
+            return None;
+        }
+        let scaled = value.checked_mul(numerator)?;
+        let (quotient, remainder) = (scaled / denominator, scaled % denominator);
+        let up = match self {
+            Rounding::Down => false,
+            Rounding::Up => remainder > 0,
+            Rounding::HalfEven => match remainder.cmp(&(denominator - remainder)) {
+                Ordering::Less => false,
+                Ordering::Greater => true,
+                Ordering::Equal => quotient % 2 == 1,
+            },
+        };
+        if up {
+            quotient.checked_add(1)
+        } else {
+            Some(quotient)
+        }
+    }
+}
+
+// Smallest units as a decimal string, e.g. 1234567 at 6 decimals is "1.234567"
+pub fn format_units(units: u128, decimals: u8) -> String {
+    match 10u128.checked_pow(decimals as u32) {
+        Some(scale) if decimals > 0 => format!("{}.{:0width$}", units / scale, units % scale, width = decimals as usize),
+        _ => units.to_string(),
+    }
+}
+
+// Amount whose asset is only known at runtime (request bodies, stored balances); mixing assets is an error
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AnyAmount {
+    pub asset: String,
+
+    // Smallest units, serialized as a string so JavaScript clients keep precision
+    #[serde(with = "units_string")]
+    pub units: u128,
+}
+
+impl AnyAmount {
+    pub fn new(asset: impl Into<String>, units: u128) -> Self {
+        AnyAmount { asset: asset.into(), units }
+    }
+
+    fn same_asset(&self, other: &AnyAmount) -> Result<(), AmountError> {
+        if self.asset != other.asset {
+            return Err(AmountError::AssetMismatch { left: self.asset.clone(), right: other.asset.clone() });
+        }
+        Ok(())
+    }
+
+    pub fn checked_add(&self, other: &AnyAmount) -> Result<AnyAmount, AmountError> {
+        self.same_asset(other)?;
+        let units = self.units.checked_add(other.units).ok_or(AmountError::Overflow)?;
+        Ok(AnyAmount { asset: self.asset.clone(), units })
+    }
+
+    pub fn checked_sub(&self, other: &AnyAmount) -> Result<AnyAmount, AmountError> {
+        self.same_asset(other)?;
+        let units = self.units.checked_sub(other.units).ok_or(AmountError::Underflow)?;
+        Ok(AnyAmount { asset: self.asset.clone(), units })
+    }
+
+    // No `PartialOrd`: comparing different assets has no answer
+    pub fn checked_cmp(&self, other: &AnyAmount) -> Result<Ordering, AmountError> {
+        self.same_asset(other)?;
+        Ok(self.units.cmp(&other.units))
+    }
+}
+
+impl fmt::Display for AnyAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.units, self.asset)
+    }
+}
+
+pub(crate) mod units_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(units: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&units.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rounding;
+
+    #[test]
+    fn mul_div_rounds_each_way() {
+        // 10 * 2 / 3 = 6.67
+        assert_eq!(Rounding::Down.mul_div(10, 2, 3), Some(6));
+        assert_eq!(Rounding::Up.mul_div(10, 2, 3), Some(7));
+        assert_eq!(Rounding::HalfEven.mul_div(10, 2, 3), Some(7));
+
+        // 10 / 3 = 3.33
+        assert_eq!(Rounding::Up.mul_div(10, 1, 3), Some(4));
+        assert_eq!(Rounding::HalfEven.mul_div(10, 1, 3), Some(3));
+    }
+
+    #[test]
+    fn mul_div_is_exact_without_remainder() {
+        for rounding in [Rounding::Down, Rounding::Up, Rounding::HalfEven] {
+            assert_eq!(rounding.mul_div(1_000_000, 30, 10_000), Some(3_000));
+        }
+    }
+
+    #[test]
+    fn half_even_breaks_ties_to_the_even_unit() {
+        assert_eq!(Rounding::HalfEven.mul_div(5, 1, 2), Some(2));
+        assert_eq!(Rounding::HalfEven.mul_div(7, 1, 2), Some(4));
+        assert_eq!(Rounding::HalfEven.mul_div(9, 1, 2), Some(4));
+    }
+
+    #[test]
+    fn mul_div_refuses_overflow_and_zero_denominator() {
+        assert_eq!(Rounding::Down.mul_div(1, 1, 0), None);
+        assert_eq!(Rounding::Down.mul_div(u128::MAX, 2, 2), None);
+        assert_eq!(Rounding::Up.mul_div(u128::MAX, 1, 1), Some(u128::MAX));
+    }
+}
@@ -0,0 +1,217 @@
+use linfa::prelude::*;
+use linfa_logistic::{FittedLogisticRegression, LogisticRegression};
+use ndarray::{Array1, Array2};
+use serde::Deserialize;
+use std::path::PathBuf;
+#[cfg(feature = "onnx")]
+use ort::{GraphOptimizationLevel, Session};
+
+// Feature vector extracted from a request
+pub struct Features {
+    pub values: Vec<f32>,
+}
+
+// Labeled example supplied by an operator or the pipeline
+pub struct Feedback {
+    pub features: Features,
+    pub anomalous: bool,
+}
+
+// Common interface for every anomaly scoring backend
+pub trait AnomalyModel: Send + Sync {
+    // Backend name shown to operators
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    // Score in [0, 1], higher means more anomalous; a backend that cannot score says so rather than passing the request
+    fn score(&self, features: &Features) -> Result<f32, String>;
+
+    // Incorporate a labeled example
+    fn update(&mut self, feedback: &Feedback);
+
+    // Serialized learned state for checkpoints, empty when the backend has none
+    fn export_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn import_state(&mut self, _state: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// Backend selection from the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ModelBackend {
+    Linfa { refit_every: usize },
+    Onnx { model_path: String },
+}
+
+// `model` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ModelConfig {
+    pub backend: ModelBackend,
+
+    // Score above which a request is treated as anomalous until an operator tunes it through /admin/ai
+    pub threshold: f32,
+
+    // What the engine learned is restored from here at startup, and written every checkpoint_secs and on shutdown
+    pub checkpoint_path: PathBuf,
+    pub checkpoint_secs: u64,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        ModelConfig {
+            backend: ModelBackend::Linfa { refit_every: 50 },
+            threshold: 0.8,
+            checkpoint_path: PathBuf::from("data/model.json"),
+            checkpoint_secs: 300,
+        }
+    }
+}
+
+impl ModelConfig {
+    // The configured backend and the threshold it starts at
+    pub fn build(&self) -> Result<(Box<dyn AnomalyModel>, f32), String> {
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err(format!("model.threshold {} is outside [0, 1]", self.threshold));
+        }
+        Ok((build_model(&self.backend)?, self.threshold))
+    }
+}
+
+pub fn build_model(backend: &ModelBackend) -> Result<Box<dyn AnomalyModel>, String> {
+    match backend {
+        ModelBackend::Linfa { refit_every } => Ok(Box::new(LinfaModel::new(*refit_every))),
+        #[cfg(feature = "onnx")]
+        ModelBackend::Onnx { model_path } => Ok(Box::new(OnnxModel::load(model_path)?)),
+        #[cfg(not(feature = "onnx"))]
+        ModelBackend::Onnx { model_path } => Err(format!("cannot load {}: built without the onnx feature", model_path)),
+    }
+}
+
+// Logistic regression trained in-process with linfa
+pub struct LinfaModel {
+    fitted: Option<FittedLogisticRegression<f64, bool>>,
+    examples: Vec<(Vec<f64>, bool)>,
+    refit_every: usize,
+}
+
+impl LinfaModel {
+    pub fn new(refit_every: usize) -> Self {
+        LinfaModel { fitted: None, examples: Vec::new(), refit_every: refit_every.max(1) }
+    }
+
+    // Refit on all collected examples, needs both classes present
+    fn refit(&mut self) {
+        let has_both = self.examples.iter().any(|(_, l)| *l) && self.examples.iter().any(|(_, l)| !*l);
+        if !has_both {
+            return;
+        }
+        let width = self.examples[0].0.len();
+        let rows: Vec<f64> = self.examples.iter().flat_map(|(x, _)| x.iter().copied()).collect();
+        let records = match Array2::from_shape_vec((self.examples.len(), width), rows) {
+            Ok(records) => records,
+            Err(_) => return,
+        };
+        let targets = Array1::from_iter(self.examples.iter().map(|(_, l)| *l));
+        let dataset = Dataset::new(records, targets);
+        if let Ok(fitted) = LogisticRegression::default().max_iterations(100).fit(&dataset) {
+            self.fitted = Some(fitted);
+        }
+    }
+}
+
+impl AnomalyModel for LinfaModel {
+    fn name(&self) -> &'static str {
+        "linfa_logistic_regression"
+    }
+
+    // Nothing is anomalous until both classes have been labeled
+    fn score(&self, features: &Features) -> Result<f32, String> {
+        let fitted = match &self.fitted {
+            Some(fitted) => fitted,
+            None => return Ok(0.0),
+        };
+        let row: Vec<f64> = features.values.iter().map(|v| *v as f64).collect();
+        if row.len() != fitted.params().len() {
+            return Err(format!("model expects {} features, got {}", fitted.params().len(), row.len()));
+        }
+        let x = Array2::from_shape_vec((1, row.len()), row).map_err(|e| e.to_string())?;
+        Ok(fitted.predict_probabilities(&x)[0] as f32)
+    }
+
+    fn update(&mut self, feedback: &Feedback) {
+        let row = feedback.features.values.iter().map(|v| *v as f64).collect();
+        self.examples.push((row, feedback.anomalous));
+        if self.examples.len().is_multiple_of(self.refit_every) {
+            self.refit();
+        }
+    }
+
+    // The training set is the state, the regression is refit on import
+    fn export_state(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.examples).unwrap_or_default()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<(), String> {
+        self.examples = serde_json::from_slice(state).map_err(|e| e.to_string())?;
+        self.refit();
+        Ok(())
+    }
+}
+
+// Externally trained model served through ONNX Runtime
+#[cfg(feature = "onnx")]
+pub struct OnnxModel {
+    session: Session,
+}
+
+#[cfg(feature = "onnx")]
+impl OnnxModel {
+    pub fn load(model_path: &str) -> Result<Self, String> {
+        let session = Session::builder()
+            .and_then(|b| b.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|b| b.commit_from_file(model_path))
+            .map_err(|e| format!("failed to load ONNX model {}: {}", model_path, e))?;
+        Ok(OnnxModel { session })
+    }
+}
+
+#[cfg(feature = "onnx")]
+impl AnomalyModel for OnnxModel {
+    fn name(&self) -> &'static str {
+        "onnx"
+    }
+
+    fn score(&self, features: &Features) -> Result<f32, String> {
+        let input = Array2::from_shape_vec((1, features.values.len()), features.values.clone()).map_err(|e| e.to_string())?;
+        let outputs = ort::inputs![input].and_then(|inputs| self.session.run(inputs)).map_err(|e| format!("ONNX inference failed: {}", e))?;
+        let scores = outputs[0].try_extract_tensor::<f32>().map_err(|e| format!("ONNX output is not a score: {}", e))?;
+        scores.iter().next().copied().ok_or_else(|| "ONNX model returned no score".to_string())
+    }
+
+    // Externally trained models are read-only, retraining happens offline
+    fn update(&mut self, _feedback: &Feedback) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_the_configured_backend() {
+        let config: ModelConfig = serde_yaml::from_str("backend: { kind: linfa, refit_every: 10 }\nthreshold: 0.6").unwrap();
+        let (model, threshold) = config.build().unwrap();
+        assert_eq!((model.name(), threshold), ("linfa_logistic_regression", 0.6));
+
+        let config = ModelConfig { threshold: 1.5, ..ModelConfig::default() };
+        assert!(config.build().err().unwrap().contains("threshold"));
+
+        let onnx: ModelConfig = serde_yaml::from_str("backend: { kind: onnx, model_path: models/missing.onnx }").unwrap();
+        assert!(onnx.build().is_err());
+    }
+}
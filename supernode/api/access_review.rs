@@ -0,0 +1,163 @@
+use crate::api::auth::{Auth, Principal, Scope};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use warp::{Filter, Rejection, Reply};
+
+// Last use of one credential as seen by this node
+#[derive(Clone, Debug, Default)]
+struct UsageRecord {
+    last_used: Option<DateTime<Utc>>,
+    ips: BTreeSet<IpAddr>,
+    tenants: BTreeSet<String>,
+}
+
+// Records every successful authentication, keyed by credential fingerprint
+#[derive(Clone, Default)]
+pub struct CredentialUsage {
+    records: Arc<Mutex<HashMap<String, (Principal, UsageRecord)>>>,
+}
+
+impl CredentialUsage {
+    pub fn record(&self, principal: &Principal, ip: Option<IpAddr>) {
+        let mut records = self.records.lock().unwrap();
+        let (latest, record) = records
+            .entry(principal.credential_id.clone())
+            .or_insert_with(|| (principal.clone(), UsageRecord::default()));
+        *latest = principal.clone();
+        record.last_used = Some(Utc::now());
+        record.ips.extend(ip);
+        record.tenants.extend(principal.tenant.clone());
+    }
+}
+
+// One row of an access review
+#[derive(Clone, Debug, Serialize)]
+pub struct CredentialReport {
+    pub credential: String,
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+    pub last_used: Option<DateTime<Utc>>,
+    pub ips: Vec<IpAddr>,
+    pub tenants: Vec<String>,
+}
+
+// Every configured API key, used or not, plus any bearer token subjects seen since startup
+pub fn report(auth: &Auth) -> Vec<CredentialReport> {
+    let records = auth.usage().records.lock().unwrap();
+    let mut rows: Vec<CredentialReport> = auth
+        .static_credentials()
+        .into_iter()
+        .map(|(credential, config)| {
+            let record = records.get(&credential).map(|(_, r)| r.clone()).unwrap_or_default();
+            let mut tenants: BTreeSet<String> = record.tenants;
+            tenants.extend(config.tenant);
+            CredentialReport {
+                subject: config.subject,
+                scopes: config.scopes,
+                last_used: record.last_used,
+                ips: record.ips.into_iter().collect(),
+                tenants: tenants.into_iter().collect(),
+                credential,
+            }
+        })
+        .collect();
+    for (credential, (principal, record)) in records.iter() {
+        if !credential.starts_with("jwt:") {
+            continue;
+        }
+        rows.push(CredentialReport {
+            credential: credential.clone(),
+            subject: principal.subject.clone(),
+            scopes: principal.scopes.clone(),
+            last_used: record.last_used,
+            ips: record.ips.iter().copied().collect(),
+            tenants: record.tenants.iter().cloned().collect(),
+        });
+    }
+    rows.sort_by(|a, b| a.subject.cmp(&b.subject).then_with(|| a.credential.cmp(&b.credential)));
+    rows
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Multi-valued columns are joined with `;`
+pub fn to_csv(rows: &[CredentialReport]) -> String {
+    let mut out = String::from("credential,subject,scopes,last_used,ips,tenants\n");
+    for row in rows {
+        let scopes: Vec<&str> = row.scopes.iter().map(|s| s.as_str()).collect();
+        let ips: Vec<String> = row.ips.iter().map(|ip| ip.to_string()).collect();
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            csv_field(&row.credential),
+            csv_field(&row.subject),
+            csv_field(&scopes.join(";")),
+            row.last_used.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            csv_field(&ips.join(";")),
+            csv_field(&row.tenants.join(";")),
+        );
+    }
+    out
+}
+
+// GET /admin/access-review and GET /admin/access-review.csv
+pub fn routes(auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let reviewed = auth.clone();
+    let json = warp::path!("admin" / "access-review")
+        .and(warp::get())
+        .and(auth.authorized())
+        .map(move |_| warp::reply::json(&report(&reviewed)));
+    let reviewed = auth.clone();
+    let csv = warp::path!("admin" / "access-review.csv")
+        .and(warp::get())
+        .and(auth.authorized())
+        .map(move |_| {
+            warp::reply::with_header(
+                warp::reply::with_header(to_csv(&report(&reviewed)), "content-type", "text/csv"),
+                "content-disposition",
+                "attachment; filename=\"access-review.csv\"",
+            )
+        });
+    json.or(csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig};
+    use crate::api::router::Router;
+
+    #[tokio::test]
+    async fn admins_download_the_review_with_last_use() {
+        let key = |subject: &str, scopes, tenant: Option<&str>| ApiKeyConfig { subject: subject.to_string(), scopes, tenant: tenant.map(str::to_string) };
+        let api_keys = HashMap::from([
+            ("k-admin".to_string(), key("ops", vec![Scope::Admin], None)),
+            ("k-acme".to_string(), key("acme-app", vec![Scope::Convert], Some("acme"))),
+        ]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let api = warp::any().and(Router::new().mount("access_review", routes(&auth)).build());
+        let get = |key: &str, path: &str| warp::test::request().path(path).header("x-api-key", key).reply(&api);
+
+        assert_eq!(get("k-acme", "/admin/access-review").await.status(), 403);
+        let review = get("k-admin", "/admin/access-review").await;
+        assert_eq!(review.status(), 200);
+        let rows: serde_json::Value = serde_json::from_slice(review.body()).unwrap();
+        let acme = rows.as_array().unwrap().iter().find(|row| row["subject"] == "acme-app").unwrap();
+        assert!(!acme["last_used"].is_null());
+        assert_eq!(acme["tenants"], serde_json::json!(["acme"]));
+
+        let csv = get("k-admin", "/admin/access-review.csv").await;
+        assert_eq!(csv.headers()["content-type"], "text/csv");
+        assert!(String::from_utf8_lossy(csv.body()).starts_with("credential,subject,scopes,last_used,ips,tenants\n"));
+    }
+}
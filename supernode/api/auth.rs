@@ -0,0 +1,400 @@
+use crate::api::access_review::CredentialUsage;
+use crate::api::authz::Policy;
+use crate::api::signing::{RequestSigningConfig, SignedBy};
+use crate::server::PeerAddr;
+use crate::tenant_usage::UsageMeter;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::debug;
+use warp::http::Method;
+use warp::reject::Reject;
+use warp::{Filter, Rejection};
+
+// `auth` section of the node config
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    // Static API key -> principal
+    pub api_keys: HashMap<String, ApiKeyConfig>,
+
+    pub jwt: Option<JwtConfig>,
+
+    // Decoy keys that raise a critical alert whenever presented
+    pub honeytokens: Vec<String>,
+
+    pub request_signing: RequestSigningConfig,
+
+    // Route authorization matrix; the built-in config/authz.yaml when unset
+    pub policy_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKeyConfig {
+    pub subject: String,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+// Permission carried by a key or token
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Issue,
+    Redeem,
+    Convert,
+
+    // Peers reading /v1/sync to bootstrap from this node
+    Sync,
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Issue => "issue",
+            Scope::Redeem => "redeem",
+            Scope::Convert => "convert",
+            Scope::Sync => "sync",
+            Scope::Admin => "admin",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Scope> {
+        match s {
+            "issue" => Some(Scope::Issue),
+            "redeem" => Some(Scope::Redeem),
+            "convert" => Some(Scope::Convert),
+            "sync" => Some(Scope::Sync),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtConfig {
+    pub issuer: String,
+    pub audience: Option<String>,
+
+    // HS256 shared secret or RS256 PEM public key
+    pub hs256_secret: Option<String>,
+    pub rs256_public_key_pem: Option<String>,
+}
+
+// Authenticated caller
+#[derive(Clone, Debug)]
+pub struct Principal {
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+
+    // Stable, non-secret identifier of the key or token subject used
+    pub credential_id: String,
+    pub tenant: Option<String>,
+}
+
+impl Principal {
+    // Admin implies every other scope
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin)
+    }
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    Missing,
+    Invalid(String),
+    Forbidden,
+    MissingScope(Scope),
+
+    // Tenant is over a daily or issuance quota
+    QuotaExceeded(String),
+
+    // Route is not part of the tenant's plan
+    NotEntitled(String),
+}
+
+impl Reject for AuthError {}
+
+// Scopes come from the OAuth-style space separated `scope` claim
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(default)]
+    scope: String,
+}
+
+// Keys issued at runtime (e.g. by the tenant registry), consulted after the static ones
+pub trait KeyStore: Send + Sync {
+    fn lookup(&self, key: &str) -> Option<ApiKeyConfig>;
+
+    // Called for every request a key from this store authenticates; `Err` refuses it
+    fn admit(&self, principal: &Principal) -> Result<(), AuthError>;
+}
+
+// Cookie sessions for browser clients, consulted when a request carries neither an API key nor a bearer token
+pub trait SessionVerifier: Send + Sync {
+    fn cookie_name(&self) -> &str;
+
+    // `csrf` is the `x-csrf-token` header, required for methods that change state
+    fn verify(&self, token: &str, method: &Method, csrf: Option<&str>) -> Result<Principal, AuthError>;
+}
+
+// Per-tenant plans, checked on authorized routes after the policy admitted the caller
+pub trait Entitlements: Send + Sync {
+    fn entitled(&self, principal: &Principal, method: &Method, path: &str) -> Result<(), AuthError>;
+}
+
+// What a request presented to authenticate with
+struct Presented {
+    method: Method,
+    api_key: Option<String>,
+    authorization: Option<String>,
+    cookie: Option<String>,
+    csrf: Option<String>,
+}
+
+impl Presented {
+    fn session_token(&self, name: &str) -> Option<&str> {
+        self.cookie.as_deref()?.split(';').filter_map(|pair| pair.trim().split_once('=')).find(|(k, _)| *k == name).map(|(_, v)| v)
+    }
+}
+
+fn presented() -> impl Filter<Extract = (Presented,), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::header::optional::<String>("x-api-key"))
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>("cookie"))
+        .and(warp::header::optional::<String>("x-csrf-token"))
+        .map(|method, api_key, authorization, cookie, csrf| Presented { method, api_key, authorization, cookie, csrf })
+}
+
+struct Verifier {
+    api_keys: HashMap<String, ApiKeyConfig>,
+    key_store: Option<Arc<dyn KeyStore>>,
+    jwt: Option<(DecodingKey, Validation)>,
+
+    // Subjects that must arrive with a verified request signature
+    signed_subjects: HashSet<String>,
+}
+
+impl Verifier {
+    fn new(config: &AuthConfig) -> Result<Self, String> {
+        let jwt = match &config.jwt {
+            None => None,
+            Some(jwt) => {
+                let (key, algorithm) = match (&jwt.hs256_secret, &jwt.rs256_public_key_pem) {
+                    (Some(secret), _) => (DecodingKey::from_secret(secret.as_bytes()), Algorithm::HS256),
+                    (None, Some(pem)) => {
+                        (DecodingKey::from_rsa_pem(pem.as_bytes()).map_err(|e| e.to_string())?, Algorithm::RS256)
+                    }
+                    (None, None) => return Err("jwt requires hs256_secret or rs256_public_key_pem".to_string()),
+                };
+                let mut validation = Validation::new(algorithm);
+                validation.set_issuer(&[&jwt.issuer]);
+                match &jwt.audience {
+                    Some(audience) => validation.set_audience(&[audience]),
+                    None => validation.validate_aud = false,
+                }
+                Some((key, validation))
+            }
+        };
+        if config.honeytokens.iter().any(|token| config.api_keys.contains_key(token)) {
+            return Err("a honeytoken is also configured as a real API key".to_string());
+        }
+        let signed_subjects = config.request_signing.required_for.iter().cloned().collect();
+        Ok(Verifier { api_keys: config.api_keys.clone(), key_store: None, jwt, signed_subjects })
+    }
+
+    // Every configured static credential, for access reviews
+    fn static_credentials(&self) -> Vec<(String, &ApiKeyConfig)> {
+        self.api_keys.iter().map(|(key, config)| (key_fingerprint(key), config)).collect()
+    }
+
+    // A valid signature binds the request to the signing key's subject, so a replayed
+    // credential cannot be used with a different or missing signature
+    fn verify(&self, sessions: Option<&dyn SessionVerifier>, presented: Presented, signed: Option<SignedBy>) -> Result<Principal, AuthError> {
+        let session = sessions.and_then(|s| Some((s, presented.session_token(s.cookie_name())?)));
+        let principal = match session {
+            Some((sessions, token)) if presented.api_key.is_none() && presented.authorization.is_none() => {
+                sessions.verify(token, &presented.method, presented.csrf.as_deref())?
+            }
+            _ => self.verify_credential(presented.api_key, presented.authorization)?,
+        };
+        match signed {
+            Some(signed) if signed.subject != principal.subject => {
+                Err(AuthError::Invalid(format!("request signed by key {} which does not belong to this caller", signed.key_id)))
+            }
+            None if self.signed_subjects.contains(&principal.subject) => {
+                Err(AuthError::Invalid("requests from this caller must be signed".to_string()))
+            }
+            _ => Ok(principal),
+        }
+    }
+
+    fn verify_credential(&self, api_key: Option<String>, authorization: Option<String>) -> Result<Principal, AuthError> {
+        if let Some(key) = api_key {
+            if let Some(config) = self.api_keys.get(&key) {
+                return Ok(Principal {
+                    subject: config.subject.clone(),
+                    scopes: config.scopes.clone(),
+                    credential_id: key_fingerprint(&key),
+                    tenant: config.tenant.clone(),
+                });
+            }
+            let store = self.key_store.as_ref().ok_or_else(|| AuthError::Invalid("unknown API key".to_string()))?;
+            let config = store.lookup(&key).ok_or_else(|| AuthError::Invalid("unknown API key".to_string()))?;
+            let principal =
+                Principal { subject: config.subject, scopes: config.scopes, credential_id: key_fingerprint(&key), tenant: config.tenant };
+            store.admit(&principal)?;
+            return Ok(principal);
+        }
+        let token = authorization
+            .as_deref()
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or(AuthError::Missing)?;
+        let (key, validation) = self.jwt.as_ref().ok_or_else(|| AuthError::Invalid("bearer tokens are not enabled".to_string()))?;
+        let data = decode::<Claims>(token, key, validation).map_err(|e| AuthError::Invalid(e.to_string()))?;
+        let scopes = data.claims.scope.split_whitespace().filter_map(Scope::parse).collect();
+        let credential_id = format!("jwt:{}", data.claims.sub);
+        Ok(Principal { subject: data.claims.sub, scopes, credential_id, tenant: None })
+    }
+}
+
+// Keys are never reported, only a short hash of them
+pub fn key_fingerprint(key: &str) -> String {
+    format!("key:{}", &hex::encode(Sha256::digest(key.as_bytes()))[..16])
+}
+
+#[derive(Clone)]
+pub struct Auth {
+    verifier: Arc<Verifier>,
+    policy: Arc<Policy>,
+    usage: CredentialUsage,
+    sessions: Option<Arc<dyn SessionVerifier>>,
+    entitlements: Option<Arc<dyn Entitlements>>,
+    meter: Option<UsageMeter>,
+}
+
+impl Auth {
+    pub fn new(config: &AuthConfig) -> Result<Self, String> {
+        Self::build(config, None)
+    }
+
+    // Also accept keys from `store`, e.g. `Auth::with_key_store(&config.auth, Arc::new(tenants.clone()))`
+    pub fn with_key_store(config: &AuthConfig, store: Arc<dyn KeyStore>) -> Result<Self, String> {
+        Self::build(config, Some(store))
+    }
+
+    fn build(config: &AuthConfig, key_store: Option<Arc<dyn KeyStore>>) -> Result<Self, String> {
+        let policy = match &config.policy_file {
+            Some(path) => Policy::load(path)?,
+            None => Policy::builtin(),
+        };
+        let verifier = Verifier { key_store, ..Verifier::new(config)? };
+        Ok(Auth { verifier: Arc::new(verifier), policy: Arc::new(policy), usage: CredentialUsage::default(), sessions: None, entitlements: None, meter: None })
+    }
+
+    // Also accept session cookies, e.g. `auth.with_sessions(Arc::new(sessions.clone()))`
+    pub fn with_sessions(mut self, sessions: Arc<dyn SessionVerifier>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    // Also hold tenants to their plans, e.g. `auth.with_entitlements(Arc::new(tenants.clone()))`
+    pub fn with_entitlements(mut self, entitlements: Arc<dyn Entitlements>) -> Self {
+        self.entitlements = Some(entitlements);
+        self
+    }
+
+    // Count each call `authorized()` lets through against the caller's tenant, e.g. `auth.with_usage_meter(usage.clone())`
+    pub fn with_usage_meter(mut self, meter: UsageMeter) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    // The plan check `authorized()` applies to `method path`, for judging a request without making it
+    pub fn entitled(&self, principal: &Principal, method: &Method, path: &str) -> Result<(), AuthError> {
+        self.entitlements.as_ref().map_or(Ok(()), |e| e.entitled(principal, method, path))
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    pub fn usage(&self) -> &CredentialUsage {
+        &self.usage
+    }
+
+    pub fn static_credentials(&self) -> Vec<(String, ApiKeyConfig)> {
+        self.verifier.static_credentials().into_iter().map(|(id, c)| (id, c.clone())).collect()
+    }
+
+    // Require an authenticated caller on a route: `warp::path("issuance").and(auth.required())`
+    pub fn required(&self) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone {
+        let verifier = self.verifier.clone();
+        let sessions = self.sessions.clone();
+        let usage = self.usage.clone();
+        presented()
+            .and(warp::ext::optional::<PeerAddr>())
+            .and(warp::ext::optional::<SignedBy>())
+            .and_then(move |presented: Presented, peer: Option<PeerAddr>, signed: Option<SignedBy>| {
+                let result = verifier.verify(sessions.as_deref(), presented, signed);
+                if let Ok(principal) = &result {
+                    usage.record(principal, peer.map(|p| p.0.ip()));
+                }
+                async move { result.map_err(warp::reject::custom) }
+            })
+    }
+
+    // Require a caller the authorization policy admits to this method and path;
+    // what each route needs lives in the policy file, not in the handler
+    pub fn authorized(&self) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone {
+        let policy = self.policy.clone();
+        let entitlements = self.entitlements.clone();
+        let meter = self.meter.clone();
+        warp::method().and(warp::path::full()).and(self.required()).and_then(
+            move |method: Method, path: warp::path::FullPath, principal: Principal| {
+                let result = policy
+                    .check(method.as_str(), path.as_str(), &principal)
+                    .map_err(|denial| {
+                        let rule = policy.rule_for(method.as_str(), path.as_str());
+                        debug!(%method, path = path.as_str(), ?rule, %denial, "request denied by policy");
+                        AuthError::from(denial)
+                    })
+                    .and_then(|()| entitlements.as_ref().map_or(Ok(()), |e| e.entitled(&principal, &method, path.as_str())))
+                    .map(|()| principal)
+                    .map_err(warp::reject::custom);
+                if let (Ok(Principal { tenant: Some(tenant), .. }), Some(meter)) = (&result, &meter) {
+                    meter.record_api_call(tenant);
+                }
+                async move { result }
+            },
+        )
+    }
+
+    // Routes that also serve anonymous callers get `None` instead of a rejection
+    pub fn optional(&self) -> impl Filter<Extract = (Option<Principal>,), Error = Rejection> + Clone {
+        let verifier = self.verifier.clone();
+        let sessions = self.sessions.clone();
+        let usage = self.usage.clone();
+        presented()
+            .and(warp::ext::optional::<PeerAddr>())
+            .and(warp::ext::optional::<SignedBy>())
+            .and_then(move |presented: Presented, peer: Option<PeerAddr>, signed: Option<SignedBy>| {
+                let anonymous = presented.api_key.is_none()
+                    && presented.authorization.is_none()
+                    && sessions.as_ref().is_none_or(|s| presented.session_token(s.cookie_name()).is_none());
+                let result = if anonymous { Ok(None) } else { verifier.verify(sessions.as_deref(), presented, signed).map(Some) };
+                if let Ok(Some(principal)) = &result {
+                    usage.record(principal, peer.map(|p| p.0.ip()));
+                }
+                async move { result.map_err(warp::reject::custom) }
+            })
+    }
+}
@@ -0,0 +1,209 @@
+use crate::api::auth::{AuthError, Principal, Scope};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+// Policy used when `auth.policy_file` is not set
+const BUILTIN_POLICY: &str = include_str!("../../config/authz.yaml");
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub path: String,
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyFile {
+    pub rules: Vec<RuleConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Any,
+    Capture(String),
+}
+
+#[derive(Clone, Debug)]
+enum TenantRule {
+    Any,
+    Capture(String),
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    segments: Vec<Segment>,
+    rest: bool,
+    methods: Vec<String>,
+    scopes: Vec<Scope>,
+    subjects: Vec<String>,
+    tenant: Option<TenantRule>,
+    source: String,
+}
+
+// Why a request was refused, for logs; callers only see 403
+#[derive(Debug)]
+pub enum Denial {
+    NoRule,
+    Scope(Scope),
+    Subject,
+    Tenant,
+}
+
+impl fmt::Display for Denial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Denial::NoRule => write!(f, "no policy rule matches this route"),
+            Denial::Scope(scope) => write!(f, "missing scope {}", scope.as_str()),
+            Denial::Subject => write!(f, "caller is not listed for this route"),
+            Denial::Tenant => write!(f, "route belongs to another tenant"),
+        }
+    }
+}
+
+impl From<Denial> for AuthError {
+    fn from(denial: Denial) -> Self {
+        match denial {
+            Denial::Scope(scope) => AuthError::MissingScope(scope),
+            _ => AuthError::Forbidden,
+        }
+    }
+}
+
+impl Rule {
+    fn compile(config: RuleConfig) -> Result<Rule, String> {
+        let source = config.path.clone();
+        let mut parts: Vec<&str> = config.path.trim_matches('/').split('/').filter(|s| !s.is_empty()).collect();
+        let rest = parts.last() == Some(&"**");
+        if rest {
+            parts.pop();
+        }
+        let segments: Vec<Segment> = parts
+            .iter()
+            .map(|part| match *part {
+                "**" => Err(format!("rule {}: `**` is only allowed at the end", source)),
+                "*" => Ok(Segment::Any),
+                p if p.starts_with('{') && p.ends_with('}') => Ok(Segment::Capture(p[1..p.len() - 1].to_string())),
+                p => Ok(Segment::Literal(p.to_string())),
+            })
+            .collect::<Result<_, _>>()?;
+        let tenant = match config.tenant.as_deref() {
+            None => None,
+            Some("any") => Some(TenantRule::Any),
+            Some(t) if t.starts_with('{') && t.ends_with('}') => {
+                let name = t[1..t.len() - 1].to_string();
+                if !segments.contains(&Segment::Capture(name.clone())) {
+                    return Err(format!("rule {}: tenant refers to {} which the path does not capture", source, t));
+                }
+                Some(TenantRule::Capture(name))
+            }
+            Some(t) => return Err(format!("rule {}: tenant must be `any` or a path capture, not {}", source, t)),
+        };
+        Ok(Rule {
+            segments,
+            rest,
+            methods: config.methods.iter().map(|m| m.to_uppercase()).collect(),
+            scopes: config.scopes,
+            subjects: config.subjects,
+            tenant,
+            source,
+        })
+    }
+
+    // Path captures when the rule applies to the request
+    fn matches(&self, method: &str, path: &str) -> Option<HashMap<String, String>> {
+        if !self.methods.is_empty() && !self.methods.iter().any(|m| m == method) {
+            return None;
+        }
+        let parts: Vec<&str> = path.trim_matches('/').split('/').filter(|s| !s.is_empty()).collect();
+        if parts.len() < self.segments.len() || (!self.rest && parts.len() != self.segments.len()) {
+            return None;
+        }
+        let mut captures = HashMap::new();
+        for (segment, part) in self.segments.iter().zip(&parts) {
+            match segment {
+                Segment::Literal(literal) if literal != part => return None,
+                Segment::Capture(name) => {
+                    captures.insert(name.clone(), part.to_string());
+                }
+                _ => {}
+            }
+        }
+        Some(captures)
+    }
+
+    fn allows(&self, principal: &Principal, captures: &HashMap<String, String>) -> Result<(), Denial> {
+        if let Some(scope) = self.scopes.iter().find(|s| !principal.has_scope(**s)) {
+            return Err(Denial::Scope(*scope));
+        }
+        if !self.subjects.is_empty() && !self.subjects.contains(&principal.subject) {
+            return Err(Denial::Subject);
+        }
+        if principal.has_scope(Scope::Admin) {
+            return Ok(());
+        }
+        match &self.tenant {
+            None => Ok(()),
+            Some(TenantRule::Any) if principal.tenant.is_some() => Ok(()),
+            Some(TenantRule::Capture(name)) if principal.tenant.is_some() && principal.tenant.as_ref() == captures.get(name) => Ok(()),
+            Some(_) => Err(Denial::Tenant),
+        }
+    }
+}
+
+// A path and methods in policy syntax, matched without any caller checks
+#[derive(Clone, Debug)]
+pub struct RoutePattern(Rule);
+
+impl RoutePattern {
+    pub fn compile(path: &str, methods: &[String]) -> Result<Self, String> {
+        let config = RuleConfig { path: path.to_string(), methods: methods.to_vec(), scopes: Vec::new(), subjects: Vec::new(), tenant: None };
+        Rule::compile(config).map(RoutePattern)
+    }
+
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        self.0.matches(&method.to_uppercase(), path).is_some()
+    }
+}
+
+// Route pattern -> requirements, evaluated in file order so the matrix reads top to bottom
+#[derive(Clone, Debug)]
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    pub fn parse(text: &str) -> Result<Policy, String> {
+        let file: PolicyFile = serde_yaml::from_str(text).map_err(|e| format!("invalid authorization policy: {}", e))?;
+        Ok(Policy { rules: file.rules.into_iter().map(Rule::compile).collect::<Result<_, _>>()? })
+    }
+
+    pub fn builtin() -> Policy {
+        Policy::parse(BUILTIN_POLICY).expect("built-in authorization policy is valid")
+    }
+
+    pub fn load(path: &Path) -> Result<Policy, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        Policy::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Pattern of the rule that decides `method path`, e.g. for explaining a denial
+    pub fn rule_for(&self, method: &str, path: &str) -> Option<&str> {
+        self.rules.iter().find(|r| r.matches(method, path).is_some()).map(|r| r.source.as_str())
+    }
+
+    pub fn check(&self, method: &str, path: &str, principal: &Principal) -> Result<(), Denial> {
+        let (rule, captures) = self.rules.iter().find_map(|r| r.matches(method, path).map(|c| (r, c))).ok_or(Denial::NoRule)?;
+        rule.allows(principal, &captures)
+    }
+}
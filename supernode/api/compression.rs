@@ -0,0 +1,177 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hyper::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, VARY};
+use hyper::{Body, Response, StatusCode};
+use serde::Deserialize;
+use std::io::Write;
+use tracing::debug;
+
+// `server.compression` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+
+    // Smaller bodies are sent as they are; compressing them costs more than it saves
+    pub min_bytes: usize,
+
+    // Offered encodings in order of preference, out of `br` and `gzip`; the client's q-values win over this order
+    pub encodings: Vec<String>,
+    pub gzip_level: u32,
+    pub brotli_quality: u32,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            enabled: true,
+            min_bytes: 1024,
+            encodings: vec!["br".to_string(), "gzip".to_string()],
+            gzip_level: 6,
+            brotli_quality: 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "br" => Some(Encoding::Brotli),
+            "gzip" => Some(Encoding::Gzip),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(unknown) = self.encodings.iter().find(|e| Encoding::parse(e).is_none()) {
+            return Err(format!("server.compression: unsupported encoding {}, expected br or gzip", unknown));
+        }
+        if self.gzip_level > 9 {
+            return Err("server.compression: gzip_level must be 0..=9".to_string());
+        }
+        if self.brotli_quality > 11 {
+            return Err("server.compression: brotli_quality must be 0..=11".to_string());
+        }
+        Ok(())
+    }
+
+    // Encoding to use for a request's Accept-Encoding: highest q-value, ties broken by `encodings` order
+    fn negotiate(&self, accept: &str) -> Option<Encoding> {
+        let offered: Vec<(&str, f32)> = accept
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let name = parts.next()?.trim();
+                let q = parts
+                    .filter_map(|p| p.trim().strip_prefix("q="))
+                    .next()
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (!name.is_empty()).then_some((name, q))
+            })
+            .collect();
+        let q_of = |name: &str| {
+            offered
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .or_else(|| offered.iter().find(|(n, _)| *n == "*"))
+                .map_or(0.0, |(_, q)| *q)
+        };
+        self.encodings
+            .iter()
+            .filter_map(|name| Some((Encoding::parse(name)?, q_of(name))))
+            .filter(|(_, q)| *q > 0.0)
+            .fold(None, |best: Option<(Encoding, f32)>, (encoding, q)| match best {
+                Some((_, best_q)) if best_q >= q => best,
+                _ => Some((encoding, q)),
+            })
+            .map(|(encoding, _)| encoding)
+    }
+
+    fn encode(&self, encoding: Encoding, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match encoding {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::new(self.gzip_level));
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            Encoding::Brotli => {
+                let mut out = Vec::with_capacity(body.len() / 4);
+                {
+                    let mut encoder = brotli::CompressorWriter::new(&mut out, 4096, self.brotli_quality, 22);
+                    encoder.write_all(body)?;
+                }
+                Ok(out)
+            }
+        }
+    }
+}
+
+// Already compressed, or streamed and must not be buffered
+fn compressible(headers: &HeaderMap) -> bool {
+    let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("");
+    !(content_type.starts_with("image/")
+        || content_type.starts_with("video/")
+        || content_type.starts_with("audio/")
+        || content_type.starts_with("text/event-stream")
+        || content_type == "application/zstd"
+        || content_type == "application/gzip"
+        || content_type == "application/zip")
+}
+
+// Compress `response` for a request that sent `accept`, if both sides allow it
+pub async fn compress(config: &CompressionConfig, accept: Option<&HeaderValue>, response: Response<Body>) -> Response<Body> {
+    if !config.enabled
+        || response.headers().contains_key(CONTENT_ENCODING)
+        || matches!(response.status(), StatusCode::SWITCHING_PROTOCOLS | StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED)
+        || !compressible(response.headers())
+    {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    parts.headers.append(VARY, HeaderValue::from_name(ACCEPT_ENCODING));
+    let encoding = accept.and_then(|v| v.to_str().ok()).and_then(|accept| config.negotiate(accept));
+    let declared = parts.headers.get(CONTENT_LENGTH).and_then(|v| v.to_str().ok()).and_then(|v| v.parse::<usize>().ok());
+    let Some(encoding) = encoding.filter(|_| declared.is_none_or(|len| len >= config.min_bytes)) else {
+        return Response::from_parts(parts, body);
+    };
+
+    let bytes = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            debug!(error = %e, "response body failed before compression");
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    if bytes.len() < config.min_bytes {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+    let encoded = match config.encode(encoding, &bytes) {
+        Ok(encoded) if encoded.len() < bytes.len() => encoded,
+        _ => return Response::from_parts(parts, Body::from(bytes)),
+    };
+    debug!(encoding = encoding.name(), from = bytes.len(), to = encoded.len(), "response compressed");
+    parts.headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+    parts.headers.insert(CONTENT_LENGTH, HeaderValue::from(encoded.len()));
+    // The encoded body is a different representation, so a strong validator no longer holds
+    if let Some(etag) = parts.headers.get(ETAG).and_then(|v| v.to_str().ok()).filter(|v| !v.starts_with("W/")) {
+        if let Ok(weak) = HeaderValue::from_str(&format!("W/{}", etag)) {
+            parts.headers.insert(ETAG, weak);
+        }
+    }
+    Response::from_parts(parts, Body::from(encoded))
+}
@@ -0,0 +1,172 @@
+use crate::admin::policy_params::{PolicyParamStore, PolicyParams};
+use crate::api::problem::ApiError;
+use crate::api::validation::{validated_json, FieldError, Validate, ValidationConfig};
+use crate::cache::AdaptiveLru;
+use crate::fees::{FeeCharge, FeeError, FeeItem, FeeOperation, FeeSchedule};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Issue,
+    Redeem,
+    Convert,
+}
+
+impl Operation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Issue => "issue",
+            Operation::Redeem => "redeem",
+            Operation::Convert => "convert",
+        }
+    }
+}
+
+// Hypothetical transaction; nothing is created or reserved
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FeeEstimateRequest {
+    pub operation: Operation,
+    pub asset: String,
+
+    // Target asset for conversions
+    #[serde(default)]
+    pub to_asset: Option<String>,
+
+    pub amount: u128,
+}
+
+// Amount limits are reported in the estimate rather than rejected, only the assets are checked here
+impl Validate for FeeEstimateRequest {
+    fn validate(&self, rules: &ValidationConfig) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        rules.asset("asset", &self.asset, &mut errors);
+        match (&self.operation, &self.to_asset) {
+            (Operation::Convert, None) => errors.push(FieldError::new("to_asset", "is required for conversions")),
+            (Operation::Convert, Some(to)) => {
+                rules.asset("to_asset", to, &mut errors);
+            }
+            (_, Some(_)) => errors.push(FieldError::new("to_asset", "is only allowed for conversions")),
+            (_, None) => {}
+        }
+        errors
+    }
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct LimitCheck {
+    pub name: String,
+    pub limit: u128,
+    pub within: bool,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct FeeEstimate {
+    pub operation: Operation,
+    pub amount: u128,
+
+    // Itemized exactly as `FeeSchedule::charge` would take them
+    pub fees: Vec<FeeItem>,
+    pub total_fees: u128,
+
+    // What the caller receives after fees (before the exchange rate for conversions)
+    pub net_amount: u128,
+
+    pub limits: Vec<LimitCheck>,
+    pub settlement_path: Vec<String>,
+}
+
+// Limit parameters are looked up most specific first:
+// `limit.max_amount.convert.USD`, then `limit.max_amount.convert`
+fn lookup(params: &PolicyParams, name: &str, op: Operation, asset: &str) -> Option<f64> {
+    let op = op.as_str();
+    params
+        .values
+        .get(&format!("{}.{}.{}", name, op, asset))
+        .or_else(|| params.values.get(&format!("{}.{}", name, op)))
+        .copied()
+}
+
+// Policy values are floats; a limit that is not a whole, non-negative number of units is ignored rather than truncated or saturated
+fn whole_units(value: f64) -> Option<u128> {
+    if !value.is_finite() || value < 0.0 || value.fract() != 0.0 {
+        return None;
+    }
+    format!("{:.0}", value).parse().ok()
+}
+
+// Amount limits the policy params set for this operation and asset
+pub fn policy_limits(params: &PolicyParams, request: &FeeEstimateRequest) -> Vec<LimitCheck> {
+    ["limit.max_amount", "limit.min_amount"]
+        .into_iter()
+        .filter_map(|name| {
+            let limit = whole_units(lookup(params, name, request.operation, &request.asset)?)?;
+            let within = if name.ends_with("max_amount") { request.amount <= limit } else { request.amount >= limit };
+            Some(LimitCheck { name: name.trim_start_matches("limit.").to_string(), limit, within })
+        })
+        .collect()
+}
+
+// Redemptions carry no fee
+pub fn estimate(fees: &FeeSchedule, params: &PolicyParams, request: &FeeEstimateRequest) -> Result<FeeEstimate, FeeError> {
+    let op = request.operation;
+    let (items, total_fees, net_amount) = match op {
+        Operation::Issue => charged(fees.charge(FeeOperation::Issuance, &request.asset, request.amount)?),
+        Operation::Convert => charged(fees.charge(FeeOperation::Conversion, &request.asset, request.amount)?),
+        Operation::Redeem => (Vec::new(), 0, request.amount),
+    };
+
+    let settlement_path = match (op, &request.to_asset) {
+        (Operation::Issue, _) => vec![format!("mint:{}", request.asset), "credit:account".to_string()],
+        (Operation::Redeem, _) => vec!["debit:account".to_string(), format!("burn:{}", request.asset)],
+        (Operation::Convert, to) => vec![
+            format!("debit:{}", request.asset),
+            format!("convert:{}->{}", request.asset, to.as_deref().unwrap_or("?")),
+            format!("credit:{}", to.as_deref().unwrap_or("?")),
+        ],
+    };
+
+    Ok(FeeEstimate {
+        operation: op,
+        amount: request.amount,
+        fees: items,
+        total_fees,
+        net_amount,
+        limits: policy_limits(params, request),
+        settlement_path,
+    })
+}
+
+fn charged(charge: FeeCharge) -> (Vec<FeeItem>, u128, u128) {
+    (charge.items, charge.total, charge.net_amount)
+}
+
+// POST /v1/fees/estimate
+// Quotes are keyed by the params version, so an activated change never serves a stale quote
+pub fn routes(
+    fees: FeeSchedule,
+    params: PolicyParamStore,
+    rules: Arc<ValidationConfig>,
+    quotes: AdaptiveLru<String, FeeEstimate>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("v1" / "fees" / "estimate").and(warp::post()).and(validated_json(rules)).and_then(move |request: FeeEstimateRequest| {
+        let current = params.current();
+        let key = format!(
+            "{}|{}|{}|{}|{}",
+            current.version,
+            request.operation.as_str(),
+            request.asset,
+            request.to_asset.as_deref().unwrap_or(""),
+            request.amount
+        );
+        let quote = match quotes.get(&key) {
+            Some(quote) => Ok(quote),
+            None => estimate(&fees, &current.value, &request).inspect(|quote| quotes.insert(key, quote.clone())),
+        };
+        async move { quote.map(|quote| warp::reply::json(&quote)).map_err(|e| warp::reject::custom(ApiError::Unprocessable(e.to_string()))) }
+    })
+}
@@ -0,0 +1,202 @@
+use crate::api::auth::{Auth, Principal};
+use crate::api::redemption::LedgerAccount;
+use crate::storage::entities::{EntityError, EntityStore};
+use crate::storage::ledger_history::{LedgerHistory, ThreatRecord, TransactionRecord};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Result, Schema, SimpleObject};
+use async_graphql_warp::GraphQLResponse;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::debug;
+use warp::{Filter, Rejection, Reply};
+
+// `graphql` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GraphqlConfig {
+    pub max_depth: usize,
+
+    // Every field costs 1; list fields multiply their children by the requested page size
+    pub max_complexity: usize,
+    pub max_page_size: usize,
+}
+
+impl Default for GraphqlConfig {
+    fn default() -> Self {
+        GraphqlConfig { max_depth: 8, max_complexity: 2000, max_page_size: 100 }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Transaction {
+    pub tx_id: String,
+    pub kind: String,
+    pub asset: String,
+    pub amount: String,
+    pub to_asset: Option<String>,
+    pub amount_out: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+impl From<TransactionRecord> for Transaction {
+    fn from(r: TransactionRecord) -> Self {
+        Transaction { tx_id: r.tx_id, kind: r.kind, asset: r.asset, amount: r.amount, to_asset: r.to_asset, amount_out: r.amount_out, at: r.at }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ThreatEvent {
+    pub id: String,
+    pub source: String,
+    pub severity: f32,
+    pub detail: String,
+    pub at: DateTime<Utc>,
+}
+
+impl From<ThreatRecord> for ThreatEvent {
+    fn from(r: ThreatRecord) -> Self {
+        ThreatEvent { id: r.id, source: r.source, severity: r.severity, detail: r.detail, at: r.at }
+    }
+}
+
+// Balances are u128 on the ledger, which GraphQL has no scalar for
+#[derive(SimpleObject)]
+pub struct Balance {
+    pub asset: String,
+    pub amount: String,
+}
+
+#[derive(SimpleObject)]
+pub struct Account {
+    pub id: String,
+    pub public_key: String,
+    pub balances: Vec<Balance>,
+}
+
+pub struct Query;
+
+// Complexity is computed before resolving, without the schema data, so `schema` leaves the page size cap here for it
+static MAX_PAGE_SIZE: AtomicUsize = AtomicUsize::new(100);
+
+fn page_size(ctx: &Context<'_>, first: Option<usize>) -> usize {
+    let max = ctx.data_unchecked::<GraphqlConfig>().max_page_size;
+    first.unwrap_or(20).min(max)
+}
+
+// A list costs its children once per row it can return
+fn page_complexity(first: Option<usize>, child_complexity: usize) -> usize {
+    first.unwrap_or(20).min(MAX_PAGE_SIZE.load(Ordering::Relaxed)).saturating_mul(child_complexity)
+}
+
+#[Object]
+impl Query {
+    async fn transaction(&self, ctx: &Context<'_>, tx_id: String) -> Option<Transaction> {
+        ctx.data_unchecked::<LedgerHistory>().transaction(&tx_id).map(Transaction::from)
+    }
+
+    // Newest first; pass the last `txId` seen as `before` for the next page
+    #[graphql(complexity = "page_complexity(first, child_complexity)")]
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        asset: Option<String>,
+        before: Option<String>,
+        first: Option<usize>,
+    ) -> Vec<Transaction> {
+        let history = ctx.data_unchecked::<LedgerHistory>();
+        history.transactions(asset.as_deref(), before.as_deref(), page_size(ctx, first)).into_iter().map(Transaction::from).collect()
+    }
+
+    async fn account(&self, ctx: &Context<'_>, id: String) -> Result<Option<Account>> {
+        match ctx.data_unchecked::<EntityStore<LedgerAccount>>().get(&id) {
+            Ok(entity) => {
+                let mut balances: Vec<Balance> =
+                    entity.value.balances.into_iter().map(|(asset, amount)| Balance { asset, amount: amount.to_string() }).collect();
+                balances.sort_by(|a, b| a.asset.cmp(&b.asset));
+                Ok(Some(Account { id, public_key: entity.value.public_key, balances }))
+            }
+            Err(EntityError::NotFound) => Ok(None),
+            Err(e) => Err(e.to_string().into()),
+        }
+    }
+
+    #[graphql(complexity = "page_complexity(first, child_complexity)")]
+    async fn threats(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] min_severity: f32,
+        before: Option<String>,
+        first: Option<usize>,
+    ) -> Vec<ThreatEvent> {
+        let history = ctx.data_unchecked::<LedgerHistory>();
+        history.threats(min_severity, before.as_deref(), page_size(ctx, first)).into_iter().map(ThreatEvent::from).collect()
+    }
+}
+
+pub type LedgerSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+pub fn schema(config: &GraphqlConfig, history: LedgerHistory, accounts: EntityStore<LedgerAccount>) -> LedgerSchema {
+    MAX_PAGE_SIZE.store(config.max_page_size, Ordering::Relaxed);
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .limit_depth(config.max_depth)
+        .limit_complexity(config.max_complexity)
+        .data(config.clone())
+        .data(history)
+        .data(accounts)
+        .finish()
+}
+
+// POST /graphql, read-only; every account and transaction is visible, so the policy keeps it to admins
+pub fn routes(schema: LedgerSchema, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("graphql").and(auth.authorized()).and(async_graphql_warp::graphql(schema)).and_then(
+        |principal: Principal, (schema, request): (LedgerSchema, async_graphql::Request)| async move {
+            debug!(subject = %principal.subject, "graphql query");
+            Ok::<_, Infallible>(GraphQLResponse::from(schema.execute(request).await))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use crate::storage::mvcc::Store;
+    use std::collections::HashMap;
+    use warp::http::StatusCode;
+
+    fn schema_with(config: &GraphqlConfig) -> LedgerSchema {
+        let store = Store::new();
+        schema(config, LedgerHistory::new(store.clone()), EntityStore::new(store, "accounts"))
+    }
+
+    #[tokio::test]
+    async fn charges_pages_at_most_the_page_size_cap() {
+        let config = GraphqlConfig { max_depth: 8, max_complexity: 500, max_page_size: 100 };
+        let schema = schema_with(&config);
+        let capped = schema.execute("{ transactions(first: 1000000) { txId kind } }").await;
+        assert!(capped.errors.is_empty(), "{:?}", capped.errors);
+
+        let huge = format!("{{ threats(first: {}) {{ id source severity detail at }} }}", i32::MAX);
+        assert!(schema.execute(huge).await.errors.is_empty());
+
+        let refused = schema.execute("{ transactions(first: 1000) { txId kind asset amount toAsset amountOut at } }").await;
+        assert!(refused.errors.iter().any(|e| e.message.contains("complex")), "{:?}", refused.errors);
+    }
+
+    #[tokio::test]
+    async fn is_for_admins_only() {
+        let keys = HashMap::from([
+            ("k-admin".to_string(), ApiKeyConfig { subject: "ops".to_string(), scopes: vec![Scope::Admin], tenant: None }),
+            ("k-conv".to_string(), ApiKeyConfig { subject: "alice".to_string(), scopes: vec![Scope::Convert], tenant: None }),
+        ]);
+        let auth = Auth::new(&AuthConfig { api_keys: keys, ..AuthConfig::default() }).unwrap();
+        let api = warp::any().and(Router::new().mount("graphql", routes(schema_with(&GraphqlConfig::default()), &auth)).build());
+        let query = |key: &str| {
+            warp::test::request().method("POST").path("/graphql").header("x-api-key", key).json(&serde_json::json!({ "query": "{ transactions { txId } }" }))
+        };
+        assert_eq!(query("k-conv").reply(&api).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(query("k-admin").reply(&api).await.status(), StatusCode::OK);
+    }
+}
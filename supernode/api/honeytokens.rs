@@ -0,0 +1,148 @@
+use crate::alerting::{Alert, Alerter, Severity};
+use crate::api::auth::{key_fingerprint, Auth};
+use crate::server::PeerAddr;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::Infallible;
+use std::sync::{Arc, RwLock};
+use tracing::error;
+use warp::http::{HeaderMap, Method};
+use warp::path::FullPath;
+use warp::{Filter, Rejection, Reply};
+
+// Everything known about a request that presented a decoy credential
+#[derive(Clone, Debug, Serialize)]
+pub struct Capture {
+    pub at: DateTime<Utc>,
+    pub credential: String,
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub peer: Option<String>,
+    pub headers: BTreeMap<String, String>,
+}
+
+// Decoy API keys that no legitimate client holds; any use means a credential store leaked
+#[derive(Clone)]
+pub struct Tripwire {
+    tokens: Arc<RwLock<HashSet<String>>>,
+    alerter: Alerter,
+}
+
+impl Tripwire {
+    pub fn new(tokens: &[String], alerter: Alerter) -> Self {
+        Tripwire { tokens: Arc::new(RwLock::new(tokens.iter().cloned().collect())), alerter }
+    }
+
+    // Mint a new decoy key to plant in a config repo, vault or CI secret store
+    pub fn create(&self) -> String {
+        let mut bytes = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = hex::encode(bytes);
+        self.tokens.write().unwrap().insert(token.clone());
+        token
+    }
+
+    fn tripped(&self, api_key: Option<&str>, authorization: Option<&str>) -> Option<String> {
+        let tokens = self.tokens.read().unwrap();
+        let bearer = authorization.and_then(|h| h.strip_prefix("Bearer "));
+        [api_key, bearer].into_iter().flatten().find(|t| tokens.contains(*t)).map(key_fingerprint)
+    }
+
+    // Checked ahead of every route; the caller still gets an ordinary 401 from auth
+    pub fn filter(&self) -> impl Filter<Extract = (), Error = Infallible> + Clone {
+        let tripwire = self.clone();
+        warp::method()
+            .and(warp::path::full())
+            .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+            .and(warp::header::headers_cloned())
+            .and(warp::ext::optional::<PeerAddr>())
+            .map(move |method: Method, path: FullPath, query: String, headers: HeaderMap, peer: Option<PeerAddr>| {
+                let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+                if let Some(credential) = tripwire.tripped(header("x-api-key"), header("authorization")) {
+                    tripwire.alert(Capture {
+                        at: Utc::now(),
+                        credential,
+                        method: method.to_string(),
+                        path: path.as_str().to_string(),
+                        query: Some(query).filter(|q| !q.is_empty()),
+                        peer: peer.map(|p| p.0.to_string()),
+                        headers: headers
+                            .iter()
+                            .filter(|(name, _)| *name != "x-api-key" && *name != "authorization")
+                            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+                            .collect(),
+                    });
+                }
+            })
+            .untuple_one()
+    }
+
+    fn alert(&self, capture: Capture) {
+        error!(credential = %capture.credential, path = %capture.path, peer = ?capture.peer, "honeytoken used");
+        self.alerter.raise(Alert {
+            kind: "honeytoken_used".to_string(),
+            severity: Severity::Critical,
+            message: serde_json::to_string(&capture).unwrap_or_else(|_| capture.credential.clone()),
+            at: capture.at,
+        });
+    }
+
+    // POST /admin/honeytokens
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let tripwire = self.clone();
+        warp::path!("admin" / "honeytokens")
+            .and(warp::post())
+            .and(auth.authorized())
+            .map(move |_| warp::reply::with_status(warp::reply::json(&tripwire.create()), warp::http::StatusCode::CREATED))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::{AlertChannel, Delivery, Route};
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use warp::http::StatusCode;
+
+    #[derive(Default)]
+    struct Captured(Mutex<Vec<Alert>>);
+
+    impl AlertChannel for Captured {
+        fn name(&self) -> &str {
+            "captured"
+        }
+
+        fn send(&self, alerts: &[Alert]) {
+            self.0.lock().unwrap().extend_from_slice(alerts);
+        }
+    }
+
+    #[tokio::test]
+    async fn minted_decoys_trip_the_alarm_and_still_get_a_401() {
+        let alerts = Arc::new(Captured::default());
+        let route = Route { channel: alerts.clone(), min_severity: Severity::Info, delivery: Delivery::Immediate, quiet_hours: None };
+        let tripwire = Tripwire::new(&["planted".to_string()], Alerter::new(vec![route]));
+        let api_keys = HashMap::from([("k-admin".to_string(), ApiKeyConfig { subject: "ops".to_string(), scopes: vec![Scope::Admin], tenant: None })]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let api = tripwire.filter().and(Router::new().mount("honeytokens", tripwire.routes(&auth)).build());
+
+        let minted = warp::test::request().method("POST").path("/admin/honeytokens").header("x-api-key", "k-admin").reply(&api).await;
+        assert_eq!(minted.status(), StatusCode::CREATED);
+        assert!(alerts.0.lock().unwrap().is_empty());
+        let decoy: String = serde_json::from_slice(minted.body()).unwrap();
+
+        for credential in ["planted", decoy.as_str()] {
+            let used = warp::test::request().method("POST").path("/admin/honeytokens").header("x-api-key", credential).reply(&api).await;
+            assert_eq!(used.status(), StatusCode::UNAUTHORIZED);
+        }
+        let alerts = alerts.0.lock().unwrap();
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts.iter().all(|a| a.kind == "honeytoken_used" && a.severity == Severity::Critical));
+    }
+}
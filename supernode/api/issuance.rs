@@ -0,0 +1,232 @@
+use crate::amount::{units_string, AnyAmount};
+use crate::api::auth::{Auth, Principal};
+use crate::api::problem::ApiError;
+use crate::api::redemption::{stage_credits, LedgerAccount};
+use crate::api::validation::{validated_json, FieldError, Validate, ValidationConfig};
+use crate::events::bus::{Event, EventBus};
+use crate::fees::{FeeCharge, FeeError, FeeOperation, FeeSchedule};
+use crate::ids::TxId;
+use crate::storage::entities::EntityStore;
+use crate::storage::mvcc::WriteBatch;
+use crate::tenants::{TenantError, TenantRegistry};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use warp::{Filter, Rejection, Reply};
+
+// Internal issuance order; wire formats convert into this so it can change without breaking clients
+#[derive(Clone, Debug)]
+pub struct Issuance {
+    pub amount: AnyAmount,
+    pub recipient: String,
+    pub memo: Option<String>,
+}
+
+impl Issuance {
+    // Fees on this issuance; the recipient is credited `net_amount` and the fee recipient `total`
+    pub fn fees(&self, schedule: &FeeSchedule) -> Result<FeeCharge, FeeError> {
+        schedule.charge(FeeOperation::Issuance, &self.amount.asset, self.amount.units)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidRequest(pub String);
+
+impl fmt::Display for InvalidRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub enum IssuanceError {
+    Fee(FeeError),
+    Quota(TenantError),
+
+    // The credits could not be committed
+    Ledger(String),
+}
+
+impl fmt::Display for IssuanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IssuanceError::Fee(e) => write!(f, "{}", e),
+            IssuanceError::Quota(e) => write!(f, "{}", e),
+            IssuanceError::Ledger(e) => write!(f, "issuance not recorded: {}", e),
+        }
+    }
+}
+
+impl From<IssuanceError> for ApiError {
+    fn from(error: IssuanceError) -> Self {
+        match error {
+            IssuanceError::Fee(_) => ApiError::Unprocessable(error.to_string()),
+            IssuanceError::Quota(e) => ApiError::from(e),
+            IssuanceError::Ledger(_) => ApiError::Unavailable(error.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct IssuanceResponse {
+    #[schema(value_type = String)]
+    pub tx_id: TxId,
+    pub asset: String,
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub amount: u128,
+    pub recipient: String,
+
+    // What the recipient was credited: `amount` less fees
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub credited: u128,
+
+    // Itemized; absent when nothing was charged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<FeeCharge>,
+}
+
+// Mints to a recipient account, less fees credited to the fee recipient in the same commit
+#[derive(Clone)]
+pub struct Issuances {
+    accounts: EntityStore<LedgerAccount>,
+    fees: FeeSchedule,
+    bus: EventBus,
+
+    // Charges tenant callers' issuance quotas
+    tenants: Option<TenantRegistry>,
+}
+
+impl Issuances {
+    pub fn new(accounts: EntityStore<LedgerAccount>, fees: FeeSchedule, bus: EventBus) -> Self {
+        Issuances { accounts, fees, bus, tenants: None }
+    }
+
+    pub fn with_tenants(mut self, tenants: TenantRegistry) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    // Issue for `principal`; a tenant's quota is reserved first and given back if the issuance fails
+    pub fn issue(&self, principal: &Principal, issuance: &Issuance) -> Result<IssuanceResponse, IssuanceError> {
+        let quota = self.tenants.as_ref().zip(principal.tenant.as_deref());
+        if let Some((tenants, tenant)) = quota {
+            tenants.reserve_issuance(tenant, &issuance.amount).map_err(IssuanceError::Quota)?;
+        }
+        let result = self.credit(issuance, principal.tenant.as_deref());
+        if let (Err(_), Some((tenants, tenant))) = (&result, quota) {
+            if let Err(e) = tenants.release_issuance(tenant, &issuance.amount) {
+                warn!(tenant, error = %e, "could not release issuance quota after a failed issuance");
+            }
+        }
+        result
+    }
+
+    fn credit(&self, issuance: &Issuance, tenant: Option<&str>) -> Result<IssuanceResponse, IssuanceError> {
+        let fees = issuance.fees(&self.fees).map_err(IssuanceError::Fee)?;
+        let asset = issuance.amount.asset.as_str();
+        let mut credits = vec![(issuance.recipient.as_str(), asset, fees.net_amount)];
+        if fees.total > 0 {
+            credits.push((fees.recipient.as_str(), asset, fees.total));
+        }
+        let guard = self.accounts.lock();
+        let mut batch = WriteBatch::default();
+        stage_credits(&self.accounts, &mut batch, &credits).map_err(IssuanceError::Ledger)?;
+        self.accounts.commit(batch).map_err(|e| IssuanceError::Ledger(e.to_string()))?;
+        drop(guard);
+
+        let tx_id = TxId::new();
+        let credited = fees.net_amount;
+        let fees = (fees.total > 0).then_some(fees);
+        self.bus.publish(Event::IssuanceCompleted {
+            tx_id: tx_id.to_string(),
+            asset: asset.to_string(),
+            amount: issuance.amount.units.to_string(),
+            fee: fees.clone(),
+            tenant: tenant.map(str::to_string),
+        });
+        info!(%tx_id, recipient = %issuance.recipient, asset, amount = %issuance.amount.units, credited, "issuance completed");
+        Ok(IssuanceResponse {
+            tx_id,
+            asset: asset.to_string(),
+            amount: issuance.amount.units,
+            recipient: issuance.recipient.clone(),
+            credited,
+            fees,
+        })
+    }
+
+    // POST /v1/issuance
+    pub fn routes(&self, auth: &Auth, rules: Arc<ValidationConfig>) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let issuances = self.clone();
+        warp::path!("v1" / "issuance").and(warp::post()).and(auth.authorized()).and(validated_json(rules)).and_then(
+            move |principal: Principal, request: v1::IssuanceRequest| {
+                let result = Issuance::try_from(request)
+                    .map_err(|e| ApiError::Unprocessable(e.to_string()))
+                    .and_then(|issuance| issuances.issue(&principal, &issuance).map_err(ApiError::from))
+                    .map_err(|e| {
+                        warn!(subject = %principal.subject, error = %e, "issuance rejected");
+                        warp::reject::custom(e)
+                    });
+                async move { result.map(|response| warp::reply::json(&response)) }
+            },
+        )
+    }
+}
+
+// Wire types of /v1; frozen once published, later versions get their own module
+pub mod v1 {
+    use super::*;
+
+    #[derive(Clone, Debug, Deserialize, ToSchema)]
+    #[serde(deny_unknown_fields)]
+    pub struct IssuanceRequest {
+        pub asset: String,
+
+        // Integer amount in the asset's smallest unit, as a string so JavaScript clients keep precision
+        pub amount: String,
+
+        pub recipient: String,
+        #[serde(default)]
+        pub memo: Option<String>,
+    }
+
+    impl Validate for IssuanceRequest {
+        fn validate(&self, rules: &ValidationConfig) -> Vec<FieldError> {
+            let mut errors = Vec::new();
+            let limits = rules.asset("asset", &self.asset, &mut errors);
+            match self.amount.parse::<u128>() {
+                Ok(amount) => rules.amount("amount", amount, limits, &mut errors),
+                Err(_) => errors.push(FieldError::new("amount", "must be a non-negative integer string")),
+            }
+            if self.recipient.trim().is_empty() {
+                errors.push(FieldError::new("recipient", "is required"));
+            }
+            if self.memo.as_ref().is_some_and(|m| m.chars().count() > rules.max_memo_chars) {
+                errors.push(FieldError::new("memo", format!("must be at most {} characters", rules.max_memo_chars)));
+            }
+            errors
+        }
+    }
+
+    impl TryFrom<IssuanceRequest> for Issuance {
+        type Error = InvalidRequest;
+
+        fn try_from(request: IssuanceRequest) -> Result<Self, Self::Error> {
+            let amount = request
+                .amount
+                .parse::<u128>()
+                .map_err(|_| InvalidRequest(format!("amount `{}` is not a non-negative integer", request.amount)))?;
+            if amount == 0 {
+                return Err(InvalidRequest("amount must be positive".to_string()));
+            }
+            if request.recipient.is_empty() {
+                return Err(InvalidRequest("recipient is required".to_string()));
+            }
+            Ok(Issuance { amount: AnyAmount::new(request.asset, amount), recipient: request.recipient, memo: request.memo })
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::ai::explain::{MatchedFeature, RejectionReport};
+use crate::ai::feedback::{FeedbackRequest, FeedbackStats, Label, StatsResponse};
+use crate::amount::Rounding;
+use crate::api::fee_estimate::{FeeEstimate, FeeEstimateRequest, LimitCheck, Operation};
+use crate::api::issuance::v1::IssuanceRequest;
+use crate::api::issuance::IssuanceResponse;
+use crate::api::problem::Problem;
+use crate::api::preflight::{Check, CheckKind, ItemVerdict, PreflightItem, PreflightReport, PreflightRequest};
+use crate::api::redemption::{AccountKey, RedemptionRequest, RedemptionResponse};
+use crate::api::validation::FieldError;
+use crate::assets::{AssetConfig, RateSource};
+use crate::converter::{AcceptedConversion, Conversion, ConvertRequest, Direction, QuotedRate, RateQuote};
+use crate::fees::{FeeCharge, FeeItem};
+use crate::health::{CheckResult, DependencyReport, DependencyResult, HealthReport, Status};
+use crate::tenant_usage::TenantUsage;
+use std::sync::Arc;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::Config;
+use warp::http::{StatusCode, Uri};
+use warp::path::{FullPath, Tail};
+use warp::{Filter, Rejection, Reply};
+
+// Route handlers are warp filter chains, so each operation is described on a stub here
+#[allow(dead_code)]
+mod paths {
+    #[utoipa::path(post, path = "/v1/issuance", tag = "ledger", request_body = IssuanceRequest,
+        security(("api_key" = []), ("bearer" = [])),
+        responses(
+            (status = 200, description = "Recipient credited the amount less fees; fees credited to the fee recipient", body = IssuanceResponse),
+            (status = 422, description = "Field-level validation errors, or fees that would take the whole amount", body = Problem),
+        ))]
+    fn issuance() {}
+
+    #[utoipa::path(post, path = "/v1/redemption", tag = "ledger", request_body = RedemptionRequest,
+        security(("api_key" = []), ("bearer" = [])),
+        responses(
+            (status = 200, description = "Balance burned", body = RedemptionResponse),
+            (status = 403, description = "Burn authorization signature is invalid"),
+            (status = 404, description = "Unknown account"),
+            (status = 409, description = "No key registered, nonce reused or insufficient balance"),
+            (status = 422, description = "Field-level validation errors", body = Problem),
+        ))]
+    fn redemption() {}
+
+    #[utoipa::path(put, path = "/v1/accounts/{account}/key", tag = "ledger", request_body = AccountKey,
+        security(("api_key" = []), ("bearer" = [])),
+        params(("account" = String, Path, description = "Account id, the holder's subject")),
+        responses(
+            (status = 200, description = "Key registered; burns of the account must now be signed by it", body = AccountKey),
+            (status = 403, description = "Caller is neither the holder nor an admin"),
+            (status = 409, description = "A key is already registered and only an admin may replace it"),
+            (status = 422, description = "Not an ed25519 public key", body = Problem),
+        ))]
+    fn account_key() {}
+
+    #[utoipa::path(post, path = "/v1/fees/estimate", tag = "ledger", request_body = FeeEstimateRequest,
+        responses((status = 200, description = "Fee breakdown for a hypothetical transaction", body = FeeEstimate),
+            (status = 422, description = "Field-level validation errors, or fees that overflow or take the whole amount", body = Problem)))]
+    fn fee_estimate() {}
+
+    #[utoipa::path(post, path = "/v1/preflight", tag = "ledger", request_body = PreflightRequest,
+        security(("api_key" = []), ("bearer" = [])),
+        responses((status = 200, description = "Per-item verdicts for a proposed batch; nothing is submitted", body = PreflightReport),
+            (status = 422, description = "Empty or oversized batch", body = Problem)))]
+    fn preflight() {}
+
+    #[utoipa::path(post, path = "/convert", tag = "ledger", request_body = ConvertRequest,
+        security(("api_key" = []), ("bearer" = [])),
+        responses((status = 200, description = "Conversion accepted at the current rate and recorded in the ledger under `id`", body = AcceptedConversion),
+            (status = 404, description = "No rate for the pair", body = Problem),
+            (status = 403, description = "Rejected by the anomaly engine; `report` explains why", body = Problem),
+            (status = 409, description = "The rate moved against quoted_rate by more than max_slippage_bps, too little liquidity for the direction, or too small a balance", body = Problem),
+            (status = 422, description = "Field-level validation errors", body = Problem),
+            (status = 503, description = "The conversion could not be screened or recorded", body = Problem)))]
+    fn convert() {}
+
+    #[utoipa::path(get, path = "/rates", tag = "ledger",
+        responses((status = 200, description = "Current conversion rates", body = [RateQuote])))]
+    fn rates() {}
+
+    #[utoipa::path(get, path = "/rates/{from}/{to}", tag = "ledger", params(("from" = String, Path,), ("to" = String, Path,)),
+        responses((status = 200, description = "Current rate for the pair", body = RateQuote),
+            (status = 404, description = "No rate for the pair", body = Problem),
+            (status = 422, description = "Asset unknown, disabled or not a conversion target", body = Problem)))]
+    fn rate() {}
+
+    #[utoipa::path(get, path = "/v1/assets", tag = "ledger",
+        responses((status = 200, description = "Assets by symbol, with decimals, whether enabled, and their rate source")))]
+    fn assets() {}
+
+    #[utoipa::path(post, path = "/v1/feedback", tag = "ai", request_body = FeedbackRequest,
+        security(("api_key" = []), ("bearer" = [])),
+        responses((status = 202, description = "Label recorded"), (status = 404, description = "Unknown request id", body = Problem)))]
+    fn feedback() {}
+
+    #[utoipa::path(get, path = "/v1/feedback/stats", tag = "ai",
+        security(("api_key" = []), ("bearer" = [])),
+        responses((status = 200, description = "Confusion matrix and current threshold", body = StatsResponse)))]
+    fn feedback_stats() {}
+
+    #[utoipa::path(get, path = "/v1/decisions/{id}", tag = "ai", params(("id" = String, Path, description = "Decision id")),
+        security(("api_key" = []), ("bearer" = [])),
+        responses((status = 200, description = "Why the decision rejected the request", body = RejectionReport),
+            (status = 404, description = "Unknown decision, or one that rejected another caller", body = Problem)))]
+    fn decision() {}
+
+    #[utoipa::path(get, path = "/v1/tenants/{tenant}/usage", tag = "tenants", params(("tenant" = String, Path,)),
+        responses((status = 200, description = "Resources consumed by the tenant", body = TenantUsage),
+            (status = 401, description = "Authentication required"), (status = 403, description = "Key belongs to another tenant")))]
+    fn tenant_usage() {}
+
+    #[utoipa::path(get, path = "/healthz", tag = "operations",
+        responses((status = 200, description = "Alive", body = HealthReport), (status = 503, description = "A task died", body = HealthReport)))]
+    fn healthz() {}
+
+    #[utoipa::path(get, path = "/healthz/dependencies", tag = "operations",
+        responses((status = 200, description = "Every dependency reachable", body = DependencyReport),
+            (status = 503, description = "A dependency is unreachable or timed out", body = DependencyReport)))]
+    fn healthz_dependencies() {}
+
+    #[utoipa::path(get, path = "/readyz", tag = "operations",
+        responses((status = 200, description = "Ready to serve", body = HealthReport), (status = 503, description = "A dependency is failing", body = HealthReport)))]
+    fn readyz() {}
+
+    #[utoipa::path(get, path = "/metrics", tag = "operations",
+        responses((status = 200, description = "Prometheus text exposition", content_type = "text/plain")))]
+    fn metrics() {}
+}
+
+struct Security;
+
+impl Modify for Security {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))));
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Pi Supernode API"),
+    paths(
+        paths::issuance,
+        paths::redemption,
+        paths::account_key,
+        paths::fee_estimate,
+        paths::preflight,
+        paths::convert,
+        paths::rates,
+        paths::rate,
+        paths::assets,
+        paths::feedback,
+        paths::feedback_stats,
+        paths::decision,
+        paths::tenant_usage,
+        paths::healthz,
+        paths::healthz_dependencies,
+        paths::readyz,
+        paths::metrics,
+    ),
+    components(schemas(
+        IssuanceRequest,
+        IssuanceResponse,
+        RedemptionRequest,
+        RedemptionResponse,
+        AccountKey,
+        FeeEstimateRequest,
+        FeeEstimate,
+        LimitCheck,
+        Operation,
+        PreflightRequest,
+        PreflightItem,
+        PreflightReport,
+        ItemVerdict,
+        Check,
+        CheckKind,
+        ConvertRequest,
+        QuotedRate,
+        AcceptedConversion,
+        Conversion,
+        Direction,
+        FeeCharge,
+        FeeItem,
+        Rounding,
+        RateQuote,
+        AssetConfig,
+        RateSource,
+        FeedbackRequest,
+        FeedbackStats,
+        Label,
+        StatsResponse,
+        RejectionReport,
+        MatchedFeature,
+        TenantUsage,
+        HealthReport,
+        DependencyReport,
+        DependencyResult,
+        CheckResult,
+        Status,
+        Problem,
+        FieldError,
+    )),
+    modifiers(&Security)
+)]
+pub struct ApiDoc;
+
+async fn serve_swagger(full_path: FullPath, tail: Tail, config: Arc<Config<'static>>) -> Result<Box<dyn Reply>, Rejection> {
+    if full_path.as_str() == "/swagger-ui" {
+        return Ok(Box::new(warp::redirect::found(Uri::from_static("/swagger-ui/"))));
+    }
+    match utoipa_swagger_ui::serve(tail.as_str(), config) {
+        Ok(Some(file)) => Ok(Box::new(warp::reply::with_header(file.bytes.to_vec(), "content-type", file.content_type))),
+        Ok(None) => Ok(Box::new(StatusCode::NOT_FOUND)),
+        Err(e) => Ok(Box::new(warp::reply::with_status(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))),
+    }
+}
+
+const METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+// The API document with only the operations `keep(method, path)` accepts; paths left empty are dropped
+pub fn document_where(keep: impl Fn(&str, &str) -> bool) -> serde_json::Value {
+    let mut document = serde_json::to_value(ApiDoc::openapi()).unwrap_or_default();
+    if let Some(paths) = document.get_mut("paths").and_then(|p| p.as_object_mut()) {
+        paths.retain(|path, item| {
+            let Some(item) = item.as_object_mut() else { return false };
+            item.retain(|key, _| !METHODS.contains(&key.as_str()) || keep(&key.to_uppercase(), path));
+            METHODS.iter().any(|m| item.contains_key(*m))
+        });
+    }
+    document
+}
+
+// GET /openapi.json and the Swagger UI under /swagger-ui/
+pub fn routes() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let document = warp::path!("openapi.json").and(warp::get()).map(|| warp::reply::json(&ApiDoc::openapi()));
+    let config = Arc::new(Config::from("/openapi.json"));
+    let swagger = warp::path("swagger-ui")
+        .and(warp::get())
+        .and(warp::path::full())
+        .and(warp::path::tail())
+        .and(warp::any().map(move || config.clone()))
+        .and_then(serve_swagger);
+    document.or(swagger)
+}
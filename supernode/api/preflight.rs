@@ -0,0 +1,308 @@
+use crate::admin::policy_params::{PolicyParamStore, PolicyParams};
+use crate::amount::AnyAmount;
+use crate::api::auth::{Auth, AuthError, Principal, Scope};
+use crate::api::fee_estimate::{policy_limits, FeeEstimateRequest, Operation};
+use crate::api::redemption::LedgerAccount;
+use crate::api::validation::{validated_json, FieldError, Validate, ValidationConfig};
+use crate::storage::entities::{EntityError, EntityStore};
+use crate::tenants::TenantRegistry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+use utoipa::ToSchema;
+use warp::http::Method;
+use warp::{Filter, Rejection, Reply};
+
+// Largest batch judged in one request
+const MAX_ITEMS: usize = 1000;
+
+// One proposed operation; nothing is created, reserved or debited
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PreflightItem {
+    pub operation: Operation,
+    pub asset: String,
+
+    // Target asset for conversions
+    #[serde(default)]
+    pub to_asset: Option<String>,
+
+    pub amount: u128,
+
+    // Account debited by redemptions and conversions; required for redemptions
+    #[serde(default)]
+    pub account: Option<String>,
+
+    // Caller's own id for the item, echoed in its verdict
+    #[serde(default)]
+    pub reference: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PreflightRequest {
+    pub items: Vec<PreflightItem>,
+}
+
+// Only the batch size is rejected up front; everything about an item is reported in its verdict
+impl Validate for PreflightRequest {
+    fn validate(&self, _rules: &ValidationConfig) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.items.is_empty() {
+            errors.push(FieldError::new("items", "must not be empty"));
+        } else if self.items.len() > MAX_ITEMS {
+            errors.push(FieldError::new("items", format!("at most {} per request", MAX_ITEMS)));
+        }
+        errors
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    // Supported assets, amount bounds and tenant issuance quotas
+    Limits,
+    Balance,
+
+    // Routes the caller's tenant plan includes
+    Entitlement,
+
+    // Amount limits set through policy params
+    Policy,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct Check {
+    pub kind: CheckKind,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ItemVerdict {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+
+    // Every check passed
+    pub passed: bool,
+    pub checks: Vec<Check>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct PreflightReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub items: Vec<ItemVerdict>,
+}
+
+fn check(kind: CheckKind, result: Result<String, String>) -> Check {
+    match result {
+        Ok(detail) => Check { kind, passed: true, detail },
+        Err(detail) => Check { kind, passed: false, detail },
+    }
+}
+
+// Route and scope an operation is submitted with
+fn route(operation: Operation) -> (&'static str, Scope) {
+    match operation {
+        Operation::Issue => ("/v1/issuance", Scope::Issue),
+        Operation::Redeem => ("/v1/redemption", Scope::Redeem),
+        Operation::Convert => ("/v1/conversions", Scope::Convert),
+    }
+}
+
+// Judges a batch against the node's current state as if it were submitted item by item, in order: items that
+// pass count against the balances and quotas later ones see, so a batch that only fits partly fails at the right item
+#[derive(Clone)]
+pub struct Preflight {
+    rules: Arc<ValidationConfig>,
+    params: PolicyParamStore,
+    auth: Auth,
+    accounts: EntityStore<LedgerAccount>,
+    tenants: Option<TenantRegistry>,
+}
+
+// What earlier items of the batch would have used
+#[derive(Default)]
+struct Planned {
+    // (account, asset) -> debited
+    debits: HashMap<(String, String), u128>,
+
+    // Asset -> issued by the caller's tenant
+    issued: HashMap<String, u128>,
+}
+
+impl Planned {
+    fn record(&mut self, item: &PreflightItem) {
+        match (item.operation, &item.account) {
+            (Operation::Issue, _) => {
+                let issued = self.issued.entry(item.asset.clone()).or_default();
+                *issued = issued.saturating_add(item.amount);
+            }
+            (_, Some(account)) => {
+                let debited = self.debits.entry((account.clone(), item.asset.clone())).or_default();
+                *debited = debited.saturating_add(item.amount);
+            }
+            (_, None) => {}
+        }
+    }
+}
+
+impl Preflight {
+    pub fn new(rules: Arc<ValidationConfig>, params: PolicyParamStore, auth: Auth, accounts: EntityStore<LedgerAccount>) -> Self {
+        Preflight { rules, params, auth, accounts, tenants: None }
+    }
+
+    // Also check tenant callers' issuance quotas
+    pub fn with_tenants(mut self, tenants: TenantRegistry) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    pub fn run(&self, principal: &Principal, request: &PreflightRequest) -> PreflightReport {
+        let params = self.params.current().value;
+        let mut planned = Planned::default();
+        let items: Vec<ItemVerdict> = request
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let mut checks = vec![check(CheckKind::Limits, self.limits(principal, item, &planned))];
+                if let Some(balance) = self.balance(item, &planned) {
+                    checks.push(check(CheckKind::Balance, balance));
+                }
+                checks.push(check(CheckKind::Entitlement, self.entitlement(principal, item)));
+                checks.push(check(CheckKind::Policy, policy(&params, item)));
+                let passed = checks.iter().all(|c| c.passed);
+                if passed {
+                    planned.record(item);
+                }
+                ItemVerdict { index, reference: item.reference.clone(), passed, checks }
+            })
+            .collect();
+        let passed = items.iter().filter(|i| i.passed).count();
+        PreflightReport { passed, failed: items.len() - passed, items }
+    }
+
+    fn limits(&self, principal: &Principal, item: &PreflightItem, planned: &Planned) -> Result<String, String> {
+        let mut errors = Vec::new();
+        let limits = self.rules.asset("asset", &item.asset, &mut errors);
+        self.rules.amount("amount", item.amount, limits, &mut errors);
+        match (item.operation, &item.to_asset) {
+            (Operation::Convert, Some(to)) => {
+                self.rules.asset("to_asset", to, &mut errors);
+            }
+            (Operation::Convert, None) => errors.push(FieldError::new("to_asset", "is required for conversions")),
+            (_, Some(_)) => errors.push(FieldError::new("to_asset", "is only allowed for conversions")),
+            (_, None) => {}
+        }
+        if let Some(e) = errors.first() {
+            return Err(format!("{} {}", e.field, e.message));
+        }
+
+        let (Operation::Issue, Some(tenants), Some(tenant)) = (item.operation, &self.tenants, &principal.tenant) else {
+            return Ok("within asset limits".to_string());
+        };
+        let issued = planned.issued.get(&item.asset).copied().unwrap_or(0);
+        tenants.check_issuance(tenant, &AnyAmount::new(item.asset.clone(), item.amount), issued).map_err(|e| e.to_string())?;
+        Ok("within asset limits and the tenant's issuance quota".to_string())
+    }
+
+    // `None` for operations that debit nothing
+    fn balance(&self, item: &PreflightItem, planned: &Planned) -> Option<Result<String, String>> {
+        let account = match (item.operation, &item.account) {
+            (Operation::Issue, _) | (Operation::Convert, None) => return None,
+            (Operation::Redeem, None) => return Some(Err("account is required for redemptions".to_string())),
+            (_, Some(account)) => account,
+        };
+        let balance = match self.accounts.get(account) {
+            Ok(entity) => entity.value.balances.get(&item.asset).copied().unwrap_or(0),
+            Err(EntityError::NotFound) => return Some(Err(format!("unknown account {}", account))),
+            Err(e) => return Some(Err(e.to_string())),
+        };
+        let debited = planned.debits.get(&(account.clone(), item.asset.clone())).copied().unwrap_or(0);
+        let available = balance.saturating_sub(debited);
+        if item.amount > available {
+            return Some(Err(format!("insufficient balance, {} available after earlier items", available)));
+        }
+        Some(Ok(format!("{} available", available)))
+    }
+
+    fn entitlement(&self, principal: &Principal, item: &PreflightItem) -> Result<String, String> {
+        let (path, _) = route(item.operation);
+        match self.auth.entitled(principal, &Method::POST, path) {
+            Ok(()) => Ok(format!("POST {} allowed", path)),
+            Err(AuthError::NotEntitled(e)) => Err(e),
+            Err(_) => Err(format!("POST {} is not allowed for this caller", path)),
+        }
+    }
+}
+
+fn policy(params: &PolicyParams, item: &PreflightItem) -> Result<String, String> {
+    let request = FeeEstimateRequest { operation: item.operation, asset: item.asset.clone(), to_asset: item.to_asset.clone(), amount: item.amount };
+    let limits = policy_limits(params, &request);
+    match limits.iter().find(|l| !l.within) {
+        Some(l) => Err(format!("{} is {}", l.name, l.limit)),
+        None if limits.is_empty() => Ok("no policy limits apply".to_string()),
+        None => Ok("within policy limits".to_string()),
+    }
+}
+
+// POST /v1/preflight; the caller needs the scope of every operation in the batch, as if it submitted them
+pub fn routes(preflight: Preflight, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let rules = preflight.rules.clone();
+    warp::path!("v1" / "preflight").and(warp::post()).and(auth.authorized()).and(validated_json(rules)).and_then(
+        move |principal: Principal, request: PreflightRequest| {
+            let missing = request.items.iter().map(|item| route(item.operation).1).find(|scope| !principal.has_scope(*scope));
+            let result = match missing {
+                Some(scope) => Err(warp::reject::custom(AuthError::MissingScope(scope))),
+                None => {
+                    let report = preflight.run(&principal, &request);
+                    info!(subject = %principal.subject, items = request.items.len(), failed = report.failed, "preflight checked");
+                    Ok(warp::reply::json(&report))
+                }
+            };
+            async move { result }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::policy_params::PolicyGuardConfig;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig};
+    use crate::api::router::Router;
+    use crate::api::validation::AssetLimits;
+    use crate::storage::mvcc::Store;
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn callers_only_preflight_operations_they_could_submit() {
+        let store = Store::new();
+        let key = |subject: &str, scopes| ApiKeyConfig { subject: subject.to_string(), scopes, tenant: None };
+        let api_keys = HashMap::from([("k-convert".to_string(), key("app", vec![Scope::Convert])), ("k-probe".to_string(), key("probe", vec![]))]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let rules = Arc::new(ValidationConfig {
+            assets: BTreeMap::from([("PI".to_string(), AssetLimits { min_amount: 1, max_amount: 1_000_000 })]),
+            ..ValidationConfig::default()
+        });
+        let params = PolicyParamStore::new(
+            EntityStore::new(store.clone(), "policy_params"),
+            EntityStore::new(store.clone(), "policy_params_pending"),
+            PolicyGuardConfig::default(),
+        );
+        let preflight = Preflight::new(rules, params, auth.clone(), EntityStore::new(store, "accounts"));
+        let api = warp::any().and(Router::new().mount("preflight", routes(preflight, &auth)).build());
+        let post = |key: &str, operation: &str| {
+            let item = serde_json::json!({ "operation": operation, "asset": "PI", "to_asset": "PI", "amount": 10 });
+            warp::test::request().method("POST").path("/v1/preflight").header("x-api-key", key).json(&serde_json::json!({ "items": [item] })).reply(&api)
+        };
+
+        assert_eq!(post("k-convert", "convert").await.status(), 200);
+        assert_eq!(post("k-convert", "issue").await.status(), 403);
+        assert_eq!(post("k-probe", "convert").await.status(), 403);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::ai::explain::RejectionReport;
+use crate::api::auth::AuthError;
+use crate::api::validation::{FieldError, ValidationFailed};
+use crate::rate_limit::RateLimited;
+use crate::runtime::deadline::DeadlineExceeded;
+use crate::runtime::forensic::ForensicMode;
+use crate::storage::entities::{EntityError, PreconditionRequired};
+use serde::Serialize;
+use std::convert::Infallible;
+use utoipa::ToSchema;
+use warp::http::header::{HeaderValue, CONTENT_TYPE, RETRY_AFTER, WWW_AUTHENTICATE};
+use warp::http::StatusCode;
+use warp::reject::Reject;
+use warp::reply::Response;
+use warp::{Rejection, Reply};
+
+// Handler failure surfaced to clients; reject with `warp::reject::custom(ApiError::...)`
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Unprocessable(String),
+
+    // The resource existed but is no longer usable, e.g. an expired quote
+    Gone(String),
+
+    // A tenant quota or similar budget is spent for now
+    TooManyRequests(String),
+
+    // The anomaly engine blocked the request; the report goes out with the problem
+    Rejected(Box<RejectionReport>),
+    Unavailable(String),
+    Internal(String),
+}
+
+impl Reject for ApiError {}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ApiError::BadRequest(e)
+            | ApiError::Forbidden(e)
+            | ApiError::NotFound(e)
+            | ApiError::Conflict(e)
+            | ApiError::Unprocessable(e)
+            | ApiError::Gone(e)
+            | ApiError::TooManyRequests(e)
+            | ApiError::Unavailable(e)
+            | ApiError::Internal(e) => write!(f, "{}", e),
+            ApiError::Rejected(report) => write!(f, "{}", report),
+        }
+    }
+}
+
+impl ApiError {
+    fn status_and_code(&self) -> (StatusCode, &'static str) {
+        match self {
+            ApiError::BadRequest(_) => (StatusCode::BAD_REQUEST, "bad_request"),
+            ApiError::Forbidden(_) => (StatusCode::FORBIDDEN, "forbidden"),
+            ApiError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
+            ApiError::Conflict(_) => (StatusCode::CONFLICT, "conflict"),
+            ApiError::Unprocessable(_) => (StatusCode::UNPROCESSABLE_ENTITY, "unprocessable"),
+            ApiError::Gone(_) => (StatusCode::GONE, "gone"),
+            ApiError::TooManyRequests(_) => (StatusCode::TOO_MANY_REQUESTS, "too_many_requests"),
+            ApiError::Rejected(_) => (StatusCode::FORBIDDEN, "rejected"),
+            ApiError::Unavailable(_) => (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+            ApiError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        }
+    }
+}
+
+// RFC 7807 problem details body
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    // Stable machine-readable code clients can branch on
+    pub code: &'static str,
+
+    // Per-field failures of a 422
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+
+    // Why the anomaly engine rejected the request, also served under GET /v1/decisions/{id}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<RejectionReport>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: &'static str, detail: Option<String>) -> Self {
+        Problem {
+            kind: format!("https://docs.pi-supernode.dev/errors/{}", code),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail,
+            code,
+            errors: Vec::new(),
+            report: None,
+        }
+    }
+
+    pub(crate) fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = warp::reply::with_status(warp::reply::json(&self), status).into_response();
+        response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/problem+json"));
+        response
+    }
+}
+
+fn with_header(mut response: Response, name: warp::http::header::HeaderName, value: String) -> Response {
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        response.headers_mut().insert(name, value);
+    }
+    response
+}
+
+fn auth_problem(error: &AuthError) -> Response {
+    match error {
+        AuthError::Missing => with_header(
+            Problem::new(StatusCode::UNAUTHORIZED, "authentication_required", None).into_response(),
+            WWW_AUTHENTICATE,
+            "Bearer".to_string(),
+        ),
+        AuthError::Invalid(e) => with_header(
+            Problem::new(StatusCode::UNAUTHORIZED, "invalid_credentials", Some(e.clone())).into_response(),
+            WWW_AUTHENTICATE,
+            "Bearer error=\"invalid_token\"".to_string(),
+        ),
+        AuthError::Forbidden => with_header(
+            Problem::new(StatusCode::FORBIDDEN, "forbidden", None).into_response(),
+            WWW_AUTHENTICATE,
+            "Bearer error=\"insufficient_scope\"".to_string(),
+        ),
+        AuthError::MissingScope(scope) => with_header(
+            Problem::new(StatusCode::FORBIDDEN, "insufficient_scope", Some(format!("requires the {} scope", scope.as_str())))
+                .into_response(),
+            WWW_AUTHENTICATE,
+            format!("Bearer error=\"insufficient_scope\", scope=\"{}\"", scope.as_str()),
+        ),
+        AuthError::NotEntitled(e) => Problem::new(StatusCode::FORBIDDEN, "not_entitled", Some(e.clone())).into_response(),
+        AuthError::QuotaExceeded(e) => Problem::new(StatusCode::TOO_MANY_REQUESTS, "quota_exceeded", Some(e.clone())).into_response(),
+    }
+}
+
+fn entity_problem(error: &EntityError) -> Response {
+    let (status, code) = match error {
+        EntityError::NotFound => (StatusCode::NOT_FOUND, "not_found"),
+        EntityError::Conflict { .. } => (StatusCode::PRECONDITION_FAILED, "version_conflict"),
+        EntityError::AlreadyExists => (StatusCode::CONFLICT, "already_exists"),
+        EntityError::Codec(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        EntityError::ReadOnly => (StatusCode::SERVICE_UNAVAILABLE, "forensic_mode"),
+        EntityError::Unavailable(_) => (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+    };
+    Problem::new(status, code, Some(error.to_string())).into_response()
+}
+
+// Final handler for every route: `routes.recover(problem::recover)`
+pub async fn recover(rejection: Rejection) -> Result<Response, Infallible> {
+    let response = if let Some(e) = rejection.find::<AuthError>() {
+        auth_problem(e)
+    } else if let Some(ValidationFailed(errors)) = rejection.find::<ValidationFailed>() {
+        let mut problem = Problem::new(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed", None);
+        problem.errors = errors.clone();
+        problem.into_response()
+    } else if let Some(e) = rejection.find::<ApiError>() {
+        let (status, code) = e.status_and_code();
+        let mut problem = Problem::new(status, code, Some(e.to_string()));
+        if let ApiError::Rejected(report) = e {
+            problem.report = Some(report.as_ref().clone());
+        }
+        problem.into_response()
+    } else if let Some(e) = rejection.find::<EntityError>() {
+        entity_problem(e)
+    } else if rejection.find::<PreconditionRequired>().is_some() {
+        let detail = "mutating calls need an If-Match header with the entity version".to_string();
+        Problem::new(StatusCode::PRECONDITION_REQUIRED, "precondition_required", Some(detail)).into_response()
+    } else if let Some(e) = rejection.find::<DeadlineExceeded>() {
+        Problem::new(StatusCode::GATEWAY_TIMEOUT, "deadline_exceeded", Some(e.to_string())).into_response()
+    } else if let Some(limited) = rejection.find::<RateLimited>() {
+        with_header(
+            Problem::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", None).into_response(),
+            RETRY_AFTER,
+            limited.retry_after.as_secs().max(1).to_string(),
+        )
+    } else if rejection.is_not_found() {
+        Problem::new(StatusCode::NOT_FOUND, "not_found", None).into_response()
+    } else if let Some(e) = rejection.find::<warp::filters::body::BodyDeserializeError>() {
+        Problem::new(StatusCode::BAD_REQUEST, "invalid_body", Some(e.to_string())).into_response()
+    } else if let Some(e) = rejection.find::<warp::reject::InvalidQuery>() {
+        Problem::new(StatusCode::BAD_REQUEST, "invalid_query", Some(e.to_string())).into_response()
+    } else if let Some(e) = rejection.find::<warp::reject::MissingHeader>() {
+        Problem::new(StatusCode::BAD_REQUEST, "missing_header", Some(e.to_string())).into_response()
+    } else if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        Problem::new(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large", None).into_response()
+    } else if rejection.find::<warp::reject::LengthRequired>().is_some() {
+        Problem::new(StatusCode::LENGTH_REQUIRED, "length_required", None).into_response()
+    } else if rejection.find::<warp::reject::UnsupportedMediaType>().is_some() {
+        Problem::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type", None).into_response()
+    } else if let Some(e) = rejection.find::<ForensicMode>() {
+        Problem::new(StatusCode::SERVICE_UNAVAILABLE, "forensic_mode", Some(e.to_string())).into_response()
+    } else if let Some(e) = rejection.find::<warp::filters::cors::CorsForbidden>() {
+        Problem::new(StatusCode::FORBIDDEN, "cors_forbidden", Some(e.to_string())).into_response()
+    } else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        Problem::new(StatusCode::METHOD_NOT_ALLOWED, "method_not_allowed", None).into_response()
+    } else {
+        tracing::error!(?rejection, "unhandled rejection");
+        Problem::new(StatusCode::INTERNAL_SERVER_ERROR, "internal", None).into_response()
+    };
+    Ok(response)
+}
@@ -0,0 +1,471 @@
+use crate::amount::{units_string, AnyAmount};
+use crate::api::auth::{Auth, Principal, Scope};
+use crate::api::problem::ApiError;
+use crate::api::validation::{validated_json, FieldError, Validate, ValidationConfig};
+use crate::api::versioning::{deprecated, Deprecation};
+use crate::events::bus::{Event, EventBus, Step};
+use crate::ids::TxId;
+use crate::storage::entities::{EntityError, EntityStore};
+use crate::storage::mvcc::WriteBatch;
+use crate::tenant_usage::UsageMeter;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use warp::{Filter, Rejection, Reply};
+
+// Ledger state of one holder: the key that authorizes burns and balances by asset
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LedgerAccount {
+    // Hex-encoded ed25519 public key
+    pub public_key: String,
+    pub balances: HashMap<String, u128>,
+
+    // Each burn authorization must use this nonce, so a signature cannot be replayed
+    pub next_nonce: u64,
+}
+
+// Why staged balance changes cannot be applied
+#[derive(Debug)]
+pub enum PostingError {
+    InsufficientBalance { account: String, asset: String, available: u128 },
+    Overflow { account: String, asset: String },
+    Storage(String),
+}
+
+impl std::fmt::Display for PostingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PostingError::InsufficientBalance { account, asset, available } => {
+                write!(f, "insufficient {} balance in {}, {} available", asset, account, available)
+            }
+            PostingError::Overflow { account, asset } => write!(f, "{} balance of {} overflows", asset, account),
+            PostingError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// Stage each `(account, asset, units)` debit and then each credit into `batch`; a debit never opens an account,
+// a credit opens a missing one without a key, which the holder registers before redeeming. The caller holds `accounts.lock()` until the batch is committed
+pub fn stage_postings(
+    accounts: &EntityStore<LedgerAccount>,
+    batch: &mut WriteBatch,
+    debits: &[(&str, &str, u128)],
+    credits: &[(&str, &str, u128)],
+) -> Result<(), PostingError> {
+    let mut posted: BTreeMap<&str, LedgerAccount> = BTreeMap::new();
+    let postings = debits.iter().map(|posting| (posting, true)).chain(credits.iter().map(|posting| (posting, false)));
+    for (&(account, asset, units), debit) in postings {
+        let holder = match posted.entry(account) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(match accounts.get(account) {
+                Ok(entity) => entity.value,
+                Err(EntityError::NotFound) if !debit => LedgerAccount { public_key: String::new(), balances: HashMap::new(), next_nonce: 0 },
+                Err(EntityError::NotFound) => {
+                    return Err(PostingError::InsufficientBalance { account: account.to_string(), asset: asset.to_string(), available: 0 })
+                }
+                Err(e) => return Err(PostingError::Storage(e.to_string())),
+            }),
+        };
+        let balance = holder.balances.entry(asset.to_string()).or_insert(0);
+        *balance = if debit {
+            balance.checked_sub(units).ok_or_else(|| PostingError::InsufficientBalance {
+                account: account.to_string(),
+                asset: asset.to_string(),
+                available: *balance,
+            })?
+        } else {
+            balance.checked_add(units).ok_or_else(|| PostingError::Overflow { account: account.to_string(), asset: asset.to_string() })?
+        };
+    }
+    for (account, value) in posted {
+        accounts.stage(batch, account, value).map_err(|e| PostingError::Storage(e.to_string()))?;
+    }
+    Ok(())
+}
+
+// `stage_postings` with credits only
+pub fn stage_credits(accounts: &EntityStore<LedgerAccount>, batch: &mut WriteBatch, credits: &[(&str, &str, u128)]) -> Result<(), String> {
+    stage_postings(accounts, batch, &[], credits).map_err(|e| e.to_string())
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RedemptionRequest {
+    pub account: String,
+    pub asset: String,
+
+    // Smallest units, as a string so JavaScript clients keep precision
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub amount: u128,
+    pub nonce: u64,
+
+    // Hex-encoded signature of the burn authorization by the account key
+    pub signature: String,
+}
+
+impl Validate for RedemptionRequest {
+    fn validate(&self, rules: &ValidationConfig) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.account.is_empty() {
+            errors.push(FieldError::new("account", "is required"));
+        }
+        let limits = rules.asset("asset", &self.asset, &mut errors);
+        rules.amount("amount", self.amount, limits, &mut errors);
+        if self.signature.len() != 128 || hex::decode(&self.signature).is_err() {
+            errors.push(FieldError::new("signature", "must be 64 hex-encoded bytes"));
+        }
+        errors
+    }
+}
+
+impl RedemptionRequest {
+    // What the account key signs: the fields but the signature, length-prefixed, then the amount and nonce big-endian
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = b"pi-supernode/redemption/v1".to_vec();
+        for part in [self.account.as_bytes(), self.asset.as_bytes()] {
+            bytes.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(part);
+        }
+        bytes.extend_from_slice(&self.amount.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce.to_be_bytes());
+        bytes
+    }
+
+    fn verify(&self, public_key: &str) -> Result<(), RedemptionError> {
+        let invalid = |e: String| RedemptionError::InvalidSignature(e);
+        let key: [u8; 32] = hex::decode(public_key)
+            .map_err(|e| invalid(e.to_string()))?
+            .try_into()
+            .map_err(|_| invalid("account key is not 32 bytes".to_string()))?;
+        let key = VerifyingKey::from_bytes(&key).map_err(|e| invalid(e.to_string()))?;
+        let signature = hex::decode(&self.signature).map_err(|e| invalid(e.to_string()))?;
+        let signature = Signature::from_slice(&signature).map_err(|e| invalid(e.to_string()))?;
+        key.verify(&self.signed_bytes(), &signature).map_err(|e| invalid(e.to_string()))
+    }
+}
+
+// Body of PUT /v1/accounts/{account}/key
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AccountKey {
+    // Hex-encoded ed25519 public key that signs the account's burn authorizations
+    pub public_key: String,
+}
+
+impl Validate for AccountKey {
+    fn validate(&self, _rules: &ValidationConfig) -> Vec<FieldError> {
+        let key = hex::decode(&self.public_key).ok().and_then(|key| <[u8; 32]>::try_from(key).ok());
+        if key.is_none_or(|key| VerifyingKey::from_bytes(&key).is_err()) {
+            return vec![FieldError::new("public_key", "must be a hex-encoded ed25519 public key")];
+        }
+        Vec::new()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct RedemptionResponse {
+    #[schema(value_type = String, example = "tx_01HV3K8Z6V6Q4M1X9J6T5B2C7D")]
+    pub tx_id: TxId,
+    pub account: String,
+    pub asset: String,
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub amount: u128,
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub remaining_balance: u128,
+}
+
+#[derive(Debug)]
+pub enum RedemptionError {
+    UnknownAccount,
+
+    // Credited before its holder registered a key
+    NoKey,
+
+    // Only the holder sets its own key, and only an admin replaces one
+    NotHolder,
+    KeyAlreadySet,
+    InvalidSignature(String),
+    StaleNonce { expected: u64 },
+    InsufficientBalance { available: u128 },
+    Ledger(EntityError),
+}
+
+impl std::fmt::Display for RedemptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RedemptionError::UnknownAccount => write!(f, "unknown account"),
+            RedemptionError::NoKey => write!(f, "account has no key registered"),
+            RedemptionError::NotHolder => write!(f, "only the account holder or an admin may set its key"),
+            RedemptionError::KeyAlreadySet => write!(f, "account key already registered, an admin must replace it"),
+            RedemptionError::InvalidSignature(e) => write!(f, "burn authorization is not valid: {}", e),
+            RedemptionError::StaleNonce { expected } => write!(f, "nonce already used, expected {}", expected),
+            RedemptionError::InsufficientBalance { available } => write!(f, "insufficient balance, {} available", available),
+            RedemptionError::Ledger(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<RedemptionError> for ApiError {
+    fn from(error: RedemptionError) -> Self {
+        match error {
+            RedemptionError::UnknownAccount => ApiError::NotFound(error.to_string()),
+            RedemptionError::InvalidSignature(_) | RedemptionError::NotHolder => ApiError::Forbidden(error.to_string()),
+            RedemptionError::NoKey | RedemptionError::KeyAlreadySet => ApiError::Conflict(error.to_string()),
+            RedemptionError::StaleNonce { .. } | RedemptionError::InsufficientBalance { .. } => ApiError::Conflict(error.to_string()),
+            RedemptionError::Ledger(EntityError::Conflict { .. } | EntityError::AlreadyExists) => ApiError::Conflict(error.to_string()),
+            RedemptionError::Ledger(EntityError::ReadOnly | EntityError::Unavailable(_)) => ApiError::Unavailable(error.to_string()),
+            RedemptionError::Ledger(_) => ApiError::Internal(error.to_string()),
+        }
+    }
+}
+
+// Burns stablecoins against a holder-signed authorization
+#[derive(Clone)]
+pub struct Redemptions {
+    accounts: EntityStore<LedgerAccount>,
+    bus: EventBus,
+    meter: Option<UsageMeter>,
+}
+
+impl Redemptions {
+    pub fn new(accounts: EntityStore<LedgerAccount>, bus: EventBus) -> Self {
+        Redemptions { accounts, bus, meter: None }
+    }
+
+    // Count burn signature checks against the caller's tenant
+    pub fn with_usage_meter(mut self, meter: UsageMeter) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    // `tenant` is the caller's and is only carried on the completion event
+    pub fn redeem(&self, request: &RedemptionRequest, tenant: Option<&str>) -> Result<RedemptionResponse, RedemptionError> {
+        let mut entity = self.accounts.get(&request.account).map_err(|e| match e {
+            EntityError::NotFound => RedemptionError::UnknownAccount,
+            e => RedemptionError::Ledger(e),
+        })?;
+        if entity.value.public_key.is_empty() {
+            return Err(RedemptionError::NoKey);
+        }
+        if let (Some(meter), Some(tenant)) = (&self.meter, tenant) {
+            meter.record_crypto_op(tenant);
+        }
+        request.verify(&entity.value.public_key)?;
+        if request.nonce != entity.value.next_nonce {
+            return Err(RedemptionError::StaleNonce { expected: entity.value.next_nonce });
+        }
+        let available = AnyAmount::new(request.asset.clone(), entity.value.balances.get(&request.asset).copied().unwrap_or(0));
+        let remaining = available
+            .checked_sub(&AnyAmount::new(request.asset.clone(), request.amount))
+            .map_err(|_| RedemptionError::InsufficientBalance { available: available.units })?;
+        let remaining_balance = remaining.units;
+        entity.value.balances.insert(request.asset.clone(), remaining_balance);
+        entity.value.next_nonce += 1;
+
+        // Compare-and-swap on the account version, so a concurrent burn cannot double-spend
+        self.accounts.update(&request.account, entity.version, entity.value).map_err(RedemptionError::Ledger)?;
+
+        let tx_id = TxId::new();
+        self.bus.publish(Event::TransactionStep {
+            tx_id: tx_id.to_string(),
+            step: Step::Signature,
+            outcome: "verified".to_string(),
+            detail: Some(format!("burn authorization by account {}", request.account)),
+            related: None,
+        });
+        self.bus.publish(Event::RedemptionCompleted {
+            tx_id: tx_id.to_string(),
+            asset: request.asset.clone(),
+            amount: request.amount.to_string(),
+            tenant: tenant.map(str::to_string),
+        });
+        info!(%tx_id, account = %request.account, asset = %request.asset, amount = %request.amount, "redemption completed");
+        Ok(RedemptionResponse {
+            tx_id,
+            account: request.account.clone(),
+            asset: request.asset.clone(),
+            amount: request.amount,
+            remaining_balance,
+        })
+    }
+
+    // Set the key of `account` for `principal`, opening the account when nothing was credited to it yet. The holder, whose
+    // subject is the account, sets it once; replacing a registered key takes an admin
+    pub fn register_key(&self, principal: &Principal, account: &str, key: &AccountKey) -> Result<(), RedemptionError> {
+        let admin = principal.has_scope(Scope::Admin);
+        if principal.subject != account && !admin {
+            return Err(RedemptionError::NotHolder);
+        }
+        match self.accounts.get(account) {
+            Ok(mut entity) => {
+                if !entity.value.public_key.is_empty() && !admin {
+                    return Err(RedemptionError::KeyAlreadySet);
+                }
+                entity.value.public_key = key.public_key.clone();
+                self.accounts.update(account, entity.version, entity.value).map_err(RedemptionError::Ledger)?;
+            }
+            Err(EntityError::NotFound) => {
+                let value = LedgerAccount { public_key: key.public_key.clone(), balances: HashMap::new(), next_nonce: 0 };
+                // A credit racing in between fails this as `AlreadyExists`, a conflict to retry
+                self.accounts.create(account, value).map_err(RedemptionError::Ledger)?;
+            }
+            Err(e) => return Err(RedemptionError::Ledger(e)),
+        }
+        info!(account, subject = %principal.subject, "account key registered");
+        Ok(())
+    }
+
+    // POST /v1/redemption, the deprecated unversioned /redemption, and PUT /v1/accounts/{account}/key
+    pub fn routes(&self, auth: &Auth, rules: Arc<ValidationConfig>) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let v1 = warp::path!("v1" / "redemption").and(self.handler(auth, rules.clone()));
+        let legacy = deprecated(warp::path!("redemption").and(self.handler(auth, rules.clone())), Deprecation::unversioned("/v1/redemption"));
+        let redemptions = self.clone();
+        let key = warp::path!("v1" / "accounts" / String / "key").and(warp::put()).and(auth.authorized()).and(validated_json(rules)).and_then(
+            move |account: String, principal: Principal, key: AccountKey| {
+                let result = redemptions.register_key(&principal, &account, &key).map_err(|e| {
+                    warn!(subject = %principal.subject, %account, error = %e, "account key not registered");
+                    warp::reject::custom(ApiError::from(e))
+                });
+                async move { result.map(|()| warp::reply::json(&key)) }
+            },
+        );
+        v1.or(legacy).or(key)
+    }
+
+    fn handler(&self, auth: &Auth, rules: Arc<ValidationConfig>) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        let redemptions = self.clone();
+        warp::post()
+            .and(auth.authorized())
+            .and(validated_json(rules))
+            .and_then(move |principal: Principal, request: RedemptionRequest| {
+                let result = redemptions.redeem(&request, principal.tenant.as_deref()).map_err(|e| {
+                    warn!(subject = %principal.subject, account = %request.account, error = %e, "redemption rejected");
+                    warp::reject::custom(ApiError::from(e))
+                });
+                async move { result.map(|response| warp::reply::json(&response)) }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::issuance::{Issuance, Issuances};
+    use crate::fees::{FeeSchedule, FeeScheduleConfig};
+    use crate::storage::mvcc::Store;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn holder() -> SigningKey {
+        SigningKey::from_bytes(&[3; 32])
+    }
+
+    // Account "alice" holding 1000 PI, with `holder()` as its key
+    fn redemptions() -> (Redemptions, EntityStore<LedgerAccount>) {
+        let accounts = EntityStore::new(Store::new(), "accounts");
+        let account = LedgerAccount {
+            public_key: hex::encode(holder().verifying_key().to_bytes()),
+            balances: HashMap::from([("PI".to_string(), 1_000)]),
+            next_nonce: 0,
+        };
+        accounts.create("alice", account).unwrap();
+        (Redemptions::new(accounts.clone(), EventBus::new()), accounts)
+    }
+
+    fn signed(key: &SigningKey, account: &str, amount: u128, nonce: u64) -> RedemptionRequest {
+        let mut request = RedemptionRequest { account: account.to_string(), asset: "PI".to_string(), amount, nonce, signature: String::new() };
+        request.signature = hex::encode(key.sign(&request.signed_bytes()).to_bytes());
+        request
+    }
+
+    #[test]
+    fn burns_from_the_balance_and_advances_the_nonce() {
+        let (redemptions, accounts) = redemptions();
+        let response = redemptions.redeem(&signed(&holder(), "alice", 400, 0), None).unwrap();
+        assert_eq!((response.amount, response.remaining_balance), (400, 600));
+
+        let account = accounts.get("alice").unwrap().value;
+        assert_eq!((account.balances["PI"], account.next_nonce), (600, 1));
+    }
+
+    #[test]
+    fn refuses_a_replayed_authorization() {
+        let (redemptions, _) = redemptions();
+        let request = signed(&holder(), "alice", 100, 0);
+        redemptions.redeem(&request, None).unwrap();
+        assert!(matches!(redemptions.redeem(&request, None), Err(RedemptionError::StaleNonce { expected: 1 })));
+    }
+
+    #[test]
+    fn refuses_more_than_the_balance() {
+        let (redemptions, accounts) = redemptions();
+        let refused = redemptions.redeem(&signed(&holder(), "alice", 1_001, 0), None);
+        assert!(matches!(refused, Err(RedemptionError::InsufficientBalance { available: 1_000 })));
+        assert_eq!(accounts.get("alice").unwrap().value.next_nonce, 0);
+    }
+
+    #[test]
+    fn refuses_other_keys_and_unknown_accounts() {
+        let (redemptions, _) = redemptions();
+        let stranger = SigningKey::from_bytes(&[4; 32]);
+        assert!(matches!(redemptions.redeem(&signed(&stranger, "alice", 1, 0), None), Err(RedemptionError::InvalidSignature(_))));
+
+        let mut tampered = signed(&holder(), "alice", 1, 0);
+        tampered.amount = 500;
+        assert!(matches!(redemptions.redeem(&tampered, None), Err(RedemptionError::InvalidSignature(_))));
+
+        assert!(matches!(redemptions.redeem(&signed(&holder(), "bob", 1, 0), None), Err(RedemptionError::UnknownAccount)));
+    }
+
+    fn principal(subject: &str, scope: Scope) -> Principal {
+        Principal { subject: subject.to_string(), scopes: vec![scope], credential_id: subject.to_string(), tenant: None }
+    }
+
+    fn key_of(key: &SigningKey) -> AccountKey {
+        AccountKey { public_key: hex::encode(key.verifying_key().to_bytes()) }
+    }
+
+    #[test]
+    fn issued_balances_are_redeemable_once_the_holder_registers_a_key() {
+        let accounts = EntityStore::new(Store::new(), "accounts");
+        let redemptions = Redemptions::new(accounts.clone(), EventBus::new());
+        let issuances = Issuances::new(accounts.clone(), FeeSchedule::new(FeeScheduleConfig::default()).unwrap(), EventBus::new());
+        let issuance = Issuance { amount: AnyAmount::new("PI".to_string(), 1_000), recipient: "bob".to_string(), memo: None };
+        issuances.issue(&principal("minter", Scope::Issue), &issuance).unwrap();
+        assert!(matches!(redemptions.redeem(&signed(&holder(), "bob", 400, 0), None), Err(RedemptionError::NoKey)));
+
+        redemptions.register_key(&principal("bob", Scope::Redeem), "bob", &key_of(&holder())).unwrap();
+        let response = redemptions.redeem(&signed(&holder(), "bob", 400, 0), None).unwrap();
+        assert_eq!(response.remaining_balance, 600);
+    }
+
+    #[test]
+    fn only_the_holder_sets_a_key_and_only_an_admin_replaces_it() {
+        let (redemptions, accounts) = redemptions();
+        let stranger = SigningKey::from_bytes(&[4; 32]);
+        let refused = redemptions.register_key(&principal("mallory", Scope::Redeem), "carol", &key_of(&stranger));
+        assert!(matches!(refused, Err(RedemptionError::NotHolder)));
+
+        // Registering first opens the account
+        redemptions.register_key(&principal("carol", Scope::Redeem), "carol", &key_of(&holder())).unwrap();
+        assert!(accounts.get("carol").unwrap().value.balances.is_empty());
+
+        let refused = redemptions.register_key(&principal("alice", Scope::Redeem), "alice", &key_of(&stranger));
+        assert!(matches!(refused, Err(RedemptionError::KeyAlreadySet)));
+        redemptions.register_key(&principal("ops", Scope::Admin), "alice", &key_of(&stranger)).unwrap();
+        assert!(redemptions.redeem(&signed(&stranger, "alice", 1, 0), None).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_key_that_is_not_ed25519() {
+        let rules = ValidationConfig::default();
+        assert_eq!(AccountKey { public_key: "ab".repeat(31) }.validate(&rules).len(), 1);
+        assert!(key_of(&holder()).validate(&rules).is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::api::auth::key_fingerprint;
+use crate::cache::{self, AdaptiveLru, ResponseCacheConfig};
+use crate::runtime::scheduler::Scheduler;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+use warp::filters::BoxedFilter;
+use warp::http::header::{HeaderValue, AGE, CACHE_CONTROL, CONTENT_TYPE};
+use warp::http::{Method, StatusCode};
+use warp::hyper::body::{self, Body, Bytes};
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Clone)]
+struct CachedResponse {
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+    stored_at: Instant,
+    ttl: Duration,
+    private: bool,
+}
+
+impl CachedResponse {
+    fn fresh(&self) -> bool {
+        self.stored_at.elapsed() < self.ttl
+    }
+}
+
+fn cache_control(private: bool, max_age: Duration) -> HeaderValue {
+    let scope = if private { "private" } else { "public" };
+    HeaderValue::from_str(&format!("{}, max-age={}", scope, max_age.as_secs())).unwrap_or(HeaderValue::from_static("no-cache"))
+}
+
+// TTL cache in front of idempotent GET routes, what the AI engine's `cache_responses` rule asks for, e.g.
+// `admin::ai::routes` for GET /admin/rules or `StablecoinConverter::with_cache`.
+// Only 200s are stored. Successful writes through a cached route drop that route's entries, and
+// `invalidate` does the same for writes made elsewhere.
+#[derive(Clone)]
+pub struct ResponseCache {
+    config: Arc<ResponseCacheConfig>,
+    entries: AdaptiveLru<String, CachedResponse>,
+}
+
+impl ResponseCache {
+    pub fn new(config: ResponseCacheConfig) -> Self {
+        let entries = AdaptiveLru::new("responses", config.entries.clone());
+        ResponseCache { config: Arc::new(config), entries }
+    }
+
+    pub fn register_tuning(&self, scheduler: &Scheduler) {
+        cache::register_tuning(scheduler, self.entries.clone());
+    }
+
+    pub fn ttl(&self, route: &str) -> Duration {
+        Duration::from_secs(self.config.ttl_secs.get(route).copied().unwrap_or(self.config.default_ttl_secs))
+    }
+
+    // Drop every cached response of `route`; returns how many
+    pub fn invalidate(&self, route: &str) -> usize {
+        let prefix = format!("{}|", route);
+        let dropped = self.entries.remove_where(|key| key.starts_with(&prefix));
+        debug!(route, dropped, "cached responses invalidated");
+        dropped
+    }
+
+    fn lookup(&self, key: &str) -> Option<Response> {
+        let cached = self.entries.get(&key.to_string()).filter(CachedResponse::fresh)?;
+        let age = cached.stored_at.elapsed();
+        let mut response = Response::new(Body::from(cached.body));
+        let headers = response.headers_mut();
+        if let Some(content_type) = cached.content_type {
+            headers.insert(CONTENT_TYPE, content_type);
+        }
+        headers.insert(CACHE_CONTROL, cache_control(cached.private, cached.ttl.saturating_sub(age)));
+        headers.insert(AGE, HeaderValue::from(age.as_secs()));
+        Some(response)
+    }
+
+    async fn store(&self, route: &'static str, key: Option<(String, bool)>, response: Response) -> Response {
+        let Some((key, private)) = key else {
+            if response.status().is_success() {
+                self.invalidate(route);
+            }
+            return response;
+        };
+        if response.status() != StatusCode::OK {
+            return response;
+        }
+        let (mut parts, body) = response.into_parts();
+        let body = match body::to_bytes(body).await {
+            Ok(bytes) => bytes,
+            Err(_) => return Response::from_parts(parts, Body::empty()),
+        };
+        let ttl = self.ttl(route);
+        let cached =
+            CachedResponse { content_type: parts.headers.get(CONTENT_TYPE).cloned(), body: body.clone(), stored_at: Instant::now(), ttl, private };
+        self.entries.insert(key, cached);
+        parts.headers.insert(CACHE_CONTROL, cache_control(private, ttl));
+        Response::from_parts(parts, Body::from(body))
+    }
+
+    // Serve `filter`'s GETs from the cache under `route`; its other methods invalidate `route` when they succeed.
+    // Credentialed requests are keyed per credential and marked private, so a hit is only served to the
+    // credential whose request produced it; keep TTLs of authenticated routes short.
+    pub fn cached<F, R>(&self, route: &'static str, filter: F) -> BoxedFilter<(Response,)>
+    where
+        F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+        R: Reply,
+    {
+        let cache = self.clone();
+        let hit = cache_key(route).and_then(move |key: Option<(String, bool)>| {
+            let response = key.and_then(|(key, _)| cache.lookup(&key));
+            async move { response.ok_or_else(warp::reject) }
+        });
+
+        let cache = self.clone();
+        let miss = cache_key(route).and(filter.map(|reply: R| reply.into_response())).and_then(
+            move |key: Option<(String, bool)>, response: Response| {
+                let cache = cache.clone();
+                async move { Ok::<_, Rejection>(cache.store(route, key, response).await) }
+            },
+        );
+
+        hit.or(miss).unify().boxed()
+    }
+}
+
+// `Some((key, private))` for GETs: route, path, query and a fingerprint of any credential
+fn cache_key(route: &'static str) -> impl Filter<Extract = (Option<(String, bool)>,), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::header::optional::<String>("x-api-key"))
+        .and(warp::header::optional::<String>("authorization"))
+        .map(move |method: Method, path: FullPath, query: String, api_key: Option<String>, authorization: Option<String>| {
+            if method != Method::GET {
+                return None;
+            }
+            let credential = api_key.or(authorization).map(|c| key_fingerprint(&c));
+            let private = credential.is_some();
+            Some((format!("{}|{}?{}|{}", route, path.as_str(), query, credential.unwrap_or_default()), private))
+        })
+}
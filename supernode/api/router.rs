@@ -0,0 +1,117 @@
+use crate::api::problem::recover;
+use crate::metrics;
+use crate::runtime::forensic::Forensic;
+use crate::telemetry;
+use crate::traffic_mirror::TrafficMirror;
+use std::convert::Infallible;
+use std::time::Duration;
+use warp::filters::cors::Cors;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::hyper::body::Bytes;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+// A mounted module's routes, type-erased so the router does not grow one nested `Or` type per module
+pub type Route = BoxedFilter<(Response,)>;
+
+// Collects each module's `routes()` under a name and applies the cross-cutting layers once:
+// request metrics per mounted route, RFC 7807 rendering of every rejection, and one trace span
+// around it all so latency observations carry the request's trace id as an exemplar
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<(&'static str, Route)>,
+    cors: Option<Cors>,
+    forensic: Option<Forensic>,
+    mirror: Option<TrafficMirror>,
+}
+
+fn record(route: &str, status: StatusCode, elapsed: Option<Duration>) {
+    metrics::API_REQUESTS.with_label_values(&[route, status.as_str()]).inc();
+    if let Some(elapsed) = elapsed {
+        metrics::observe_latency("api", route, elapsed, telemetry::current_trace_id());
+    }
+}
+
+// Requests no mounted route accepted, counted under "rejected"
+async fn recover_counted(rejection: Rejection) -> Result<Response, Infallible> {
+    let response = recover(rejection).await?;
+    record("rejected", response.status(), None);
+    Ok(response)
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Mount `filter`; `name` labels its requests in metrics, e.g. "redemption"
+    pub fn mount<F>(mut self, name: &'static str, filter: F) -> Self
+    where
+        F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+        F::Extract: Reply,
+    {
+        let route = filter
+            .with(warp::log::custom(move |info| record(name, info.status(), Some(info.elapsed()))))
+            .map(Reply::into_response)
+            .boxed();
+        self.routes.push((name, route));
+        self
+    }
+
+    // Answer preflights and add CORS headers per `server.cors`, see `CorsConfig::filter`
+    pub fn cors(mut self, cors: Option<Cors>) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    // Refuse mutating requests before any route runs, see `Forensic::filter`
+    pub fn forensic(mut self, forensic: Option<Forensic>) -> Self {
+        self.forensic = forensic;
+        self
+    }
+
+    // Shadow sampled reads to a canary and compare its answers, see `TrafficMirror::layer`
+    pub fn mirror(mut self, mirror: Option<TrafficMirror>) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.routes.iter().map(|(name, _)| *name).collect()
+    }
+
+    // Routes are tried in mount order
+    pub fn build(self) -> Route {
+        let cors = self.cors;
+        let mut routes = self.routes.into_iter().map(|(_, route)| route);
+        let first = routes
+            .next()
+            .unwrap_or_else(|| warp::any().and_then(|| async { Err::<Response, Rejection>(warp::reject::not_found()) }).boxed());
+        let routes = routes.fold(first, |acc, route| acc.or(route).unify().boxed());
+        let routes = match self.mirror {
+            Some(mirror) => mirror.layer(routes),
+            None => routes,
+        };
+        let routes = match self.forensic {
+            Some(forensic) => forensic.filter().and(routes).boxed(),
+            None => routes,
+        };
+        let routes = match cors {
+            // Inside `recover`, so refused preflights still render as problem+json
+            Some(cors) => routes.with(cors).map(Reply::into_response).boxed(),
+            None => routes,
+        };
+        routes.recover(recover_counted).unify().with(telemetry::trace_layer()).map(Reply::into_response).boxed()
+    }
+}
+
+// Run one module's filter against a `warp::test::request()`, rendering rejections like the router does,
+// so handlers can be exercised without mounting the whole API
+pub async fn call<F>(filter: &F, request: warp::test::RequestBuilder) -> warp::http::Response<Bytes>
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    request.reply(&filter.clone().recover(recover)).await
+}
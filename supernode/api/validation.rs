@@ -0,0 +1,112 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use warp::hyper::body::Bytes;
+use warp::reject::Reject;
+use warp::{Filter, Rejection};
+
+// Amount bounds for one asset, in its smallest unit
+#[derive(Clone, Debug, Deserialize)]
+pub struct AssetLimits {
+    #[serde(default = "default_min_amount")]
+    pub min_amount: u128,
+    #[serde(default = "default_max_amount")]
+    pub max_amount: u128,
+}
+
+fn default_min_amount() -> u128 {
+    1
+}
+
+fn default_max_amount() -> u128 {
+    u64::MAX as u128
+}
+
+// `validation` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    // Accepted asset symbols; anything else is rejected
+    pub assets: BTreeMap<String, AssetLimits>,
+
+    // Request bodies above this are refused before parsing
+    pub max_body_bytes: u64,
+
+    pub max_memo_chars: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig { assets: BTreeMap::new(), max_body_bytes: 16 * 1024, max_memo_chars: 256 }
+    }
+}
+
+impl ValidationConfig {
+    pub fn asset(&self, field: &str, asset: &str, errors: &mut Vec<FieldError>) -> Option<&AssetLimits> {
+        let limits = self.assets.get(asset);
+        if limits.is_none() {
+            errors.push(FieldError::new(field, format!("unsupported asset `{}`", asset)));
+        }
+        limits
+    }
+
+    pub fn amount(&self, field: &str, amount: u128, limits: Option<&AssetLimits>, errors: &mut Vec<FieldError>) {
+        let Some(limits) = limits else { return };
+        if amount < limits.min_amount {
+            errors.push(FieldError::new(field, format!("must be at least {}", limits.min_amount)));
+        } else if amount > limits.max_amount {
+            errors.push(FieldError::new(field, format!("must be at most {}", limits.max_amount)));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError { field: field.to_string(), message: message.into() }
+    }
+}
+
+// Rendered as 422 with the field errors by `problem::recover`
+#[derive(Debug)]
+pub struct ValidationFailed(pub Vec<FieldError>);
+
+impl Reject for ValidationFailed {}
+
+// Request body checks that run before the handler; report every problem, not just the first
+pub trait Validate {
+    fn validate(&self, rules: &ValidationConfig) -> Vec<FieldError>;
+}
+
+// serde_json reports unknown fields as "unknown field `x`, expected ..."; point at the field when it does
+fn decode_error(e: serde_json::Error) -> FieldError {
+    let message = e.to_string();
+    let field = message.strip_prefix("unknown field `").and_then(|rest| rest.split('`').next()).unwrap_or("body").to_string();
+    FieldError { field, message }
+}
+
+// Size-limited JSON body, strictly deserialized and validated, in place of `warp::body::json()`
+pub fn validated_json<T>(rules: Arc<ValidationConfig>) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Validate + Send,
+{
+    warp::body::content_length_limit(rules.max_body_bytes).and(warp::body::bytes()).and_then(move |body: Bytes| {
+        let rules = rules.clone();
+        async move {
+            let value: T = serde_json::from_slice(&body).map_err(|e| warp::reject::custom(ValidationFailed(vec![decode_error(e)])))?;
+            let errors = value.validate(&rules);
+            if errors.is_empty() {
+                Ok(value)
+            } else {
+                Err(warp::reject::custom(ValidationFailed(errors)))
+            }
+        }
+    })
+}
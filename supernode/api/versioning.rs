@@ -0,0 +1,43 @@
+use chrono::{DateTime, TimeZone, Utc};
+use warp::{Filter, Rejection, Reply};
+
+// Retirement notice attached to every response of an old route (RFC 8594 and draft-ietf-httpapi-deprecation-header)
+#[derive(Clone, Debug)]
+pub struct Deprecation {
+    pub since: DateTime<Utc>,
+    pub sunset: DateTime<Utc>,
+
+    // Path of the replacement, advertised as `Link: <...>; rel="successor-version"`
+    pub successor: &'static str,
+}
+
+impl Deprecation {
+    // Unprefixed routes that predate /v1
+    pub fn unversioned(successor: &'static str) -> Self {
+        Deprecation {
+            since: Utc.with_ymd_and_hms(2026, 10, 17, 0, 0, 0).unwrap(),
+            sunset: Utc.with_ymd_and_hms(2027, 4, 30, 0, 0, 0).unwrap(),
+            successor,
+        }
+    }
+}
+
+// Serve `filter` with deprecation headers, e.g. an unversioned alias kept while clients migrate to /v1
+pub fn deprecated<F, R>(filter: F, deprecation: Deprecation) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    filter.map(move |reply: R| {
+        tracing::debug!(successor = deprecation.successor, "deprecated route used");
+        warp::reply::with_header(
+            warp::reply::with_header(
+                warp::reply::with_header(reply, "deprecation", format!("@{}", deprecation.since.timestamp())),
+                "sunset",
+                deprecation.sunset.to_rfc2822(),
+            ),
+            "link",
+            format!("<{}>; rel=\"successor-version\"", deprecation.successor),
+        )
+    })
+}
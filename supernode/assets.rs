@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use warp::{Filter, Rejection, Reply};
+
+// Where an asset's conversion rates come from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RateSource {
+    // `converter.rates`, or set by an operator at runtime
+    #[default]
+    Static,
+
+    // Kept current by the price oracle
+    Oracle,
+}
+
+// One entry of the `assets` config section
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AssetConfig {
+    // Smallest units per whole token, as a power of ten
+    pub decimals: u8,
+
+    // Disabled assets stay listed but cannot be converted from or to
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+
+    // Whether callers may pick it as a conversion target
+    #[serde(default)]
+    pub stablecoin: bool,
+
+    // Whether stablecoins may be converted back into it
+    #[serde(default)]
+    pub reversible: bool,
+    #[serde(default)]
+    pub rate_source: RateSource,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    Unknown(String),
+    Disabled(String),
+    NotATarget(String),
+}
+
+impl std::fmt::Display for AssetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AssetError::Unknown(asset) => write!(f, "unknown asset {}", asset),
+            AssetError::Disabled(asset) => write!(f, "asset {} is disabled", asset),
+            AssetError::NotATarget(asset) => write!(f, "{} is not an asset conversions from this source can target", asset),
+        }
+    }
+}
+
+// Assets the node converts between, e.g. USDC, USDT, DAI and EURC, keyed by symbol
+#[derive(Clone, Default)]
+pub struct AssetRegistry {
+    assets: Arc<BTreeMap<String, AssetConfig>>,
+}
+
+impl AssetRegistry {
+    pub fn new(assets: BTreeMap<String, AssetConfig>) -> Result<Self, String> {
+        if let Some((symbol, asset)) = assets.iter().find(|(_, a)| 10u128.checked_pow(a.decimals as u32).is_none()) {
+            return Err(format!("assets.{}: {} decimals is out of range", symbol, asset.decimals));
+        }
+        Ok(AssetRegistry { assets: Arc::new(assets) })
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&AssetConfig> {
+        self.assets.get(symbol)
+    }
+
+    pub fn decimals(&self, symbol: &str) -> Option<u8> {
+        self.get(symbol).map(|a| a.decimals)
+    }
+
+    fn enabled(&self, symbol: &str) -> Result<&AssetConfig, AssetError> {
+        match self.get(symbol) {
+            None => Err(AssetError::Unknown(symbol.to_string())),
+            Some(asset) if !asset.enabled => Err(AssetError::Disabled(symbol.to_string())),
+            Some(asset) => Ok(asset),
+        }
+    }
+
+    // A conversion needs both assets enabled and either a stablecoin target or, converting back out of a
+    // stablecoin, a reversible one; an empty registry allows every pair
+    pub fn check_pair(&self, from: &str, to: &str) -> Result<(), AssetError> {
+        if self.assets.is_empty() {
+            return Ok(());
+        }
+        let source = self.enabled(from)?;
+        let target = self.enabled(to)?;
+        if !(target.stablecoin || source.stablecoin && target.reversible) {
+            return Err(AssetError::NotATarget(to.to_string()));
+        }
+        Ok(())
+    }
+
+    // Enabled stablecoins, in symbol order
+    pub fn targets(&self) -> Vec<&str> {
+        self.assets.iter().filter(|(_, a)| a.enabled && a.stablecoin).map(|(s, _)| s.as_str()).collect()
+    }
+
+    // GET /v1/assets
+    pub fn routes(&self) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let registry = self.clone();
+        warp::path!("v1" / "assets").and(warp::get()).map(move || warp::reply::json(&*registry.assets))
+    }
+}
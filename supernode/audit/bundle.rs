@@ -0,0 +1,201 @@
+use crate::api::auth::Auth;
+use crate::audit::merkle::{leaf_hash, Hash, MerkleTree, ProofStep};
+use crate::ids::ReceiptId;
+use crate::keys::NodeKey;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Sha3_256;
+use std::sync::Arc;
+use tracing::info;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// Proof of a completed ledger operation handed to the customer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Receipt {
+    pub id: ReceiptId,
+    pub tx_id: String,
+    pub issued_at: DateTime<Utc>,
+    pub body: serde_json::Value,
+}
+
+impl Receipt {
+    pub fn leaf(&self) -> Hash {
+        leaf_hash(&serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+// Signature by a node or notary over a Merkle root
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attestation {
+    #[serde(with = "hex::serde")]
+    pub signer: [u8; 32],
+    #[serde(with = "hex::serde")]
+    pub root: Hash,
+    pub signature: String,
+}
+
+// Entry of the append-only audit log; each SHA3-256 hash covers the previous one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub event: serde_json::Value,
+    #[serde(with = "hex::serde")]
+    pub prev_hash: Hash,
+    #[serde(with = "hex::serde")]
+    pub hash: Hash,
+}
+
+impl AuditRecord {
+    pub fn compute_hash(seq: u64, at: &DateTime<Utc>, event: &serde_json::Value, prev_hash: &Hash) -> Hash {
+        let mut hasher = Sha3_256::new();
+        Self::feed(&mut hasher, seq, at, event, prev_hash);
+        hasher.finalize().into()
+    }
+
+    // Chains in format 1 bundles were hashed with SHA-256
+    pub fn compute_hash_v1(seq: u64, at: &DateTime<Utc>, event: &serde_json::Value, prev_hash: &Hash) -> Hash {
+        let mut hasher = Sha256::new();
+        Self::feed(&mut hasher, seq, at, event, prev_hash);
+        hasher.finalize().into()
+    }
+
+    fn feed(hasher: &mut impl Digest, seq: u64, at: &DateTime<Utc>, event: &serde_json::Value, prev_hash: &Hash) {
+        hasher.update(prev_hash);
+        hasher.update(seq.to_be_bytes());
+        hasher.update(at.to_rfc3339().as_bytes());
+        hasher.update(serde_json::to_vec(event).unwrap_or_default());
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProvenReceipt {
+    pub receipt: Receipt,
+    pub proof: Vec<ProofStep>,
+}
+
+// Everything an auditor needs for a date range, verifiable without the node
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BundleContents {
+    pub format_version: u32,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "hex::serde")]
+    pub merkle_root: Hash,
+    pub receipts: Vec<ProvenReceipt>,
+    pub attestations: Vec<Attestation>,
+    pub audit_chain: Vec<AuditRecord>,
+}
+
+// Contents plus the exporting node's signature over their canonical JSON
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedBundle {
+    pub contents: BundleContents,
+    #[serde(with = "hex::serde")]
+    pub signer: [u8; 32],
+    pub signature: String,
+}
+
+pub const BUNDLE_FORMAT_VERSION: u32 = 2;
+
+// Domain tag for attestation signatures over a receipts root
+pub const ATTESTATION_CONTEXT: &[u8] = b"pi-supernode/receipts-root/v1";
+
+// Domain tag for the bundle signature
+pub const BUNDLE_CONTEXT: &[u8] = b"pi-supernode/audit-bundle/v1";
+
+pub fn signed_bytes(context: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut bytes = context.to_vec();
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+// Where receipts and the audit log live
+pub trait AuditSource: Send + Sync {
+    fn receipts(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Receipt>;
+
+    // Independent attestations (e.g. peer notaries) already collected for a root
+    fn attestations(&self, root: &Hash) -> Vec<Attestation>;
+
+    // Audit log entries in the range, plus the one before it so continuity can be checked
+    fn audit_segment(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<AuditRecord>;
+}
+
+#[derive(Clone)]
+pub struct BundleExporter {
+    source: Arc<dyn AuditSource>,
+    key: NodeKey,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl BundleExporter {
+    pub fn new(source: Arc<dyn AuditSource>, key: NodeKey) -> Self {
+        BundleExporter { source, key }
+    }
+
+    pub fn build(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> SignedBundle {
+        let mut receipts = self.source.receipts(from, to);
+        receipts.sort_by_key(|r| r.id);
+        let tree = MerkleTree::new(receipts.iter().map(Receipt::leaf).collect());
+        let merkle_root = tree.root();
+
+        // The exporting node always notarizes the root itself
+        let mut attestations = self.source.attestations(&merkle_root);
+        attestations.push(Attestation {
+            signer: self.key.public(),
+            root: merkle_root,
+            signature: hex::encode(self.key.sign(&signed_bytes(ATTESTATION_CONTEXT, &merkle_root)).to_bytes()),
+        });
+
+        let contents = BundleContents {
+            format_version: BUNDLE_FORMAT_VERSION,
+            from,
+            to,
+            created_at: Utc::now(),
+            merkle_root,
+            receipts: receipts.into_iter().enumerate().map(|(i, receipt)| ProvenReceipt { receipt, proof: tree.proof(i) }).collect(),
+            attestations,
+            audit_chain: self.source.audit_segment(from, to),
+        };
+        let payload = serde_json::to_vec(&contents).unwrap_or_default();
+        let signature = self.key.sign(&signed_bytes(BUNDLE_CONTEXT, &payload));
+        SignedBundle { contents, signer: self.key.public(), signature: hex::encode(signature.to_bytes()) }
+    }
+
+    // zstd-compressed JSON, one file per export
+    pub fn export(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<u8>, String> {
+        let bundle = self.build(from, to);
+        info!(%from, %to, receipts = bundle.contents.receipts.len(), "audit bundle exported");
+        let json = serde_json::to_vec(&bundle).map_err(|e| e.to_string())?;
+        zstd::encode_all(json.as_slice(), 3).map_err(|e| e.to_string())
+    }
+
+    // GET /admin/audit/bundle?from=..&to=..
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let exporter = self.clone();
+        warp::path!("admin" / "audit" / "bundle")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and(warp::query::<ExportQuery>())
+            .map(move |_, query: ExportQuery| match exporter.export(query.from, query.to) {
+                Ok(archive) => warp::reply::with_status(
+                    warp::reply::with_header(
+                        warp::reply::with_header(archive, "content-type", "application/zstd"),
+                        "content-disposition",
+                        format!("attachment; filename=\"audit-{}-{}.json.zst\"", query.from.date_naive(), query.to.date_naive()),
+                    ),
+                    StatusCode::OK,
+                )
+                .into_response(),
+                Err(e) => warp::reply::with_status(e, StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+            })
+    }
+}
@@ -0,0 +1,377 @@
+use crate::api::auth::{key_fingerprint, Auth};
+use crate::api::signing::KEY_ID_HEADER;
+use crate::audit::bundle::{signed_bytes, AuditRecord};
+use crate::audit::merkle::Hash;
+use crate::audit::verify::{verify_log, ChainReport};
+use crate::keys::{key_id, NodeKey};
+use crate::runtime::scheduler::Scheduler;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// Domain tag for anchor signatures over the chain head
+pub const ANCHOR_CONTEXT: &[u8] = b"pi-supernode/audit-anchor/v1";
+
+// `audit_log` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct AuditLogConfig {
+    pub enabled: bool,
+    pub dir: PathBuf,
+
+    // How often the chain head is signed; nothing is written while the head has not moved
+    pub anchor_interval_secs: u64,
+
+    // Largest page returned by the export endpoint
+    pub max_export_records: usize,
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        AuditLogConfig { enabled: true, dir: PathBuf::from("data/audit"), anchor_interval_secs: 300, max_export_records: 10_000 }
+    }
+}
+
+// Node signature over the chain head at `seq`; records up to it cannot be rewritten without the node key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Anchor {
+    pub seq: u64,
+    #[serde(with = "hex::serde")]
+    pub hash: Hash,
+    pub at: DateTime<Utc>,
+    #[serde(with = "hex::serde")]
+    pub signer: [u8; 32],
+    pub signature: String,
+}
+
+impl Anchor {
+    pub fn message(seq: u64, hash: &Hash, at: &DateTime<Utc>) -> Vec<u8> {
+        let mut payload = seq.to_be_bytes().to_vec();
+        payload.extend_from_slice(hash);
+        payload.extend_from_slice(at.to_rfc3339().as_bytes());
+        signed_bytes(ANCHOR_CONTEXT, &payload)
+    }
+}
+
+// A contiguous run of records with the anchors covering it, verifiable offline
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChainExport {
+    #[serde(with = "hex::serde")]
+    pub signer: [u8; 32],
+    pub records: Vec<AuditRecord>,
+    pub anchors: Vec<Anchor>,
+}
+
+// One API call as written to the log
+#[derive(Serialize)]
+struct ApiCall<'a> {
+    kind: &'static str,
+    method: &'a str,
+    path: &'a str,
+    status: u16,
+    elapsed_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+
+    // Fingerprint of the credential presented, never the credential itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credential: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signing_key: Option<&'a str>,
+}
+
+struct Head {
+    next_seq: u64,
+    hash: Hash,
+    anchored_seq: Option<u64>,
+}
+
+// Append-only `chain.jsonl` plus `anchors.jsonl`; appends are serialized so the chain never forks
+#[derive(Clone)]
+pub struct AuditLog {
+    config: AuditLogConfig,
+    key: NodeKey,
+    head: Arc<Mutex<Head>>,
+
+    // Forensic mode: the files are left exactly as found and nothing is appended or anchored
+    read_only: Arc<AtomicBool>,
+}
+
+fn read_lines<T: serde::de::DeserializeOwned>(path: &PathBuf) -> Vec<T> {
+    let Ok(file) = fs::File::open(path) else { return Vec::new() };
+    BufReader::new(file).lines().map_while(Result::ok).filter_map(|line| serde_json::from_str(&line).ok()).collect()
+}
+
+fn append_line(path: &PathBuf, value: &impl Serialize) -> Result<(), String> {
+    let line = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).and_then(|_| file.sync_data()).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+impl AuditLog {
+    // Resumes the chain from the last record on disk
+    pub fn open(config: AuditLogConfig, key: NodeKey) -> Result<Self, String> {
+        fs::create_dir_all(&config.dir).map_err(|e| format!("failed to create {}: {}", config.dir.display(), e))?;
+        let log = AuditLog {
+            config,
+            key,
+            head: Arc::new(Mutex::new(Head { next_seq: 0, hash: [0u8; 32], anchored_seq: None })),
+            read_only: Arc::default(),
+        };
+        let last = log.records(0, usize::MAX).pop();
+        let anchored_seq = read_lines::<Anchor>(&log.anchors_file()).last().map(|a| a.seq);
+        {
+            let mut head = log.head.lock().unwrap();
+            if let Some(last) = last {
+                head.next_seq = last.seq + 1;
+                head.hash = last.hash;
+            }
+            head.anchored_seq = anchored_seq;
+        }
+        Ok(log)
+    }
+
+    fn chain_file(&self) -> PathBuf {
+        self.config.dir.join("chain.jsonl")
+    }
+
+    fn anchors_file(&self) -> PathBuf {
+        self.config.dir.join("anchors.jsonl")
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::SeqCst);
+    }
+
+    fn writable(&self) -> Result<(), String> {
+        if self.read_only.load(Ordering::SeqCst) {
+            return Err("audit log is read-only in forensic mode".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn signer(&self) -> [u8; 32] {
+        self.key.public()
+    }
+
+    pub fn append(&self, event: serde_json::Value) -> Result<AuditRecord, String> {
+        self.writable()?;
+        let mut head = self.head.lock().unwrap();
+        let at = Utc::now();
+        let hash = AuditRecord::compute_hash(head.next_seq, &at, &event, &head.hash);
+        let record = AuditRecord { seq: head.next_seq, at, event, prev_hash: head.hash, hash };
+        append_line(&self.chain_file(), &record)?;
+        head.next_seq += 1;
+        head.hash = hash;
+        Ok(record)
+    }
+
+    // Sign the current head unless it was already anchored
+    pub fn anchor(&self) -> Result<Option<Anchor>, String> {
+        self.writable()?;
+        let mut head = self.head.lock().unwrap();
+        let Some(seq) = head.next_seq.checked_sub(1) else { return Ok(None) };
+        if head.anchored_seq == Some(seq) {
+            return Ok(None);
+        }
+        let at = Utc::now();
+        let signature = self.key.sign(&Anchor::message(seq, &head.hash, &at));
+        let anchor = Anchor { seq, hash: head.hash, at, signer: self.signer(), signature: hex::encode(signature.to_bytes()) };
+        append_line(&self.anchors_file(), &anchor)?;
+        head.anchored_seq = Some(seq);
+        info!(seq, "audit log anchored");
+        Ok(Some(anchor))
+    }
+
+    // Anchors by the current key; those by a revoked key stay in the file but were re-signed by `resign`
+    fn anchors(&self) -> Vec<Anchor> {
+        let signer = self.signer();
+        read_lines::<Anchor>(&self.anchors_file()).into_iter().filter(|a| a.signer == signer).collect()
+    }
+
+    // After a key compromise: sign every head the revoked key anchored again with the current key,
+    // then record the rotation in the chain itself and anchor it
+    pub fn resign(&self, revoked: &[u8; 32]) -> Result<usize, String> {
+        self.writable()?;
+        let signer = self.signer();
+        let stale: Vec<Anchor> = read_lines::<Anchor>(&self.anchors_file()).into_iter().filter(|a| &a.signer == revoked).collect();
+        for old in &stale {
+            let at = Utc::now();
+            let signature = self.key.sign(&Anchor::message(old.seq, &old.hash, &at));
+            let anchor = Anchor { seq: old.seq, hash: old.hash, at, signer, signature: hex::encode(signature.to_bytes()) };
+            append_line(&self.anchors_file(), &anchor)?;
+        }
+        self.append(serde_json::json!({ "kind": "key_rotated", "revoked": key_id(revoked), "successor": key_id(&signer) }))?;
+        self.anchor()?;
+        info!(resigned = stale.len(), revoked = %key_id(revoked), "audit anchors re-signed");
+        Ok(stale.len())
+    }
+
+    pub fn records(&self, from_seq: u64, limit: usize) -> Vec<AuditRecord> {
+        read_lines::<AuditRecord>(&self.chain_file()).into_iter().filter(|r| r.seq >= from_seq).take(limit).collect()
+    }
+
+    // Records in `[from, to]` plus the one before them, as `AuditSource::audit_segment` expects
+    pub fn segment(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<AuditRecord> {
+        let records = read_lines::<AuditRecord>(&self.chain_file());
+        let start = records.iter().position(|r| r.at >= from).unwrap_or(records.len());
+        records.into_iter().skip(start.saturating_sub(1)).take_while(|r| r.at <= to).collect()
+    }
+
+    pub fn export(&self, from_seq: u64, limit: usize) -> ChainExport {
+        let records = self.records(from_seq, limit.min(self.config.max_export_records));
+        let last = records.last().map_or(0, |r| r.seq);
+        let anchors = self.anchors().into_iter().filter(|a| a.seq >= from_seq && a.seq <= last).collect();
+        ChainExport { signer: self.signer(), records, anchors }
+    }
+
+    // Whole chain on disk, which must also end where this process last wrote it
+    pub fn verify(&self) -> ChainReport {
+        let export = ChainExport {
+            signer: self.signer(),
+            records: self.records(0, usize::MAX),
+            anchors: self.anchors(),
+        };
+        let mut report = verify_log(&export, Some(&self.signer()));
+        let (next_seq, hash) = {
+            let head = self.head.lock().unwrap();
+            (head.next_seq, head.hash)
+        };
+        let on_disk = export.records.last().map(|r| (r.seq + 1, r.hash));
+        let head_ok = on_disk.unwrap_or((0, [0u8; 32])) == (next_seq, hash);
+        report.check("head", head_ok.then_some(()).ok_or_else(|| "log on disk does not end at the in-memory head; it was truncated".to_string()));
+        report
+    }
+
+    // Wrap the API so every call, including rejected ones, is appended once it completes
+    pub fn layer(&self) -> warp::log::Log<impl Fn(warp::log::Info) + Clone> {
+        let log = self.clone();
+        warp::log::custom(move |info| {
+            if log.read_only.load(Ordering::SeqCst) {
+                return;
+            }
+            let header = |name: &str| info.request_headers().get(name).and_then(|v| v.to_str().ok());
+            let credential = header("x-api-key")
+                .or_else(|| header("authorization").map(|a| a.trim_start_matches("Bearer ")))
+                .map(key_fingerprint);
+            let call = ApiCall {
+                kind: "api_call",
+                method: info.method().as_str(),
+                path: info.path(),
+                status: info.status().as_u16(),
+                elapsed_ms: info.elapsed().as_millis(),
+                remote: info.remote_addr().map(|a| a.ip().to_string()),
+                credential,
+                signing_key: header(KEY_ID_HEADER),
+            };
+            match serde_json::to_value(&call) {
+                Ok(event) => {
+                    if let Err(e) = log.append(event) {
+                        warn!(error = %e, "audit log append failed");
+                    }
+                }
+                Err(e) => warn!(error = %e, "audit event not serializable"),
+            }
+        })
+    }
+
+    // GET /admin/audit/log?from_seq=..&limit=.., GET /admin/audit/log/verify,
+    // and POST /admin/audit/log/verify to check an export against this node's key
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let log = self.clone();
+        let export = warp::path!("admin" / "audit" / "log")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and(warp::query::<ExportQuery>())
+            .map(move |_, q: ExportQuery| warp::reply::json(&log.export(q.from_seq.unwrap_or(0), q.limit.unwrap_or(usize::MAX))));
+
+        let log = self.clone();
+        let verify_stored = warp::path!("admin" / "audit" / "log" / "verify").and(warp::get()).and(auth.authorized()).map(move |_| {
+            let report = log.verify();
+            let status = if report.valid { StatusCode::OK } else { StatusCode::CONFLICT };
+            warp::reply::with_status(warp::reply::json(&report), status)
+        });
+
+        let log = self.clone();
+        let verify_export = warp::path!("admin" / "audit" / "log" / "verify")
+            .and(warp::post())
+            .and(auth.authorized())
+            .and(warp::body::json())
+            .map(move |_, export: ChainExport| {
+                let report = verify_log(&export, Some(&log.signer()));
+                let status = if report.valid { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+                warp::reply::with_status(warp::reply::json(&report), status)
+            });
+
+        export.or(verify_stored).or(verify_export)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    pub from_seq: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+pub fn register(scheduler: &Scheduler, log: AuditLog) {
+    scheduler.register(
+        "audit_log:anchor",
+        Duration::from_secs(log.config.anchor_interval_secs.max(1)),
+        Duration::ZERO,
+        Arc::new(move || {
+            let log = log.clone();
+            Box::pin(async move {
+                if let Err(e) = tokio::task::spawn_blocking(move || log.anchor()).await.unwrap_or_else(|e| Err(e.to_string())) {
+                    warn!(error = %e, "audit log anchor failed");
+                }
+            })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use ed25519_dalek::SigningKey;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn admin_calls_are_chained_and_anchored() {
+        let dir = std::env::temp_dir().join(format!("audit-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = AuditLogConfig { dir: dir.clone(), ..AuditLogConfig::default() };
+        let log = AuditLog::open(config.clone(), NodeKey::new(SigningKey::from_bytes(&[3; 32]))).unwrap();
+
+        let admin = ApiKeyConfig { subject: "ops".to_string(), scopes: vec![Scope::Admin], tenant: None };
+        let auth = Auth::new(&AuthConfig { api_keys: HashMap::from([("k-admin".to_string(), admin)]), ..AuthConfig::default() }).unwrap();
+        let api = Router::new().mount("audit_log", log.routes(&auth)).build().with(log.layer());
+
+        let response = warp::test::request().path("/admin/audit/log/verify").header("x-api-key", "k-admin").reply(&api).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = warp::test::request().path("/admin/audit/log").reply(&api).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let anchor = log.anchor().unwrap().unwrap();
+        assert_eq!(anchor.seq, 1);
+
+        let records = log.records(0, 10);
+        assert_eq!(records[0].event["path"], "/admin/audit/log/verify");
+        assert_eq!(records[0].event["credential"], key_fingerprint("k-admin"));
+        assert_eq!(records[1].event["status"], 401);
+
+        // A restarted node continues the same chain
+        let reopened = AuditLog::open(config, NodeKey::new(SigningKey::from_bytes(&[3; 32]))).unwrap();
+        reopened.append(serde_json::json!({ "kind": "restart" })).unwrap();
+        assert!(reopened.verify().valid);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub type Hash = [u8; 32];
+
+// Leaves and interior nodes are domain separated so a leaf can never pose as a node
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+// Sibling on the path from a leaf to the root
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofStep {
+    #[serde(with = "hex::serde")]
+    pub sibling: Hash,
+    pub sibling_on_left: bool,
+}
+
+// Binary SHA-256 tree; an odd node at the end of a level is promoted unchanged
+pub struct MerkleTree {
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Hash>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().is_some_and(|l| l.len() > 1) {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| if pair.len() == 2 { node_hash(&pair[0], &pair[1]) } else { pair[0] })
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree { levels }
+    }
+
+    // Root of an empty tree is all zeros
+    pub fn root(&self) -> Hash {
+        self.levels.last().and_then(|l| l.first()).copied().unwrap_or([0u8; 32])
+    }
+
+    pub fn proof(&self, mut index: usize) -> Vec<ProofStep> {
+        let mut steps = Vec::new();
+        for level in &self.levels[..self.levels.len().saturating_sub(1)] {
+            let sibling = index ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                steps.push(ProofStep { sibling: *hash, sibling_on_left: sibling < index });
+            }
+            index /= 2;
+        }
+        steps
+    }
+}
+
+pub fn verify_proof(leaf: Hash, proof: &[ProofStep], root: Hash) -> bool {
+    let computed = proof.iter().fold(leaf, |acc, step| {
+        if step.sibling_on_left {
+            node_hash(&step.sibling, &acc)
+        } else {
+            node_hash(&acc, &step.sibling)
+        }
+    });
+    computed == root
+}
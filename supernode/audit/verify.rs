@@ -0,0 +1,172 @@
+use crate::audit::bundle::{signed_bytes, AuditRecord, SignedBundle, ATTESTATION_CONTEXT, BUNDLE_CONTEXT, BUNDLE_FORMAT_VERSION};
+use crate::audit::log::{Anchor, ChainExport};
+use crate::audit::merkle::{verify_proof, Hash};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+// Machine-readable result; `valid` only when every check passed
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    pub receipts: usize,
+    pub audit_records: usize,
+    pub checks: Vec<Check>,
+}
+
+impl VerificationReport {
+    fn check(&mut self, name: impl Into<String>, result: Result<(), String>) {
+        let (ok, detail) = match result {
+            Ok(()) => (true, None),
+            Err(e) => (false, Some(e)),
+        };
+        self.valid &= ok;
+        self.checks.push(Check { name: name.into(), ok, detail });
+    }
+}
+
+fn verify_signature(signer: &[u8; 32], message: &[u8], signature_hex: &str) -> Result<(), String> {
+    let key = VerifyingKey::from_bytes(signer).map_err(|e| e.to_string())?;
+    let signature = hex::decode(signature_hex).map_err(|e| e.to_string())?;
+    let signature = Signature::from_slice(&signature).map_err(|e| e.to_string())?;
+    key.verify(message, &signature).map_err(|_| "signature does not match".to_string())
+}
+
+// Result of checking an exported audit log
+#[derive(Debug, Serialize)]
+pub struct ChainReport {
+    pub valid: bool,
+    pub records: usize,
+    pub anchors: usize,
+
+    // Last record covered by a valid anchor; later records are only as trustworthy as the storage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchored_through: Option<u64>,
+    pub checks: Vec<Check>,
+}
+
+impl ChainReport {
+    pub fn check(&mut self, name: impl Into<String>, result: Result<(), String>) {
+        let (ok, detail) = match result {
+            Ok(()) => (true, None),
+            Err(e) => (false, Some(e)),
+        };
+        self.valid &= ok;
+        self.checks.push(Check { name: name.into(), ok, detail });
+    }
+}
+
+type ChainHash = fn(u64, &DateTime<Utc>, &serde_json::Value, &Hash) -> Hash;
+
+fn verify_chain(records: &[AuditRecord], compute_hash: ChainHash) -> Result<(), String> {
+    for (i, record) in records.iter().enumerate() {
+        let expected = compute_hash(record.seq, &record.at, &record.event, &record.prev_hash);
+        if expected != record.hash {
+            return Err(format!("record {} hash does not match its contents", record.seq));
+        }
+        if let Some(prev) = i.checked_sub(1).map(|p| &records[p]) {
+            if record.prev_hash != prev.hash || record.seq != prev.seq + 1 {
+                return Err(format!("chain breaks between records {} and {}", prev.seq, record.seq));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Pure function of the bundle: no network, no storage
+pub fn verify(bundle: &SignedBundle) -> VerificationReport {
+    let contents = &bundle.contents;
+    let mut report = VerificationReport {
+        valid: true,
+        signer: Some(hex::encode(bundle.signer)),
+        receipts: contents.receipts.len(),
+        audit_records: contents.audit_chain.len(),
+        checks: Vec::new(),
+    };
+
+    // Format 1 differs only in hashing the audit chain with SHA-256
+    report.check(
+        "format_version",
+        (1..=BUNDLE_FORMAT_VERSION)
+            .contains(&contents.format_version)
+            .then_some(())
+            .ok_or_else(|| format!("unsupported bundle format {}", contents.format_version)),
+    );
+    let payload = serde_json::to_vec(contents).unwrap_or_default();
+    report.check("bundle_signature", verify_signature(&bundle.signer, &signed_bytes(BUNDLE_CONTEXT, &payload), &bundle.signature));
+
+    for proven in &contents.receipts {
+        let ok = verify_proof(proven.receipt.leaf(), &proven.proof, contents.merkle_root);
+        report.check(format!("receipt:{}", proven.receipt.id), ok.then_some(()).ok_or_else(|| "Merkle proof does not reach the root".to_string()));
+    }
+
+    for attestation in &contents.attestations {
+        let result = if attestation.root != contents.merkle_root {
+            Err("attests a different root".to_string())
+        } else {
+            verify_signature(&attestation.signer, &signed_bytes(ATTESTATION_CONTEXT, &attestation.root), &attestation.signature)
+        };
+        report.check(format!("attestation:{}", hex::encode(attestation.signer)), result);
+    }
+    if contents.attestations.is_empty() {
+        report.check("attestations", Err("bundle carries no attestations".to_string()));
+    }
+
+    let compute_hash: ChainHash = if contents.format_version == 1 { AuditRecord::compute_hash_v1 } else { AuditRecord::compute_hash };
+    report.check("audit_chain", verify_chain(&contents.audit_chain, compute_hash));
+    report
+}
+
+fn verify_anchor(anchor: &Anchor, records: &[AuditRecord]) -> Result<(), String> {
+    verify_signature(&anchor.signer, &Anchor::message(anchor.seq, &anchor.hash, &anchor.at), &anchor.signature)?;
+    match records.iter().find(|r| r.seq == anchor.seq) {
+        Some(record) if record.hash == anchor.hash => Ok(()),
+        Some(_) => Err(format!("record {} differs from what was anchored", anchor.seq)),
+        None => Err(format!("anchored record {} is not in the export", anchor.seq)),
+    }
+}
+
+// Pure function of the export; `trusted` pins the node key instead of taking the export's word for it
+pub fn verify_log(export: &ChainExport, trusted: Option<&[u8; 32]>) -> ChainReport {
+    let mut report =
+        ChainReport { valid: true, records: export.records.len(), anchors: export.anchors.len(), anchored_through: None, checks: Vec::new() };
+    if let Some(trusted) = trusted {
+        report.check("signer", (&export.signer == trusted).then_some(()).ok_or_else(|| "export was not produced by this node".to_string()));
+    }
+    if let Some(first) = export.records.first().filter(|r| r.seq == 0) {
+        report.check("genesis", (first.prev_hash == [0u8; 32]).then_some(()).ok_or_else(|| "record 0 does not start the chain".to_string()));
+    }
+    report.check("chain", verify_chain(&export.records, AuditRecord::compute_hash));
+
+    for anchor in &export.anchors {
+        let result = if anchor.signer != export.signer {
+            Err("anchor signed by a different key".to_string())
+        } else {
+            verify_anchor(anchor, &export.records)
+        };
+        if result.is_ok() {
+            report.anchored_through = report.anchored_through.max(Some(anchor.seq));
+        }
+        report.check(format!("anchor:{}", anchor.seq), result);
+    }
+    report
+}
+
+// Read a `.json.zst` archive as written by the exporter
+pub fn verify_file(path: &Path) -> Result<VerificationReport, String> {
+    let compressed = std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let json = zstd::decode_all(compressed.as_slice()).map_err(|e| format!("{} is not a zstd archive: {}", path.display(), e))?;
+    let bundle: SignedBundle = serde_json::from_slice(&json).map_err(|e| format!("{} is not an audit bundle: {}", path.display(), e))?;
+    Ok(verify(&bundle))
+}
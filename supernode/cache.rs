@@ -0,0 +1,200 @@
+use crate::metrics;
+use crate::runtime::scheduler::Scheduler;
+use lru::LruCache;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::debug;
+
+// One entry of the `caches` config section
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub min_entries: usize,
+    pub max_entries: usize,
+
+    // Memory budget; capacity never grows past max_bytes / entry_bytes
+    pub max_bytes: usize,
+
+    // Rough size of one entry including its key, used to turn the budget into a capacity
+    pub entry_bytes: usize,
+
+    // Grow while the hit rate is below this and entries are being evicted; shrink when well above
+    pub target_hit_rate: f64,
+    pub tune_interval_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            min_entries: 256,
+            max_entries: 65_536,
+            max_bytes: 16 * 1024 * 1024,
+            entry_bytes: 256,
+            target_hit_rate: 0.8,
+            tune_interval_secs: 60,
+        }
+    }
+}
+
+impl CacheConfig {
+    fn ceiling(&self) -> usize {
+        self.max_entries.min(self.max_bytes / self.entry_bytes.max(1)).max(self.min_entries).max(1)
+    }
+}
+
+// `caches` section of the node config
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CachesConfig {
+    pub decisions: CacheConfig,
+    pub quotes: CacheConfig,
+    pub responses: ResponseCacheConfig,
+}
+
+// Cached GET responses; see `api::response_cache`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    pub entries: CacheConfig,
+
+    // Freshness per cached route name, falling back to `default_ttl_secs`
+    pub ttl_secs: BTreeMap<String, u64>,
+    pub default_ttl_secs: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        ResponseCacheConfig {
+            entries: CacheConfig { min_entries: 64, max_entries: 4096, max_bytes: 8 * 1024 * 1024, entry_bytes: 4096, ..CacheConfig::default() },
+            ttl_secs: BTreeMap::new(),
+            default_ttl_secs: 5,
+        }
+    }
+}
+
+struct Inner<K: Hash + Eq, V> {
+    entries: LruCache<K, V>,
+
+    // Counters for the current tuning window
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+// LRU whose capacity follows its own hit rate between the configured bounds
+pub struct AdaptiveLru<K: Hash + Eq, V> {
+    name: &'static str,
+    config: CacheConfig,
+    inner: Arc<Mutex<Inner<K, V>>>,
+}
+
+impl<K: Hash + Eq, V> Clone for AdaptiveLru<K, V> {
+    fn clone(&self) -> Self {
+        AdaptiveLru { name: self.name, config: self.config.clone(), inner: self.inner.clone() }
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> AdaptiveLru<K, V> {
+    pub fn new(name: &'static str, config: CacheConfig) -> Self {
+        let capacity = NonZeroUsize::new(config.min_entries.clamp(1, config.ceiling())).unwrap_or(NonZeroUsize::MIN);
+        metrics::CACHE_CAPACITY.with_label_values(&[name]).set(capacity.get() as i64);
+        AdaptiveLru { name, config, inner: Arc::new(Mutex::new(Inner { entries: LruCache::new(capacity), hits: 0, misses: 0, evictions: 0 })) }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut inner = self.inner.lock().unwrap();
+        let value = inner.entries.get(key).cloned();
+        if value.is_some() {
+            inner.hits += 1;
+        } else {
+            inner.misses += 1;
+        }
+        metrics::CACHE_LOOKUPS.with_label_values(&[self.name, if value.is_some() { "hit" } else { "miss" }]).inc();
+        value
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut inner = self.inner.lock().unwrap();
+        let replacing = inner.entries.contains(&key);
+        if inner.entries.push(key, value).is_some() && !replacing {
+            inner.evictions += 1;
+        }
+    }
+
+    pub fn get_or_insert_with(&self, key: K, compute: impl FnOnce() -> V) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let value = compute();
+        self.insert(key, value.clone());
+        value
+    }
+
+    // Drop everything, e.g. after the inputs the values were derived from changed
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+
+    // Resize from the last window's hit rate; returns the new capacity
+    pub fn tune(&self) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let lookups = inner.hits + inner.misses;
+        let capacity = inner.entries.cap().get();
+        let mut next = capacity;
+        if lookups > 0 {
+            let hit_rate = inner.hits as f64 / lookups as f64;
+            if hit_rate < self.config.target_hit_rate && inner.evictions > 0 {
+                next = (capacity + capacity / 4).max(capacity + 1);
+            } else if hit_rate > (self.config.target_hit_rate + 1.0) / 2.0 && inner.entries.len() < capacity / 2 {
+                next = capacity - capacity / 4;
+            }
+            debug!(cache = self.name, hit_rate, evictions = inner.evictions, capacity, "cache window");
+        }
+        let next = next.clamp(self.config.min_entries.max(1), self.config.ceiling());
+        if next != capacity {
+            if let Some(size) = NonZeroUsize::new(next) {
+                inner.entries.resize(size);
+            }
+            metrics::CACHE_CAPACITY.with_label_values(&[self.name]).set(next as i64);
+        }
+        inner.hits = 0;
+        inner.misses = 0;
+        inner.evictions = 0;
+        next
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> AdaptiveLru<K, V> {
+    // Drop the entries whose key matches, e.g. one route's responses after a write; returns how many
+    pub fn remove_where(&self, matches: impl Fn(&K) -> bool) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let keys: Vec<K> = inner.entries.iter().filter(|(k, _)| matches(k)).map(|(k, _)| k.clone()).collect();
+        for key in &keys {
+            inner.entries.pop(key);
+        }
+        keys.len()
+    }
+}
+
+pub fn register_tuning<K, V>(scheduler: &Scheduler, cache: AdaptiveLru<K, V>)
+where
+    K: Hash + Eq + Send + 'static,
+    V: Clone + Send + 'static,
+{
+    let interval = Duration::from_secs(cache.config.tune_interval_secs.max(1));
+    scheduler.register(
+        &format!("cache:{}:tune", cache.name),
+        interval,
+        Duration::ZERO,
+        Arc::new(move || {
+            let cache = cache.clone();
+            Box::pin(async move {
+                cache.tune();
+            })
+        }),
+    );
+}
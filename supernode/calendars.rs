@@ -0,0 +1,243 @@
+use crate::api::auth::{Auth, Principal};
+use crate::storage::entities::{if_match, EntityError, EntityStore};
+use crate::storage::mvcc::Store;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use tracing::info;
+use warp::http::StatusCode;
+use warp::hyper::body::Bytes;
+use warp::{Filter, Rejection, Reply};
+
+// Searching further than this for a business day means the calendar is closed for good
+const MAX_SCAN_DAYS: u32 = 366;
+
+// `calendars` section of the node config
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CalendarsConfig {
+    // Calendar name -> file loaded at startup when storage has no calendar of that name yet
+    pub files: BTreeMap<String, PathBuf>,
+
+    // Currency or asset -> calendars that must all be open for it to settle, e.g. USD: [US]
+    pub currencies: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Holiday {
+    pub date: NaiveDate,
+    pub name: String,
+}
+
+// Non-banking days of one jurisdiction or market; the same shape as a calendar file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BusinessCalendar {
+    #[serde(default = "default_weekend")]
+    pub weekend: Vec<Weekday>,
+    #[serde(default)]
+    pub holidays: Vec<Holiday>,
+}
+
+fn default_weekend() -> Vec<Weekday> {
+    vec![Weekday::Sat, Weekday::Sun]
+}
+
+impl BusinessCalendar {
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !self.weekend.contains(&date.weekday()) && !self.holidays.iter().any(|h| h.date == date)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.weekend.len() >= 7 {
+            return Err("a calendar needs at least one weekday that is not weekend".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum CalendarError {
+    Unknown(String),
+    Invalid(String),
+    NoBusinessDay { after: NaiveDate },
+    Storage(EntityError),
+}
+
+impl fmt::Display for CalendarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalendarError::Unknown(name) => write!(f, "unknown calendar {}", name),
+            CalendarError::Invalid(e) => write!(f, "invalid calendar: {}", e),
+            CalendarError::NoBusinessDay { after } => write!(f, "no business day within {} days after {}", MAX_SCAN_DAYS, after),
+            CalendarError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn storage_error(name: &str, e: EntityError) -> CalendarError {
+    match e {
+        EntityError::NotFound => CalendarError::Unknown(name.to_string()),
+        e => CalendarError::Storage(e),
+    }
+}
+
+// Business-day calendars per jurisdiction, kept in storage so operators can edit them at runtime,
+// and the currencies they govern; currencies without calendars are open every day
+#[derive(Clone)]
+pub struct Calendars {
+    calendars: EntityStore<BusinessCalendar>,
+    currencies: BTreeMap<String, Vec<String>>,
+}
+
+impl Calendars {
+    pub fn new(config: &CalendarsConfig, store: Store) -> Result<Self, String> {
+        let calendars = Calendars { calendars: EntityStore::new(store, "calendars"), currencies: config.currencies.clone() };
+        for (name, path) in &config.files {
+            if calendars.calendars.get(name).is_ok() {
+                continue;
+            }
+            let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+            let calendar = parse(text.as_bytes()).map_err(|e| format!("{}: {}", path.display(), e))?;
+            calendars.calendars.create(name, calendar).map_err(|e| format!("calendar {}: {}", name, e))?;
+            info!(calendar = %name, path = %path.display(), "business calendar loaded");
+        }
+        Ok(calendars)
+    }
+
+    pub fn get(&self, name: &str) -> Result<BusinessCalendar, CalendarError> {
+        self.calendars.get(name).map(|v| v.value).map_err(|e| storage_error(name, e))
+    }
+
+    pub fn is_business_day(&self, name: &str, date: NaiveDate) -> Result<bool, CalendarError> {
+        Ok(self.get(name)?.is_business_day(date))
+    }
+
+    // Whether `currency` can settle on `date`: every calendar it follows is open; an unknown calendar counts as closed
+    pub fn is_open(&self, currency: &str, date: NaiveDate) -> bool {
+        self.currencies.get(currency).is_none_or(|names| names.iter().all(|name| self.is_business_day(name, date).unwrap_or(false)))
+    }
+
+    // The `days`-th business day of `currency` after `date`; `date` itself when `days` is 0 and it is open
+    pub fn add_business_days(&self, currency: &str, date: NaiveDate, days: u32) -> Result<NaiveDate, CalendarError> {
+        let mut current = date;
+        let mut remaining = days;
+        let mut scanned = 0;
+        while remaining > 0 || !self.is_open(currency, current) {
+            if scanned > MAX_SCAN_DAYS + days {
+                return Err(CalendarError::NoBusinessDay { after: date });
+            }
+            current = current.succ_opt().ok_or(CalendarError::NoBusinessDay { after: date })?;
+            scanned += 1;
+            if self.is_open(currency, current) {
+                remaining = remaining.saturating_sub(1);
+            }
+        }
+        Ok(current)
+    }
+
+    // When an SLA of `days` business days started at `start` runs out, at the same time of day
+    pub fn deadline(&self, currency: &str, start: DateTime<Utc>, days: u32) -> Result<DateTime<Utc>, CalendarError> {
+        let date = self.add_business_days(currency, start.date_naive(), days)?;
+        Ok(start + ChronoDuration::days((date - start.date_naive()).num_days()))
+    }
+
+    // Create `name`, or replace it when `expected_version` is given
+    pub fn put(&self, name: &str, expected_version: Option<u64>, calendar: BusinessCalendar) -> Result<u64, CalendarError> {
+        calendar.validate().map_err(CalendarError::Invalid)?;
+        let written = match expected_version {
+            None => self.calendars.create(name, calendar),
+            Some(version) => self.calendars.update(name, version, calendar),
+        };
+        written.map(|v| v.version).map_err(|e| storage_error(name, e))
+    }
+
+    // GET /admin/calendars, GET/PUT/DELETE /admin/calendars/{name}, GET /admin/calendars/currencies/{currency}/next?after=&days=
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let calendars = self.clone();
+        let list = warp::path!("admin" / "calendars").and(warp::get()).and(auth.authorized()).map(move |_| {
+            let all: BTreeMap<String, BusinessCalendar> = calendars.calendars.list().into_iter().map(|(name, v)| (name, v.value)).collect();
+            warp::reply::json(&all)
+        });
+
+        let calendars = self.clone();
+        let get = warp::path!("admin" / "calendars" / String).and(warp::get()).and(auth.authorized()).map(move |name: String, _| {
+            match calendars.calendars.get(&name) {
+                Ok(entity) => warp::reply::with_header(warp::reply::json(&entity.value), "etag", entity.etag()).into_response(),
+                Err(e) => error_reply(&storage_error(&name, e)).into_response(),
+            }
+        });
+
+        // The body is a calendar file, YAML or JSON; without If-Match the calendar must not exist yet
+        let calendars = self.clone();
+        let put = warp::path!("admin" / "calendars" / String)
+            .and(warp::put())
+            .and(auth.authorized())
+            .and(warp::header::optional::<String>("if-match"))
+            .and(warp::body::bytes())
+            .map(move |name: String, principal: Principal, if_match: Option<String>, body: Bytes| {
+                let expected = if_match.as_deref().and_then(|h| h.trim().trim_start_matches("W/").trim_matches('"').parse().ok());
+                let result = parse(&body).map_err(CalendarError::Invalid).and_then(|calendar| calendars.put(&name, expected, calendar));
+                match result {
+                    Ok(version) => {
+                        info!(calendar = %name, version, by = %principal.subject, "business calendar written");
+                        warp::reply::with_status(warp::reply::json(&version), StatusCode::OK)
+                    }
+                    Err(e) => error_reply(&e),
+                }
+            });
+
+        let calendars = self.clone();
+        let delete = warp::path!("admin" / "calendars" / String)
+            .and(warp::delete())
+            .and(auth.authorized())
+            .and(if_match())
+            .map(move |name: String, principal: Principal, version: u64| match calendars.calendars.delete(&name, version) {
+                Ok(()) => {
+                    info!(calendar = %name, by = %principal.subject, "business calendar deleted");
+                    warp::reply::with_status(warp::reply::json(&name), StatusCode::OK)
+                }
+                Err(e) => error_reply(&storage_error(&name, e)),
+            });
+
+        let calendars = self.clone();
+        let next = warp::path!("admin" / "calendars" / "currencies" / String / "next")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and(warp::query::<NextQuery>())
+            .map(move |currency: String, _, query: NextQuery| {
+                let after = query.after.unwrap_or_else(|| Utc::now().date_naive());
+                match calendars.add_business_days(&currency, after, query.days.unwrap_or(1)) {
+                    Ok(date) => warp::reply::with_status(warp::reply::json(&date), StatusCode::OK),
+                    Err(e) => error_reply(&e),
+                }
+            });
+
+        list.or(next).or(get).or(put).or(delete)
+    }
+}
+
+fn parse(bytes: &[u8]) -> Result<BusinessCalendar, String> {
+    let calendar: BusinessCalendar = serde_yaml::from_slice(bytes).map_err(|e| e.to_string())?;
+    calendar.validate()?;
+    Ok(calendar)
+}
+
+#[derive(Deserialize)]
+struct NextQuery {
+    after: Option<NaiveDate>,
+    days: Option<u32>,
+}
+
+fn error_reply(error: &CalendarError) -> warp::reply::WithStatus<warp::reply::Json> {
+    let status = match error {
+        CalendarError::Unknown(_) => StatusCode::NOT_FOUND,
+        CalendarError::Invalid(_) | CalendarError::NoBusinessDay { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        CalendarError::Storage(EntityError::AlreadyExists) | CalendarError::Storage(EntityError::Conflict { .. }) => StatusCode::CONFLICT,
+        CalendarError::Storage(EntityError::ReadOnly | EntityError::Unavailable(_)) => StatusCode::SERVICE_UNAVAILABLE,
+        CalendarError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    warp::reply::with_status(warp::reply::json(&error.to_string()), status)
+}
@@ -0,0 +1,140 @@
+use crate::audit::verify::verify_file;
+use crate::config::NodeConfig;
+use crate::doctor::{diagnose, support_bundle, write_bundle};
+use crate::health::Status;
+use crate::storage::sync::{bootstrap, write_state};
+use crate::upgrade::advise;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "pi-supernode", version, about = "Pi Network supernode")]
+pub struct Cli {
+    #[arg(long, default_value = "config/config.yaml", global = true, help = "Node configuration file")]
+    pub config: PathBuf,
+
+    #[arg(long, global = true, help = "Run read-only for an incident investigation; same as forensic.enabled")]
+    pub forensic: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Run the node (the default)")]
+    Run,
+
+    #[command(about = "Check an exported audit bundle offline and print a JSON verification report")]
+    VerifyBundle { archive: PathBuf },
+
+    #[command(about = "Initialize local state from a trusted peer's signed snapshot and event log")]
+    Bootstrap {
+        #[arg(long, help = "Base URL of the peer's API, e.g. https://node-1.example.com:3030")]
+        from: String,
+    },
+
+    #[command(about = "Report required migrations and deprecated settings for this version without changing anything")]
+    UpgradeCheck,
+
+    #[command(about = "Check config, storage, keys, oracle, peers and clock, and write a redacted support bundle")]
+    Doctor {
+        #[arg(long, help = "Price oracle URL to probe")]
+        oracle_url: Option<String>,
+
+        #[arg(long, help = "Directory whose *.log files are included in the bundle")]
+        logs: Option<PathBuf>,
+
+        #[arg(long, default_value = "pi-supernode-support.json.zst", help = "Where to write the support bundle")]
+        out: PathBuf,
+    },
+
+    #[command(about = "Open the admin console of the running node (console.enabled must be set)")]
+    Attach {
+        #[arg(long, help = "Console socket; defaults to console.socket_path")]
+        socket: Option<PathBuf>,
+    },
+}
+
+fn fail(e: impl std::fmt::Display) -> ExitCode {
+    eprintln!("error: {}", e);
+    ExitCode::from(2)
+}
+
+fn run_bootstrap(config_path: &std::path::Path, peer: &str) -> Result<ExitCode, String> {
+    let config = NodeConfig::load(config_path)?;
+    let runtime = config.server.runtime().map_err(|e| e.to_string())?;
+    let store = runtime.block_on(bootstrap(peer, &config.bootstrap))?;
+    if let Some(dir) = config.bootstrap.state_path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    write_state(&store, &config.bootstrap.state_path)?;
+    // The old journal continues a state that was just replaced
+    match std::fs::remove_file(&config.bootstrap.journal_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(format!("{}: {}", config.bootstrap.journal_path.display(), e)),
+        _ => {}
+    }
+    println!("state initialized at {} (seq {})", config.bootstrap.state_path.display(), store.read_txn().seq());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_doctor(config_path: &std::path::Path, oracle_url: Option<&str>, logs: Option<&std::path::Path>, out: &std::path::Path) -> Result<ExitCode, String> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|e| e.to_string())?;
+    let report = runtime.block_on(diagnose(config_path, oracle_url));
+    for check in &report.checks {
+        let mark = if check.status == Status::Ok { "ok  " } else { "FAIL" };
+        println!("[{}] {}{}", mark, check.name, check.detail.as_ref().map(|d| format!(": {}", d)).unwrap_or_default());
+    }
+    let healthy = report.status == Status::Ok;
+    write_bundle(&support_bundle(config_path, report, logs), out)?;
+    println!("support bundle written to {}", out.display());
+    Ok(if healthy { ExitCode::SUCCESS } else { ExitCode::from(1) })
+}
+
+fn run_upgrade_check(config_path: &std::path::Path) -> Result<ExitCode, String> {
+    let config = NodeConfig::load(config_path)?;
+    let report = advise(config_path, &config.upgrade.manifest_path)?;
+    println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+    Ok(if report.blocking() { ExitCode::from(1) } else { ExitCode::SUCCESS })
+}
+
+#[cfg(unix)]
+fn run_attach(config_path: &std::path::Path, socket: Option<&std::path::Path>) -> Result<ExitCode, String> {
+    let socket = match socket {
+        Some(socket) => socket.to_path_buf(),
+        None => NodeConfig::load(config_path)?.console.socket_path,
+    };
+    crate::console::attach(&socket)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(unix))]
+fn run_attach(_: &std::path::Path, _: Option<&std::path::Path>) -> Result<ExitCode, String> {
+    Err("the admin console needs Unix domain sockets".to_string())
+}
+
+// Subcommands that run to completion instead of starting the node; `None` means run it
+pub fn run_command(cli: &Cli) -> Option<ExitCode> {
+    let command = cli.command.as_ref()?;
+    match command {
+        Command::Run => None,
+        Command::Bootstrap { from } => Some(run_bootstrap(&cli.config, from).unwrap_or_else(fail)),
+        Command::UpgradeCheck => Some(run_upgrade_check(&cli.config).unwrap_or_else(fail)),
+        Command::Attach { socket } => Some(run_attach(&cli.config, socket.as_deref()).unwrap_or_else(fail)),
+        Command::Doctor { oracle_url, logs, out } => {
+            Some(run_doctor(&cli.config, oracle_url.as_deref(), logs.as_deref(), out).unwrap_or_else(fail))
+        }
+        Command::VerifyBundle { archive } => Some(match verify_file(archive) {
+            Ok(report) => {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+                if report.valid {
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::from(1)
+                }
+            }
+            Err(e) => fail(e),
+        }),
+    }
+}
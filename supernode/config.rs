@@ -0,0 +1,99 @@
+use crate::admin::policy_params::PolicyGuardConfig;
+use crate::alert_correlation::CorrelationConfig;
+use crate::alerting::AlertingConfig;
+use crate::anomaly_model::ModelConfig;
+use crate::ai::self_heal::SelfHealConfig;
+use crate::api::auth::AuthConfig;
+use crate::api::graphql::GraphqlConfig;
+use crate::api::validation::ValidationConfig;
+use crate::assets::AssetConfig;
+use crate::audit::log::AuditLogConfig;
+use crate::cache::CachesConfig;
+use crate::calendars::CalendarsConfig;
+use crate::console::ConsoleConfig;
+use crate::converter::ConverterConfig;
+use crate::events::log::EventLogConfig;
+use crate::fees::FeeScheduleConfig;
+use crate::job_queue::JobQueueConfig;
+use crate::key_compromise::KeyCompromiseConfig;
+use crate::logging::LoggingConfig;
+use crate::metrics_history::MetricsHistoryConfig;
+use crate::netting::NettingConfig;
+use crate::oracle::OracleConfig;
+use crate::p2p::address_book::PeerConfig;
+use crate::plans::PlansConfig;
+use crate::pricing_experiments::ExperimentConfig;
+use crate::quotes::QuoteConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::runbooks::RunbooksConfig;
+use crate::runtime::clock::ClockConfig;
+use crate::runtime::forensic::ForensicConfig;
+use crate::server::{ServerConfig, TlsConfig};
+use crate::sessions::SessionConfig;
+use crate::storage::profiler::StorageProfilingConfig;
+use crate::storage::sync::BootstrapConfig;
+use crate::telemetry::TelemetryConfig;
+use crate::traffic_mirror::TrafficMirrorConfig;
+use crate::upgrade::UpgradeConfig;
+use crate::webhooks::WebhookConfig;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// Node configuration loaded from config/config.yaml; missing sections use defaults
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NodeConfig {
+    pub node_name: String,
+    pub node_type: String,
+    pub network_id: String,
+    pub logging: LoggingConfig,
+    pub telemetry: TelemetryConfig,
+    pub self_heal: SelfHealConfig,
+    pub model: ModelConfig,
+    pub p2p: PeerConfig,
+    pub clock: ClockConfig,
+    pub webhooks: Vec<WebhookConfig>,
+    pub auth: AuthConfig,
+    pub rate_limit: RateLimitConfig,
+    pub server: ServerConfig,
+    pub tls: Option<TlsConfig>,
+    pub policy_guard: PolicyGuardConfig,
+    pub experiments: Vec<ExperimentConfig>,
+    pub bootstrap: BootstrapConfig,
+    pub graphql: GraphqlConfig,
+    pub metrics_history: MetricsHistoryConfig,
+    pub validation: ValidationConfig,
+    pub upgrade: UpgradeConfig,
+    pub caches: CachesConfig,
+    pub audit_log: AuditLogConfig,
+    pub alert_correlation: CorrelationConfig,
+    pub converter: ConverterConfig,
+    pub forensic: ForensicConfig,
+    pub traffic_mirror: TrafficMirrorConfig,
+    pub key_compromise: KeyCompromiseConfig,
+    pub sessions: SessionConfig,
+    pub event_log: EventLogConfig,
+    pub oracle: OracleConfig,
+    pub plans: PlansConfig,
+    pub netting: NettingConfig,
+    pub calendars: CalendarsConfig,
+
+    // Symbol -> asset the converter handles
+    pub assets: BTreeMap<String, AssetConfig>,
+    pub fees: FeeScheduleConfig,
+    pub storage_profiling: StorageProfilingConfig,
+    pub quotes: QuoteConfig,
+    pub console: ConsoleConfig,
+    pub jobs: JobQueueConfig,
+    pub alerting: AlertingConfig,
+    pub runbooks: RunbooksConfig,
+}
+
+impl NodeConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&text).map_err(|e| format!("invalid config {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,382 @@
+use crate::events::log::EventLog;
+use crate::ids::{ConversionId, QuoteId};
+use crate::job_queue::JobQueue;
+use crate::netting::NettingEngine;
+use crate::quotes::QuoteBook;
+use crate::runtime::scheduler::Scheduler;
+use crate::storage::ledger_history::LedgerHistory;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+// Ends every response on the wire; response lines starting with `.` are sent with an extra `.`
+const END: &str = ".";
+
+// `console` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ConsoleConfig {
+    pub enabled: bool,
+    pub socket_path: PathBuf,
+}
+
+impl Default for ConsoleConfig {
+    fn default() -> Self {
+        ConsoleConfig { enabled: false, socket_path: PathBuf::from("data/admin.sock") }
+    }
+}
+
+type FlagGet = Arc<dyn Fn() -> bool + Send + Sync>;
+type FlagSet = Arc<dyn Fn(bool) + Send + Sync>;
+
+#[derive(Serialize)]
+struct QueueSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    netting_pending: Option<usize>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    jobs_by_status: BTreeMap<String, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scheduled_jobs: Option<usize>,
+}
+
+const HELP: &str = "\
+help                       this list
+queues                     sizes of the netting queue, job queue and scheduler
+peek netting|jobs|schedule [n]
+                           first n entries of a queue (default 20)
+tx <id>                    everything known about a transaction, conversion or quote id
+flags                      feature flags and their state
+flag <name> on|off         flip a feature flag
+quit                       close the session";
+
+// Operator terminal served on a local Unix socket (`pi-supernode attach`); only the node's own user and root may
+// connect. Every command reads live state, so a running node can be inspected without a restart.
+#[derive(Clone, Default)]
+pub struct Console {
+    scheduler: Option<Scheduler>,
+    netting: Option<NettingEngine>,
+    jobs: Option<JobQueue>,
+    history: Option<LedgerHistory>,
+    events: Option<EventLog>,
+    quotes: Option<QuoteBook>,
+    flags: BTreeMap<String, (FlagGet, FlagSet)>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Scheduled jobs are also listed as `job:<name>` flags, on while the job runs on schedule
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    pub fn with_netting(mut self, netting: NettingEngine) -> Self {
+        self.netting = Some(netting);
+        self
+    }
+
+    pub fn with_jobs(mut self, jobs: JobQueue) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    pub fn with_history(mut self, history: LedgerHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    pub fn with_events(mut self, events: EventLog) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn with_quotes(mut self, quotes: QuoteBook) -> Self {
+        self.quotes = Some(quotes);
+        self
+    }
+
+    // A runtime toggle the embedder owns, e.g. `with_flag("netting", ...)` around an AtomicBool
+    pub fn with_flag(
+        mut self,
+        name: &str,
+        get: impl Fn() -> bool + Send + Sync + 'static,
+        set: impl Fn(bool) + Send + Sync + 'static,
+    ) -> Self {
+        self.flags.insert(name.to_string(), (Arc::new(get), Arc::new(set)));
+        self
+    }
+
+    fn flag_states(&self) -> BTreeMap<String, bool> {
+        let mut states: BTreeMap<String, bool> = self.flags.iter().map(|(name, (get, _))| (name.clone(), get())).collect();
+        if let Some(scheduler) = &self.scheduler {
+            states.extend(scheduler.jobs().into_iter().map(|job| (format!("job:{}", job.name), !job.paused)));
+        }
+        states
+    }
+
+    fn set_flag(&self, name: &str, on: bool) -> Result<(), String> {
+        if let Some((_, set)) = self.flags.get(name) {
+            set(on);
+            return Ok(());
+        }
+        let scheduler = self.scheduler.as_ref();
+        let flipped = name.strip_prefix("job:").and_then(|job| scheduler.map(|s| if on { s.resume(job) } else { s.pause(job) }));
+        if flipped == Some(true) {
+            Ok(())
+        } else {
+            Err(format!("no flag {}; `flags` lists them", name))
+        }
+    }
+
+    fn queues(&self) -> QueueSummary {
+        let mut jobs_by_status = BTreeMap::new();
+        for job in self.jobs.iter().flat_map(JobQueue::jobs) {
+            *jobs_by_status.entry(format!("{:?}", job.status)).or_insert(0) += 1;
+        }
+        QueueSummary {
+            netting_pending: self.netting.as_ref().map(|n| n.pending().len()),
+            jobs_by_status,
+            scheduled_jobs: self.scheduler.as_ref().map(|s| s.jobs().len()),
+        }
+    }
+
+    fn peek(&self, queue: &str, n: usize) -> Result<serde_json::Value, String> {
+        let missing = || format!("{} is not attached to this console", queue);
+        let value = match queue {
+            "netting" => serde_json::to_value(self.netting.as_ref().ok_or_else(missing)?.pending().into_iter().take(n).collect::<Vec<_>>()),
+            "jobs" => serde_json::to_value(self.jobs.as_ref().ok_or_else(missing)?.jobs().into_iter().take(n).collect::<Vec<_>>()),
+            "schedule" => serde_json::to_value(self.scheduler.as_ref().ok_or_else(missing)?.jobs().into_iter().take(n).collect::<Vec<_>>()),
+            other => return Err(format!("unknown queue {}; one of netting, jobs, schedule", other)),
+        };
+        value.map_err(|e| e.to_string())
+    }
+
+    // History record, every logged event naming the id, and netting or quote state when it is one of theirs
+    fn transaction(&self, id: &str) -> serde_json::Value {
+        let mut dump = serde_json::Map::new();
+        if let Some(record) = self.history.as_ref().and_then(|h| h.transaction(id)) {
+            dump.insert("record".to_string(), serde_json::to_value(record).unwrap_or_default());
+        }
+        if let Some(events) = &self.events {
+            dump.insert("events".to_string(), serde_json::to_value(events.mentioning(id)).unwrap_or_default());
+        }
+        if let (Some(netting), Ok(conversion)) = (&self.netting, id.parse::<ConversionId>()) {
+            if let Some(queued) = netting.pending().into_iter().find(|q| q.id == conversion) {
+                dump.insert("queued".to_string(), serde_json::to_value(queued).unwrap_or_default());
+            }
+            if let Some(settlement) = netting.settlement_of(&conversion) {
+                dump.insert("settlement".to_string(), serde_json::to_value(settlement).unwrap_or_default());
+            }
+        }
+        if let (Some(quotes), Ok(quote)) = (&self.quotes, id.parse::<QuoteId>()) {
+            if let Some(quote) = quotes.lookup(quote) {
+                dump.insert("quote".to_string(), serde_json::to_value(quote).unwrap_or_default());
+            }
+        }
+        serde_json::Value::Object(dump)
+    }
+
+    // Answer one command line; `None` ends the session
+    pub fn execute(&self, line: &str) -> Option<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let reply = match words.as_slice() {
+            [] => String::new(),
+            ["quit"] | ["exit"] => return None,
+            ["help"] => HELP.to_string(),
+            ["queues"] => pretty(&self.queues()),
+            ["peek", queue] => self.peek(queue, 20).map_or_else(|e| format!("error: {}", e), |v| pretty(&v)),
+            ["peek", queue, n] => match n.parse() {
+                Ok(n) => self.peek(queue, n).map_or_else(|e| format!("error: {}", e), |v| pretty(&v)),
+                Err(_) => format!("error: {} is not a count", n),
+            },
+            ["tx", id] => pretty(&self.transaction(id)),
+            ["flags"] => pretty(&self.flag_states()),
+            ["flag", name, state @ ("on" | "off")] => match self.set_flag(name, *state == "on") {
+                Ok(()) => {
+                    info!(flag = %name, state = %state, "feature flag flipped from the console");
+                    format!("{} is {}", name, state)
+                }
+                Err(e) => format!("error: {}", e),
+            },
+            _ => format!("error: unknown command `{}`; try `help`", line.trim()),
+        };
+        Some(reply)
+    }
+}
+
+fn pretty(value: &impl Serialize) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|e| format!("error: {}", e))
+}
+
+// Dot-stuff a reply and terminate it, so replies may contain any line
+fn frame(reply: &str) -> String {
+    let mut out = String::new();
+    for line in reply.lines() {
+        if line.starts_with('.') {
+            out.push('.');
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(END);
+    out.push('\n');
+    out
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio_util::sync::CancellationToken;
+
+    // Serve the console on `config.socket_path` until `shutdown` is cancelled
+    pub async fn serve(console: Console, config: &ConsoleConfig, shutdown: CancellationToken) -> Result<(), String> {
+        let path = &config.socket_path;
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        }
+        // A socket left by a previous run would make bind fail
+        if path.exists() {
+            std::fs::remove_file(path).map_err(|e| format!("failed to remove stale {}: {}", path.display(), e))?;
+        }
+        let listener = UnixListener::bind(path).map_err(|e| format!("failed to bind {}: {}", path.display(), e))?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(|e| format!("failed to restrict {}: {}", path.display(), e))?;
+        let owner = std::fs::metadata(path).map_err(|e| e.to_string())?.uid();
+        info!(socket = %path.display(), "admin console listening");
+        let console = Arc::new(console);
+        loop {
+            let stream = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!(error = %e, "console accept failed");
+                        continue;
+                    }
+                },
+            };
+            // The socket mode already keeps others out; checking the peer also covers a loosened mode or directory
+            let uid = match stream.peer_cred() {
+                Ok(cred) => cred.uid(),
+                Err(e) => {
+                    warn!(error = %e, "console peer credentials unavailable, connection refused");
+                    continue;
+                }
+            };
+            if uid != owner && uid != 0 {
+                warn!(uid, "console connection from another user refused");
+                continue;
+            }
+            info!(uid, "admin console attached");
+            tokio::spawn(session(console.clone(), stream, shutdown.clone()));
+        }
+        let _ = std::fs::remove_file(path);
+        Ok(())
+    }
+
+    async fn session(console: Arc<Console>, stream: UnixStream, shutdown: CancellationToken) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = AsyncBufReader::new(reader).lines();
+        loop {
+            let line = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => line,
+                    _ => break,
+                },
+            };
+            let Some(reply) = console.execute(&line) else { break };
+            if writer.write_all(frame(&reply).as_bytes()).await.is_err() {
+                break;
+            }
+        }
+        info!("admin console detached");
+    }
+
+    // `pi-supernode attach`: a line-based terminal on the console socket
+    pub fn attach(socket: &std::path::Path) -> Result<(), String> {
+        let stream = std::os::unix::net::UnixStream::connect(socket).map_err(|e| format!("cannot attach to {}: {}", socket.display(), e))?;
+        let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+        let mut replies = BufReader::new(stream).lines();
+        let stdin = std::io::stdin();
+        println!("attached to {}; `help` lists commands", socket.display());
+        loop {
+            print!("pi> ");
+            std::io::stdout().flush().map_err(|e| e.to_string())?;
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                return Ok(());
+            }
+            writeln!(writer, "{}", line.trim_end()).map_err(|e| e.to_string())?;
+            loop {
+                match replies.next() {
+                    Some(Ok(reply)) if reply == END => break,
+                    Some(Ok(reply)) => println!("{}", reply.strip_prefix('.').unwrap_or(&reply)),
+                    Some(Err(e)) => return Err(e.to_string()),
+                    // The node closes the session on `quit` and when it shuts down
+                    None => return Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{attach, serve};
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::job_queue::{JobKind, JobQueue};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    use tokio_util::sync::CancellationToken;
+
+    #[tokio::test]
+    async fn serves_commands_on_the_socket_until_shutdown() {
+        let dir = std::env::temp_dir().join(format!("console-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let jobs = JobQueue::open(dir.join("jobs.json"), 60).unwrap();
+        jobs.enqueue(JobKind::Export, "ledger".to_string(), 1, 0).unwrap();
+        let config = ConsoleConfig { enabled: true, socket_path: dir.join("admin.sock") };
+        let shutdown = CancellationToken::new();
+        let served = tokio::spawn({
+            let (config, shutdown) = (config.clone(), shutdown.clone());
+            async move { serve(Console::new().with_jobs(jobs), &config, shutdown).await }
+        });
+
+        let mut stream = None;
+        for _ in 0..50 {
+            if let Ok(connected) = UnixStream::connect(&config.socket_path).await {
+                stream = Some(connected);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let (reader, mut writer) = stream.expect("console socket").into_split();
+        writer.write_all(b"queues\n").await.unwrap();
+        let mut lines = BufReader::new(reader).lines();
+        let mut reply = String::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if line == END {
+                break;
+            }
+            reply.push_str(&line);
+        }
+        assert!(reply.contains("\"Ready\": 1"), "{}", reply);
+
+        shutdown.cancel();
+        served.await.unwrap().unwrap();
+        assert!(!config.socket_path.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
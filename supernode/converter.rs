@@ -0,0 +1,789 @@
+use crate::ai::backtest::{HistoricalRequest, RequestArchive};
+use crate::ai::engine::{AIEngine, Source};
+use crate::ai::explain::{build_report, DecisionStore, RejectionReport};
+use crate::amount::{format_units, units_string, Rounding};
+use crate::api::auth::{Auth, Principal};
+use crate::api::problem::ApiError;
+use crate::api::redemption::{stage_postings, LedgerAccount, PostingError};
+use crate::anomaly_model::Features;
+use crate::assets::{AssetError, AssetRegistry};
+use crate::fees::{FeeCharge, FeeError, FeeOperation, FeeSchedule};
+use crate::ids::{ConversionId, QuoteId};
+use crate::api::response_cache::ResponseCache;
+use crate::pricing_experiments::Variant;
+use crate::api::validation::{validated_json, FieldError, Validate, ValidationConfig};
+use crate::storage::conversion_ledger::{ConversionEntry, ConversionLedger};
+use crate::storage::entities::EntityStore;
+use crate::storage::mvcc::WriteBatch;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+// One configured pair; `numerator` smallest units of `to` per `denominator` smallest units of `from`
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateConfig {
+    pub from: String,
+    pub to: String,
+    pub numerator: u128,
+    pub denominator: u128,
+}
+
+// Which way a conversion goes: into a stablecoin, or from a stablecoin back into a Pi-ecosystem asset
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Forward,
+    Reverse,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Forward => "forward",
+            Direction::Reverse => "reverse",
+        }
+    }
+}
+
+// Bounds on conversions in one direction, in smallest units; assets not listed are unbounded
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DirectionLimits {
+    // Largest amount converted at once, by source asset
+    pub max_amount: BTreeMap<String, u128>,
+
+    // What the node can pay out, by target asset; drawn down by every accepted conversion and replaceable at runtime with `set_liquidity`
+    pub liquidity: BTreeMap<String, u128>,
+}
+
+// `converter` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ConverterConfig {
+    pub rates: Vec<RateConfig>,
+
+    // Also quote `to -> from` at the inverse of each configured rate
+    pub inverse_pairs: bool,
+
+    // Applied to converted amounts; `down` never credits more than the rate allows
+    pub rounding: Rounding,
+
+    // Smallest units per whole token of each asset, as a power of ten, for the decimal form of quotes
+    pub decimals: BTreeMap<String, u8>,
+
+    pub forward: DirectionLimits,
+    pub reverse: DirectionLimits,
+}
+
+impl Default for ConverterConfig {
+    fn default() -> Self {
+        ConverterConfig {
+            rates: Vec::new(),
+            inverse_pairs: true,
+            rounding: Rounding::Down,
+            decimals: BTreeMap::new(),
+            forward: DirectionLimits::default(),
+            reverse: DirectionLimits::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RateQuote {
+    pub from: String,
+    pub to: String,
+    pub numerator: u128,
+    pub denominator: u128,
+    pub updated_at: DateTime<Utc>,
+
+    // Derived from the `to -> from` rate rather than set directly
+    pub inverse: bool,
+}
+
+// Rate a caller saw before converting, in the same units as `RateQuote`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema)]
+pub struct QuotedRate {
+    pub numerator: u128,
+    pub denominator: u128,
+}
+
+// How far the applied rate may fall short of the rate the caller was quoted
+#[derive(Clone, Copy, Debug)]
+pub struct SlippageLimit {
+    pub quoted: QuotedRate,
+    pub max_bps: u32,
+}
+
+impl SlippageLimit {
+    // Shortfall of `rate` against the quoted rate in basis points, rounded up; 0 when `rate` is as good or better
+    pub fn shortfall_bps(&self, rate: &RateQuote) -> Result<u128, ConvertError> {
+        let quoted = self.quoted.numerator.checked_mul(rate.denominator).ok_or(ConvertError::Overflow)?;
+        let applied = rate.numerator.checked_mul(self.quoted.denominator).ok_or(ConvertError::Overflow)?;
+        if applied >= quoted {
+            return Ok(0);
+        }
+        Rounding::Up.mul_div(quoted - applied, 10_000, quoted).ok_or(ConvertError::Overflow)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ConvertRequest {
+    pub asset: String,
+    pub to_asset: String,
+
+    // Smallest units of `asset`, as a string so JavaScript clients keep precision
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub amount: u128,
+
+    // Rate the caller expects, e.g. from GET /rates; required with `max_slippage_bps`
+    #[serde(default)]
+    pub quoted_rate: Option<QuotedRate>,
+
+    // Abort when the applied rate is worse than `quoted_rate` by more than this, 50 = 0.5%
+    #[serde(default)]
+    pub max_slippage_bps: Option<u32>,
+}
+
+impl ConvertRequest {
+    pub fn slippage_limit(&self) -> Option<SlippageLimit> {
+        Some(SlippageLimit { quoted: self.quoted_rate?, max_bps: self.max_slippage_bps? })
+    }
+}
+
+impl Validate for ConvertRequest {
+    fn validate(&self, rules: &ValidationConfig) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        let limits = rules.asset("asset", &self.asset, &mut errors);
+        rules.amount("amount", self.amount, limits, &mut errors);
+        rules.asset("to_asset", &self.to_asset, &mut errors);
+        if self.asset == self.to_asset {
+            errors.push(FieldError::new("to_asset", "must differ from asset"));
+        }
+        match (self.quoted_rate, self.max_slippage_bps) {
+            (Some(rate), _) if rate.numerator == 0 || rate.denominator == 0 => {
+                errors.push(FieldError::new("quoted_rate", "numerator and denominator must be positive"))
+            }
+            (None, Some(_)) => errors.push(FieldError::new("quoted_rate", "is required with max_slippage_bps")),
+            (Some(_), None) => errors.push(FieldError::new("max_slippage_bps", "is required with quoted_rate")),
+            (_, Some(bps)) if bps > 10_000 => errors.push(FieldError::new("max_slippage_bps", "must be at most 10000")),
+            _ => {}
+        }
+        errors
+    }
+}
+
+// Quote only; nothing is debited or credited
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct Conversion {
+    pub asset: String,
+    pub amount: u128,
+
+    // `amount` in whole tokens, when the source asset's decimals are configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_decimal: Option<String>,
+    pub to_asset: String,
+    #[serde(default)]
+    pub direction: Direction,
+
+    // Fees taken from `amount` before converting, itemized; absent when nothing was charged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<FeeCharge>,
+
+    // The amount after fees at the rate, rounded per `rounding`
+    pub converted_amount: u128,
+    pub rounding: Rounding,
+
+    // `converted_amount` in whole tokens, when the target asset's decimals are configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub converted_decimal: Option<String>,
+    pub rate: RateQuote,
+
+    // How much worse `rate` was than the caller's quoted rate, in basis points, when a slippage limit was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slippage_bps: Option<u128>,
+
+    // Taken off the amount after fees on top of them, when a pricing experiment priced the conversion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spread_bps: Option<u32>,
+
+    // Hex SHA-256 over the assets, amounts, rate and fee, to match a quote, its execution and its ledger entry
+    #[serde(default)]
+    pub hash: String,
+
+    // Set when the conversion was priced for a signed quote
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "quote_01HV3K8Z6V6Q4M1X9J6T5B2C7D")]
+    pub quote_id: Option<QuoteId>,
+}
+
+impl Conversion {
+    // Earned on the conversion in the source asset: fees plus the spread, rounded up like when it was taken
+    pub fn revenue(&self) -> u128 {
+        let (fee, net) = self.fees.as_ref().map_or((0, self.amount), |f| (f.total, f.net_amount));
+        fee + Rounding::Up.mul_div(net, self.spread_bps.unwrap_or(0) as u128, 10_000).unwrap_or(0)
+    }
+
+    fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [self.asset.as_str(), &self.to_asset, &self.rate.updated_at.to_rfc3339()] {
+            hasher.update((part.len() as u32).to_be_bytes());
+            hasher.update(part.as_bytes());
+        }
+        let fee = self.fees.as_ref().map_or(0, |f| f.total);
+        for value in [self.amount, self.converted_amount, self.rate.numerator, self.rate.denominator, fee] {
+            hasher.update(value.to_be_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+// One line for logs, e.g. `100.0000000 PI -> 31.415900 USDC at 314159/10000000, fee 30000 PI`
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let amount = self.amount_decimal.clone().unwrap_or_else(|| self.amount.to_string());
+        let converted = self.converted_decimal.clone().unwrap_or_else(|| self.converted_amount.to_string());
+        write!(f, "{} {} -> {} {} at {}/{}", amount, self.asset, converted, self.to_asset, self.rate.numerator, self.rate.denominator)?;
+        if let Some(fees) = &self.fees {
+            write!(f, ", fee {} {}", fees.total, fees.asset)?;
+        }
+        if let Some(quote_id) = &self.quote_id {
+            write!(f, ", quote {}", quote_id)?;
+        }
+        Ok(())
+    }
+}
+
+// A conversion the node accepted, under the id of its ledger entry
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct AcceptedConversion {
+    #[schema(value_type = String, example = "conv_01HV3K8Z6V6Q4M1X9J6T5B2C7D")]
+    pub id: ConversionId,
+    #[serde(flatten)]
+    pub conversion: Conversion,
+}
+
+#[derive(Debug)]
+pub enum ConvertError {
+    UnknownPair { from: String, to: String },
+    Overflow,
+    InvalidRate(String),
+    Asset(AssetError),
+    Fee(FeeError),
+
+    // The rate moved against the caller by more than they accepted
+    Slippage { shortfall_bps: u128, max_bps: u32 },
+
+    AboveLimit { direction: Direction, asset: String, max_amount: u128 },
+    InsufficientLiquidity { direction: Direction, asset: String, available: u128 },
+
+    // The caller holds less of the source asset than the conversion takes
+    InsufficientBalance { asset: String, available: u128 },
+
+    // The accepted conversion could not be recorded
+    Storage(String),
+
+    // The anomaly engine scored the conversion above its threshold
+    Rejected(Box<RejectionReport>),
+
+    // The anomaly engine could not score the conversion, so it is not accepted
+    Screening(String),
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConvertError::UnknownPair { from, to } => write!(f, "no rate from {} to {}", from, to),
+            ConvertError::Overflow => write!(f, "converted amount overflows"),
+            ConvertError::InvalidRate(e) => write!(f, "{}", e),
+            ConvertError::Asset(e) => write!(f, "{}", e),
+            ConvertError::Fee(e) => write!(f, "{}", e),
+            ConvertError::Slippage { shortfall_bps, max_bps } => {
+                write!(f, "rate is {} bps worse than quoted, above the {} bps accepted", shortfall_bps, max_bps)
+            }
+            ConvertError::AboveLimit { direction, asset, max_amount } => {
+                write!(f, "{} conversions from {} are limited to {} at once", direction.as_str(), asset, max_amount)
+            }
+            ConvertError::InsufficientLiquidity { direction, asset, available } => {
+                write!(f, "only {} {} is available for {} conversions", available, asset, direction.as_str())
+            }
+            ConvertError::InsufficientBalance { asset, available } => write!(f, "insufficient {} balance, {} available", asset, available),
+            ConvertError::Storage(e) => write!(f, "conversion not recorded: {}", e),
+            ConvertError::Rejected(report) => write!(f, "{}", report),
+            ConvertError::Screening(e) => write!(f, "conversion could not be screened: {}", e),
+        }
+    }
+}
+
+impl From<ConvertError> for ApiError {
+    fn from(error: ConvertError) -> Self {
+        match error {
+            ConvertError::UnknownPair { .. } => ApiError::NotFound(error.to_string()),
+            ConvertError::Slippage { .. } | ConvertError::InsufficientLiquidity { .. } | ConvertError::InsufficientBalance { .. } => {
+                ApiError::Conflict(error.to_string())
+            }
+            ConvertError::AboveLimit { .. }
+            | ConvertError::Overflow | ConvertError::InvalidRate(_) | ConvertError::Asset(_) | ConvertError::Fee(_) => {
+                ApiError::Unprocessable(error.to_string())
+            }
+            ConvertError::Rejected(report) => ApiError::Rejected(report),
+            ConvertError::Storage(_) | ConvertError::Screening(_) => ApiError::Unavailable(error.to_string()),
+        }
+    }
+}
+
+// Names of the values `conversion_features` scores, in order
+pub const CONVERSION_FEATURES: [&str; 2] = ["amount_magnitude", "liquidity_share"];
+
+// Each in [0, 1]: the amount's order of magnitude out of u128's 39 digits, and how much of the direction's remaining
+// liquidity the payout takes (0 without a liquidity limit)
+fn conversion_features(conversion: &Conversion, available: Option<u128>) -> Features {
+    let magnitude = ((conversion.amount as f64 + 1.0).log10() / 39.0).min(1.0);
+    let share = match available {
+        None => 0.0,
+        Some(0) => 1.0,
+        Some(available) => (conversion.converted_amount as f64 / available as f64).min(1.0),
+    };
+    Features { values: vec![magnitude as f32, share as f32] }
+}
+
+// Shared rate table behind the conversion endpoints; rates can be replaced at runtime, e.g. by a price feed
+#[derive(Clone)]
+pub struct StablecoinConverter {
+    inverse_pairs: bool,
+    rounding: Rounding,
+    decimals: Arc<BTreeMap<String, u8>>,
+
+    // Which assets may be converted and to what; every pair with a rate when empty
+    assets: AssetRegistry,
+    fees: FeeSchedule,
+
+    // Where the caller is debited and paid out, and the fee recipient credited
+    accounts: Option<EntityStore<LedgerAccount>>,
+
+    // Where accepted conversions are recorded
+    ledger: Option<ConversionLedger>,
+
+    // Scores every conversion before it is accepted; rejections are explained in the decision store
+    engine: Option<(AIEngine, DecisionStore)>,
+
+    // Where screened conversions and their outcomes are kept for backtesting
+    archive: Option<RequestArchive>,
+    rates: Arc<RwLock<BTreeMap<(String, String), RateQuote>>>,
+
+    max_amounts: Arc<BTreeMap<(Direction, String), u128>>,
+    liquidity: Arc<RwLock<BTreeMap<(Direction, String), u128>>>,
+
+    // Cached GET /rates, dropped whenever a rate changes
+    cache: Option<ResponseCache>,
+}
+
+impl StablecoinConverter {
+    pub fn new(config: &ConverterConfig) -> Result<Self, String> {
+        if let Some((asset, decimals)) = config.decimals.iter().find(|(_, d)| 10u128.checked_pow(**d as u32).is_none()) {
+            return Err(format!("converter.decimals {}: {} is out of range", asset, decimals));
+        }
+        let per_direction = |select: fn(&DirectionLimits) -> &BTreeMap<String, u128>| -> BTreeMap<(Direction, String), u128> {
+            [(Direction::Forward, &config.forward), (Direction::Reverse, &config.reverse)]
+                .into_iter()
+                .flat_map(|(direction, limits)| select(limits).iter().map(move |(asset, units)| ((direction, asset.clone()), *units)))
+                .collect()
+        };
+        let converter = StablecoinConverter {
+            inverse_pairs: config.inverse_pairs,
+            rounding: config.rounding,
+            decimals: Arc::new(config.decimals.clone()),
+            assets: AssetRegistry::default(),
+            fees: FeeSchedule::default(),
+            accounts: None,
+            ledger: None,
+            engine: None,
+            archive: None,
+            rates: Arc::default(),
+            max_amounts: Arc::new(per_direction(|l| &l.max_amount)),
+            liquidity: Arc::new(RwLock::new(per_direction(|l| &l.liquidity))),
+            cache: None,
+        };
+        for rate in &config.rates {
+            converter
+                .set_rate(&rate.from, &rate.to, rate.numerator, rate.denominator)
+                .map_err(|e| format!("converter.rates {} -> {}: {}", rate.from, rate.to, e))?;
+        }
+        Ok(converter)
+    }
+
+    // Serve GET /rates through `cache` under the route name "rates"
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // Only convert between enabled assets of `assets`, into its stablecoins; its decimals take precedence
+    pub fn with_assets(mut self, assets: AssetRegistry) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    // Charge conversion fees per `fees`, taken from the input amount and credited to the fee recipient
+    pub fn with_fees(mut self, fees: FeeSchedule) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    // Post accepted conversions to the ledger accounts in `accounts`; nothing can be accepted without them
+    pub fn with_accounts(mut self, accounts: EntityStore<LedgerAccount>) -> Self {
+        self.accounts = Some(accounts);
+        self
+    }
+
+    // Record every accepted conversion in `ledger`; nothing can be accepted without one
+    pub fn with_ledger(mut self, ledger: ConversionLedger) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    pub fn with_engine(mut self, engine: AIEngine, decisions: DecisionStore) -> Self {
+        self.engine = Some((engine, decisions));
+        self
+    }
+
+    // Keep every screened conversion in `archive` so candidate models can be replayed against it
+    pub fn with_archive(mut self, archive: RequestArchive) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    pub fn set_rate(&self, from: &str, to: &str, numerator: u128, denominator: u128) -> Result<RateQuote, ConvertError> {
+        if numerator == 0 || denominator == 0 {
+            return Err(ConvertError::InvalidRate("numerator and denominator must be positive".to_string()));
+        }
+        if from == to {
+            return Err(ConvertError::InvalidRate("a rate needs two different assets".to_string()));
+        }
+        let quote =
+            RateQuote { from: from.to_string(), to: to.to_string(), numerator, denominator, updated_at: Utc::now(), inverse: false };
+        let mut rates = self.rates.write().unwrap();
+        rates.insert((from.to_string(), to.to_string()), quote.clone());
+        if self.inverse_pairs {
+            let key = (to.to_string(), from.to_string());
+            if rates.get(&key).is_none_or(|existing| existing.inverse) {
+                let inverse = RateQuote {
+                    from: to.to_string(),
+                    to: from.to_string(),
+                    numerator: denominator,
+                    denominator: numerator,
+                    updated_at: quote.updated_at,
+                    inverse: true,
+                };
+                rates.insert(key, inverse);
+            }
+        }
+        drop(rates);
+        if let Some(cache) = &self.cache {
+            cache.invalidate("rates");
+        }
+        info!(from, to, numerator, denominator, "conversion rate set");
+        Ok(quote)
+    }
+
+    // Replace what the node can pay out in `asset` for conversions in `direction`, e.g. from a treasury balance feed
+    pub fn set_liquidity(&self, direction: Direction, asset: &str, units: u128) {
+        self.liquidity.write().unwrap().insert((direction, asset.to_string()), units);
+        info!(direction = direction.as_str(), asset, units, "conversion liquidity set");
+    }
+
+    // Reverse when the target is not a stablecoin; without a registry entry, when the rate is derived
+    pub fn direction(&self, to_asset: &str, rate: &RateQuote) -> Direction {
+        match self.assets.get(to_asset) {
+            Some(target) if !target.stablecoin => Direction::Reverse,
+            Some(_) => Direction::Forward,
+            None if rate.inverse => Direction::Reverse,
+            None => Direction::Forward,
+        }
+    }
+
+    pub fn rates(&self) -> Vec<RateQuote> {
+        self.rates.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn rate(&self, from: &str, to: &str) -> Result<RateQuote, ConvertError> {
+        self.rates
+            .read()
+            .unwrap()
+            .get(&(from.to_string(), to.to_string()))
+            .cloned()
+            .ok_or_else(|| ConvertError::UnknownPair { from: from.to_string(), to: to.to_string() })
+    }
+
+    // With `slippage`, nothing is converted if the current rate fell short of the quoted one by more than allowed
+    pub fn convert(&self, asset: &str, amount: u128, to_asset: &str, slippage: Option<&SlippageLimit>) -> Result<Conversion, ConvertError> {
+        self.convert_priced(asset, amount, to_asset, slippage, None)
+    }
+
+    // Like `convert`, at a pricing experiment variant's fee and spread instead of the fee schedule's percentage
+    pub fn convert_priced(
+        &self,
+        asset: &str,
+        amount: u128,
+        to_asset: &str,
+        slippage: Option<&SlippageLimit>,
+        variant: Option<&Variant>,
+    ) -> Result<Conversion, ConvertError> {
+        self.assets.check_pair(asset, to_asset).map_err(ConvertError::Asset)?;
+        let rate = self.rate(asset, to_asset)?;
+        let direction = self.direction(to_asset, &rate);
+        if let Some(max_amount) = self.max_amounts.get(&(direction, asset.to_string())).copied().filter(|max| amount > *max) {
+            warn!(direction = direction.as_str(), asset, to_asset, amount, max_amount, "conversion refused above limit");
+            return Err(ConvertError::AboveLimit { direction, asset: asset.to_string(), max_amount });
+        }
+        let slippage_bps = slippage.map(|limit| limit.shortfall_bps(&rate)).transpose()?;
+        if let (Some(shortfall_bps), Some(limit)) = (slippage_bps, slippage) {
+            if shortfall_bps > limit.max_bps as u128 {
+                warn!(direction = direction.as_str(), asset, to_asset, shortfall_bps, max_bps = limit.max_bps, "conversion aborted on slippage");
+                return Err(ConvertError::Slippage { shortfall_bps, max_bps: limit.max_bps });
+            }
+        }
+        let fees = match variant {
+            Some(variant) => self.fees.charge_at(FeeOperation::Conversion, asset, amount, variant.fee_bps),
+            None => self.fees.charge(FeeOperation::Conversion, asset, amount),
+        }
+        .map_err(ConvertError::Fee)?;
+        let spread_bps = variant.map(|variant| variant.spread_bps.min(10_000));
+        let spread = Rounding::Up.mul_div(fees.net_amount, spread_bps.unwrap_or(0) as u128, 10_000).ok_or(ConvertError::Overflow)?;
+        let converted_amount =
+            self.rounding.mul_div(fees.net_amount - spread, rate.numerator, rate.denominator).ok_or(ConvertError::Overflow)?;
+        let available = self.liquidity.read().unwrap().get(&(direction, to_asset.to_string())).copied();
+        if let Some(available) = available.filter(|available| converted_amount > *available) {
+            warn!(direction = direction.as_str(), asset, to_asset, converted_amount, available, "conversion refused on liquidity");
+            return Err(ConvertError::InsufficientLiquidity { direction, asset: to_asset.to_string(), available });
+        }
+        debug!(direction = direction.as_str(), asset, to_asset, amount, converted_amount, inverse = rate.inverse, "conversion priced");
+        let mut conversion = Conversion {
+            asset: asset.to_string(),
+            amount,
+            amount_decimal: self.decimal(asset, amount),
+            to_asset: to_asset.to_string(),
+            direction,
+            fees: (fees.total > 0).then_some(fees),
+            converted_amount,
+            rounding: self.rounding,
+            converted_decimal: self.decimal(to_asset, converted_amount),
+            rate,
+            slippage_bps,
+            spread_bps,
+            hash: String::new(),
+            quote_id: None,
+        };
+        conversion.hash = conversion.digest();
+        Ok(conversion)
+    }
+
+    // Accept `conversion` for `subject` under `id`: its ledger entry is committed together with whatever `batch` holds,
+    // and the payout is taken from the direction's liquidity under the same lock, so two accepts cannot spend it twice
+    pub fn accept(&self, id: ConversionId, subject: &str, conversion: &Conversion, mut batch: WriteBatch) -> Result<ConversionEntry, ConvertError> {
+        let ledger = self.ledger.as_ref().ok_or_else(|| ConvertError::Storage("no conversion ledger is attached".to_string()))?;
+        let (direction, to_asset) = (conversion.direction, conversion.to_asset.clone());
+        self.screen(id, subject, conversion)?;
+        let mut liquidity = self.liquidity.write().unwrap();
+        let available = liquidity.get(&(direction, to_asset.clone())).copied();
+        if let Some(available) = available.filter(|available| conversion.converted_amount > *available) {
+            warn!(conversion = %id, direction = direction.as_str(), to_asset, available, "conversion refused on liquidity at acceptance");
+            return Err(ConvertError::InsufficientLiquidity { direction, asset: to_asset, available });
+        }
+        // The caller pays the whole amount and is paid out, and the fee recipient credited, in the same commit as the entry
+        let accounts = self.accounts.as_ref().ok_or_else(|| ConvertError::Storage("no ledger accounts are attached".to_string()))?;
+        let _accounts = accounts.lock();
+        let mut credits = vec![(subject, to_asset.as_str(), conversion.converted_amount)];
+        if let Some(fee) = &conversion.fees {
+            credits.push((&fee.recipient, &fee.asset, fee.total));
+        }
+        stage_postings(accounts, &mut batch, &[(subject, &conversion.asset, conversion.amount)], &credits).map_err(|e| match e {
+            PostingError::InsufficientBalance { asset, available, .. } => ConvertError::InsufficientBalance { asset, available },
+            e => ConvertError::Storage(e.to_string()),
+        })?;
+        let entry = ConversionEntry::new(id, subject, conversion, Utc::now());
+        ledger.record(batch, &entry).map_err(ConvertError::Storage)?;
+        if let Some(available) = available {
+            liquidity.insert((direction, to_asset), available - conversion.converted_amount);
+        }
+        drop(liquidity);
+        info!(conversion = %id, subject, details = %conversion, "conversion accepted");
+        Ok(entry)
+    }
+
+    // Decided under the conversion id, so operators can label the decision through /v1/feedback
+    // and the caller can look a rejection up under GET /v1/decisions/{id}
+    fn screen(&self, id: ConversionId, subject: &str, conversion: &Conversion) -> Result<(), ConvertError> {
+        let Some((engine, decisions)) = &self.engine else {
+            return Ok(());
+        };
+        let available = self.liquidity.read().unwrap().get(&(conversion.direction, conversion.to_asset.clone())).copied();
+        let features = conversion_features(conversion, available);
+        let decision = engine.evaluate_request(&id.to_string(), Source::Converter, &features).map_err(ConvertError::Screening)?;
+        if let Some(archive) = &self.archive {
+            archive.record(&HistoricalRequest {
+                request_id: id.to_string(),
+                source: Source::Converter.label().to_string(),
+                features: features.values.clone(),
+                amount: conversion.amount,
+                rejected: decision.rejected,
+                fee: conversion.fees.as_ref().map_or(0, |fees| fees.total),
+            });
+        }
+        if decision.rejected {
+            warn!(conversion = %id, subject, score = decision.score, "conversion rejected by the anomaly engine");
+            let report = build_report(engine, &id.to_string(), &CONVERSION_FEATURES, &features, &decision);
+            decisions.insert(subject, report.clone());
+            return Err(ConvertError::Rejected(Box::new(report)));
+        }
+        Ok(())
+    }
+
+    // `units` of `asset` in whole tokens; the asset registry's decimals take precedence over `converter.decimals`
+    fn decimal(&self, asset: &str, units: u128) -> Option<String> {
+        self.assets.decimals(asset).or_else(|| self.decimals.get(asset).copied()).map(|d| format_units(units, d))
+    }
+
+    // POST /convert, GET /rates and GET /rates/{from}/{to}
+    pub fn routes(&self, rules: Arc<ValidationConfig>, auth: &Auth) -> BoxedFilter<(Response,)> {
+        let converter = self.clone();
+        let convert = warp::path!("convert").and(warp::post()).and(auth.authorized()).and(validated_json(rules)).and_then(
+            move |principal: Principal, request: ConvertRequest| {
+                let converter = converter.clone();
+                async move {
+                    let conversion = converter
+                        .convert(&request.asset, request.amount, &request.to_asset, request.slippage_limit().as_ref())
+                        .and_then(|conversion| {
+                            let id = ConversionId::new();
+                            converter.accept(id, &principal.subject, &conversion, WriteBatch::default())?;
+                            Ok(AcceptedConversion { id, conversion })
+                        })
+                        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+                    Ok::<_, Rejection>(warp::reply::with_status(warp::reply::json(&conversion), StatusCode::OK))
+                }
+            },
+        );
+
+        let converter = self.clone();
+        let rates = warp::path!("rates").and(warp::get()).map(move || warp::reply::json(&converter.rates()));
+        let rates = match &self.cache {
+            Some(cache) => cache.cached("rates", rates),
+            None => rates.map(Reply::into_response).boxed(),
+        };
+
+        let converter = self.clone();
+        let pair = warp::path!("rates" / String / String).and(warp::get()).and_then(move |from: String, to: String| {
+            let result = converter.assets.check_pair(&from, &to).map_err(ConvertError::Asset).and_then(|()| converter.rate(&from, &to));
+            async move { result.map(|quote| warp::reply::json(&quote)).map_err(|e| warp::reject::custom(ApiError::from(e))) }
+        });
+
+        convert.map(Reply::into_response).or(rates).unify().or(pair.map(Reply::into_response)).unify().boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::backtest::{run, Candidate};
+    use crate::anomaly_model::LinfaModel;
+    use crate::api::redemption::stage_credits;
+    use crate::fees::{FeeRule, FeeScheduleConfig, FeeTier};
+    use crate::storage::mvcc::Store;
+
+    // PI -> USDC at 1:10 with a 1% conversion fee to "treasury"; alice holds 1000 PI
+    fn converter() -> (StablecoinConverter, EntityStore<LedgerAccount>, ConversionLedger) {
+        let store = Store::new();
+        let accounts = EntityStore::new(store.clone(), "accounts");
+        let mut batch = WriteBatch::default();
+        stage_credits(&accounts, &mut batch, &[("alice", "PI", 1_000)]).unwrap();
+        accounts.commit(batch).unwrap();
+        let ledger = ConversionLedger::new(store);
+        let fees = FeeSchedule::new(FeeScheduleConfig {
+            recipient: "treasury".to_string(),
+            conversion: FeeRule { tiers: vec![FeeTier { from: 0, bps: 100 }], ..FeeRule::default() },
+            ..FeeScheduleConfig::default()
+        })
+        .unwrap();
+        let config = ConverterConfig {
+            rates: vec![RateConfig { from: "PI".to_string(), to: "USDC".to_string(), numerator: 1, denominator: 10 }],
+            ..ConverterConfig::default()
+        };
+        let converter = StablecoinConverter::new(&config).unwrap().with_fees(fees).with_accounts(accounts.clone()).with_ledger(ledger.clone());
+        (converter, accounts, ledger)
+    }
+
+    fn balance(accounts: &EntityStore<LedgerAccount>, account: &str, asset: &str) -> u128 {
+        accounts.get(account).map(|entity| entity.value.balances.get(asset).copied().unwrap_or(0)).unwrap_or(0)
+    }
+
+    #[test]
+    fn accept_posts_the_debit_payout_and_fee_together() {
+        let (converter, accounts, ledger) = converter();
+        let conversion = converter.convert("PI", 600, "USDC", None).unwrap();
+        assert_eq!(conversion.converted_amount, 59);
+
+        let entry = converter.accept(ConversionId::new(), "alice", &conversion, WriteBatch::default()).unwrap();
+        assert!(ledger.get(&entry.id).is_some());
+        assert_eq!(balance(&accounts, "alice", "PI"), 400);
+        assert_eq!(balance(&accounts, "alice", "USDC"), 59);
+        assert_eq!(balance(&accounts, "treasury", "PI"), 6);
+    }
+
+    #[test]
+    fn screened_conversions_are_archived_for_backtesting() {
+        let (converter, _, _) = converter();
+        let archive = RequestArchive::new(Store::new());
+        let engine = AIEngine::new(Box::new(LinfaModel::new(10)), 0.8);
+        let converter = converter.with_engine(engine, DecisionStore::default()).with_archive(archive.clone());
+        let conversion = converter.convert("PI", 600, "USDC", None).unwrap();
+        let id = ConversionId::new();
+        converter.accept(id, "alice", &conversion, WriteBatch::default()).unwrap();
+
+        let history = archive.snapshot();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].request_id, id.to_string());
+        assert_eq!(history[0].source, "converter");
+        assert_eq!((history[0].amount, history[0].fee, history[0].rejected), (600, 6, false));
+
+        // A candidate that rejects everything flips the accepted conversion
+        let candidate = Candidate { model: Box::new(LinfaModel::new(10)), threshold: -1.0, fee_bps: 50 };
+        let report = run(&history, &candidate).unwrap();
+        assert_eq!((report.replayed, report.newly_rejected), (1, 1));
+        assert_eq!(report.baseline.fee_revenue, 6);
+    }
+
+    #[test]
+    fn accept_refuses_more_than_the_balance_and_posts_nothing() {
+        let (converter, accounts, ledger) = converter();
+        converter.set_liquidity(Direction::Forward, "USDC", 1_000);
+        let conversion = converter.convert("PI", 1_001, "USDC", None).unwrap();
+        let id = ConversionId::new();
+
+        let refused = converter.accept(id, "alice", &conversion, WriteBatch::default());
+        assert!(matches!(refused, Err(ConvertError::InsufficientBalance { ref asset, available: 1_000 }) if asset == "PI"));
+        assert!(ledger.get(&id).is_none());
+        assert_eq!(balance(&accounts, "alice", "PI"), 1_000);
+        assert!(accounts.get("treasury").is_err());
+        assert_eq!(converter.liquidity.read().unwrap()[&(Direction::Forward, "USDC".to_string())], 1_000);
+
+        assert!(matches!(converter.accept(ConversionId::new(), "bob", &conversion, WriteBatch::default()), Err(ConvertError::InsufficientBalance { .. })));
+    }
+
+    #[test]
+    fn request_amounts_are_decimal_strings() {
+        let request: ConvertRequest = serde_json::from_value(serde_json::json!({ "asset": "PI", "to_asset": "USDC", "amount": "340282366920938463463374607431768211455" })).unwrap();
+        assert_eq!(request.amount, u128::MAX);
+        assert!(serde_json::from_value::<ConvertRequest>(serde_json::json!({ "asset": "PI", "to_asset": "USDC", "amount": 600 })).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::config::NodeConfig;
+use crate::health::{CheckResult, HealthCheck, HealthReport, Status};
+use crate::runtime::clock::query_offset;
+use crate::storage::mvcc::Store;
+use crate::storage::sync::read_state;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+// Config keys whose values never leave the machine
+const SECRET_MARKERS: &[&str] = &["key", "secret", "token", "password", "honeytoken"];
+
+// Lines kept from the end of each log file
+const LOG_TAIL_LINES: usize = 2000;
+
+#[derive(Serialize)]
+pub struct Versions {
+    pub node: String,
+    pub os: String,
+    pub arch: String,
+}
+
+// Everything needed to file an issue, with secrets replaced by "<redacted>"
+#[derive(Serialize)]
+pub struct SupportBundle {
+    pub created_at: DateTime<Utc>,
+    pub versions: Versions,
+    pub report: HealthReport,
+    pub config: Option<serde_yaml::Value>,
+    pub logs: Vec<(String, Vec<String>)>,
+}
+
+fn result(name: &str, outcome: Result<(), String>) -> CheckResult {
+    CheckResult { name: name.to_string(), status: if outcome.is_ok() { Status::Ok } else { Status::Failing }, detail: outcome.err() }
+}
+
+fn writable(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let probe = dir.join(".doctor-probe");
+    std::fs::write(&probe, b"ok").map_err(|e| format!("{} is not writable: {}", dir.display(), e))?;
+    let _ = std::fs::remove_file(probe);
+    Ok(())
+}
+
+fn check_storage(config: &NodeConfig) -> Result<(), String> {
+    let dirs = [config.bootstrap.state_path.parent(), config.p2p.address_book_path.parent(), Some(config.metrics_history.dir.as_path())];
+    for dir in dirs.into_iter().flatten().filter(|d| !d.as_os_str().is_empty()) {
+        writable(dir)?;
+    }
+    read_state(&config.bootstrap.state_path, &Store::new()).map(|_| ()).map_err(|e| format!("state file unreadable: {}", e))
+}
+
+fn check_keys(config: &NodeConfig) -> Result<(), String> {
+    if let Some(tls) = &config.tls {
+        tls.validate()?;
+    }
+    for key in &config.bootstrap.trusted_keys {
+        let bytes = hex::decode(key).map_err(|_| format!("bootstrap trusted key {} is not hex", key))?;
+        if bytes.len() != 32 {
+            return Err(format!("bootstrap trusted key {} is not 32 bytes", key));
+        }
+    }
+    if config.auth.api_keys.is_empty() && config.auth.jwt.is_none() {
+        return Err("no API keys or JWT issuer configured; every authenticated route will reject".to_string());
+    }
+    Ok(())
+}
+
+async fn check_reachable(url: &str) -> Result<(), String> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(5)).build().map_err(|e| e.to_string())?;
+    let response = client.get(url).send().await.map_err(|e| format!("{}: {}", url, e))?;
+    if response.status().is_server_error() {
+        return Err(format!("{}: {}", url, response.status()));
+    }
+    Ok(())
+}
+
+// At least one configured peer must accept a TCP connection
+async fn check_peers(config: &NodeConfig) -> Result<(), String> {
+    if config.p2p.static_peers.is_empty() && config.p2p.dns_seeds.is_empty() {
+        return Err("no static_peers or dns_seeds configured".to_string());
+    }
+    let seeds = config.p2p.dns_seeds.iter().map(|s| format!("{}:{}", s, config.p2p.seed_port));
+    let mut errors = Vec::new();
+    for addr in config.p2p.static_peers.iter().cloned().chain(seeds) {
+        match tokio::time::timeout(Duration::from_secs(5), TcpStream::connect(&addr)).await {
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(e)) => errors.push(format!("{}: {}", addr, e)),
+            Err(_) => errors.push(format!("{}: timed out", addr)),
+        }
+    }
+    Err(format!("no peer reachable ({})", errors.join("; ")))
+}
+
+async fn check_clock(config: &NodeConfig) -> Result<(), String> {
+    let mut last_error = "no ntp_servers configured".to_string();
+    for server in &config.clock.ntp_servers {
+        match query_offset(server).await {
+            Ok(offset) if offset.abs() > config.clock.alert_offset_ms => {
+                return Err(format!("clock is off by {} ms per {} (limit {} ms)", offset, server, config.clock.alert_offset_ms))
+            }
+            Ok(_) => return Ok(()),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+// One of the checks below run against the loaded config on each /readyz or /healthz/dependencies call
+struct ConfigCheck {
+    name: &'static str,
+    config: NodeConfig,
+    run: fn(&NodeConfig) -> Result<(), String>,
+}
+
+#[async_trait]
+impl HealthCheck for ConfigCheck {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        (self.run)(&self.config)
+    }
+}
+
+struct OracleCheck {
+    url: String,
+}
+
+#[async_trait]
+impl HealthCheck for OracleCheck {
+    fn name(&self) -> &str {
+        "oracle"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        check_reachable(&self.url).await
+    }
+}
+
+// Storage, keystore and oracle checks for a running node, passed to `Health::new` as readiness checks
+pub fn dependency_checks(config: &NodeConfig, oracle_url: Option<&str>) -> Vec<Box<dyn HealthCheck>> {
+    let mut checks: Vec<Box<dyn HealthCheck>> = vec![
+        Box::new(ConfigCheck { name: "storage", config: config.clone(), run: check_storage }),
+        Box::new(ConfigCheck { name: "keystore", config: config.clone(), run: check_keys }),
+    ];
+    if let Some(url) = oracle_url {
+        checks.push(Box::new(OracleCheck { url: url.to_string() }));
+    }
+    checks
+}
+
+// Run every check; later checks still run when the config is broken, they just report why they were skipped
+pub async fn diagnose(config_path: &Path, oracle_url: Option<&str>) -> HealthReport {
+    let config = NodeConfig::load(config_path);
+    let mut checks = vec![result("config", config.as_ref().map(|_| ()).map_err(Clone::clone))];
+    let Ok(config) = config else {
+        for name in ["storage", "keys", "oracle", "peers", "clock"] {
+            checks.push(result(name, Err("skipped, config did not load".to_string())));
+        }
+        return HealthReport::from_checks(checks);
+    };
+    checks.push(result("storage", check_storage(&config)));
+    checks.push(result("keys", check_keys(&config)));
+    checks.push(match oracle_url {
+        Some(url) => result("oracle", check_reachable(url).await),
+        None => result("oracle", Err("skipped, pass --oracle-url to check".to_string())),
+    });
+    checks.push(result("peers", check_peers(&config).await));
+    checks.push(result("clock", check_clock(&config).await));
+    HealthReport::from_checks(checks)
+}
+
+fn redact(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for (key, value) in map.iter_mut() {
+                let secret = key.as_str().is_some_and(|k| SECRET_MARKERS.iter().any(|m| k.to_lowercase().contains(m)));
+                if secret && !value.is_null() {
+                    *value = serde_yaml::Value::String("<redacted>".to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        serde_yaml::Value::Sequence(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn log_tails(dir: &Path) -> Vec<(String, Vec<String>)> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut logs: Vec<(String, Vec<String>)> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "log"))
+        .filter_map(|e| {
+            let text = std::fs::read_to_string(e.path()).ok()?;
+            let lines: Vec<String> = text.lines().map(str::to_string).collect();
+            let tail = lines[lines.len().saturating_sub(LOG_TAIL_LINES)..].to_vec();
+            Some((e.file_name().to_string_lossy().to_string(), tail))
+        })
+        .collect();
+    logs.sort_by(|a, b| a.0.cmp(&b.0));
+    logs
+}
+
+pub fn support_bundle(config_path: &Path, report: HealthReport, log_dir: Option<&Path>) -> SupportBundle {
+    let config = std::fs::read_to_string(config_path).ok().and_then(|text| serde_yaml::from_str::<serde_yaml::Value>(&text).ok()).map(
+        |mut value| {
+            redact(&mut value);
+            value
+        },
+    );
+    SupportBundle {
+        created_at: Utc::now(),
+        versions: Versions {
+            node: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+        },
+        report,
+        config,
+        logs: log_dir.map(log_tails).unwrap_or_default(),
+    }
+}
+
+// zstd-compressed JSON, like audit bundles
+pub fn write_bundle(bundle: &SupportBundle, out: &Path) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(bundle).map_err(|e| e.to_string())?;
+    let compressed = zstd::encode_all(json.as_slice(), 3).map_err(|e| e.to_string())?;
+    std::fs::write(out, compressed).map_err(|e| format!("failed to write {}: {}", out.display(), e))
+}
@@ -0,0 +1,178 @@
+use crate::events::schemas::SchemaRegistry;
+use crate::fees::FeeCharge;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+// Typed notifications shared between modules and external sinks
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    ThreatDetected { source: String, severity: f32, detail: String },
+    IssuanceCompleted {
+        tx_id: String,
+        asset: String,
+        amount: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fee: Option<FeeCharge>,
+
+        // Tenant of the caller, when it belongs to one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tenant: Option<String>,
+    },
+    RedemptionCompleted {
+        tx_id: String,
+        asset: String,
+        amount: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tenant: Option<String>,
+    },
+    ConversionExecuted {
+        tx_id: String,
+        from: String,
+        to: String,
+        amount_in: String,
+        amount_out: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fee: Option<FeeCharge>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tenant: Option<String>,
+    },
+    SelfHealTriggered { source: String, rule: String },
+
+    // One step in the life of a transaction, conversion or quote, shown on its timeline
+    TransactionStep {
+        tx_id: String,
+        step: Step,
+
+        // e.g. queued, approved, settled, failed
+        outcome: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+
+        // Another id the transaction continues under, e.g. the conversion an executed quote was queued as
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        related: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    StateChange,
+    Decision,
+    Signature,
+    Settlement,
+    WebhookDelivery,
+}
+
+impl Event {
+    // Topic name used for filtering and schema lookup
+    pub fn topic(&self) -> &'static str {
+        match self {
+            Event::ThreatDetected { .. } => "threat_detected",
+            Event::IssuanceCompleted { .. } => "issuance_completed",
+            Event::RedemptionCompleted { .. } => "redemption_completed",
+            Event::ConversionExecuted { .. } => "conversion_executed",
+            Event::SelfHealTriggered { .. } => "self_heal_triggered",
+            Event::TransactionStep { .. } => "transaction_step",
+        }
+    }
+
+    // Transaction the event belongs to, if any
+    pub fn tx_id(&self) -> Option<&str> {
+        match self {
+            Event::IssuanceCompleted { tx_id, .. }
+            | Event::RedemptionCompleted { tx_id, .. }
+            | Event::ConversionExecuted { tx_id, .. }
+            | Event::TransactionStep { tx_id, .. } => Some(tx_id),
+            Event::ThreatDetected { .. } | Event::SelfHealTriggered { .. } => None,
+        }
+    }
+
+    // Timeline step without detail or related id
+    pub fn step(tx_id: impl Into<String>, step: Step, outcome: &str) -> Self {
+        Event::TransactionStep { tx_id: tx_id.into(), step, outcome: outcome.to_string(), detail: None, related: None }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Envelope {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+// Events buffered per subscriber before slow ones start lagging
+const CAPACITY: usize = 1024;
+
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Envelope>,
+
+    // Published events are checked against these; mismatches are logged, never dropped
+    schemas: Option<Arc<SchemaRegistry>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        EventBus { sender, schemas: None }
+    }
+
+    pub fn with_schemas(mut self, schemas: SchemaRegistry) -> Self {
+        self.schemas = Some(Arc::new(schemas));
+        self
+    }
+
+    // Publishing never blocks; having no subscribers is fine
+    pub fn publish(&self, event: Event) {
+        let envelope = Envelope { at: Utc::now(), event };
+        if let Some(schemas) = &self.schemas {
+            let errors = schemas.validate(&envelope);
+            if !errors.is_empty() {
+                warn!(topic = envelope.event.topic(), ?errors, "published event does not match its schema");
+            }
+        }
+        let _ = self.sender.send(envelope);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Envelope> {
+        self.sender.subscribe()
+    }
+
+    // Forward every event to an external sink until cancelled
+    pub async fn forward(&self, sink: Box<dyn EventSink>, token: CancellationToken) {
+        let mut receiver = self.subscribe();
+        loop {
+            let envelope = tokio::select! {
+                _ = token.cancelled() => return,
+                received = receiver.recv() => received,
+            };
+            match envelope {
+                Ok(envelope) => sink.deliver(&envelope).await,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!(sink = sink.name(), missed, "event sink lagged, events dropped")
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// External consumer of bus events (webhooks, Kafka, ...)
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    fn name(&self) -> &str;
+    async fn deliver(&self, envelope: &Envelope);
+}
@@ -0,0 +1,146 @@
+use crate::events::bus::{Envelope, EventSink};
+use crate::ids::EventId;
+use crate::runtime::scheduler::Scheduler;
+use crate::storage::mvcc::{Store, WriteBatch};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+const PREFIX: &str = "events/";
+
+// `event_log` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct EventLogConfig {
+    // Events older than this are dropped and can no longer be replayed
+    pub retention_days: u64,
+
+    // Largest replay accepted in one request
+    pub max_replay_events: usize,
+
+    // Event schemas published by the last run; the node refuses to start with changes old consumers cannot read
+    pub schemas_path: PathBuf,
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        EventLogConfig { retention_days: 30, max_replay_events: 10_000, schemas_path: PathBuf::from("data/event_schemas.json") }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    pub id: EventId,
+    #[serde(flatten)]
+    pub envelope: Envelope,
+}
+
+// Every bus event, kept for `retention_days` under ids that sort in publish order
+#[derive(Clone)]
+pub struct EventLog {
+    store: Store,
+    config: Arc<EventLogConfig>,
+}
+
+impl EventLog {
+    pub fn new(store: Store, config: EventLogConfig) -> Self {
+        EventLog { store, config: Arc::new(config) }
+    }
+
+    pub fn config(&self) -> &EventLogConfig {
+        &self.config
+    }
+
+    pub fn record(&self, envelope: &Envelope) {
+        let event = LoggedEvent { id: EventId::new(), envelope: envelope.clone() };
+        if let Ok(bytes) = serde_json::to_vec(&event) {
+            let mut batch = WriteBatch::default();
+            batch.put(format!("{}{}", PREFIX, event.id), bytes);
+            self.store.commit(batch);
+        }
+    }
+
+    // Events published in `[from, to)` on any of `topics` (all when empty), oldest first
+    pub fn range(&self, from: DateTime<Utc>, to: DateTime<Utc>, topics: &[String]) -> Vec<LoggedEvent> {
+        self.store
+            .read_txn()
+            .scan_prefix(PREFIX)
+            .into_iter()
+            .filter_map(|(_, bytes)| serde_json::from_slice::<LoggedEvent>(&bytes).ok())
+            .filter(|e| e.envelope.at >= from && e.envelope.at < to)
+            .filter(|e| topics.is_empty() || topics.iter().any(|t| t == e.envelope.event.topic()))
+            .collect()
+    }
+
+    // Events with a field equal to `id` (a transaction, conversion or quote id), oldest first
+    pub fn mentioning(&self, id: &str) -> Vec<LoggedEvent> {
+        fn mentions(value: &serde_json::Value, id: &str) -> bool {
+            match value {
+                serde_json::Value::String(s) => s == id,
+                serde_json::Value::Array(items) => items.iter().any(|v| mentions(v, id)),
+                serde_json::Value::Object(fields) => fields.values().any(|v| mentions(v, id)),
+                _ => false,
+            }
+        }
+        self.store
+            .read_txn()
+            .scan_prefix(PREFIX)
+            .into_iter()
+            .filter_map(|(_, bytes)| serde_json::from_slice::<LoggedEvent>(&bytes).ok())
+            .filter(|e| serde_json::to_value(&e.envelope.event).is_ok_and(|v| mentions(&v, id)))
+            .collect()
+    }
+
+    // Drop events past retention; returns how many
+    pub fn expire(&self) -> usize {
+        let cutoff = Utc::now() - ChronoDuration::days(self.config.retention_days as i64);
+        let mut batch = WriteBatch::default();
+        let mut expired = 0;
+        for (key, bytes) in self.store.read_txn().scan_prefix(PREFIX) {
+            match serde_json::from_slice::<LoggedEvent>(&bytes) {
+                Ok(event) if event.envelope.at >= cutoff => break,
+                _ => {
+                    batch.delete(key);
+                    expired += 1;
+                }
+            }
+        }
+        if expired > 0 {
+            self.store.commit(batch);
+        }
+        expired
+    }
+}
+
+#[async_trait]
+impl EventSink for EventLog {
+    fn name(&self) -> &str {
+        "event_log"
+    }
+
+    async fn deliver(&self, envelope: &Envelope) {
+        self.record(envelope);
+    }
+}
+
+// Expire old events hourly
+pub fn register_expiry(scheduler: &Scheduler, log: EventLog) {
+    scheduler.register(
+        "event_log:expire",
+        Duration::from_secs(3600),
+        Duration::ZERO,
+        Arc::new(move || {
+            let log = log.clone();
+            Box::pin(async move {
+                let expired = log.expire();
+                if expired > 0 {
+                    info!(expired, "expired events dropped from the event log");
+                }
+            })
+        }),
+    );
+}
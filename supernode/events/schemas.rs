@@ -0,0 +1,331 @@
+use crate::events::bus::Envelope;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    String,
+    Integer,
+    Decimal,
+    Boolean,
+    Timestamp,
+    Object,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    pub ty: FieldType,
+    pub required: bool,
+}
+
+// One version of an emitted event type (webhook, Kafka, SSE payloads share it)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventSchema {
+    pub event_type: String,
+    pub version: u32,
+    pub fields: Vec<Field>,
+}
+
+impl FieldType {
+    // Integer and Decimal are JSON numbers; amounts travel as decimal strings and are declared String
+    fn admits(&self, value: &Value) -> bool {
+        match self {
+            FieldType::String => value.is_string(),
+            FieldType::Integer => value.is_i64() || value.is_u64(),
+            FieldType::Decimal => value.is_number(),
+            FieldType::Boolean => value.is_boolean(),
+            FieldType::Timestamp => value.as_str().is_some_and(|s| DateTime::parse_from_rfc3339(s).is_ok()),
+            FieldType::Object => value.is_object(),
+        }
+    }
+}
+
+impl Field {
+    fn new(name: &str, ty: FieldType, required: bool) -> Self {
+        Field { name: name.to_string(), ty, required }
+    }
+}
+
+impl EventSchema {
+    // Problems with `payload` under this schema: missing required fields, wrong types and undeclared fields;
+    // the `type` tag is the schema's event type and is not a field
+    pub fn validate(&self, payload: &Value) -> Vec<String> {
+        let Some(payload) = payload.as_object() else {
+            return vec!["payload is not an object".to_string()];
+        };
+        let mut errors = Vec::new();
+        for field in &self.fields {
+            match payload.get(&field.name) {
+                None | Some(Value::Null) if field.required => errors.push(format!("required field `{}` is missing", field.name)),
+                None | Some(Value::Null) => {}
+                Some(value) if !field.ty.admits(value) => errors.push(format!("field `{}` is not a {:?}", field.name, field.ty)),
+                Some(_) => {}
+            }
+        }
+        for name in payload.keys().filter(|name| *name != "type") {
+            if !self.fields.iter().any(|f| &f.name == name) {
+                errors.push(format!("field `{}` is not in the schema", name));
+            }
+        }
+        errors
+    }
+
+    // A newer schema is backward compatible if old consumers can still read it:
+    // every existing field keeps its type, required fields stay required and
+    // new fields are optional
+    pub fn compatibility_errors(&self, previous: &EventSchema) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.version < previous.version {
+            errors.push(format!("version went backwards from {} to {}", previous.version, self.version));
+        }
+        for old in &previous.fields {
+            match self.fields.iter().find(|f| f.name == old.name) {
+                None if old.required => errors.push(format!("required field `{}` was removed", old.name)),
+                None => {}
+                Some(new) if new.ty != old.ty => {
+                    errors.push(format!("field `{}` changed type from {:?} to {:?}", old.name, old.ty, new.ty))
+                }
+                Some(new) if old.required && !new.required => {
+                    errors.push(format!("field `{}` is no longer required", old.name))
+                }
+                Some(_) => {}
+            }
+        }
+        for new in &self.fields {
+            if new.required && !previous.fields.iter().any(|f| f.name == new.name) {
+                errors.push(format!("new field `{}` must be optional", new.name));
+            }
+        }
+        errors
+    }
+}
+
+// All versions of every event type the node emits
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SchemaRegistry {
+    schemas: BTreeMap<String, BTreeMap<u32, EventSchema>>,
+}
+
+impl SchemaRegistry {
+    // Every event type the node publishes, as delivered to webhooks, SSE and the event log: the event's fields
+    // plus the envelope's `at`. Add a version here whenever an event changes
+    pub fn builtin() -> Self {
+        use FieldType::*;
+        let at = || Field::new("at", Timestamp, true);
+        let v1 = |event_type: &str, fields: Vec<Field>| EventSchema {
+            event_type: event_type.to_string(),
+            version: 1,
+            fields: [vec![at()], fields].concat(),
+        };
+        let mut registry = SchemaRegistry::default();
+        for schema in [
+            v1("threat_detected", vec![Field::new("source", String, true), Field::new("severity", Decimal, true), Field::new("detail", String, true)]),
+            v1(
+                "issuance_completed",
+                vec![
+                    Field::new("tx_id", String, true),
+                    Field::new("asset", String, true),
+                    Field::new("amount", String, true),
+                    Field::new("fee", Object, false),
+                    Field::new("tenant", String, false),
+                ],
+            ),
+            v1(
+                "redemption_completed",
+                vec![
+                    Field::new("tx_id", String, true),
+                    Field::new("asset", String, true),
+                    Field::new("amount", String, true),
+                    Field::new("tenant", String, false),
+                ],
+            ),
+            v1(
+                "conversion_executed",
+                vec![
+                    Field::new("tx_id", String, true),
+                    Field::new("from", String, true),
+                    Field::new("to", String, true),
+                    Field::new("amount_in", String, true),
+                    Field::new("amount_out", String, true),
+                    Field::new("fee", Object, false),
+                    Field::new("tenant", String, false),
+                ],
+            ),
+            v1("self_heal_triggered", vec![Field::new("source", String, true), Field::new("rule", String, true)]),
+            v1(
+                "transaction_step",
+                vec![
+                    Field::new("tx_id", String, true),
+                    Field::new("step", String, true),
+                    Field::new("outcome", String, true),
+                    Field::new("detail", String, false),
+                    Field::new("related", String, false),
+                ],
+            ),
+        ] {
+            registry.register(schema);
+        }
+        registry
+    }
+
+    pub fn register(&mut self, schema: EventSchema) {
+        self.schemas.entry(schema.event_type.clone()).or_default().insert(schema.version, schema);
+    }
+
+    pub fn latest(&self, event_type: &str) -> Option<&EventSchema> {
+        self.schemas.get(event_type).and_then(|versions| versions.values().next_back())
+    }
+
+    // Problems with `envelope` under the latest schema of its event type
+    pub fn validate(&self, envelope: &Envelope) -> Vec<String> {
+        let topic = envelope.event.topic();
+        let Some(schema) = self.latest(topic) else {
+            return vec![format!("no schema for {}", topic)];
+        };
+        match serde_json::to_value(envelope) {
+            Ok(payload) => schema.validate(&payload),
+            Err(e) => vec![e.to_string()],
+        }
+    }
+
+    // Startup check against the schemas published by the previous run
+    pub fn check_against(&self, published: &SchemaRegistry) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        for (event_type, versions) in &published.schemas {
+            let previous = match versions.values().next_back() {
+                Some(previous) => previous,
+                None => continue,
+            };
+            match self.latest(event_type) {
+                Some(current) => errors.extend(
+                    current.compatibility_errors(previous).into_iter().map(|e| format!("{}: {}", event_type, e)),
+                ),
+                None => errors.push(format!("{}: event type was removed", event_type)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // Check compatibility with the registry last published at `path`, if any
+    pub fn verify(&self, path: &Path) -> Result<(), Vec<String>> {
+        match fs::read(path) {
+            Ok(bytes) => self.check_against(&serde_json::from_slice(&bytes).map_err(|e| vec![e.to_string()])?),
+            Err(_) => Ok(()),
+        }
+    }
+
+    // Load the last published registry, verify compatibility and publish the current one
+    pub fn verify_and_publish(&self, path: &Path) -> Result<(), Vec<String>> {
+        self.verify(path)?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| vec![e.to_string()])?;
+        }
+        let bytes = serde_json::to_vec_pretty(self).map_err(|e| vec![e.to_string()])?;
+        fs::write(path, bytes).map_err(|e| vec![e.to_string()])
+    }
+
+    // GET /v1/events/schemas and /v1/events/schemas/{type}
+    pub fn routes(self) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let registry = Arc::new(self);
+        let all_registry = registry.clone();
+        let all = warp::path!("v1" / "events" / "schemas")
+            .and(warp::get())
+            .map(move || warp::reply::json(&all_registry.schemas));
+        let one = warp::path!("v1" / "events" / "schemas" / String)
+            .and(warp::get())
+            .and_then(move |event_type: String| {
+                let versions = registry.schemas.get(&event_type).cloned();
+                async move {
+                    match versions {
+                        Some(versions) => Ok(warp::reply::json(&versions)),
+                        None => Err(warp::reject::not_found()),
+                    }
+                }
+            });
+        all.or(one)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::bus::{Event, Step};
+    use crate::fees::FeeCharge;
+    use chrono::Utc;
+
+    fn envelope(event: Event) -> Envelope {
+        Envelope { at: Utc::now(), event }
+    }
+
+    #[test]
+    fn every_published_event_matches_its_builtin_schema() {
+        let registry = SchemaRegistry::builtin();
+        let fee = FeeCharge { asset: "PI".to_string(), items: Vec::new(), total: 1, net_amount: 9, recipient: "treasury".to_string() };
+        let events = [
+            Event::ThreatDetected { source: "crypto".to_string(), severity: 0.7, detail: "replay".to_string() },
+            Event::IssuanceCompleted { tx_id: "tx-1".to_string(), asset: "PI".to_string(), amount: "10".to_string(), fee: Some(fee.clone()), tenant: None },
+            Event::RedemptionCompleted { tx_id: "tx-2".to_string(), asset: "PI".to_string(), amount: "3".to_string(), tenant: Some("acme".to_string()) },
+            Event::ConversionExecuted {
+                tx_id: "cv-1".to_string(),
+                from: "PI".to_string(),
+                to: "USDC".to_string(),
+                amount_in: "10".to_string(),
+                amount_out: "3".to_string(),
+                fee: Some(fee),
+                tenant: Some("acme".to_string()),
+            },
+            Event::SelfHealTriggered { source: "crypto".to_string(), rule: "rotate".to_string() },
+            Event::TransactionStep {
+                tx_id: "tx-1".to_string(),
+                step: Step::Settlement,
+                outcome: "settled".to_string(),
+                detail: Some("batch 4".to_string()),
+                related: None,
+            },
+        ];
+        for event in events {
+            let topic = event.topic();
+            assert_eq!(registry.validate(&envelope(event)), Vec::<String>::new(), "{}", topic);
+        }
+    }
+
+    #[test]
+    fn drifted_payloads_are_reported() {
+        let schema = SchemaRegistry::builtin().latest("redemption_completed").cloned().unwrap();
+        let payload = serde_json::json!({ "type": "redemption_completed", "at": "yesterday", "tx_id": "tx-2", "amount": 3, "account": "alice" });
+        let errors = schema.validate(&payload);
+        assert!(errors.contains(&"field `at` is not a Timestamp".to_string()));
+        assert!(errors.contains(&"required field `asset` is missing".to_string()));
+        assert!(errors.contains(&"field `amount` is not a String".to_string()));
+        assert!(errors.contains(&"field `account` is not in the schema".to_string()));
+    }
+
+    #[test]
+    fn startup_refuses_schemas_old_consumers_cannot_read() {
+        let path = std::env::temp_dir().join(format!("event-schemas-{}", std::process::id())).join("schemas.json");
+        let _ = fs::remove_file(&path);
+        SchemaRegistry::builtin().verify_and_publish(&path).unwrap();
+
+        let mut narrowed = SchemaRegistry::builtin();
+        let mut issuance = narrowed.latest("issuance_completed").cloned().unwrap();
+        issuance.version = 2;
+        issuance.fields.retain(|f| f.name != "asset");
+        narrowed.register(issuance);
+        let errors = narrowed.verify_and_publish(&path).unwrap_err();
+        assert_eq!(errors, vec!["issuance_completed: required field `asset` was removed".to_string()]);
+        assert!(SchemaRegistry::builtin().verify(&path).is_ok());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::api::auth::{Auth, Principal};
+use crate::api::problem::ApiError;
+use crate::events::bus::{Event, Step};
+use crate::events::log::{EventLog, LoggedEvent};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::debug;
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Debug, Serialize)]
+pub struct TimelineEntry {
+    pub event_id: String,
+    pub at: DateTime<Utc>,
+
+    // Id the step was recorded under; differs from the requested id once a quote continues as a conversion
+    pub tx_id: String,
+    pub step: Step,
+    pub outcome: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub related: Option<String>,
+
+    pub since_previous_ms: i64,
+    pub since_start_ms: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Timeline {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub last_step_at: DateTime<Utc>,
+    pub elapsed_ms: i64,
+    pub steps: Vec<TimelineEntry>,
+}
+
+// tx id, step, outcome, detail, related
+type StepParts<'a> = (&'a str, Step, String, Option<String>, Option<String>);
+
+// Completion events predate `TransactionStep` and are shown as the state change they stand for
+fn as_step(event: &Event) -> Option<StepParts<'_>> {
+    match event {
+        Event::TransactionStep { tx_id, step, outcome, detail, related } => {
+            Some((tx_id, *step, outcome.clone(), detail.clone(), related.clone()))
+        }
+        Event::IssuanceCompleted { tx_id, asset, amount, .. } | Event::RedemptionCompleted { tx_id, asset, amount, .. } => {
+            Some((tx_id, Step::StateChange, event.topic().to_string(), Some(format!("{} {}", amount, asset)), None))
+        }
+        Event::ConversionExecuted { tx_id, from, to, amount_in, amount_out, .. } => Some((
+            tx_id,
+            Step::StateChange,
+            event.topic().to_string(),
+            Some(format!("{} {} -> {} {}", amount_in, from, amount_out, to)),
+            None,
+        )),
+        Event::ThreatDetected { .. } | Event::SelfHealTriggered { .. } => None,
+    }
+}
+
+// Every logged step of `id` and of the ids it continues under, oldest first; `None` when nothing was logged
+pub fn timeline(log: &EventLog, id: &str) -> Option<Timeline> {
+    let mut events: BTreeMap<String, LoggedEvent> = BTreeMap::new();
+    let direct = log.mentioning(id);
+    let related: Vec<String> = direct
+        .iter()
+        .filter_map(|e| match &e.envelope.event {
+            Event::TransactionStep { related: Some(r), .. } if r != id => Some(r.clone()),
+            _ => None,
+        })
+        .collect();
+    // One hop only: a conversion's settlement names its netting cycle, which must not pull in every other conversion
+    for event in direct.into_iter().chain(related.iter().flat_map(|r| log.mentioning(r))) {
+        if let Some(tx_id) = event.envelope.event.tx_id() {
+            if tx_id == id || related.iter().any(|r| r == tx_id) {
+                events.insert(event.id.to_string(), event);
+            }
+        }
+    }
+
+    let mut events: Vec<LoggedEvent> = events.into_values().collect();
+    events.sort_by_key(|e| e.envelope.at);
+    let started_at = events.first()?.envelope.at;
+    let mut previous = started_at;
+    let steps: Vec<TimelineEntry> = events
+        .iter()
+        .filter_map(|e| {
+            let (tx_id, step, outcome, detail, related) = as_step(&e.envelope.event)?;
+            let at = e.envelope.at;
+            let entry = TimelineEntry {
+                event_id: e.id.to_string(),
+                at,
+                tx_id: tx_id.to_string(),
+                step,
+                outcome,
+                detail,
+                related,
+                since_previous_ms: (at - previous).num_milliseconds(),
+                since_start_ms: (at - started_at).num_milliseconds(),
+            };
+            previous = at;
+            Some(entry)
+        })
+        .collect();
+    let last_step_at = steps.last()?.at;
+    Some(Timeline { id: id.to_string(), started_at, last_step_at, elapsed_ms: (last_step_at - started_at).num_milliseconds(), steps })
+}
+
+// GET /v1/transactions/{id}/timeline
+pub fn routes(log: EventLog, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("v1" / "transactions" / String / "timeline").and(warp::get()).and(auth.authorized()).and_then(
+        move |id: String, principal: Principal| {
+            let log = log.clone();
+            async move {
+                debug!(%id, by = %principal.subject, "transaction timeline requested");
+                match timeline(&log, &id) {
+                    Some(timeline) => Ok(warp::reply::json(&timeline)),
+                    None => Err(warp::reject::custom(ApiError::NotFound(format!("no logged steps for {}", id)))),
+                }
+            }
+        },
+    )
+}
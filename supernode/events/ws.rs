@@ -0,0 +1,119 @@
+use crate::api::auth::{Auth, Principal};
+use crate::events::bus::EventBus;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn};
+use warp::ws::{Message, WebSocket};
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Deserialize)]
+struct StreamQuery {
+    // Comma separated topics, e.g. `issuance_completed,threat_detected`; all topics when absent
+    topics: Option<String>,
+}
+
+// A client that cannot take a message within this long is disconnected
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn stream(socket: WebSocket, bus: EventBus, topics: Option<HashSet<String>>) {
+    let (mut sink, mut incoming) = socket.split();
+    let mut receiver = bus.subscribe();
+    loop {
+        tokio::select! {
+            // Clients only send pings and close frames; anything else ends the stream
+            message = incoming.next() => match message {
+                Some(Ok(m)) if m.is_ping() || m.is_pong() => continue,
+                _ => break,
+            },
+            received = receiver.recv() => {
+                let message = match received {
+                    Ok(envelope) => {
+                        if topics.as_ref().is_some_and(|t| !t.contains(envelope.event.topic())) {
+                            continue;
+                        }
+                        match serde_json::to_string(&envelope) {
+                            Ok(json) => Message::text(json),
+                            Err(_) => continue,
+                        }
+                    }
+                    // The bus buffer overflowed for this client: tell it so it can resync instead of silently missing events
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!(missed, "websocket client lagged");
+                        Message::text(serde_json::json!({ "type": "lagged", "missed": missed }).to_string())
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                match tokio::time::timeout(SEND_TIMEOUT, sink.send(message)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
+                        debug!(error = %e, "websocket send failed");
+                        break;
+                    }
+                    Err(_) => {
+                        warn!("websocket client too slow, disconnecting");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    let _ = sink.close().await;
+}
+
+// GET /ws/events?topics=..., authorized before the upgrade so refused callers get a plain 401/403
+pub fn routes(bus: EventBus, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("ws" / "events").and(warp::get()).and(auth.authorized()).and(warp::ws()).and(warp::query::<StreamQuery>()).map(
+        move |_: Principal, ws: warp::ws::Ws, query: StreamQuery| {
+            let bus = bus.clone();
+            let topics = query.topics.map(|t| t.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect());
+            ws.on_upgrade(move |socket| stream(socket, bus, topics))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use crate::events::bus::Event;
+    use crate::server::tests::{read_text, spawn, upgrade};
+    use std::collections::HashMap;
+
+    fn auth() -> Auth {
+        let key = ApiKeyConfig { subject: "ops".to_string(), scopes: vec![Scope::Admin], tenant: None };
+        Auth::new(&AuthConfig { api_keys: HashMap::from([("k-admin".to_string(), key)]), ..AuthConfig::default() }).unwrap()
+    }
+
+    // Keeps publishing until dropped, since the socket subscribes only once the upgrade has completed
+    fn publish_repeatedly(bus: EventBus) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                bus.publish(Event::IssuanceCompleted { tx_id: "tx_1".to_string(), asset: "PI".to_string(), amount: "5".to_string(), fee: None, tenant: None });
+                bus.publish(Event::RedemptionCompleted { tx_id: "tx_2".to_string(), asset: "PI".to_string(), amount: "3".to_string(), tenant: None });
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn streams_events_to_authorized_clients() {
+        let bus = EventBus::new();
+        let (addr, shutdown) = spawn(warp::any().and(Router::new().mount("events", routes(bus.clone(), &auth())).build())).await;
+
+        let (status, _) = upgrade(addr, "/ws/events", &[]).await;
+        assert!(status.contains("401"), "{}", status);
+
+        let (status, mut stream) = upgrade(addr, "/ws/events?topics=redemption_completed", &[("x-api-key", "k-admin")]).await;
+        assert!(status.contains("101"), "{}", status);
+        let publisher = publish_repeatedly(bus);
+        let message = tokio::time::timeout(Duration::from_secs(5), read_text(&mut stream)).await.expect("no event streamed");
+        publisher.abort();
+        let envelope: serde_json::Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(envelope["type"], "redemption_completed", "{}", message);
+        shutdown.cancel();
+    }
+}
@@ -0,0 +1,240 @@
+use crate::amount::{units_string, Rounding};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+// Percentage charged on amounts of at least `from` smallest units
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeTier {
+    pub from: u128,
+
+    // Hundredths of a percent
+    pub bps: u32,
+}
+
+// Fee of one operation on one asset, in that asset's smallest units
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeRule {
+    pub flat: u128,
+
+    // The tier with the highest `from` not above the amount applies to the whole amount
+    pub tiers: Vec<FeeTier>,
+
+    // Bounds on the percentage part
+    pub min: Option<u128>,
+    pub max: Option<u128>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetFees {
+    pub issuance: Option<FeeRule>,
+    pub conversion: Option<FeeRule>,
+}
+
+// `fees` section of the node config
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FeeScheduleConfig {
+    // Ledger account credited with every fee
+    pub recipient: String,
+    pub issuance: FeeRule,
+    pub conversion: FeeRule,
+
+    // Asset -> rules replacing the defaults for that asset
+    pub assets: BTreeMap<String, AssetFees>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeOperation {
+    Issuance,
+    Conversion,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct FeeItem {
+    pub name: String,
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub amount: u128,
+}
+
+// Fees taken from one transaction, itemized, in the asset the caller paid with
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct FeeCharge {
+    pub asset: String,
+    pub items: Vec<FeeItem>,
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub total: u128,
+
+    // What is left of the amount after fees
+    #[serde(with = "units_string")]
+    #[schema(value_type = String)]
+    pub net_amount: u128,
+    pub recipient: String,
+}
+
+#[derive(Debug)]
+pub enum FeeError {
+    // The fee would take the whole amount
+    ExceedsAmount { fee: u128, amount: u128 },
+    Overflow,
+}
+
+impl fmt::Display for FeeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeeError::ExceedsAmount { fee, amount } => write!(f, "fee of {} leaves nothing of {}", fee, amount),
+            FeeError::Overflow => write!(f, "fee overflows"),
+        }
+    }
+}
+
+// Flat plus tiered percentage fees per operation, with per-asset overrides
+#[derive(Clone, Default)]
+pub struct FeeSchedule {
+    config: Arc<FeeScheduleConfig>,
+}
+
+impl FeeSchedule {
+    pub fn new(config: FeeScheduleConfig) -> Result<Self, String> {
+        let charges_fees = |rule: &FeeRule| rule.flat > 0 || !rule.tiers.is_empty();
+        let overrides = config.assets.values().flat_map(|a| [a.issuance.as_ref(), a.conversion.as_ref()]).flatten();
+        let any_fee = charges_fees(&config.issuance) || charges_fees(&config.conversion) || overrides.clone().any(charges_fees);
+        if any_fee && config.recipient.trim().is_empty() {
+            return Err("fees.recipient is required when any fee is configured".to_string());
+        }
+        for rule in [&config.issuance, &config.conversion].into_iter().chain(overrides) {
+            if rule.tiers.iter().any(|t| t.bps > 10_000) {
+                return Err("fee tiers cannot exceed 10000 bps".to_string());
+            }
+            if let (Some(min), Some(max)) = (rule.min, rule.max) {
+                if min > max {
+                    return Err(format!("fee min {} is above max {}", min, max));
+                }
+            }
+        }
+        Ok(FeeSchedule { config: Arc::new(config) })
+    }
+
+    fn rule(&self, operation: FeeOperation, asset: &str) -> &FeeRule {
+        let overrides = self.config.assets.get(asset);
+        match operation {
+            FeeOperation::Issuance => overrides.and_then(|a| a.issuance.as_ref()).unwrap_or(&self.config.issuance),
+            FeeOperation::Conversion => overrides.and_then(|a| a.conversion.as_ref()).unwrap_or(&self.config.conversion),
+        }
+    }
+
+    // Fees on `amount` of `asset`; the percentage part rounds up so the fee never undercharges
+    pub fn charge(&self, operation: FeeOperation, asset: &str, amount: u128) -> Result<FeeCharge, FeeError> {
+        self.charge_with(operation, asset, amount, None)
+    }
+
+    // Like `charge`, with `bps` in place of the tiered percentage, e.g. a pricing experiment's fee
+    pub fn charge_at(&self, operation: FeeOperation, asset: &str, amount: u128, bps: u32) -> Result<FeeCharge, FeeError> {
+        self.charge_with(operation, asset, amount, Some(bps))
+    }
+
+    fn charge_with(&self, operation: FeeOperation, asset: &str, amount: u128, bps: Option<u32>) -> Result<FeeCharge, FeeError> {
+        let rule = self.rule(operation, asset);
+        let mut items = Vec::new();
+        if rule.flat > 0 {
+            items.push(FeeItem { name: "flat".to_string(), amount: rule.flat });
+        }
+        let bps = bps.or_else(|| rule.tiers.iter().filter(|t| t.from <= amount).max_by_key(|t| t.from).map(|tier| tier.bps));
+        if let Some(bps) = bps {
+            let percentage = Rounding::Up.mul_div(amount, bps as u128, 10_000).ok_or(FeeError::Overflow)?;
+            let percentage = percentage.max(rule.min.unwrap_or(0)).min(rule.max.unwrap_or(u128::MAX));
+            if percentage > 0 {
+                items.push(FeeItem { name: format!("percentage:{}bps", bps), amount: percentage });
+            }
+        }
+        let total = items.iter().try_fold(0u128, |sum, item| sum.checked_add(item.amount)).ok_or(FeeError::Overflow)?;
+        if total > 0 && total >= amount {
+            return Err(FeeError::ExceedsAmount { fee: total, amount });
+        }
+        Ok(FeeCharge { asset: asset.to_string(), items, total, net_amount: amount - total, recipient: self.config.recipient.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(conversion: FeeRule) -> FeeSchedule {
+        FeeSchedule::new(FeeScheduleConfig {
+            recipient: "treasury".to_string(),
+            issuance: FeeRule { flat: 5, ..FeeRule::default() },
+            conversion,
+            assets: BTreeMap::new(),
+        })
+        .unwrap()
+    }
+
+    fn tiers(tiers: &[(u128, u32)]) -> Vec<FeeTier> {
+        tiers.iter().map(|&(from, bps)| FeeTier { from, bps }).collect()
+    }
+
+    #[test]
+    fn charge_at_replaces_the_tiered_percentage() {
+        let schedule = schedule(FeeRule { flat: 2, tiers: tiers(&[(0, 100)]), ..FeeRule::default() });
+        let charge = schedule.charge_at(FeeOperation::Conversion, "PI", 1_000, 30).unwrap();
+        assert_eq!(charge.items.iter().map(|i| (i.name.as_str(), i.amount)).collect::<Vec<_>>(), [("flat", 2), ("percentage:30bps", 3)]);
+        assert_eq!(charge.total, 5);
+    }
+
+    #[test]
+    fn charges_flat_fees() {
+        let charge = schedule(FeeRule::default()).charge(FeeOperation::Issuance, "USDC", 1_000).unwrap();
+        assert_eq!(charge.items.len(), 1);
+        assert_eq!((charge.total, charge.net_amount), (5, 995));
+        assert_eq!(charge.recipient, "treasury");
+    }
+
+    #[test]
+    fn applies_the_highest_tier_reached_and_rounds_up() {
+        let fees = schedule(FeeRule { tiers: tiers(&[(0, 30), (1_000_000, 10)]), ..FeeRule::default() });
+        // 30 bps of 1001 is 3.003
+        assert_eq!(fees.charge(FeeOperation::Conversion, "PI", 1_001).unwrap().total, 4);
+        assert_eq!(fees.charge(FeeOperation::Conversion, "PI", 2_000_000).unwrap().total, 2_000);
+    }
+
+    #[test]
+    fn bounds_the_percentage_part() {
+        let fees = schedule(FeeRule { tiers: tiers(&[(0, 30)]), min: Some(10), max: Some(1_000), ..FeeRule::default() });
+        assert_eq!(fees.charge(FeeOperation::Conversion, "PI", 100).unwrap().total, 10);
+        assert_eq!(fees.charge(FeeOperation::Conversion, "PI", 10_000_000).unwrap().total, 1_000);
+    }
+
+    #[test]
+    fn asset_overrides_replace_the_default_rule() {
+        let mut config = FeeScheduleConfig { recipient: "treasury".to_string(), ..FeeScheduleConfig::default() };
+        config.conversion = FeeRule { flat: 1, ..FeeRule::default() };
+        config.assets.insert("PI".to_string(), AssetFees { issuance: None, conversion: Some(FeeRule { flat: 7, ..FeeRule::default() }) });
+        let fees = FeeSchedule::new(config).unwrap();
+        assert_eq!(fees.charge(FeeOperation::Conversion, "PI", 100).unwrap().total, 7);
+        assert_eq!(fees.charge(FeeOperation::Conversion, "USDC", 100).unwrap().total, 1);
+        assert_eq!(fees.charge(FeeOperation::Issuance, "PI", 100).unwrap().total, 0);
+    }
+
+    #[test]
+    fn refuses_fees_that_take_the_whole_amount_or_overflow() {
+        let fees = schedule(FeeRule { tiers: tiers(&[(0, 10_000)]), ..FeeRule::default() });
+        assert!(matches!(fees.charge(FeeOperation::Issuance, "USDC", 5), Err(FeeError::ExceedsAmount { fee: 5, amount: 5 })));
+        assert!(matches!(fees.charge(FeeOperation::Conversion, "PI", u128::MAX), Err(FeeError::Overflow)));
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        let unpaid = FeeScheduleConfig { issuance: FeeRule { flat: 1, ..FeeRule::default() }, ..FeeScheduleConfig::default() };
+        assert!(FeeSchedule::new(unpaid).is_err());
+        let config = |rule: FeeRule| FeeScheduleConfig { recipient: "treasury".to_string(), conversion: rule, ..FeeScheduleConfig::default() };
+        assert!(FeeSchedule::new(config(FeeRule { tiers: tiers(&[(0, 10_001)]), ..FeeRule::default() })).is_err());
+        assert!(FeeSchedule::new(config(FeeRule { min: Some(2), max: Some(1), ..FeeRule::default() })).is_err());
+    }
+}
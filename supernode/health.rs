@@ -0,0 +1,151 @@
+use crate::runtime::tasks::TaskLiveness;
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde::Serialize;
+use utoipa::ToSchema;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Failing,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: Status,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+// A readiness check against something the node needs to serve traffic
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    fn name(&self) -> &str;
+    async fn check(&self) -> Result<(), String>;
+}
+
+// A check that does not answer within this is reported as failing
+const DEPENDENCY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, ToSchema)]
+pub struct DependencyResult {
+    pub name: String,
+    pub status: Status,
+    pub latency_ms: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DependencyReport {
+    pub status: Status,
+    pub dependencies: Vec<DependencyResult>,
+}
+
+impl DependencyReport {
+    fn into_reply(self) -> impl Reply {
+        let code = if self.status == Status::Ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        warp::reply::with_status(warp::reply::json(&self), code)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthReport {
+    pub status: Status,
+    pub checks: Vec<CheckResult>,
+}
+
+impl HealthReport {
+    pub(crate) fn from_checks(checks: Vec<CheckResult>) -> Self {
+        let status = if checks.iter().all(|c| c.status == Status::Ok) { Status::Ok } else { Status::Failing };
+        HealthReport { status, checks }
+    }
+
+    fn into_reply(self) -> impl Reply {
+        let code = if self.status == Status::Ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        warp::reply::with_status(warp::reply::json(&self), code)
+    }
+}
+
+#[derive(Clone)]
+pub struct Health {
+    tasks: TaskLiveness,
+
+    // Keystore, oracle, ... checks run by /readyz
+    readiness: Arc<Vec<Box<dyn HealthCheck>>>,
+}
+
+impl Health {
+    pub fn new(tasks: TaskLiveness, readiness: Vec<Box<dyn HealthCheck>>) -> Self {
+        Health { tasks, readiness: Arc::new(readiness) }
+    }
+
+    // Alive as long as no background task has died
+    pub fn liveness(&self) -> HealthReport {
+        let dead = self.tasks.dead();
+        let checks = vec![CheckResult {
+            name: "background_tasks".to_string(),
+            status: if dead.is_empty() { Status::Ok } else { Status::Failing },
+            detail: (!dead.is_empty()).then(|| format!("stopped: {}", dead.join(", "))),
+        }];
+        HealthReport::from_checks(checks)
+    }
+
+    pub async fn readiness(&self) -> HealthReport {
+        let mut checks = self.liveness().checks;
+        for check in self.readiness.iter() {
+            let result = check.check().await;
+            checks.push(CheckResult {
+                name: check.name().to_string(),
+                status: if result.is_ok() { Status::Ok } else { Status::Failing },
+                detail: result.err(),
+            });
+        }
+        HealthReport::from_checks(checks)
+    }
+
+    // The readiness checks run concurrently, each timed and bounded by `DEPENDENCY_TIMEOUT`
+    pub async fn dependencies(&self) -> DependencyReport {
+        let runs = self.readiness.iter().map(|check| async move {
+            let started = Instant::now();
+            let result = match tokio::time::timeout(DEPENDENCY_TIMEOUT, check.check()).await {
+                Ok(result) => result,
+                Err(_) => Err(format!("no answer within {}s", DEPENDENCY_TIMEOUT.as_secs())),
+            };
+            DependencyResult {
+                name: check.name().to_string(),
+                status: if result.is_ok() { Status::Ok } else { Status::Failing },
+                latency_ms: started.elapsed().as_millis() as u64,
+                detail: result.err(),
+            }
+        });
+        let dependencies = join_all(runs).await;
+        let status = if dependencies.iter().all(|d| d.status == Status::Ok) { Status::Ok } else { Status::Failing };
+        DependencyReport { status, dependencies }
+    }
+
+    // GET /healthz, /healthz/dependencies and /readyz
+    pub fn routes(&self) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let live = self.clone();
+        let healthz = warp::path!("healthz").and(warp::get()).map(move || live.liveness().into_reply());
+        let ready = self.clone();
+        let readyz = warp::path!("readyz").and(warp::get()).then(move || {
+            let ready = ready.clone();
+            async move { ready.readiness().await.into_reply() }
+        });
+        let deps = self.clone();
+        let dependencies = warp::path!("healthz" / "dependencies").and(warp::get()).then(move || {
+            let deps = deps.clone();
+            async move { deps.dependencies().await.into_reply() }
+        });
+        healthz.or(dependencies).or(readyz)
+    }
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use ulid::{Generator, Ulid};
+
+// Monotonic within a millisecond, so ids created back to back still sort in creation order
+static GENERATOR: Mutex<Option<Generator>> = Mutex::new(None);
+
+fn next_ulid() -> Ulid {
+    let mut generator = GENERATOR.lock().unwrap();
+    let generator = generator.get_or_insert_with(Generator::new);
+    generator.generate().unwrap_or_else(|_| Ulid::new())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidId {
+    pub kind: &'static str,
+    pub input: String,
+}
+
+impl fmt::Display for InvalidId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` is not a valid {} id", self.input, self.kind)
+    }
+}
+
+impl std::error::Error for InvalidId {}
+
+// Typed ULID with a short prefix, e.g. `tx_01HV3K8Z6V6Q4M1X9J6T5B2C7D`
+macro_rules! typed_id {
+    ($name:ident, $prefix:literal, $kind:literal) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub struct $name(Ulid);
+
+        impl $name {
+            pub const PREFIX: &'static str = $prefix;
+
+            pub fn new() -> Self {
+                $name(next_ulid())
+            }
+
+            pub fn ulid(&self) -> Ulid {
+                self.0
+            }
+
+            // Creation time embedded in the id, in Unix milliseconds
+            pub fn timestamp_ms(&self) -> u64 {
+                self.0.timestamp_ms()
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}_{}", $prefix, self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = InvalidId;
+
+            // Accepts the prefixed form and, for integrators, the bare ULID
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let raw = s.strip_prefix(concat!($prefix, "_")).unwrap_or(s);
+                Ulid::from_string(raw).map($name).map_err(|_| InvalidId { kind: $kind, input: s.to_string() })
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = InvalidId;
+
+            fn try_from(s: String) -> Result<Self, Self::Error> {
+                s.parse()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> String {
+                id.to_string()
+            }
+        }
+    };
+}
+
+typed_id!(TxId, "tx", "transaction");
+typed_id!(ReceiptId, "rcpt", "receipt");
+typed_id!(JobId, "job", "job");
+typed_id!(CaseId, "case", "case");
+typed_id!(WebhookId, "wh", "webhook");
+typed_id!(ThreatId, "thr", "threat event");
+typed_id!(EventId, "evt", "event");
+typed_id!(ConversionId, "conv", "conversion");
+typed_id!(NettingCycleId, "net", "netting cycle");
+typed_id!(QuoteId, "quote", "quote");
+
+// Validate an id of any kind without knowing its type
+pub fn is_valid(s: &str) -> bool {
+    let raw = s.split_once('_').map(|(_, r)| r).unwrap_or(s);
+    Ulid::from_string(raw).is_ok()
+}
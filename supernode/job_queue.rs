@@ -0,0 +1,232 @@
+use crate::api::auth::Auth;
+use crate::ids::JobId;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use warp::{Filter, Rejection, Reply};
+
+// `jobs` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct JobQueueConfig {
+    pub path: PathBuf,
+    pub visibility_timeout_secs: u64,
+}
+
+impl Default for JobQueueConfig {
+    fn default() -> Self {
+        JobQueueConfig { path: PathBuf::from("data/jobs.json"), visibility_timeout_secs: 60 }
+    }
+}
+
+// Kinds of work routed through the queue
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum JobKind {
+    Export,
+    WebhookRetry,
+    ChainSubmission,
+    ScheduledConversion,
+    BulkOperation,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum JobStatus {
+    Ready,
+    InFlight,
+    Done,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub payload: String,
+
+    // Higher runs first
+    pub priority: u8,
+
+    // Unix seconds before which the job is invisible to workers
+    pub run_at: u64,
+
+    pub attempts: u32,
+    pub status: JobStatus,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct QueueState {
+    jobs: Vec<Job>,
+}
+
+// Persistent in-process job queue with priorities, delays and visibility timeouts
+#[derive(Clone)]
+pub struct JobQueue {
+    path: PathBuf,
+    state: Arc<Mutex<QueueState>>,
+
+    // Seconds a leased job stays invisible before it is handed out again
+    visibility_timeout: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+impl JobQueue {
+    pub fn from_config(config: &JobQueueConfig) -> std::io::Result<Self> {
+        Self::open(config.path.clone(), config.visibility_timeout_secs)
+    }
+
+    // Open the queue, restoring jobs persisted by a previous run
+    pub fn open(path: PathBuf, visibility_timeout: u64) -> std::io::Result<Self> {
+        let state = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => QueueState::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(JobQueue { path, state: Arc::new(Mutex::new(state)), visibility_timeout })
+    }
+
+    fn persist(&self, state: &QueueState) -> std::io::Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(state)?)?;
+        fs::rename(tmp, &self.path)
+    }
+
+    pub fn enqueue(&self, kind: JobKind, payload: String, priority: u8, delay_secs: u64) -> std::io::Result<JobId> {
+        let mut state = self.state.lock().unwrap();
+        let id = JobId::new();
+        state.jobs.push(Job { id, kind, payload, priority, run_at: now() + delay_secs, attempts: 0, status: JobStatus::Ready });
+        self.persist(&state)?;
+        Ok(id)
+    }
+
+    // Lease the highest priority visible job; it reappears if not acked in time
+    pub fn lease(&self) -> std::io::Result<Option<Job>> {
+        self.lease_where(|_| true)
+    }
+
+    // Same, for a worker that only handles one kind of job
+    pub fn lease_kind(&self, kind: JobKind) -> std::io::Result<Option<Job>> {
+        self.lease_where(|j| j.kind == kind)
+    }
+
+    fn lease_where(&self, wanted: impl Fn(&Job) -> bool) -> std::io::Result<Option<Job>> {
+        let mut state = self.state.lock().unwrap();
+        let now = now();
+        let visibility_timeout = self.visibility_timeout;
+        let job = state
+            .jobs
+            .iter_mut()
+            .filter(|j| matches!(j.status, JobStatus::Ready | JobStatus::InFlight) && j.run_at <= now && wanted(j))
+            .max_by_key(|j| (j.priority, std::cmp::Reverse(j.id)));
+        let leased = job.map(|j| {
+            j.status = JobStatus::InFlight;
+            j.attempts += 1;
+            j.run_at = now + visibility_timeout;
+            j.clone()
+        });
+        if leased.is_some() {
+            self.persist(&state)?;
+        }
+        Ok(leased)
+    }
+
+    pub fn ack(&self, id: JobId) -> std::io::Result<()> {
+        self.set_status(id, JobStatus::Done)
+    }
+
+    pub fn fail(&self, id: JobId) -> std::io::Result<()> {
+        self.set_status(id, JobStatus::Failed)
+    }
+
+    fn set_status(&self, id: JobId, status: JobStatus) -> std::io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(job) = state.jobs.iter_mut().find(|j| j.id == id) {
+            job.status = status;
+        }
+        state.jobs.retain(|j| j.status != JobStatus::Done);
+        self.persist(&state)
+    }
+
+    // Put a failed or stuck job back in the queue immediately
+    pub fn requeue(&self, id: JobId) -> std::io::Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        let found = match state.jobs.iter_mut().find(|j| j.id == id) {
+            Some(job) => {
+                job.status = JobStatus::Ready;
+                job.run_at = now();
+                true
+            }
+            None => false,
+        };
+        self.persist(&state)?;
+        Ok(found)
+    }
+
+    pub fn jobs(&self) -> Vec<Job> {
+        self.state.lock().unwrap().jobs.clone()
+    }
+
+    // GET /admin/jobs and POST /admin/jobs/{id}/requeue
+    pub fn admin_routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let list_queue = self.clone();
+        let list = warp::path!("admin" / "jobs")
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |_| warp::reply::json(&list_queue.jobs()));
+        let requeue_queue = self.clone();
+        let requeue = warp::path!("admin" / "jobs" / JobId / "requeue")
+            .and(warp::post())
+            .and(auth.authorized())
+            .map(move |id, _| match requeue_queue.requeue(id) {
+                Ok(true) => warp::http::StatusCode::NO_CONTENT,
+                Ok(false) => warp::http::StatusCode::NOT_FOUND,
+                Err(_) => warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        list.or(requeue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use std::collections::HashMap;
+    use warp::http::StatusCode;
+
+    fn key(subject: &str, scopes: Vec<Scope>) -> ApiKeyConfig {
+        ApiKeyConfig { subject: subject.to_string(), scopes, tenant: None }
+    }
+
+    #[tokio::test]
+    async fn admin_routes_need_the_admin_scope() {
+        let path = std::env::temp_dir().join(format!("jobs-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let queue = JobQueue::open(path.clone(), 60).unwrap();
+        let id = queue.enqueue(JobKind::Export, "ledger".to_string(), 1, 0).unwrap();
+        queue.lease().unwrap();
+        queue.fail(id).unwrap();
+
+        let api_keys = HashMap::from([("k-admin".to_string(), key("ops", vec![Scope::Admin])), ("k-iss".to_string(), key("app", vec![Scope::Issue]))]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let api = warp::any().and(Router::new().mount("jobs", queue.admin_routes(&auth)).build());
+
+        let response = warp::test::request().path("/admin/jobs").header("x-api-key", "k-iss").reply(&api).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = warp::test::request().path("/admin/jobs").header("x-api-key", "k-admin").reply(&api).await;
+        let jobs: Vec<Job> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(jobs[0].status, JobStatus::Failed);
+
+        let requeue = format!("/admin/jobs/{}/requeue", id);
+        let response = warp::test::request().method("POST").path(&requeue).header("x-api-key", "k-admin").reply(&api).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        // Persisted, so a restarted node sees the requeued job
+        let reopened = JobQueue::open(path.clone(), 60).unwrap();
+        assert_eq!(reopened.jobs()[0].status, JobStatus::Ready);
+        fs::remove_file(&path).unwrap();
+    }
+}
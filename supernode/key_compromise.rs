@@ -0,0 +1,292 @@
+use crate::alerting::{Alert, Alerter, Severity};
+use crate::api::auth::Auth;
+use crate::audit::log::AuditLog;
+use crate::keys::{self, key_id, NodeKey};
+use crate::p2p::policy_gossip::{GossipSender, KeyRevocation};
+use crate::storage::entities::EntityStore;
+use crate::storage::mvcc::Store;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::{error, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// `key_compromise` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct KeyCompromiseConfig {
+    // Where successor keys are written
+    pub key_dir: PathBuf,
+}
+
+impl Default for KeyCompromiseConfig {
+    fn default() -> Self {
+        KeyCompromiseConfig { key_dir: PathBuf::from("data/keys") }
+    }
+}
+
+// Anything that issued sessions or credentials under the node key and must make their holders authenticate again
+pub trait SessionInvalidator: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Number of sessions dropped
+    fn invalidate(&self, key_id: &str) -> Result<usize, String>;
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RevokedKey {
+    #[serde(with = "hex::serde")]
+    pub public_key: [u8; 32],
+    pub successor: String,
+    pub reason: String,
+    pub revoked_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionsInvalidated {
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// What `compromise` did; later steps still run when an earlier one fails, and failures are listed
+#[derive(Clone, Debug, Serialize)]
+pub struct CompromiseReport {
+    pub key_id: String,
+    pub successor: String,
+    pub revoked_at: DateTime<Utc>,
+    pub anchors_resigned: usize,
+    pub sessions: Vec<SessionsInvalidated>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum CompromiseError {
+    UnknownKey(String),
+    AlreadyRevoked(String),
+    Successor(String),
+}
+
+impl fmt::Display for CompromiseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompromiseError::UnknownKey(id) => write!(f, "{} is not the node's current key", id),
+            CompromiseError::AlreadyRevoked(id) => write!(f, "key {} is already revoked", id),
+            CompromiseError::Successor(e) => write!(f, "could not create a successor key: {}", e),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CompromiseRequest {
+    #[serde(default)]
+    pub reason: String,
+}
+
+// Incident response for a leaked node key: revoke, rotate, re-sign, tell peers, drop sessions, open an incident
+#[derive(Clone)]
+pub struct KeyResponse {
+    config: KeyCompromiseConfig,
+    key: NodeKey,
+
+    // Anchors it signed are re-signed under the successor; none to re-sign when the audit log is off
+    audit: Option<AuditLog>,
+    gossip: Arc<dyn GossipSender>,
+    sessions: Arc<Vec<Arc<dyn SessionInvalidator>>>,
+    alerter: Alerter,
+    revoked: EntityStore<RevokedKey>,
+
+    // One compromise at a time, so two operators cannot rotate past each other
+    running: Arc<Mutex<()>>,
+}
+
+impl KeyResponse {
+    pub fn new(
+        config: KeyCompromiseConfig,
+        key: NodeKey,
+        audit: Option<AuditLog>,
+        gossip: Arc<dyn GossipSender>,
+        sessions: Vec<Arc<dyn SessionInvalidator>>,
+        alerter: Alerter,
+        store: Store,
+    ) -> Self {
+        KeyResponse {
+            config,
+            key,
+            audit,
+            gossip,
+            sessions: Arc::new(sessions),
+            alerter,
+            revoked: EntityStore::new(store, "revoked_keys"),
+            running: Arc::default(),
+        }
+    }
+
+    pub fn is_revoked(&self, public_key: &[u8; 32]) -> bool {
+        self.revoked.get(&key_id(public_key)).is_ok()
+    }
+
+    pub fn revoked(&self) -> Vec<(String, RevokedKey)> {
+        self.revoked.list().into_iter().map(|(id, v)| (id, v.value)).collect()
+    }
+
+    pub fn compromise(&self, key_id: &str, reason: &str) -> Result<CompromiseReport, CompromiseError> {
+        let _running = self.running.lock().unwrap();
+        if self.revoked.get(key_id).is_ok() {
+            return Err(CompromiseError::AlreadyRevoked(key_id.to_string()));
+        }
+        if self.key.key_id() != key_id {
+            return Err(CompromiseError::UnknownKey(key_id.to_string()));
+        }
+
+        // Revoke first: from here on nothing signs with the old key
+        let successor = keys::generate(&self.config.key_dir).map_err(CompromiseError::Successor)?;
+        let old = self.key.replace(successor.clone());
+        let notice = KeyRevocation::sign(&old, &successor, Utc::now().timestamp());
+        let old_public = old.verifying_key().to_bytes();
+        let successor_id = self.key.key_id();
+        let revoked_at = Utc::now();
+        let record = RevokedKey { public_key: old_public, successor: successor_id.clone(), reason: reason.to_string(), revoked_at };
+        error!(key_id, successor = %successor_id, reason, "node key revoked as compromised");
+
+        let mut errors = Vec::new();
+        if let Err(e) = self.revoked.create(key_id, record) {
+            errors.push(format!("recording the revocation failed: {}", e));
+        }
+        let anchors_resigned = self.audit.as_ref().map_or(Ok(0), |audit| audit.resign(&old_public)).unwrap_or_else(|e| {
+            errors.push(format!("re-signing the audit chain failed: {}", e));
+            0
+        });
+
+        self.gossip.broadcast_revocation(&notice);
+
+        let sessions = self
+            .sessions
+            .iter()
+            .map(|source| match source.invalidate(key_id) {
+                Ok(count) => SessionsInvalidated { source: source.name().to_string(), count: Some(count), error: None },
+                Err(e) => {
+                    warn!(source = source.name(), error = %e, "session invalidation failed");
+                    errors.push(format!("{}: {}", source.name(), e));
+                    SessionsInvalidated { source: source.name().to_string(), count: None, error: Some(e) }
+                }
+            })
+            .collect();
+
+        self.alerter.raise(Alert {
+            kind: "key_compromised".to_string(),
+            severity: Severity::Critical,
+            message: format!("node key {} revoked as compromised ({}); successor {}", key_id, reason, successor_id),
+            at: revoked_at,
+        });
+
+        Ok(CompromiseReport {
+            key_id: key_id.to_string(),
+            successor: successor_id,
+            revoked_at,
+            anchors_resigned,
+            sessions,
+            errors,
+        })
+    }
+
+    // POST /admin/keys/{key_id}/compromise and GET /admin/keys/revoked
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let response = self.clone();
+        let compromise = warp::path!("admin" / "keys" / String / "compromise")
+            .and(warp::post())
+            .and(auth.authorized())
+            .and(warp::body::json())
+            .map(move |key_id: String, _, request: CompromiseRequest| match response.compromise(&key_id, &request.reason) {
+                Ok(report) => warp::reply::with_status(warp::reply::json(&report), StatusCode::OK),
+                Err(e) => {
+                    let status = match e {
+                        CompromiseError::UnknownKey(_) => StatusCode::NOT_FOUND,
+                        CompromiseError::AlreadyRevoked(_) => StatusCode::CONFLICT,
+                        CompromiseError::Successor(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                    };
+                    warp::reply::with_status(warp::reply::json(&e.to_string()), status)
+                }
+            });
+
+        let response = self.clone();
+        let revoked = warp::path!("admin" / "keys" / "revoked")
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |_| warp::reply::json(&response.revoked()));
+
+        compromise.or(revoked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::{AlertChannel, Delivery, Route};
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct Gossip(Mutex<Vec<KeyRevocation>>);
+
+    impl GossipSender for Gossip {
+        fn broadcast(&self, _bundle: &crate::p2p::policy_gossip::PolicyBundle) {}
+
+        fn broadcast_revocation(&self, notice: &KeyRevocation) {
+            self.0.lock().unwrap().push(notice.clone());
+        }
+    }
+
+    #[derive(Default)]
+    struct Capture(Mutex<Vec<Alert>>);
+
+    impl AlertChannel for Capture {
+        fn name(&self) -> &str {
+            "capture"
+        }
+
+        fn send(&self, alerts: &[Alert]) {
+            self.0.lock().unwrap().extend_from_slice(alerts);
+        }
+    }
+
+    #[tokio::test]
+    async fn compromise_route_rotates_the_key_and_gossips_the_revocation() {
+        let dir = std::env::temp_dir().join(format!("key-compromise-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = KeyCompromiseConfig { key_dir: dir.clone() };
+        let key = NodeKey::new(keys::generate(&dir).unwrap());
+        let old_id = key.key_id();
+        let gossip = Arc::new(Gossip::default());
+        let alerts = Arc::new(Capture::default());
+        let route = Route { channel: alerts.clone(), min_severity: Severity::Info, delivery: Delivery::Immediate, quiet_hours: None };
+        let response = KeyResponse::new(config, key.clone(), None, gossip.clone(), Vec::new(), Alerter::new(vec![route]), Store::new());
+
+        let key_config = |subject: &str, scopes| ApiKeyConfig { subject: subject.to_string(), scopes, tenant: None };
+        let api_keys = HashMap::from([("k-admin".to_string(), key_config("ops", vec![Scope::Admin])), ("k-iss".to_string(), key_config("app", vec![Scope::Issue]))]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let api = warp::any().and(Router::new().mount("key_compromise", response.routes(&auth)).build());
+        let path = format!("/admin/keys/{}/compromise", old_id);
+
+        let denied = warp::test::request().method("POST").path(&path).header("x-api-key", "k-iss").json(&serde_json::json!({})).reply(&api).await;
+        assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+
+        let body = serde_json::json!({ "reason": "leaked in a backup" });
+        let rotated = warp::test::request().method("POST").path(&path).header("x-api-key", "k-admin").json(&body).reply(&api).await;
+        assert_eq!(rotated.status(), StatusCode::OK);
+        assert_ne!(key.key_id(), old_id);
+        assert_eq!(gossip.0.lock().unwrap().len(), 1);
+        assert!(gossip.0.lock().unwrap()[0].verify().is_ok());
+        assert_eq!(alerts.0.lock().unwrap()[0].kind, "key_compromised");
+
+        let again = warp::test::request().method("POST").path(&path).header("x-api-key", "k-admin").json(&body).reply(&api).await;
+        assert_eq!(again.status(), StatusCode::CONFLICT);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,70 @@
+use ed25519_dalek::{Signature, Signer, SigningKey};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+// Short id of a node key: the first 8 bytes of the SHA-256 of its public half, hex encoded
+pub fn key_id(public: &[u8; 32]) -> String {
+    hex::encode(&Sha256::digest(public)[..8])
+}
+
+// The node's signing key, shared by everything that signs for the node (audit anchors, bundles),
+// so a rotation reaches all of them at once
+#[derive(Clone)]
+pub struct NodeKey {
+    inner: Arc<RwLock<SigningKey>>,
+}
+
+impl NodeKey {
+    pub fn new(key: SigningKey) -> Self {
+        NodeKey { inner: Arc::new(RwLock::new(key)) }
+    }
+
+    // 32-byte seed, hex encoded, as written by `generate`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        read(path).map(NodeKey::new)
+    }
+
+    pub fn public(&self) -> [u8; 32] {
+        self.inner.read().unwrap().verifying_key().to_bytes()
+    }
+
+    pub fn key_id(&self) -> String {
+        key_id(&self.public())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        self.inner.read().unwrap().sign(message)
+    }
+
+    // Swap in `successor`, returning the key it replaces
+    pub fn replace(&self, successor: SigningKey) -> SigningKey {
+        std::mem::replace(&mut *self.inner.write().unwrap(), successor)
+    }
+}
+
+// Key file written by `generate`
+pub fn read(path: &Path) -> Result<SigningKey, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let seed: [u8; 32] = hex::decode(text.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("{} is not a 32-byte hex key", path.display()))?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+// Fresh key written to `dir/node-<key id>.key`, readable only by the node's user
+pub fn generate(dir: &Path) -> Result<SigningKey, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    let key = SigningKey::generate(&mut OsRng);
+    let path = dir.join(format!("node-{}.key", key_id(&key.verifying_key().to_bytes())));
+    fs::write(&path, hex::encode(key.to_bytes())).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).map_err(|e| format!("failed to restrict {}: {}", path.display(), e))?;
+    }
+    Ok(key)
+}
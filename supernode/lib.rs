@@ -0,0 +1,120 @@
+// Pi Network supernode: ledger API, conversions, AI decisioning and peer sync in one process
+
+pub mod admin {
+    pub mod ai;
+    pub mod bulk;
+    pub mod policy_history;
+    pub mod policy_params;
+}
+
+pub mod ai {
+    pub mod backtest;
+    pub mod engine;
+    pub mod explain;
+    pub mod feedback;
+    pub mod persistence;
+    pub mod self_heal;
+}
+
+pub mod api {
+    pub mod access_review;
+    pub mod auth;
+    pub mod authz;
+    pub mod compression;
+    pub mod fee_estimate;
+    pub mod graphql;
+    pub mod honeytokens;
+    pub mod issuance;
+    pub mod openapi;
+    pub mod preflight;
+    pub mod problem;
+    pub mod redemption;
+    pub mod response_cache;
+    pub mod router;
+    pub mod signing;
+    pub mod validation;
+    pub mod versioning;
+}
+
+pub mod audit {
+    pub mod bundle;
+    pub mod log;
+    pub mod merkle;
+    pub mod verify;
+}
+
+pub mod events {
+    pub mod bus;
+    pub mod log;
+    pub mod schemas;
+    pub mod timeline;
+    pub mod ws;
+}
+
+pub mod p2p {
+    pub mod address_book;
+    pub mod bandwidth;
+    pub mod codec;
+    pub mod handshake;
+    pub mod network_map;
+    pub mod peers;
+    pub mod policy_gossip;
+}
+
+pub mod runtime {
+    pub mod clock;
+    pub mod deadline;
+    pub mod forensic;
+    pub mod lifecycle;
+    pub mod scheduler;
+    pub mod shutdown;
+    pub mod tasks;
+}
+
+pub mod storage {
+    pub mod conversion_ledger;
+    pub mod entities;
+    pub mod ledger_history;
+    pub mod mvcc;
+    pub mod profiler;
+    pub mod sync;
+}
+
+pub mod alert_correlation;
+pub mod alerting;
+pub mod amount;
+pub mod anomaly_model;
+pub mod assets;
+pub mod cache;
+pub mod calendars;
+pub mod cli;
+pub mod config;
+pub mod console;
+pub mod converter;
+pub mod doctor;
+pub mod fees;
+pub mod health;
+pub mod ids;
+pub mod job_queue;
+pub mod key_compromise;
+pub mod keys;
+pub mod logging;
+pub mod materialized_views;
+pub mod metrics;
+pub mod metrics_history;
+pub mod netting;
+pub mod node;
+pub mod oracle;
+pub mod plans;
+pub mod pricing_experiments;
+pub mod quotes;
+pub mod rate_limit;
+pub mod runbooks;
+pub mod server;
+pub mod sessions;
+pub mod telemetry;
+pub mod tenant_usage;
+pub mod tenants;
+pub mod traffic_mirror;
+pub mod upgrade;
+pub mod webhooks;
@@ -0,0 +1,44 @@
+use crate::telemetry::{self, TelemetryConfig};
+use serde::Deserialize;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+// `logging` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    // EnvFilter directives, overridden by RUST_LOG when set
+    pub filter: String,
+
+    pub format: LogFormat,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig { filter: "info".to_string(), format: LogFormat::Text }
+    }
+}
+
+// Install the global subscriber; call once before anything logs
+pub fn init(config: &LoggingConfig, telemetry_config: &TelemetryConfig) -> Result<(), String> {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.filter))
+        .map_err(|e| format!("invalid log filter `{}`: {}", config.filter, e))?;
+    let otel = telemetry::init_tracer(telemetry_config)
+        .map_err(|e| format!("failed to start OTLP exporter: {}", e))?
+        .map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
+    let registry = tracing_subscriber::registry().with(filter).with(otel);
+    match config.format {
+        LogFormat::Text => registry.with(fmt::layer()).try_init(),
+        LogFormat::Json => registry.with(fmt::layer().json().with_current_span(true).with_span_list(true)).try_init(),
+    }
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,18 @@
+use clap::Parser;
+use pi_supernode::cli::{run_command, Cli};
+use pi_supernode::node;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if let Some(code) = run_command(&cli) {
+        return code;
+    }
+    match node::run(&cli.config, cli.forensic) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use crate::api::auth::Auth;
+use crate::events::bus::{Envelope, EventSink};
+use crate::storage::ledger_history::{LedgerHistory, TransactionRecord};
+use async_trait::async_trait;
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use warp::{Filter, Rejection, Reply};
+
+// Bumped whenever `apply` changes; views are rebuilt from the ledger history at startup anyway
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Ledger events the views are maintained from; `day` counts days since the Unix epoch
+pub enum LedgerEvent {
+    Issued { tenant: Option<String>, asset: String, amount: u128, day: u32 },
+    Redeemed { tenant: Option<String>, asset: String, amount: u128, day: u32 },
+    Converted { tenant: Option<String>, from: String, to: String, amount_in: u128, amount_out: u128, day: u32 },
+}
+
+impl LedgerEvent {
+    // `None` for unknown kinds and amounts that do not parse
+    pub fn from_record(record: &TransactionRecord) -> Option<Self> {
+        let tenant = record.tenant.clone();
+        let asset = record.asset.clone();
+        let amount = record.amount.parse().ok()?;
+        let day = u32::try_from(record.at.timestamp().div_euclid(86_400)).ok()?;
+        match record.kind.as_str() {
+            "issuance" => Some(LedgerEvent::Issued { tenant, asset, amount, day }),
+            "redemption" => Some(LedgerEvent::Redeemed { tenant, asset, amount, day }),
+            "conversion" => Some(LedgerEvent::Converted {
+                tenant,
+                from: asset,
+                to: record.to_asset.clone()?,
+                amount_in: amount,
+                amount_out: record.amount_out.as_deref()?.parse().ok()?,
+                day,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Incrementally maintained aggregates so stats endpoints never scan the ledger
+#[derive(Default)]
+pub struct MaterializedViews {
+    // Schema version the views were built against
+    schema_version: u32,
+
+    // (day, asset) -> volume
+    daily_volumes: HashMap<(u32, String), u128>,
+
+    // (tenant, asset) -> net issued amount
+    tenant_totals: HashMap<(String, String), i128>,
+
+    // asset -> circulating supply
+    asset_supply: HashMap<String, u128>,
+}
+
+impl MaterializedViews {
+    pub fn new(schema_version: u32) -> Self {
+        MaterializedViews { schema_version, ..Default::default() }
+    }
+
+    // Apply a single event from the event stream
+    pub fn apply(&mut self, event: &LedgerEvent) {
+        match event {
+            LedgerEvent::Issued { tenant, asset, amount, day } => {
+                *self.daily_volumes.entry((*day, asset.clone())).or_insert(0) += amount;
+                if let Some(tenant) = tenant {
+                    *self.tenant_totals.entry((tenant.clone(), asset.clone())).or_insert(0) += *amount as i128;
+                }
+                *self.asset_supply.entry(asset.clone()).or_insert(0) += amount;
+            }
+            LedgerEvent::Redeemed { tenant, asset, amount, day } => {
+                *self.daily_volumes.entry((*day, asset.clone())).or_insert(0) += amount;
+                if let Some(tenant) = tenant {
+                    *self.tenant_totals.entry((tenant.clone(), asset.clone())).or_insert(0) -= *amount as i128;
+                }
+                let supply = self.asset_supply.entry(asset.clone()).or_insert(0);
+                *supply = supply.saturating_sub(*amount);
+            }
+            LedgerEvent::Converted { tenant, from, to, amount_in, amount_out, day } => {
+                *self.daily_volumes.entry((*day, from.clone())).or_insert(0) += amount_in;
+                *self.daily_volumes.entry((*day, to.clone())).or_insert(0) += amount_out;
+                if let Some(tenant) = tenant {
+                    *self.tenant_totals.entry((tenant.clone(), from.clone())).or_insert(0) -= *amount_in as i128;
+                    *self.tenant_totals.entry((tenant.clone(), to.clone())).or_insert(0) += *amount_out as i128;
+                }
+            }
+        }
+    }
+
+    // Rebuild all views from the full event history when the schema changes
+    pub fn ensure_schema<'a, I>(&mut self, schema_version: u32, history: I)
+    where
+        I: IntoIterator<Item = &'a LedgerEvent>,
+    {
+        if self.schema_version == schema_version {
+            return;
+        }
+        *self = MaterializedViews::new(schema_version);
+        for event in history {
+            self.apply(event);
+        }
+    }
+
+    pub fn daily_volume(&self, day: u32, asset: &str) -> u128 {
+        self.daily_volumes.get(&(day, asset.to_string())).copied().unwrap_or(0)
+    }
+
+    pub fn tenant_total(&self, tenant: &str, asset: &str) -> i128 {
+        self.tenant_totals.get(&(tenant.to_string(), asset.to_string())).copied().unwrap_or(0)
+    }
+
+    pub fn asset_supply(&self, asset: &str) -> u128 {
+        self.asset_supply.get(asset).copied().unwrap_or(0)
+    }
+
+    // Every view, amounts as decimal strings like on the bus
+    pub fn snapshot(&self) -> ViewsSnapshot {
+        let mut snapshot = ViewsSnapshot { schema_version: self.schema_version, ..ViewsSnapshot::default() };
+        for ((day, asset), volume) in &self.daily_volumes {
+            let date = NaiveDate::default().checked_add_days(Days::new(*day as u64)).unwrap_or_default();
+            snapshot.daily_volumes.entry(date).or_default().insert(asset.clone(), volume.to_string());
+        }
+        for ((tenant, asset), total) in &self.tenant_totals {
+            snapshot.tenant_totals.entry(tenant.clone()).or_default().insert(asset.clone(), total.to_string());
+        }
+        snapshot.asset_supply = self.asset_supply.iter().map(|(asset, supply)| (asset.clone(), supply.to_string())).collect();
+        snapshot
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ViewsSnapshot {
+    pub schema_version: u32,
+
+    // Date -> asset -> volume
+    pub daily_volumes: BTreeMap<NaiveDate, BTreeMap<String, String>>,
+
+    // Tenant -> asset -> net issued amount, negative when more was redeemed or converted away
+    pub tenant_totals: BTreeMap<String, BTreeMap<String, String>>,
+    pub asset_supply: BTreeMap<String, String>,
+}
+
+// Views shared with the routes and kept current from the bus
+#[derive(Clone)]
+pub struct LiveViews {
+    views: Arc<RwLock<MaterializedViews>>,
+}
+
+impl LiveViews {
+    // Views over every transaction already in `history`, oldest first
+    pub fn build(history: &LedgerHistory) -> Self {
+        let mut views = MaterializedViews::new(SCHEMA_VERSION);
+        for record in history.transactions(None, None, usize::MAX).iter().rev() {
+            if let Some(event) = LedgerEvent::from_record(record) {
+                views.apply(&event);
+            }
+        }
+        LiveViews { views: Arc::new(RwLock::new(views)) }
+    }
+
+    pub fn snapshot(&self) -> ViewsSnapshot {
+        self.views.read().unwrap().snapshot()
+    }
+
+    // GET /admin/views and GET /v1/tenants/{tenant}/totals
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let views = self.clone();
+        let all = warp::path!("admin" / "views").and(warp::get()).and(auth.authorized()).map(move |_| warp::reply::json(&views.snapshot()));
+
+        let views = self.clone();
+        let tenant = warp::path!("v1" / "tenants" / String / "totals").and(warp::get()).and(auth.authorized()).map(move |tenant: String, _| {
+            let totals = views.snapshot().tenant_totals.remove(&tenant).unwrap_or_default();
+            warp::reply::json(&totals)
+        });
+        all.or(tenant)
+    }
+}
+
+#[async_trait]
+impl EventSink for LiveViews {
+    fn name(&self) -> &str {
+        "views"
+    }
+
+    async fn deliver(&self, envelope: &Envelope) {
+        if let Some(event) = TransactionRecord::from_envelope(envelope).as_ref().and_then(LedgerEvent::from_record) {
+            self.views.write().unwrap().apply(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use crate::events::bus::Event;
+    use crate::storage::mvcc::Store;
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashMap;
+
+    fn at_day_one(event: Event) -> Envelope {
+        Envelope { at: Utc.with_ymd_and_hms(1970, 1, 2, 12, 0, 0).unwrap(), event }
+    }
+
+    #[tokio::test]
+    async fn views_are_rebuilt_from_history_and_kept_current_from_the_bus() {
+        let history = LedgerHistory::new(Store::new());
+        let tenant = Some("acme".to_string());
+        history.record(&at_day_one(Event::IssuanceCompleted {
+            tx_id: "tx-1".to_string(),
+            asset: "PI".to_string(),
+            amount: "1000".to_string(),
+            fee: None,
+            tenant: tenant.clone(),
+        }));
+        let views = LiveViews::build(&history);
+        views
+            .deliver(&at_day_one(Event::RedemptionCompleted { tx_id: "tx-2".to_string(), asset: "PI".to_string(), amount: "300".to_string(), tenant }))
+            .await;
+        views
+            .deliver(&at_day_one(Event::RedemptionCompleted { tx_id: "tx-3".to_string(), asset: "PI".to_string(), amount: "50".to_string(), tenant: None }))
+            .await;
+
+        let snapshot = views.snapshot();
+        assert_eq!(snapshot.asset_supply["PI"], "650");
+        assert_eq!(snapshot.daily_volumes[&NaiveDate::from_ymd_opt(1970, 1, 2).unwrap()]["PI"], "1350");
+
+        let key = |subject: &str, scopes, tenant: Option<&str>| ApiKeyConfig { subject: subject.to_string(), scopes, tenant: tenant.map(str::to_string) };
+        let api_keys = HashMap::from([
+            ("k-admin".to_string(), key("ops", vec![Scope::Admin], None)),
+            ("k-acme".to_string(), key("acme-app", vec![Scope::Convert], Some("acme"))),
+        ]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let api = warp::any().and(Router::new().mount("views", views.routes(&auth)).build());
+        let get = |key: &str, path: &str| warp::test::request().path(path).header("x-api-key", key).reply(&api);
+
+        let totals = get("k-acme", "/v1/tenants/acme/totals").await;
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(totals.body()).unwrap(), serde_json::json!({ "PI": "700" }));
+        assert_eq!(get("k-acme", "/v1/tenants/other/totals").await.status(), 403);
+        assert_eq!(get("k-acme", "/admin/views").await.status(), 403);
+        assert_eq!(get("k-admin", "/admin/views").await.status(), 200);
+    }
+}
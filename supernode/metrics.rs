@@ -0,0 +1,154 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram_vec_with_registry, register_int_counter_vec_with_registry, register_int_gauge_vec_with_registry,
+    Encoder, HistogramVec, IntCounterVec, IntGaugeVec, Registry, TextEncoder,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use warp::{Filter, Rejection, Reply};
+
+pub static REGISTRY: Lazy<Registry> = Lazy::new(|| Registry::new_custom(Some("pi_supernode".to_string()), None).unwrap());
+
+// Crypto operations by kind (encrypt, decrypt, sign, verify)
+pub static CRYPTO_OPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!("crypto_operations_total", "Crypto operations performed", &["op"], REGISTRY)
+        .unwrap()
+});
+
+// API requests by route and status
+pub static API_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!("api_requests_total", "API requests served", &["route", "status"], REGISTRY)
+        .unwrap()
+});
+
+// Requests rejected by the AI engine, by module
+pub static REJECTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!("rejections_total", "Requests rejected by the AI engine", &["source"], REGISTRY)
+        .unwrap()
+});
+
+pub static SELF_HEAL_RUNS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!("self_heal_runs_total", "Self-heal evolutions", &["source"], REGISTRY)
+        .unwrap()
+});
+
+const LATENCY_BUCKETS: [f64; 9] = [0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+// Latency of crypto, API and converter operations; record through `observe_latency` to keep exemplars
+pub static LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec_with_registry!("operation_duration_seconds", "Operation latency", &["module", "op"], LATENCY_BUCKETS.to_vec(), REGISTRY)
+        .unwrap()
+});
+
+// Trace that produced an observation, shown next to its bucket in OpenMetrics output
+#[derive(Clone, Debug)]
+struct Exemplar {
+    trace_id: String,
+    value: f64,
+    timestamp: f64,
+}
+
+// (module, op) -> latest exemplar per bucket, the last slot being +Inf
+type ExemplarSlots = HashMap<(String, String), Vec<Option<Exemplar>>>;
+
+static LATENCY_EXEMPLARS: Lazy<Mutex<ExemplarSlots>> = Lazy::new(Mutex::default);
+
+// Observe into LATENCY, keeping `trace_id` as the bucket's exemplar when the request was traced
+pub fn observe_latency(module: &str, op: &str, elapsed: Duration, trace_id: Option<String>) {
+    let value = elapsed.as_secs_f64();
+    LATENCY.with_label_values(&[module, op]).observe(value);
+    let Some(trace_id) = trace_id else { return };
+    let bucket = LATENCY_BUCKETS.iter().position(|le| value <= *le).unwrap_or(LATENCY_BUCKETS.len());
+    let exemplar = Exemplar { trace_id, value, timestamp: Utc::now().timestamp_millis() as f64 / 1000.0 };
+    let mut exemplars = LATENCY_EXEMPLARS.lock().unwrap();
+    let slots = exemplars.entry((module.to_string(), op.to_string())).or_insert_with(|| vec![None; LATENCY_BUCKETS.len() + 1]);
+    slots[bucket] = Some(exemplar);
+}
+
+// Current size of in-memory logs (threat log, decision log, ...)
+pub static LOG_SIZES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec_with_registry!("log_entries", "Entries held in in-memory logs", &["log"], REGISTRY).unwrap()
+});
+
+// Lookups in the in-memory LRU caches, by cache and hit/miss
+pub static CACHE_LOOKUPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!("cache_lookups_total", "Cache lookups", &["cache", "result"], REGISTRY).unwrap()
+});
+
+// Current auto-tuned capacity of each cache, in entries
+pub static CACHE_CAPACITY: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec_with_registry!("cache_capacity", "Cache capacity in entries", &["cache"], REGISTRY).unwrap()
+});
+
+// Price oracle fetches by source and outcome (ok, error, stale, outlier)
+pub static ORACLE_FETCHES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec_with_registry!("oracle_fetches_total", "Price oracle fetches", &["source", "outcome"], REGISTRY).unwrap()
+});
+
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer).unwrap_or_default();
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+// `key="value"` pairs of a sample line; label values here never contain `",`
+fn labels(sample: &str) -> HashMap<&str, &str> {
+    let Some(inner) = sample.split_once('{').and_then(|(_, rest)| rest.rsplit_once('}')).map(|(inner, _)| inner) else {
+        return HashMap::new();
+    };
+    inner.split("\",").filter_map(|pair| pair.split_once("=\"")).map(|(k, v)| (k, v.trim_end_matches('"'))).collect()
+}
+
+fn bucket_exemplar(line: &str, exemplars: &HashMap<(String, String), Vec<Option<Exemplar>>>) -> Option<Exemplar> {
+    let labels = labels(line);
+    let slots = exemplars.get(&(labels.get("module")?.to_string(), labels.get("op")?.to_string()))?;
+    let le = labels.get("le")?;
+    let index = match *le {
+        "+Inf" => LATENCY_BUCKETS.len(),
+        le => LATENCY_BUCKETS.iter().position(|b| le.parse::<f64>().is_ok_and(|le| (le - b).abs() < f64::EPSILON))?,
+    };
+    slots.get(index).cloned().flatten()
+}
+
+// OpenMetrics text with latency exemplars, for scrapers that negotiate it
+pub fn render_openmetrics() -> String {
+    let exemplars = LATENCY_EXEMPLARS.lock().unwrap().clone();
+    let bucket_prefix = "pi_supernode_operation_duration_seconds_bucket{";
+    let mut out = String::new();
+    for line in render().lines() {
+        // OpenMetrics names counter families without the `_total` their samples carry
+        if let Some(rest) = line.strip_prefix("# TYPE ").filter(|l| l.ends_with(" counter")) {
+            let name = rest.trim_end_matches(" counter").trim_end_matches("_total");
+            out.push_str(&format!("# TYPE {} counter\n", name));
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("# HELP ") {
+            if let Some((name, help)) = rest.split_once(' ') {
+                out.push_str(&format!("# HELP {} {}\n", name.trim_end_matches("_total"), help));
+                continue;
+            }
+        }
+        out.push_str(line);
+        if line.starts_with(bucket_prefix) {
+            if let Some(e) = bucket_exemplar(line, &exemplars) {
+                out.push_str(&format!(" # {{trace_id=\"{}\"}} {} {:.3}", e.trace_id, e.value, e.timestamp));
+            }
+        }
+        out.push('\n');
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+// GET /metrics; Prometheus text by default, OpenMetrics with exemplars when the scraper accepts it
+pub fn routes() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("metrics").and(warp::get()).and(warp::header::optional::<String>("accept")).map(|accept: Option<String>| {
+        if accept.is_some_and(|a| a.contains("application/openmetrics-text")) {
+            warp::reply::with_header(render_openmetrics(), "content-type", "application/openmetrics-text; version=1.0.0; charset=utf-8")
+        } else {
+            warp::reply::with_header(render(), "content-type", "text/plain; version=0.0.4")
+        }
+    })
+}
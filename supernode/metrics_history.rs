@@ -0,0 +1,272 @@
+use crate::metrics::REGISTRY;
+use crate::runtime::scheduler::Scheduler;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use prometheus::proto::MetricType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::warn;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// `metrics_history` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct MetricsHistoryConfig {
+    pub enabled: bool,
+    pub dir: PathBuf,
+    pub retention_days: u32,
+    pub sample_interval_secs: u64,
+
+    // Metric families sampled, without the `pi_supernode_` prefix
+    pub metrics: Vec<String>,
+}
+
+impl Default for MetricsHistoryConfig {
+    fn default() -> Self {
+        MetricsHistoryConfig {
+            enabled: true,
+            dir: PathBuf::from("data/metrics"),
+            retention_days: 14,
+            sample_interval_secs: 60,
+            metrics: vec![
+                "api_requests_total".to_string(),
+                "rejections_total".to_string(),
+                "crypto_operations_total".to_string(),
+                "self_heal_runs_total".to_string(),
+                "log_entries".to_string(),
+            ],
+        }
+    }
+}
+
+// One sampling tick: series name (family plus sorted labels) to value
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Sample {
+    t: i64,
+    v: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Point {
+    pub t: DateTime<Utc>,
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Series {
+    pub series: String,
+    pub points: Vec<Point>,
+}
+
+fn series_name(family: &str, labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return family.to_string();
+    }
+    let labels: Vec<String> = labels.iter().map(|(k, v)| format!("{}=\"{}\"", k, v)).collect();
+    format!("{}{{{}}}", family, labels.join(","))
+}
+
+// Current values of the configured families; histograms contribute `_count` and `_sum`
+fn gather(families: &[String]) -> BTreeMap<String, f64> {
+    let mut values = BTreeMap::new();
+    for family in REGISTRY.gather() {
+        let name = family.get_name().trim_start_matches("pi_supernode_");
+        if !families.iter().any(|f| f == name) {
+            continue;
+        }
+        for metric in family.get_metric() {
+            let mut labels: Vec<(String, String)> =
+                metric.get_label().iter().map(|l| (l.get_name().to_string(), l.get_value().to_string())).collect();
+            labels.sort();
+            match family.get_field_type() {
+                MetricType::COUNTER => {
+                    values.insert(series_name(name, &labels), metric.get_counter().get_value());
+                }
+                MetricType::GAUGE => {
+                    values.insert(series_name(name, &labels), metric.get_gauge().get_value());
+                }
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    values.insert(series_name(&format!("{}_count", name), &labels), histogram.get_sample_count() as f64);
+                    values.insert(series_name(&format!("{}_sum", name), &labels), histogram.get_sample_sum());
+                }
+                _ => {}
+            }
+        }
+    }
+    values
+}
+
+// Append-only day files (`YYYY-MM-DD.jsonl`) with whole files dropped past retention
+#[derive(Clone)]
+pub struct MetricsHistory {
+    config: MetricsHistoryConfig,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl MetricsHistory {
+    pub fn open(config: MetricsHistoryConfig) -> Result<Self, String> {
+        fs::create_dir_all(&config.dir).map_err(|e| format!("failed to create {}: {}", config.dir.display(), e))?;
+        Ok(MetricsHistory { config, write_lock: Arc::default() })
+    }
+
+    fn day_file(&self, day: NaiveDate) -> PathBuf {
+        self.config.dir.join(format!("{}.jsonl", day.format("%Y-%m-%d")))
+    }
+
+    pub fn sample(&self) -> Result<(), String> {
+        let now = Utc::now();
+        let line = serde_json::to_string(&Sample { t: now.timestamp(), v: gather(&self.config.metrics) }).map_err(|e| e.to_string())?;
+        let _guard = self.write_lock.lock().unwrap();
+        let mut file = OpenOptions::new().create(true).append(true).open(self.day_file(now.date_naive())).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+
+    // Remove day files older than the retention window; returns how many
+    pub fn prune(&self) -> usize {
+        let cutoff = Utc::now().date_naive() - chrono::Duration::days(self.config.retention_days as i64);
+        let Ok(entries) = fs::read_dir(&self.config.dir) else { return 0 };
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let day = name.strip_suffix(".jsonl").and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+            if day.is_some_and(|d| d < cutoff) && fs::remove_file(entry.path()).is_ok() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    fn samples(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        let mut day = from.date_naive();
+        while day <= to.date_naive() {
+            if let Ok(file) = fs::File::open(self.day_file(day)) {
+                samples.extend(
+                    BufReader::new(file)
+                        .lines()
+                        .map_while(Result::ok)
+                        .filter_map(|line| serde_json::from_str::<Sample>(&line).ok())
+                        .filter(|s| s.t >= from.timestamp() && s.t <= to.timestamp()),
+                );
+            }
+            match day.succ_opt() {
+                Some(next) => day = next,
+                None => break,
+            }
+        }
+        samples
+    }
+
+    // Series of `metric` (a family, or one exact series) in `[from, to]`, keeping the last value per `step`
+    pub fn query(&self, metric: &str, from: DateTime<Utc>, to: DateTime<Utc>, step: Duration) -> Vec<Series> {
+        let step = step.as_secs().max(self.config.sample_interval_secs).max(1) as i64;
+        let mut series: BTreeMap<String, BTreeMap<i64, f64>> = BTreeMap::new();
+        for sample in self.samples(from, to) {
+            let bucket = sample.t - sample.t.rem_euclid(step);
+            for (name, value) in sample.v {
+                if name == metric || name.strip_prefix(metric).is_some_and(|rest| rest.starts_with('{')) {
+                    series.entry(name).or_default().insert(bucket, value);
+                }
+            }
+        }
+        series
+            .into_iter()
+            .map(|(series, points)| Series {
+                series,
+                points: points.into_iter().filter_map(|(t, value)| Utc.timestamp_opt(t, 0).single().map(|t| Point { t, value })).collect(),
+            })
+            .collect()
+    }
+
+    // How much a counter series grew over the last `window`, summed across its series
+    pub fn increase(&self, metric: &str, window: Duration) -> f64 {
+        let to = Utc::now();
+        let from = to - chrono::Duration::from_std(window).unwrap_or_else(|_| chrono::Duration::zero());
+        self.query(metric, from, to, Duration::ZERO)
+            .iter()
+            .filter_map(|s| Some(s.points.last()?.value - s.points.first()?.value))
+            .map(|delta| delta.max(0.0))
+            .sum()
+    }
+
+    // GET /v1/metrics/history?metric=...&from=...&to=...&step_secs=...
+    pub fn routes(&self) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let history = self.clone();
+        warp::path!("v1" / "metrics" / "history").and(warp::get()).and(warp::query::<HistoryQuery>()).map(move |q: HistoryQuery| {
+            let to = q.to.unwrap_or_else(Utc::now);
+            let from = q.from.unwrap_or(to - chrono::Duration::hours(24));
+            if from > to {
+                return warp::reply::with_status(warp::reply::json(&"`from` must not be after `to`"), StatusCode::BAD_REQUEST);
+            }
+            let series = history.query(&q.metric, from, to, Duration::from_secs(q.step_secs.unwrap_or(0)));
+            warp::reply::with_status(warp::reply::json(&series), StatusCode::OK)
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub metric: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub step_secs: Option<u64>,
+}
+
+// Sample on the configured interval and prune once an hour
+pub fn register(scheduler: &Scheduler, history: MetricsHistory) {
+    let sampler = history.clone();
+    scheduler.register(
+        "metrics_history:sample",
+        Duration::from_secs(history.config.sample_interval_secs.max(1)),
+        Duration::ZERO,
+        Arc::new(move || {
+            let history = sampler.clone();
+            Box::pin(async move {
+                if let Err(e) = tokio::task::spawn_blocking(move || history.sample()).await.unwrap_or_else(|e| Err(e.to_string())) {
+                    warn!(error = %e, "metrics sample failed");
+                }
+            })
+        }),
+    );
+    scheduler.register(
+        "metrics_history:prune",
+        Duration::from_secs(3600),
+        Duration::from_secs(60),
+        Arc::new(move || {
+            let history = history.clone();
+            Box::pin(async move {
+                let _ = tokio::task::spawn_blocking(move || history.prune()).await;
+            })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::LOG_SIZES;
+
+    #[tokio::test]
+    async fn samples_are_served_by_the_history_route() {
+        let dir = std::env::temp_dir().join(format!("metrics-history-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = MetricsHistoryConfig { dir: dir.clone(), metrics: vec!["log_entries".to_string()], ..MetricsHistoryConfig::default() };
+        let history = MetricsHistory::open(config).unwrap();
+        LOG_SIZES.with_label_values(&["history_test"]).set(42);
+        history.sample().unwrap();
+
+        let api = history.routes();
+        let response = warp::test::request().path("/v1/metrics/history?metric=log_entries").reply(&api).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let series: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        let ours = series.as_array().unwrap().iter().find(|s| s["series"] == "log_entries{log=\"history_test\"}").unwrap();
+        assert_eq!(ours["points"][0]["value"], 42.0);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
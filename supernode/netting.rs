@@ -0,0 +1,674 @@
+use crate::api::auth::{Auth, Principal, Scope};
+use crate::amount::Rounding;
+use crate::api::problem::ApiError;
+use crate::api::validation::{validated_json, ValidationConfig};
+use crate::calendars::Calendars;
+use crate::converter::{Conversion, ConvertError, ConvertRequest, StablecoinConverter};
+use crate::events::bus::{Event, EventBus, Step};
+use crate::health::HealthCheck;
+use crate::ids::{ConversionId, NettingCycleId};
+use crate::runtime::scheduler::Scheduler;
+use crate::storage::mvcc::{Store, WriteBatch};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+const PENDING: &str = "netting/pending/";
+const CYCLES: &str = "netting/cycles/";
+const SETTLED: &str = "netting/settled/";
+
+// `netting` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct NettingConfig {
+    pub enabled: bool,
+
+    // Conversions accumulate this long before their pairs are netted and settled
+    pub window_secs: u64,
+
+    // Accepted shortfall between what a net settlement delivers and what users are owed, in basis points (50 = 0.5%)
+    pub max_slippage_bps: u32,
+}
+
+impl Default for NettingConfig {
+    fn default() -> Self {
+        NettingConfig { enabled: false, window_secs: 300, max_slippage_bps: 50 }
+    }
+}
+
+// A user conversion waiting for the next netting cycle
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedConversion {
+    pub id: ConversionId,
+    pub subject: String,
+    pub conversion: ConversionRecord,
+    pub queued_at: DateTime<Utc>,
+}
+
+// The parts of a `Conversion` settlement needs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConversionRecord {
+    pub asset: String,
+
+    // What the node keeps of the input: the fee was credited to the fee recipient on acceptance and is not sold again.
+    // Records queued before fees were split out carry the gross `amount` and settle as they did
+    #[serde(alias = "amount")]
+    pub net_amount: u128,
+    pub to_asset: String,
+    pub converted_amount: u128,
+}
+
+impl From<&Conversion> for ConversionRecord {
+    fn from(c: &Conversion) -> Self {
+        ConversionRecord {
+            asset: c.asset.clone(),
+            net_amount: c.fees.as_ref().map_or(c.amount, |fee| fee.net_amount),
+            to_asset: c.to_asset.clone(),
+            converted_amount: c.converted_amount,
+        }
+    }
+}
+
+// One on-chain swap covering a pair's net flow: sell `amount` of `from` for at least `min_received` of `to`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettlementOrder {
+    pub from: String,
+    pub to: String,
+    pub amount: u128,
+    pub min_received: u128,
+}
+
+// Executes net settlements on-chain
+#[async_trait]
+pub trait Settler: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Returns the transaction reference
+    async fn settle(&self, order: &SettlementOrder) -> Result<String, String>;
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    // The net order was executed
+    Settled,
+
+    // Flows offset each other (or left a surplus on both sides), nothing to send on-chain
+    FullyNetted,
+
+    // Owed more of both assets than was taken in, e.g. after a rate jump; held for the operator
+    Shortfall,
+
+    // The settler refused or failed; the conversions stay queued for the next cycle
+    Failed,
+
+    // An asset's market is closed today (weekend or holiday); the conversions wait for its next business day
+    Deferred,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Settled => "settled",
+            Outcome::FullyNetted => "fully_netted",
+            Outcome::Shortfall => "shortfall",
+            Outcome::Failed => "failed",
+            Outcome::Deferred => "deferred",
+        }
+    }
+}
+
+// What one cycle did for one asset pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PairSettlement {
+    // Assets in name order
+    pub assets: (String, String),
+    pub conversions: Vec<ConversionId>,
+
+    // Per asset: taken in from users, and owed to them
+    pub received: (u128, u128),
+    pub owed: (u128, u128),
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<SettlementOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Report of one netting cycle; each user conversion appears under exactly one pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NettingCycle {
+    pub id: NettingCycleId,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub pairs: Vec<PairSettlement>,
+
+    // On-chain transactions sent, against one per conversion without netting
+    pub settlements: usize,
+    pub conversions: usize,
+}
+
+// Stored per netted conversion, pointing at its cycle
+#[derive(Serialize, Deserialize)]
+struct Netted {
+    cycle: NettingCycleId,
+    subject: String,
+}
+
+// Where a user conversion was settled
+#[derive(Serialize)]
+pub struct ConversionSettlement {
+    pub conversion: ConversionId,
+    pub cycle: NettingCycleId,
+    pub settlement: PairSettlement,
+}
+
+// What was taken in and is owed of each asset of `assets` across `queued`; `None` when a sum overflows
+fn totals(assets: &(String, String), queued: &[QueuedConversion]) -> Option<((u128, u128), (u128, u128))> {
+    let (mut received, mut owed) = ((0u128, 0u128), (0u128, 0u128));
+    for q in queued {
+        let c = &q.conversion;
+        if c.asset == assets.0 {
+            received.0 = received.0.checked_add(c.net_amount)?;
+            owed.1 = owed.1.checked_add(c.converted_amount)?;
+        } else {
+            received.1 = received.1.checked_add(c.net_amount)?;
+            owed.0 = owed.0.checked_add(c.converted_amount)?;
+        }
+    }
+    Some((received, owed))
+}
+
+// Net of one pair: the order to send, or why there is none
+fn net(assets: &(String, String), received: (u128, u128), owed: (u128, u128), max_slippage_bps: u32) -> (Outcome, Option<SettlementOrder>) {
+    // Surplus of each asset, or how much is missing; kept in u128 so no amount is truncated
+    let balance = |received: u128, owed: u128| received.checked_sub(owed).ok_or_else(|| owed - received);
+    let order = |from: &String, to: &String, amount: u128, needed: u128| SettlementOrder {
+        from: from.clone(),
+        to: to.clone(),
+        amount,
+        min_received: min_received(needed, max_slippage_bps),
+    };
+    match (balance(received.0, owed.0), balance(received.1, owed.1)) {
+        (Ok(_), Ok(_)) => (Outcome::FullyNetted, None),
+        (Ok(surplus), Err(needed)) => (Outcome::Settled, Some(order(&assets.0, &assets.1, surplus, needed))),
+        (Err(needed), Ok(surplus)) => (Outcome::Settled, Some(order(&assets.1, &assets.0, surplus, needed))),
+        (Err(_), Err(_)) => (Outcome::Shortfall, None),
+    }
+}
+
+// Least a net order may deliver of `needed`, rounded down; a slippage above 10000 bps accepts anything
+fn min_received(needed: u128, max_slippage_bps: u32) -> u128 {
+    let keep = 10_000 - u128::from(max_slippage_bps.min(10_000));
+    // Amounts too large to scale are divided first, giving up at most 10000 units of precision
+    Rounding::Down.mul_div(needed, keep, 10_000).unwrap_or(needed / 10_000 * keep)
+}
+
+// Accumulates conversions, nets opposite flows per asset pair each window, and settles only the remainder on-chain
+#[derive(Clone)]
+pub struct NettingEngine {
+    store: Store,
+    config: Arc<NettingConfig>,
+    settler: Arc<dyn Settler>,
+    calendars: Option<Calendars>,
+
+    // Where queueing and settlement attempts are published for transaction timelines
+    events: Option<EventBus>,
+
+    // One cycle at a time
+    running: Arc<Mutex<()>>,
+}
+
+impl NettingEngine {
+    pub fn new(store: Store, config: NettingConfig, settler: Arc<dyn Settler>) -> Self {
+        NettingEngine { store, config: Arc::new(config), settler, calendars: None, events: None, running: Arc::default() }
+    }
+
+    // Hold pairs whose fiat leg is on a non-banking day, per the assets' business calendars
+    pub fn with_calendars(mut self, calendars: Calendars) -> Self {
+        self.calendars = Some(calendars);
+        self
+    }
+
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    fn publish(&self, event: Event) {
+        if let Some(events) = &self.events {
+            events.publish(event);
+        }
+    }
+
+    fn market_open(&self, assets: &(String, String)) -> bool {
+        let today = Utc::now().date_naive();
+        self.calendars.as_ref().is_none_or(|c| c.is_open(&assets.0, today) && c.is_open(&assets.1, today))
+    }
+
+    // Queue `conversion` for the next cycle; `converter` accepts it in the same commit
+    pub fn submit(&self, converter: &StablecoinConverter, subject: &str, conversion: &Conversion) -> Result<QueuedConversion, ConvertError> {
+        let queued = QueuedConversion {
+            id: ConversionId::new(),
+            subject: subject.to_string(),
+            conversion: ConversionRecord::from(conversion),
+            queued_at: Utc::now(),
+        };
+        let mut batch = WriteBatch::default();
+        batch.put(format!("{}{}", PENDING, queued.id), serde_json::to_vec(&queued).map_err(|e| ConvertError::Storage(e.to_string()))?);
+        converter.accept(queued.id, subject, conversion, batch)?;
+        self.publish(Event::step(queued.id.to_string(), Step::StateChange, "queued"));
+        info!(conversion = %queued.id, subject, details = %conversion, "conversion queued");
+        Ok(queued)
+    }
+
+    pub fn pending(&self) -> Vec<QueuedConversion> {
+        self.store.read_txn().scan_prefix(PENDING).into_iter().filter_map(|(_, bytes)| serde_json::from_slice(&bytes).ok()).collect()
+    }
+
+    // Net and settle everything queued; `None` when nothing was
+    pub async fn run_cycle(&self) -> Result<Option<NettingCycle>, String> {
+        let Ok(_running) = self.running.try_lock() else { return Ok(None) };
+        let started_at = Utc::now();
+        let mut by_pair: BTreeMap<(String, String), Vec<QueuedConversion>> = BTreeMap::new();
+        for queued in self.pending() {
+            let c = &queued.conversion;
+            let key = if c.asset < c.to_asset { (c.asset.clone(), c.to_asset.clone()) } else { (c.to_asset.clone(), c.asset.clone()) };
+            by_pair.entry(key).or_default().push(queued);
+        }
+        if by_pair.is_empty() {
+            return Ok(None);
+        }
+
+        // Every pair is totalled before anything is settled, so an overflow stops the cycle with nothing sent
+        let totals = by_pair.into_iter().map(|(assets, queued)| {
+            let (received, owed) = totals(&assets, &queued).ok_or_else(|| format!("flows of {:?} overflow, cycle not run", assets))?;
+            Ok((assets, queued, received, owed))
+        });
+        let totals = totals.collect::<Result<Vec<_>, String>>()?;
+
+        let id = NettingCycleId::new();
+        let mut pairs = Vec::new();
+        let mut conversions = 0;
+        // Settled and fully netted pairs are dequeued in the commit that records the cycle, so no conversion is marked
+        // settled by a cycle that does not exist; failed, short and deferred pairs stay queued for the next cycle
+        let mut batch = WriteBatch::default();
+        for (assets, queued, received, owed) in totals {
+            let (mut outcome, mut order) = net(&assets, received, owed, self.config.max_slippage_bps);
+            let (mut transaction, mut error) = (None, None);
+            if order.is_some() && !self.market_open(&assets) {
+                outcome = Outcome::Deferred;
+                order = None;
+            }
+            if let Some(order) = &order {
+                match self.settler.settle(order).await {
+                    Ok(tx) => transaction = Some(tx),
+                    Err(e) => {
+                        warn!(settler = self.settler.name(), from = %order.from, to = %order.to, amount = order.amount, error = %e, "net settlement failed");
+                        outcome = Outcome::Failed;
+                        error = Some(e);
+                    }
+                }
+            }
+            if outcome == Outcome::Shortfall {
+                warn!(pair = ?assets, ?received, ?owed, "netting shortfall, conversions held for the operator");
+            }
+            for q in &queued {
+                self.publish(Event::TransactionStep {
+                    tx_id: q.id.to_string(),
+                    step: Step::Settlement,
+                    outcome: outcome.as_str().to_string(),
+                    detail: transaction.clone().or_else(|| error.clone()),
+                    related: Some(id.to_string()),
+                });
+            }
+            let settlement = PairSettlement {
+                assets,
+                conversions: queued.iter().map(|q| q.id).collect(),
+                received,
+                owed,
+                outcome,
+                order,
+                transaction,
+                error,
+            };
+            if matches!(outcome, Outcome::Settled | Outcome::FullyNetted) {
+                for q in &queued {
+                    let netted = Netted { cycle: id, subject: q.subject.clone() };
+                    batch.delete(format!("{}{}", PENDING, q.id));
+                    batch.put(format!("{}{}", SETTLED, q.id), serde_json::to_vec(&netted).map_err(|e| e.to_string())?);
+                }
+                conversions += queued.len();
+            }
+            pairs.push(settlement);
+        }
+        let cycle = NettingCycle {
+            id,
+            started_at,
+            finished_at: Utc::now(),
+            settlements: pairs.iter().filter(|p| p.transaction.is_some()).count(),
+            conversions,
+            pairs,
+        };
+        batch.put(format!("{}{}", CYCLES, id), serde_json::to_vec(&cycle).map_err(|e| e.to_string())?);
+        self.store.try_commit(batch).map_err(|e| format!("cycle {} ran but was not recorded, its conversions stay queued: {}", id, e))?;
+        info!(cycle = %id, conversions, settlements = cycle.settlements, "netting cycle finished");
+        Ok(Some(cycle))
+    }
+
+    // Newest first
+    pub fn cycles(&self, limit: usize) -> Vec<NettingCycle> {
+        let mut cycles: Vec<NettingCycle> =
+            self.store.read_txn().scan_prefix(CYCLES).into_iter().filter_map(|(_, bytes)| serde_json::from_slice(&bytes).ok()).collect();
+        cycles.reverse();
+        cycles.truncate(limit);
+        cycles
+    }
+
+    pub fn cycle(&self, id: &NettingCycleId) -> Option<NettingCycle> {
+        self.store.get(&format!("{}{}", CYCLES, id)).and_then(|bytes| serde_json::from_slice(&bytes).ok())
+    }
+
+    fn netted(&self, conversion: &ConversionId) -> Option<Netted> {
+        serde_json::from_slice(&self.store.get(&format!("{}{}", SETTLED, conversion))?).ok()
+    }
+
+    pub fn settlement_of(&self, conversion: &ConversionId) -> Option<ConversionSettlement> {
+        let cycle = self.netted(conversion)?.cycle;
+        let settlement = self.cycle(&cycle)?.pairs.into_iter().find(|p| p.conversions.contains(conversion))?;
+        Some(ConversionSettlement { conversion: *conversion, cycle, settlement })
+    }
+
+    // Subject that submitted the conversion, queued or netted
+    fn owner(&self, conversion: &ConversionId) -> Option<String> {
+        let queued = self.store.get(&format!("{}{}", PENDING, conversion)).and_then(|b| serde_json::from_slice::<QueuedConversion>(&b).ok());
+        queued.map(|q| q.subject).or_else(|| self.netted(conversion).map(|n| n.subject))
+    }
+
+    fn is_queued(&self, conversion: &ConversionId) -> bool {
+        self.store.get(&format!("{}{}", PENDING, conversion)).is_some()
+    }
+
+    // POST /v1/conversions, GET /v1/conversions/{id}/settlement, GET /admin/netting/cycles[/{id}], POST /admin/netting/run
+    pub fn routes(&self, converter: StablecoinConverter, rules: Arc<ValidationConfig>, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let engine = self.clone();
+        let submit = warp::path!("v1" / "conversions")
+            .and(warp::post())
+            .and(auth.authorized())
+            .and(validated_json(rules))
+            .and_then(move |principal: Principal, request: ConvertRequest| {
+                let result = converter
+                    .convert(&request.asset, request.amount, &request.to_asset, request.slippage_limit().as_ref())
+                    .and_then(|conversion| engine.submit(&converter, &principal.subject, &conversion))
+                    .map_err(ApiError::from);
+                async move {
+                    result.map(|queued| warp::reply::with_status(warp::reply::json(&queued), StatusCode::ACCEPTED)).map_err(warp::reject::custom)
+                }
+            });
+
+        let engine = self.clone();
+        let settlement = warp::path!("v1" / "conversions" / ConversionId / "settlement")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and_then(move |id: ConversionId, principal: Principal| {
+                // Other callers' conversions are reported as missing
+                let owned = engine.owner(&id).is_some_and(|owner| owner == principal.subject || principal.has_scope(Scope::Admin));
+                let result = if !owned {
+                    Err(ApiError::NotFound(format!("no conversion {}", id)))
+                } else if engine.is_queued(&id) {
+                    Ok(warp::reply::with_status(warp::reply::json(&"queued for the next netting cycle"), StatusCode::ACCEPTED))
+                } else {
+                    match engine.settlement_of(&id) {
+                        Some(settlement) => Ok(warp::reply::with_status(warp::reply::json(&settlement), StatusCode::OK)),
+                        None => Err(ApiError::NotFound(format!("no conversion {}", id))),
+                    }
+                };
+                async move { result.map_err(warp::reject::custom) }
+            });
+
+        let engine = self.clone();
+        let cycles = warp::path!("admin" / "netting" / "cycles")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and(warp::query::<CyclesQuery>())
+            .map(move |_, query: CyclesQuery| warp::reply::json(&engine.cycles(query.limit.unwrap_or(50))));
+
+        let engine = self.clone();
+        let cycle = warp::path!("admin" / "netting" / "cycles" / NettingCycleId).and(warp::get()).and(auth.authorized()).and_then(
+            move |id: NettingCycleId, _| {
+                let cycle = engine.cycle(&id).ok_or_else(|| warp::reject::custom(ApiError::NotFound(format!("no netting cycle {}", id))));
+                async move { cycle.map(|cycle| warp::reply::json(&cycle)) }
+            },
+        );
+
+        let engine = self.clone();
+        let run = warp::path!("admin" / "netting" / "run").and(warp::post()).and(auth.authorized()).and_then(move |_| {
+            let engine = engine.clone();
+            async move {
+                match engine.run_cycle().await {
+                    Ok(Some(cycle)) => Ok(warp::reply::json(&cycle)),
+                    Ok(None) => Ok(warp::reply::json(&"nothing to net")),
+                    Err(e) => Err(warp::reject::custom(ApiError::Unavailable(e))),
+                }
+            }
+        });
+
+        submit.or(settlement).or(cycles).or(cycle).or(run)
+    }
+}
+
+#[derive(Deserialize)]
+struct CyclesQuery {
+    limit: Option<usize>,
+}
+
+// Run a netting cycle every `window_secs`
+// Failing while the newest cycle has a pair the settler could not settle
+#[async_trait]
+impl HealthCheck for NettingEngine {
+    fn name(&self) -> &str {
+        "settler"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let Some(cycle) = self.cycles(1).pop() else { return Ok(()) };
+        match cycle.pairs.iter().find(|p| p.outcome == Outcome::Failed) {
+            Some(pair) => Err(format!(
+                "{} could not settle {}/{} in cycle {}: {}",
+                self.settler.name(),
+                pair.assets.0,
+                pair.assets.1,
+                cycle.id,
+                pair.error.as_deref().unwrap_or("unknown error")
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn register(scheduler: &Scheduler, engine: NettingEngine) {
+    if !engine.config.enabled {
+        return;
+    }
+    scheduler.register(
+        "netting:cycle",
+        Duration::from_secs(engine.config.window_secs.max(1)),
+        Duration::ZERO,
+        Arc::new(move || {
+            let engine = engine.clone();
+            Box::pin(async move {
+                if let Err(e) = engine.run_cycle().await {
+                    warn!(error = %e, "netting cycle not recorded");
+                }
+            })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::RateQuote;
+    use crate::fees::FeeCharge;
+
+    fn pair() -> (String, String) {
+        ("PI".to_string(), "USDC".to_string())
+    }
+
+    #[test]
+    fn offsetting_flows_need_no_order() {
+        let (outcome, order) = net(&pair(), (100, 50), (80, 50), 50);
+        assert_eq!(outcome, Outcome::FullyNetted);
+        assert!(order.is_none());
+    }
+
+    #[test]
+    fn sells_the_surplus_for_the_shortfall() {
+        let (outcome, order) = net(&pair(), (1_000, 0), (0, 30_001), 50);
+        assert_eq!(outcome, Outcome::Settled);
+        let order = order.unwrap();
+        assert_eq!((order.from.as_str(), order.to.as_str(), order.amount), ("PI", "USDC", 1_000));
+        // 30001 less 0.5% is 29850.995, rounded down
+        assert_eq!(order.min_received, 29_850);
+
+        let (_, order) = net(&pair(), (0, 400), (7, 0), 0);
+        let order = order.unwrap();
+        assert_eq!((order.from.as_str(), order.to.as_str(), order.amount, order.min_received), ("USDC", "PI", 400, 7));
+    }
+
+    #[test]
+    fn holds_a_shortfall_on_both_sides() {
+        let (outcome, order) = net(&pair(), (1, 1), (2, 2), 50);
+        assert_eq!(outcome, Outcome::Shortfall);
+        assert!(order.is_none());
+    }
+
+    #[test]
+    fn nets_amounts_beyond_i128() {
+        let big = u128::MAX - 1;
+        let (outcome, order) = net(&pair(), (u128::MAX, 0), (0, big), 10_000);
+        assert_eq!(outcome, Outcome::Settled);
+        let order = order.unwrap();
+        assert_eq!((order.amount, order.min_received), (u128::MAX, 0));
+
+        let (_, order) = net(&pair(), (u128::MAX, 0), (0, big), 0);
+        assert_eq!(order.unwrap().min_received, big / 10_000 * 10_000);
+    }
+
+    struct Filled;
+
+    #[async_trait]
+    impl Settler for Filled {
+        fn name(&self) -> &str {
+            "filled"
+        }
+
+        async fn settle(&self, _order: &SettlementOrder) -> Result<String, String> {
+            Ok("tx-1".to_string())
+        }
+    }
+
+    fn engine() -> NettingEngine {
+        NettingEngine::new(Store::new(), NettingConfig { enabled: true, ..NettingConfig::default() }, Arc::new(Filled))
+    }
+
+    fn queue(engine: &NettingEngine, asset: &str, net_amount: u128, to_asset: &str, converted_amount: u128) -> ConversionId {
+        let conversion = ConversionRecord { asset: asset.to_string(), net_amount, to_asset: to_asset.to_string(), converted_amount };
+        let queued = QueuedConversion { id: ConversionId::new(), subject: "alice".to_string(), conversion, queued_at: Utc::now() };
+        let mut batch = WriteBatch::default();
+        batch.put(format!("{}{}", PENDING, queued.id), serde_json::to_vec(&queued).unwrap());
+        engine.store.try_commit(batch).unwrap();
+        queued.id
+    }
+
+    #[test]
+    fn records_the_amount_after_fees() {
+        let fees = FeeCharge { asset: "PI".to_string(), items: Vec::new(), total: 6, net_amount: 594, recipient: "treasury".to_string() };
+        let rate = RateQuote { from: "PI".to_string(), to: "USDC".to_string(), numerator: 10, denominator: 1, updated_at: Utc::now(), inverse: false };
+        let conversion = Conversion {
+            asset: "PI".to_string(),
+            amount: 600,
+            amount_decimal: None,
+            to_asset: "USDC".to_string(),
+            direction: Default::default(),
+            fees: Some(fees),
+            converted_amount: 5_940,
+            rounding: Rounding::Down,
+            converted_decimal: None,
+            rate,
+            slippage_bps: None,
+            spread_bps: None,
+            hash: String::new(),
+            quote_id: None,
+        };
+        assert_eq!(ConversionRecord::from(&conversion).net_amount, 594);
+
+        // Queued before fees were split out
+        let old: ConversionRecord = serde_json::from_value(serde_json::json!({ "asset": "PI", "amount": 600, "to_asset": "USDC", "converted_amount": 6_000 })).unwrap();
+        assert_eq!(old.net_amount, 600);
+    }
+
+    #[tokio::test]
+    async fn records_the_cycle_with_the_conversions_it_settled() {
+        let engine = engine();
+        let sell = queue(&engine, "PI", 594, "USDC", 5_940);
+        let buy = queue(&engine, "USDC", 990, "PI", 99);
+
+        let cycle = engine.run_cycle().await.unwrap().unwrap();
+        let pair = &cycle.pairs[0];
+        assert_eq!((pair.received, pair.owed), ((594, 990), (99, 5_940)));
+        assert_eq!(pair.outcome, Outcome::Settled);
+        assert_eq!(cycle.conversions, 2);
+        assert!(engine.pending().is_empty());
+        for conversion in [sell, buy] {
+            assert_eq!(engine.settlement_of(&conversion).unwrap().cycle, cycle.id);
+        }
+    }
+
+    #[tokio::test]
+    async fn overflowing_flows_stop_the_cycle_with_nothing_dequeued() {
+        let engine = engine();
+        for _ in 0..2 {
+            queue(&engine, "PI", u128::MAX, "USDC", 1);
+        }
+        assert!(engine.run_cycle().await.unwrap_err().contains("overflow"));
+        assert_eq!(engine.pending().len(), 2);
+    }
+
+    struct Rejected;
+
+    #[async_trait]
+    impl Settler for Rejected {
+        fn name(&self) -> &str {
+            "rejected"
+        }
+
+        async fn settle(&self, _order: &SettlementOrder) -> Result<String, String> {
+            Err("insufficient liquidity".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn is_unready_while_the_last_cycle_failed_to_settle() {
+        let engine = NettingEngine::new(Store::new(), NettingConfig { enabled: true, ..NettingConfig::default() }, Arc::new(Rejected));
+        assert!(engine.check().await.is_ok());
+
+        queue(&engine, "PI", 594, "USDC", 5_940);
+        engine.run_cycle().await.unwrap();
+        let failing = engine.check().await.unwrap_err();
+        assert!(failing.contains("rejected could not settle PI/USDC") && failing.contains("insufficient liquidity"), "{}", failing);
+        assert_eq!(engine.pending().len(), 1);
+    }
+}
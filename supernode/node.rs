@@ -0,0 +1,508 @@
+use crate::admin::bulk::{self, BulkOps, RequeueFailedJobs, RevokeSessions};
+use crate::admin::policy_params::PolicyParamStore;
+use crate::alert_correlation::{self, Correlator};
+use crate::alerting::{Alerter, Delivery, Route, Severity};
+use crate::ai::backtest::{self, RequestArchive};
+use crate::ai::engine::AIEngine;
+use crate::ai::persistence;
+use crate::ai::explain::DecisionStore;
+use crate::ai::self_heal;
+use crate::api::auth::Auth;
+use crate::api::honeytokens::Tripwire;
+use crate::api::issuance::Issuances;
+use crate::api::preflight::{self, Preflight};
+use crate::api::redemption::Redemptions;
+use crate::api::response_cache::ResponseCache;
+use crate::api::router::Router;
+use crate::api::signing::RequestVerifier;
+use crate::api::{access_review, fee_estimate, graphql, openapi};
+use crate::assets::AssetRegistry;
+use crate::audit::log::{self as audit_log, AuditLog};
+use crate::calendars::Calendars;
+use crate::cache::AdaptiveLru;
+use crate::config::NodeConfig;
+#[cfg(unix)]
+use crate::console::{self, Console};
+use crate::converter::StablecoinConverter;
+use crate::events::bus::{EventBus, EventSink};
+use crate::events::log::{self as event_log, EventLog};
+use crate::events::schemas::SchemaRegistry;
+use crate::events::{timeline, ws};
+use crate::fees::FeeSchedule;
+use crate::job_queue::JobQueue;
+use crate::key_compromise::KeyResponse;
+use crate::keys::{self, NodeKey};
+use crate::materialized_views::LiveViews;
+use crate::metrics_history::{self, MetricsHistory};
+use crate::netting::{self, NettingConfig, NettingEngine, SettlementOrder, Settler};
+use crate::oracle::{self, PriceOracle};
+use crate::p2p::address_book::AddressBook;
+use crate::p2p::bandwidth::BandwidthMeter;
+use crate::p2p::network_map::{NetworkMapStore, PeerHealth};
+use crate::p2p::peers::{self, HealthFn, Peers};
+use crate::plans::Plans;
+use crate::pricing_experiments::PricingExperiments;
+use crate::quotes::{self, QuoteBook};
+use crate::rate_limit::RateLimiter;
+use crate::runbooks::{Action, ActionExecutor, Runbooks, RunbooksConfig};
+use crate::runtime::clock::{self, ClockGuard};
+use crate::runtime::forensic::Forensic;
+use crate::runtime::lifecycle::Lifecycle;
+use crate::runtime::scheduler::Scheduler;
+use crate::runtime::tasks::TaskGroup;
+use crate::sessions::{self, SessionManager};
+use crate::server::{self, cancel_on_signal};
+use crate::storage::conversion_ledger::{self, ConversionLedger};
+use crate::storage::entities::EntityStore;
+use crate::storage::ledger_history::LedgerHistory;
+use crate::storage::mvcc::Store;
+use crate::storage::profiler::{self, StorageProfiler};
+use crate::storage::sync::{self as state, read_state, SyncServer};
+use crate::tenant_usage::UsageMeter;
+use crate::tenants::TenantRegistry;
+use crate::traffic_mirror::TrafficMirror;
+use crate::webhooks::{self, WebhookDispatcher};
+use crate::{doctor, health, logging, metrics, telemetry};
+use async_trait::async_trait;
+use ed25519_dalek::SigningKey;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use warp::{Filter, Reply};
+
+// Changes kept for peers syncing from this node
+const CHANGE_LOG_CAPACITY: usize = 100_000;
+
+// Stands in while netting is off, for the settlement lookups of conversions netted before; every net order fails
+struct NoSettler;
+
+#[async_trait]
+impl Settler for NoSettler {
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    async fn settle(&self, _order: &SettlementOrder) -> Result<String, String> {
+        Err("no on-chain settler is configured".to_string())
+    }
+}
+
+// This build has no on-chain settler, so enabled netting would only queue conversions that never settle
+fn settler(config: &NettingConfig) -> Result<Arc<dyn Settler>, String> {
+    if config.enabled {
+        return Err("netting.enabled needs an on-chain settler and none is available in this build; set it to false".to_string());
+    }
+    Ok(Arc::new(NoSettler))
+}
+
+// Runbook actions this node can carry out: restarting its scheduled jobs, and maintenance mode, which stops them all
+struct NodeActions {
+    scheduler: Scheduler,
+}
+
+impl NodeActions {
+    // Refuse rules this build could only ever fail, and rules that would run unaudited
+    fn check(config: &RunbooksConfig, audited: bool) -> Result<(), String> {
+        if !config.rules.is_empty() && !audited {
+            return Err("runbooks.rules need audit_log.enabled, every runbook action is audited".to_string());
+        }
+        for rule in &config.rules {
+            if let Action::OpenCircuitBreaker(_) | Action::SwitchOracleProvider(_) = rule.action {
+                return Err(format!("runbook for {}: {:?} is not available in this build", rule.alert_kind, rule.action));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ActionExecutor for NodeActions {
+    fn execute(&self, action: &Action) -> Result<(), String> {
+        match action {
+            Action::RestartJob(name) => {
+                if !self.scheduler.jobs().iter().any(|job| &job.name == name) {
+                    return Err(format!("no scheduled job {}", name));
+                }
+                let (scheduler, name) = (self.scheduler.clone(), name.clone());
+                tokio::runtime::Handle::try_current().map_err(|e| e.to_string())?.spawn(async move { scheduler.trigger(&name).await });
+                Ok(())
+            }
+            Action::EnterMaintenanceMode => {
+                self.scheduler.freeze();
+                warn!("maintenance mode: scheduled jobs stopped until restart");
+                Ok(())
+            }
+            other => Err(format!("{:?} is not available in this build", other)),
+        }
+    }
+}
+
+// The newest `node-*.key` in `dir`, or a fresh one written there on first start
+// What this node tells peers about itself: its ledger position and the non-derived conversion rates it applies
+fn peer_health(node_id: String, store: Store, converter: StablecoinConverter) -> HealthFn {
+    Arc::new(move || {
+        let mut rates: Vec<String> =
+            converter.rates().into_iter().filter(|r| !r.inverse).map(|r| format!("{}/{}={}/{}", r.from, r.to, r.numerator, r.denominator)).collect();
+        rates.sort();
+        PeerHealth {
+            node_id: node_id.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            ledger_height: store.read_txn().seq(),
+            ledger_head: hex::encode(store.chain_head()),
+            peg_view: rates.join(","),
+        }
+    })
+}
+
+fn node_key(dir: &Path) -> Result<SigningKey, String> {
+    let newest = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("node-"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max();
+    match newest {
+        Some((_, path)) => keys::read(&path),
+        None => {
+            let key = keys::generate(dir)?;
+            info!(key_id = %keys::key_id(&key.verifying_key().to_bytes()), "node key generated");
+            Ok(key)
+        }
+    }
+}
+
+// Load the config, run the node until SIGTERM or Ctrl-C, and save its state on the way out
+pub fn run(config_path: &Path, forensic: bool) -> Result<(), String> {
+    let mut config = NodeConfig::load(config_path)?;
+    config.forensic.enabled |= forensic;
+    let runtime = config.server.runtime().map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        logging::init(&config.logging, &config.telemetry)?;
+        let shutdown = CancellationToken::new();
+        cancel_on_signal(shutdown.clone());
+        serve(config, shutdown).await
+    })
+}
+
+// Run the node in the caller's runtime until `shutdown` is cancelled, e.g. embedded in a test; the caller sets up logging
+pub async fn serve(config: NodeConfig, shutdown: CancellationToken) -> Result<(), String> {
+    let store = Store::with_change_log(CHANGE_LOG_CAPACITY);
+    let storage_profiler = StorageProfiler::new(&config.storage_profiling);
+    if let Some(profiler) = &storage_profiler {
+        store.set_profiler(profiler.clone());
+    }
+    if read_state(&config.bootstrap.state_path, &store)? {
+        info!(path = %config.bootstrap.state_path.display(), seq = store.read_txn().seq(), "state loaded");
+    }
+    let forensic = Forensic::new(&config.forensic);
+    store.set_read_only(forensic.is_some());
+    let replayed = store.open_journal(&config.bootstrap.journal_path)?;
+    if replayed > 0 {
+        info!(path = %config.bootstrap.journal_path.display(), replayed, seq = store.read_txn().seq(), "journal replayed");
+    }
+    let signing_key = node_key(&config.key_compromise.key_dir)?;
+    let key = NodeKey::new(signing_key.clone());
+    let audit = config.audit_log.enabled.then(|| AuditLog::open(config.audit_log.clone(), key.clone())).transpose()?;
+    if let Some(audit) = &audit {
+        audit.set_read_only(store.is_read_only());
+    }
+    let rules = Arc::new(config.validation.clone());
+
+    // Consumers codegen against the published schemas, so an incompatible change stops the node here
+    let schemas = SchemaRegistry::builtin();
+    let schemas_path = &config.event_log.schemas_path;
+    let published = if store.is_read_only() { schemas.verify(schemas_path) } else { schemas.verify_and_publish(schemas_path) };
+    published.map_err(|errors| format!("event schemas in {}: {}", schemas_path.display(), errors.join("; ")))?;
+    let bus = EventBus::new().with_schemas(schemas.clone());
+    let scheduler = Scheduler::new();
+    NodeActions::check(&config.runbooks, audit.is_some())?;
+    let mut channels = config.alerting.routes()?;
+    if let Some(audit) = audit.clone().filter(|_| !config.runbooks.rules.is_empty()) {
+        let actions = Arc::new(NodeActions { scheduler: scheduler.clone() });
+        let runbooks = Runbooks::new(config.runbooks.rules.clone(), actions, Arc::new(audit), config.runbooks.dry_run);
+        channels.push(Route { channel: Arc::new(runbooks), min_severity: Severity::Info, delivery: Delivery::Immediate, quiet_hours: None });
+    }
+    // Alerts are folded into incidents first, and only incident summaries reach the configured channels and runbooks
+    let notify = Alerter::new(channels);
+    let correlator = Correlator::new(config.alert_correlation.clone(), alert_correlation::escalation_policies(&notify));
+    let correlated = Route { channel: Arc::new(correlator.clone()), min_severity: Severity::Info, delivery: Delivery::Immediate, quiet_hours: None };
+    let alerter = Alerter::new(vec![correlated]);
+    let clock = ClockGuard::new(config.clock.clone());
+    let log = EventLog::new(store.clone(), config.event_log.clone());
+    let history = LedgerHistory::new(store.clone());
+    let views = LiveViews::build(&history);
+
+    let tenants = TenantRegistry::new(store.clone()).with_plans(Plans::new(config.plans.clone())?);
+    let sessions = SessionManager::new(config.sessions.clone(), store.clone())?;
+    let usage = UsageMeter::new(None);
+    let auth = Auth::with_key_store(&config.auth, Arc::new(tenants.clone()))?
+        .with_entitlements(Arc::new(tenants.clone()))
+        .with_sessions(Arc::new(sessions.clone()))
+        .with_usage_meter(usage.clone());
+    let limiter = RateLimiter::from_config(config.rate_limit.clone()).await?;
+    let signing = (!config.auth.request_signing.keys.is_empty()).then(|| RequestVerifier::new(config.auth.request_signing.clone())).transpose()?;
+
+    let accounts = EntityStore::new(store.clone(), "accounts");
+    let params = PolicyParamStore::new(
+        EntityStore::new(store.clone(), "policy_params"),
+        EntityStore::new(store.clone(), "policy_params_pending"),
+        config.policy_guard.clone(),
+    );
+
+    let (model, threshold) = config.model.build()?;
+    let engine = AIEngine::with_cache(model, threshold, config.caches.decisions.clone()).with_events(bus.clone());
+    match persistence::load(&engine, &config.model.checkpoint_path) {
+        Ok(true) => info!(path = %config.model.checkpoint_path.display(), "model restored"),
+        Ok(false) => {}
+        Err(e) => warn!(path = %config.model.checkpoint_path.display(), error = %e, "model not restored, starting from the configured defaults"),
+    }
+    let decisions = DecisionStore::default();
+    let archive = RequestArchive::new(store.clone());
+
+    let ledger = ConversionLedger::new(store.clone());
+    let responses = ResponseCache::new(config.caches.responses.clone());
+    let assets = AssetRegistry::new(config.assets.clone())?;
+    let fees = FeeSchedule::new(config.fees.clone())?;
+    let converter = StablecoinConverter::new(&config.converter)?
+        .with_assets(assets.clone())
+        .with_fees(fees.clone())
+        .with_accounts(accounts.clone())
+        .with_ledger(ledger.clone())
+        .with_cache(responses.clone())
+        .with_engine(engine.clone(), decisions.clone())
+        .with_archive(archive.clone());
+    // Sets the converter's rates each poll; a pair the sources cannot price keeps its last rate
+    let oracle = PriceOracle::new(config.oracle.clone(), converter.clone())?;
+    let calendars = Calendars::new(&config.calendars, store.clone())?;
+    let netting = NettingEngine::new(store.clone(), config.netting.clone(), settler(&config.netting)?)
+        .with_calendars(calendars.clone())
+        .with_events(bus.clone());
+    let experiments = PricingExperiments::new(config.experiments.clone())?;
+    if config.fees.recipient.trim().is_empty() && config.experiments.iter().flat_map(|e| &e.variants).any(|v| v.fee_bps > 0) {
+        return Err("fees.recipient is required when a pricing experiment charges fees".to_string());
+    }
+    let mut quotes = QuoteBook::new(config.quotes.clone(), converter.clone(), key.clone(), store.clone())
+        .with_events(bus.clone())
+        .with_experiments(experiments.clone());
+    if config.netting.enabled {
+        quotes = quotes.with_netting(netting.clone());
+    }
+
+    let issuances = Issuances::new(accounts.clone(), fees.clone(), bus.clone()).with_tenants(tenants.clone());
+    let redemptions = Redemptions::new(accounts.clone(), bus.clone()).with_usage_meter(usage.clone());
+    let preflight = Preflight::new(rules.clone(), params.clone(), auth.clone(), accounts.clone()).with_tenants(tenants.clone());
+    let schema = graphql::schema(&config.graphql, history.clone(), accounts.clone());
+    let sync = SyncServer::new(store.clone(), signing_key);
+    let network_map = NetworkMapStore::default();
+    let bandwidth = BandwidthMeter::new(&config.p2p.bandwidth);
+    let peers = Peers::new(&key.key_id(), &config.network_id, config.tls.is_some())
+        .with_health(peer_health(key.key_id(), store.clone(), converter.clone()), network_map.clone())
+        .with_meter(bandwidth.clone());
+    let key_response = KeyResponse::new(
+        config.key_compromise.clone(),
+        key.clone(),
+        audit.clone(),
+        Arc::new(peers.clone()),
+        vec![Arc::new(sessions.clone())],
+        alerter.clone(),
+        store.clone(),
+    );
+    let job_queue = JobQueue::from_config(&config.jobs).map_err(|e| format!("{}: {}", config.jobs.path.display(), e))?;
+    let bulk_ops = BulkOps::new(
+        vec![Arc::new(RevokeSessions(sessions.clone())), Arc::new(RequeueFailedJobs(job_queue.clone()))],
+        EntityStore::new(store.clone(), "bulk_runs"),
+        job_queue.clone(),
+    );
+    let webhooks = WebhookDispatcher::new(config.webhooks.clone(), Some(job_queue.clone())).with_log(log.clone());
+
+    netting::register(&scheduler, netting.clone());
+    quotes::register(&scheduler, quotes.clone());
+    oracle::register(&scheduler, oracle.clone());
+    event_log::register_expiry(&scheduler, log.clone());
+    clock::register(&scheduler, clock.clone(), alerter.clone());
+    let mut book = AddressBook::load(config.p2p.address_book_path.clone());
+    book.bootstrap(&config.p2p).await;
+    peers::register(&scheduler, peers.clone(), book);
+    sessions::register_purge(&scheduler, sessions.clone());
+    let metric_samples = config.metrics_history.enabled.then(|| MetricsHistory::open(config.metrics_history.clone())).transpose()?;
+    if let Some(history) = &metric_samples {
+        metrics_history::register(&scheduler, history.clone());
+    }
+    webhooks::register_retries(&scheduler, webhooks.clone());
+    bulk::register_worker(&scheduler, bulk_ops.clone());
+    responses.register_tuning(&scheduler);
+    alert_correlation::register(&scheduler, correlator.clone());
+    if let Some(audit) = &audit {
+        audit_log::register(&scheduler, audit.clone());
+    }
+    state::register_checkpoint(&scheduler, store.clone(), config.bootstrap.clone());
+    self_heal::register(&scheduler, &engine, &bus, &config.self_heal);
+    let checkpoint = Duration::from_secs(config.model.checkpoint_secs.max(1));
+    persistence::register_checkpoint(&scheduler, &engine, config.model.checkpoint_path.clone(), checkpoint);
+
+    let mut tasks = TaskGroup::new();
+    let digests = notify.clone();
+    tasks.spawn("alerting:digest", move |token| digests.digest_loop(token));
+    for sink in [Box::new(log.clone()) as Box<dyn EventSink>, Box::new(history.clone()), Box::new(views.clone()), Box::new(webhooks.clone())] {
+        let bus = bus.clone();
+        tasks.spawn(&format!("events:{}", sink.name()), move |token| async move { bus.forward(sink, token).await });
+    }
+    let jobs = scheduler.clone();
+    tasks.spawn("scheduler", move |token| jobs.run(token));
+    #[cfg(unix)]
+    if config.console.enabled {
+        let console = Console::new()
+            .with_scheduler(scheduler.clone())
+            .with_netting(netting.clone())
+            .with_jobs(job_queue.clone())
+            .with_history(history.clone())
+            .with_events(log.clone())
+            .with_quotes(quotes.clone());
+        let console_config = config.console.clone();
+        tasks.spawn("console", move |token| async move {
+            if let Err(e) = console::serve(console, &console_config, token).await {
+                error!(error = %e, "admin console stopped");
+            }
+        });
+    }
+    let mut dependencies = doctor::dependency_checks(&config, None);
+    dependencies.push(Box::new(oracle.clone()));
+    dependencies.push(Box::new(netting.clone()));
+    let health = health::Health::new(tasks.liveness(), dependencies);
+
+    let tripwire = Tripwire::new(&config.auth.honeytokens, alerter.clone());
+    let mut router = Router::new()
+        .mount("health", health.routes())
+        .mount("metrics", metrics::routes())
+        .mount("openapi", openapi::routes())
+        .mount("issuance", issuances.routes(&auth, rules.clone()))
+        .mount("redemption", redemptions.routes(&auth, rules.clone()))
+        .mount("converter", converter.routes(rules.clone(), &auth))
+        .mount("assets", assets.routes())
+        .mount("quotes", quotes.routes(rules.clone(), &auth))
+        .mount("netting", netting.routes(converter.clone(), rules.clone(), &auth))
+        .mount("calendars", calendars.routes(&auth))
+        .mount("conversions", conversion_ledger::routes(ledger, &auth))
+        .mount("fee_estimate", fee_estimate::routes(fees, params.clone(), rules.clone(), AdaptiveLru::new("fee_quotes", config.caches.quotes.clone())))
+        .mount("preflight", preflight::routes(preflight, &auth))
+        .mount("policy_params", params.routes(&auth))
+        .mount("tenants", tenants.routes(&auth))
+        .mount("tenant_usage", usage.routes(&auth))
+        .mount("graphql", graphql::routes(schema, &auth))
+        .mount("timeline", timeline::routes(log.clone(), &auth))
+        .mount("events", ws::routes(bus.clone(), &auth))
+        .mount("sync", sync.routes(&auth))
+        .mount("admin_ai", crate::admin::ai::routes(engine.clone(), bus.clone(), config.self_heal.log_threshold, &auth, &responses))
+        .mount("decisions", decisions.routes(&auth))
+        .mount("feedback", crate::ai::feedback::routes(engine.clone(), &auth))
+        .mount("backtest", backtest::routes(archive, &auth))
+        .mount("jobs", job_queue.admin_routes(&auth))
+        .mount("clock", clock.routes(&auth))
+        .mount("incidents", correlator.routes(&auth))
+        .mount("peers", peers.routes(&auth))
+        .mount("network_map", network_map.routes())
+        .mount("bandwidth", bandwidth.routes())
+        .mount("key_compromise", key_response.routes(&auth))
+        .mount("sessions", sessions.routes(&auth))
+        .mount("access_review", access_review::routes(&auth))
+        .mount("webhooks", webhooks.routes(log.clone(), &auth))
+        .mount("honeytokens", tripwire.routes(&auth))
+        .mount("bulk", bulk_ops.routes(&auth))
+        .mount("views", views.routes(&auth))
+        .mount("event_schemas", schemas.routes())
+        .mount("experiments", experiments.routes(&auth));
+    if let Some(history) = &metric_samples {
+        router = router.mount("metrics_history", history.routes());
+    }
+    if let Some(profiler) = &storage_profiler {
+        router = router.mount("storage_latency", profiler::routes(profiler.clone(), &auth));
+    }
+    if let Some(audit) = &audit {
+        router = router.mount("audit_log", audit.routes(&auth));
+    }
+    let mirror = TrafficMirror::from_config(&config.traffic_mirror)?;
+    if let Some(mirror) = &mirror {
+        router = router.mount("traffic_mirror", mirror.routes(&auth));
+    }
+    let routes = router.cors(config.server.cors.filter()?).forensic(forensic).mirror(mirror).build();
+    // Every call, admin ones included, is chained into the audit log with the fingerprint of the credential that made it
+    let routes = match &audit {
+        Some(audit) => routes.with(audit.layer()).map(Reply::into_response).boxed(),
+        None => routes,
+    };
+    // Decoy credentials are caught ahead of rate limiting, so even a throttled attempt raises the alarm
+    let routes = tripwire.filter().and(limiter.limit()).and(routes);
+
+    let lifecycle = Lifecycle::new().with_timeout(Duration::from_secs(config.server.drain_timeout_secs));
+    let served = server::serve_with_lifecycle(routes, &config.server, config.tls.as_ref(), signing, shutdown, lifecycle).await;
+
+    tasks.shutdown(Duration::from_secs(5)).await;
+    if !store.is_read_only() {
+        match state::checkpoint(&store, &config.bootstrap) {
+            Ok(seq) => info!(path = %config.bootstrap.state_path.display(), seq, "state saved"),
+            Err(e) => warn!(error = %e, "state not saved"),
+        }
+        match persistence::save(&engine, &config.model.checkpoint_path) {
+            Ok(()) => info!(path = %config.model.checkpoint_path.display(), "model saved"),
+            Err(e) => warn!(error = %e, "model not saved"),
+        }
+    }
+    telemetry::shutdown();
+    served
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_netting_without_a_settler() {
+        let enabled = NettingConfig { enabled: true, ..NettingConfig::default() };
+        assert!(settler(&enabled).err().unwrap().contains("settler"));
+        assert_eq!(settler(&NettingConfig::default()).unwrap().name(), "none");
+    }
+
+    #[tokio::test]
+    async fn runbooks_restart_jobs_on_matching_alerts() {
+        let scheduler = Scheduler::new();
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = runs.clone();
+        scheduler.register(
+            "oracle:refresh",
+            Duration::from_secs(3600),
+            Duration::ZERO,
+            Arc::new(move || {
+                let counted = counted.clone();
+                Box::pin(async move {
+                    counted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                })
+            }),
+        );
+        let config: RunbooksConfig = serde_yaml::from_str(
+            "dry_run: false\nrules:\n  - { alert_kind: oracle_stale, min_severity: warning, action: !restart_job \"oracle:refresh\" }\n",
+        )
+        .unwrap();
+        assert!(NodeActions::check(&config, false).unwrap_err().contains("audit_log"));
+        NodeActions::check(&config, true).unwrap();
+
+        let audit = Arc::new(crate::runbooks::MemoryAudit::default());
+        let runbooks = Runbooks::new(config.rules, Arc::new(NodeActions { scheduler: scheduler.clone() }), audit.clone(), false);
+        let route = Route { channel: Arc::new(runbooks), min_severity: Severity::Info, delivery: Delivery::Immediate, quiet_hours: None };
+        let alert = |severity| crate::alerting::Alert { kind: "oracle_stale".to_string(), severity, message: String::new(), at: chrono::Utc::now() };
+        let alerter = Alerter::new(vec![route]);
+        alerter.raise(alert(Severity::Info));
+        alerter.raise(alert(Severity::Warning));
+        tokio::task::yield_now().await;
+
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+        let entries = audit.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].outcome, "executed");
+
+        let unsupported: RunbooksConfig = serde_yaml::from_str("rules:\n  - { alert_kind: x, min_severity: info, action: !switch_oracle_provider b }\n").unwrap();
+        assert!(NodeActions::check(&unsupported, true).unwrap_err().contains("not available"));
+    }
+}
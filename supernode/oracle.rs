@@ -0,0 +1,506 @@
+use crate::converter::StablecoinConverter;
+use crate::health::HealthCheck;
+use crate::metrics;
+use crate::runtime::deadline;
+use crate::runtime::scheduler::Scheduler;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+// `oracle` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct OracleConfig {
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+
+    // Per source request
+    pub timeout_secs: u64,
+
+    // Quotes needed, after outliers are dropped, before a rate is updated
+    pub min_sources: usize,
+
+    // Quotes further than this from the median are dropped, 0.02 = 2%
+    pub max_deviation: f64,
+
+    // Fixed-point precision of the rates handed to the converter
+    pub scale: u128,
+    pub pairs: Vec<OraclePair>,
+    pub sources: Vec<OracleSource>,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        OracleConfig {
+            enabled: false,
+            poll_interval_secs: 60,
+            timeout_secs: 5,
+            min_sources: 2,
+            max_deviation: 0.02,
+            scale: 1_000_000_000,
+            pairs: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+fn default_decimals() -> u32 {
+    6
+}
+
+// Rate to maintain, with the decimals of each asset so whole-token prices become smallest-unit rates
+#[derive(Clone, Debug, Deserialize)]
+pub struct OraclePair {
+    pub from: String,
+    pub to: String,
+    #[serde(default = "default_decimals")]
+    pub from_decimals: u32,
+    #[serde(default = "default_decimals")]
+    pub to_decimals: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    // JSON API read through `pointer`
+    #[default]
+    Http,
+
+    // Stellar DEX via Horizon at `url`: the mid of the best bid and ask, or the reserve ratio of the constant-product pool
+    HorizonOrderBook,
+    HorizonLiquidityPool,
+}
+
+// Price source. For `http`, `url` and `pointer` may use `{from}` and `{to}`, replaced by the asset's entry in `symbols`
+// (or the asset itself, lowercased); `pointer` is a JSON pointer to the price of one `from` in `to`, as a number or string.
+//   CoinGecko: url https://api.coingecko.com/api/v3/simple/price?ids={from}&vs_currencies={to}, pointer /{from}/{to}
+//   Binance:   url https://api.binance.com/api/v3/ticker/price?symbol={from}{to}, pointer /price
+// For the Horizon kinds `url` is the Horizon server and `symbols` maps each asset to `native` or `CODE:ISSUER`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OracleSource {
+    pub name: String,
+    #[serde(default)]
+    pub kind: SourceKind,
+    pub url: String,
+    #[serde(default)]
+    pub pointer: String,
+    #[serde(default)]
+    pub symbols: HashMap<String, String>,
+
+    // Pairs this source quotes, as FROM/TO; all pairs when empty
+    #[serde(default)]
+    pub pairs: Vec<String>,
+}
+
+impl OracleSource {
+    fn quotes(&self, pair: &OraclePair) -> bool {
+        self.pairs.is_empty() || self.pairs.iter().any(|p| *p == format!("{}/{}", pair.from, pair.to))
+    }
+
+    fn symbol(&self, asset: &str) -> String {
+        self.symbols.get(asset).cloned().unwrap_or_else(|| asset.to_lowercase())
+    }
+
+    fn fill(&self, template: &str, pair: &OraclePair) -> String {
+        template.replace("{from}", &self.symbol(&pair.from)).replace("{to}", &self.symbol(&pair.to))
+    }
+
+    // The asset as Horizon names it
+    fn stellar_asset(&self, asset: &str) -> Result<StellarAsset, String> {
+        let symbol = self.symbols.get(asset).ok_or_else(|| format!("no Stellar asset configured for {}", asset))?;
+        if symbol == "native" {
+            return Ok(StellarAsset::Native);
+        }
+        match symbol.split_once(':') {
+            Some((code, issuer)) if (1..=12).contains(&code.len()) && issuer.len() == 56 => {
+                Ok(StellarAsset::Credit { code: code.to_string(), issuer: issuer.to_string() })
+            }
+            _ => Err(format!("{} is not `native` or CODE:ISSUER", symbol)),
+        }
+    }
+}
+
+enum StellarAsset {
+    Native,
+    Credit { code: String, issuer: String },
+}
+
+impl StellarAsset {
+    // Query parameters of an order book side, e.g. `selling`
+    fn params(&self, side: &str) -> Vec<(String, String)> {
+        match self {
+            StellarAsset::Native => vec![(format!("{}_asset_type", side), "native".to_string())],
+            StellarAsset::Credit { code, issuer } => {
+                let kind = if code.len() <= 4 { "credit_alphanum4" } else { "credit_alphanum12" };
+                vec![
+                    (format!("{}_asset_type", side), kind.to_string()),
+                    (format!("{}_asset_code", side), code.clone()),
+                    (format!("{}_asset_issuer", side), issuer.clone()),
+                ]
+            }
+        }
+    }
+
+    // Canonical form used in liquidity pool reserves
+    fn canonical(&self) -> String {
+        match self {
+            StellarAsset::Native => "native".to_string(),
+            StellarAsset::Credit { code, issuer } => format!("{}:{}", code, issuer),
+        }
+    }
+}
+
+fn number(value: Option<&serde_json::Value>) -> Option<f64> {
+    match value? {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+// Aggregated price of one whole `from` in `to`
+#[derive(Clone, Debug, Serialize)]
+pub struct OraclePrice {
+    pub from: String,
+    pub to: String,
+    pub price: f64,
+    pub sources: Vec<String>,
+    pub at: DateTime<Utc>,
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] })
+}
+
+// Polls the configured sources, takes the median per pair, and sets it on the shared converter
+#[derive(Clone)]
+pub struct PriceOracle {
+    config: Arc<OracleConfig>,
+    client: reqwest::Client,
+    converter: StablecoinConverter,
+    latest: Arc<RwLock<BTreeMap<(String, String), OraclePrice>>>,
+}
+
+impl PriceOracle {
+    pub fn new(config: OracleConfig, converter: StablecoinConverter) -> Result<Self, String> {
+        if config.scale == 0 {
+            return Err("oracle.scale must be positive".to_string());
+        }
+        for source in &config.sources {
+            reqwest::Url::parse(&source.url.replace(['{', '}'], "")).map_err(|e| format!("oracle source {}: invalid url: {}", source.name, e))?;
+            match source.kind {
+                SourceKind::Http if source.pointer.is_empty() => return Err(format!("oracle source {}: pointer is required", source.name)),
+                SourceKind::Http => {}
+                SourceKind::HorizonOrderBook | SourceKind::HorizonLiquidityPool => {
+                    for pair in config.pairs.iter().filter(|p| source.quotes(p)) {
+                        for asset in [&pair.from, &pair.to] {
+                            source.stellar_asset(asset).map_err(|e| format!("oracle source {}: {}", source.name, e))?;
+                        }
+                    }
+                }
+            }
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .user_agent("pi-supernode")
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(PriceOracle { config: Arc::new(config), client, converter, latest: Arc::default() })
+    }
+
+    async fn get_json(&self, request: reqwest::RequestBuilder) -> Result<serde_json::Value, String> {
+        let response = deadline::call("oracle", Duration::from_secs(self.config.timeout_secs), request.send())
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("status {}", response.status()));
+        }
+        response.json().await.map_err(|e| e.to_string())
+    }
+
+    async fn fetch(&self, source: &OracleSource, pair: &OraclePair) -> Result<f64, String> {
+        let price = match source.kind {
+            SourceKind::Http => {
+                let body = self.get_json(self.client.get(source.fill(&source.url, pair))).await?;
+                let pointer = source.fill(&source.pointer, pair);
+                number(body.pointer(&pointer)).ok_or_else(|| format!("no price at {}", pointer))?
+            }
+            SourceKind::HorizonOrderBook => self.order_book_mid(source, pair).await?,
+            SourceKind::HorizonLiquidityPool => self.pool_price(source, pair).await?,
+        };
+        if !price.is_finite() || price <= 0.0 {
+            return Err(format!("implausible price {}", price));
+        }
+        Ok(price)
+    }
+
+    // Mid of the best bid and ask for `from` priced in `to`; a one-sided book has no price
+    async fn order_book_mid(&self, source: &OracleSource, pair: &OraclePair) -> Result<f64, String> {
+        let mut query = source.stellar_asset(&pair.from)?.params("selling");
+        query.extend(source.stellar_asset(&pair.to)?.params("buying"));
+        query.push(("limit".to_string(), "1".to_string()));
+        let url = format!("{}/order_book", source.url.trim_end_matches('/'));
+        let book = self.get_json(self.client.get(url).query(&query)).await?;
+        let bid = number(book.pointer("/bids/0/price")).ok_or("no bids")?;
+        let ask = number(book.pointer("/asks/0/price")).ok_or("no asks")?;
+        Ok((bid + ask) / 2.0)
+    }
+
+    // `to` reserve over `from` reserve of the deepest pool holding both
+    async fn pool_price(&self, source: &OracleSource, pair: &OraclePair) -> Result<f64, String> {
+        let (from, to) = (source.stellar_asset(&pair.from)?.canonical(), source.stellar_asset(&pair.to)?.canonical());
+        let url = format!("{}/liquidity_pools", source.url.trim_end_matches('/'));
+        let body = self.get_json(self.client.get(url).query(&[("reserves", format!("{},{}", from, to))])).await?;
+        let reserve = |pool: &serde_json::Value, asset: &str| {
+            pool["reserves"].as_array()?.iter().find(|r| r["asset"] == asset).and_then(|r| number(r.get("amount")))
+        };
+        let pools = body.pointer("/_embedded/records").and_then(|r| r.as_array()).ok_or("malformed liquidity pool response")?;
+        let (from_reserve, to_reserve) = pools
+            .iter()
+            .filter_map(|pool| reserve(pool, &from).zip(reserve(pool, &to)))
+            .filter(|(f, t)| *f > 0.0 && *t > 0.0)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .ok_or("no liquidity pool holds both assets")?;
+        Ok(to_reserve / from_reserve)
+    }
+
+    // Median of the sources that answered, after dropping those too far from the first median
+    async fn aggregate(&self, pair: &OraclePair) -> Result<OraclePrice, String> {
+        let sources: Vec<&OracleSource> = self.config.sources.iter().filter(|s| s.quotes(pair)).collect();
+        let fetched = futures::future::join_all(sources.iter().map(|s| self.fetch(s, pair))).await;
+        let mut quotes = Vec::new();
+        for (source, result) in sources.iter().zip(fetched) {
+            match result {
+                Ok(price) => quotes.push((source.name.as_str(), price)),
+                Err(e) => {
+                    metrics::ORACLE_FETCHES.with_label_values(&[&source.name, "error"]).inc();
+                    warn!(source = %source.name, from = %pair.from, to = %pair.to, error = %e, "oracle fetch failed");
+                }
+            }
+        }
+        let center = median(&mut quotes.iter().map(|(_, p)| *p).collect::<Vec<_>>()).ok_or("no source answered")?;
+        let (kept, outliers): (Vec<_>, Vec<_>) = quotes.into_iter().partition(|(_, p)| ((p - center) / center).abs() <= self.config.max_deviation);
+        for (name, price) in outliers {
+            metrics::ORACLE_FETCHES.with_label_values(&[name, "outlier"]).inc();
+            warn!(source = name, price, median = center, from = %pair.from, to = %pair.to, "oracle quote dropped as an outlier");
+        }
+        for (name, _) in &kept {
+            metrics::ORACLE_FETCHES.with_label_values(&[name, "ok"]).inc();
+        }
+        if kept.len() < self.config.min_sources.max(1) {
+            return Err(format!("{} usable quotes, {} needed", kept.len(), self.config.min_sources));
+        }
+        let price = median(&mut kept.iter().map(|(_, p)| *p).collect::<Vec<_>>()).ok_or("no usable quote")?;
+        Ok(OraclePrice {
+            from: pair.from.clone(),
+            to: pair.to.clone(),
+            price,
+            sources: kept.into_iter().map(|(name, _)| name.to_string()).collect(),
+            at: Utc::now(),
+        })
+    }
+
+    // Whole-token price to a smallest-unit rate: `numerator` units of `to` per `denominator` units of `from`
+    fn rate(&self, pair: &OraclePair, price: f64) -> Result<(u128, u128), String> {
+        let (from_scale, to_scale) = (10u128.checked_pow(pair.from_decimals), 10u128.checked_pow(pair.to_decimals));
+        let (from_scale, to_scale) = from_scale.zip(to_scale).ok_or("decimals out of range")?;
+        let numerator = (price * self.config.scale as f64).round() as u128;
+        let numerator = numerator.checked_mul(to_scale).ok_or("rate overflows")?;
+        let denominator = self.config.scale.checked_mul(from_scale).ok_or("rate overflows")?;
+        if numerator == 0 {
+            return Err(format!("price {} rounds to zero at scale {}", price, self.config.scale));
+        }
+        Ok((numerator, denominator))
+    }
+
+    // One round over every pair; a pair that cannot be priced keeps its previous rate
+    pub async fn refresh(&self) {
+        for pair in &self.config.pairs {
+            let price = match self.aggregate(pair).await {
+                Ok(price) => price,
+                Err(e) => {
+                    warn!(from = %pair.from, to = %pair.to, error = %e, "oracle rate not updated");
+                    continue;
+                }
+            };
+            let result = self
+                .rate(pair, price.price)
+                .and_then(|(n, d)| self.converter.set_rate(&pair.from, &pair.to, n, d).map_err(|e| e.to_string()));
+            match result {
+                Ok(_) => {
+                    debug!(from = %pair.from, to = %pair.to, price = price.price, sources = price.sources.len(), "oracle rate updated");
+                    self.latest.write().unwrap().insert((pair.from.clone(), pair.to.clone()), price);
+                }
+                Err(e) => warn!(from = %pair.from, to = %pair.to, error = %e, "oracle rate rejected"),
+            }
+        }
+    }
+
+    // Latest aggregated prices, with the sources each came from
+    pub fn prices(&self) -> Vec<OraclePrice> {
+        self.latest.read().unwrap().values().cloned().collect()
+    }
+}
+
+// Polls a rate may miss before readiness reports it stale
+const STALE_AFTER_POLLS: u64 = 3;
+
+// Failing while any pair has no price, or one older than `STALE_AFTER_POLLS` polls
+#[async_trait]
+impl HealthCheck for PriceOracle {
+    fn name(&self) -> &str {
+        "oracle"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let stale_after = chrono::Duration::seconds((self.config.poll_interval_secs.max(1) * STALE_AFTER_POLLS) as i64);
+        let latest = self.latest.read().unwrap();
+        for pair in &self.config.pairs {
+            match latest.get(&(pair.from.clone(), pair.to.clone())) {
+                None => return Err(format!("no price for {}/{} yet", pair.from, pair.to)),
+                Some(price) if Utc::now() - price.at > stale_after => {
+                    return Err(format!("{}/{} last priced at {}", pair.from, pair.to, price.at.to_rfc3339()))
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn register(scheduler: &Scheduler, oracle: PriceOracle) {
+    if !oracle.config.enabled {
+        return;
+    }
+    info!(pairs = oracle.config.pairs.len(), sources = oracle.config.sources.len(), "price oracle enabled");
+    scheduler.register(
+        "oracle:refresh",
+        Duration::from_secs(oracle.config.poll_interval_secs.max(1)),
+        Duration::ZERO,
+        Arc::new(move || {
+            let oracle = oracle.clone();
+            Box::pin(async move { oracle.refresh().await })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::ConverterConfig;
+    use crate::server::tests::spawn;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use warp::Filter;
+
+    #[tokio::test]
+    async fn sets_the_median_rate_and_keeps_it_when_sources_fail() {
+        let up = Arc::new(AtomicBool::new(true));
+        let serving = up.clone();
+        let price = warp::path!("price" / String).map(move |source: String| {
+            let price = if source == "b" { "1.50" } else { "1.00" };
+            let body = warp::reply::json(&serde_json::json!({ "price": price }));
+            let status = if serving.load(Ordering::Relaxed) { warp::http::StatusCode::OK } else { warp::http::StatusCode::BAD_GATEWAY };
+            warp::reply::with_status(body, status)
+        });
+        let (addr, shutdown) = spawn(price).await;
+
+        let source = |name: &str| OracleSource {
+            name: name.to_string(),
+            kind: SourceKind::Http,
+            url: format!("http://{}/price/{}", addr, name),
+            pointer: "/price".to_string(),
+            symbols: HashMap::new(),
+            pairs: Vec::new(),
+        };
+        let config = OracleConfig {
+            enabled: true,
+            min_sources: 2,
+            scale: 1_000,
+            pairs: vec![OraclePair { from: "USDC".to_string(), to: "USD".to_string(), from_decimals: 6, to_decimals: 2 }],
+            sources: vec![source("a"), source("b"), source("c")],
+            ..OracleConfig::default()
+        };
+        let converter = StablecoinConverter::new(&ConverterConfig::default()).unwrap();
+        let oracle = PriceOracle::new(config, converter.clone()).unwrap();
+
+        oracle.refresh().await;
+        let rate = converter.rate("USDC", "USD").unwrap();
+        // 1.00 cents-per-token is 100 cents per 10^6 units
+        assert_eq!((rate.numerator, rate.denominator), (1_000 * 100, 1_000 * 1_000_000));
+        assert_eq!(oracle.prices()[0].sources, ["a", "c"], "b is an outlier");
+
+        up.store(false, Ordering::Relaxed);
+        oracle.refresh().await;
+        let kept = converter.rate("USDC", "USD").unwrap();
+        assert_eq!((kept.numerator, kept.updated_at), (rate.numerator, rate.updated_at));
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn prices_from_the_stellar_dex() {
+        const USDC: &str = "USDC:GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN";
+        let book = warp::path!("order_book").map(|| warp::reply::json(&serde_json::json!({ "bids": [{ "price": "0.118" }], "asks": [{ "price": "0.122" }] })));
+        let pools = warp::path!("liquidity_pools").map(|| {
+            let pool = |xlm: &str, usdc: &str| serde_json::json!({ "reserves": [{ "asset": "native", "amount": xlm }, { "asset": USDC, "amount": usdc }] });
+            // The pool with the most XLM is used
+            warp::reply::json(&serde_json::json!({ "_embedded": { "records": [pool("10", "5"), pool("1000", "120")] } }))
+        });
+        let (addr, shutdown) = spawn(book.or(pools)).await;
+
+        let source = |name: &str, kind| OracleSource {
+            name: name.to_string(),
+            kind,
+            url: format!("http://{}", addr),
+            pointer: String::new(),
+            symbols: HashMap::from([("XLM".to_string(), "native".to_string()), ("USDC".to_string(), USDC.to_string())]),
+            pairs: vec!["XLM/USDC".to_string()],
+        };
+        let config = OracleConfig {
+            enabled: true,
+            max_deviation: 0.5,
+            scale: 1_000,
+            pairs: vec![OraclePair { from: "XLM".to_string(), to: "USDC".to_string(), from_decimals: 7, to_decimals: 6 }],
+            sources: vec![source("dex", SourceKind::HorizonOrderBook), source("pool", SourceKind::HorizonLiquidityPool)],
+            ..OracleConfig::default()
+        };
+        let oracle = PriceOracle::new(config, StablecoinConverter::new(&ConverterConfig::default()).unwrap()).unwrap();
+        let pair = &oracle.config.pairs[0];
+        assert_eq!(oracle.fetch(&oracle.config.sources[0], pair).await.unwrap(), 0.12);
+        assert_eq!(oracle.fetch(&oracle.config.sources[1], pair).await.unwrap(), 0.12);
+
+        oracle.refresh().await;
+        assert_eq!(oracle.prices()[0].sources, ["dex", "pool"]);
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn is_unready_until_every_pair_is_priced_recently() {
+        let config = OracleConfig {
+            enabled: true,
+            pairs: vec![OraclePair { from: "USDC".to_string(), to: "USD".to_string(), from_decimals: 6, to_decimals: 2 }],
+            ..OracleConfig::default()
+        };
+        let oracle = PriceOracle::new(config, StablecoinConverter::new(&ConverterConfig::default()).unwrap()).unwrap();
+        assert!(oracle.check().await.unwrap_err().contains("no price"));
+
+        let priced = |at| OraclePrice { from: "USDC".to_string(), to: "USD".to_string(), price: 1.0, sources: Vec::new(), at };
+        let key = ("USDC".to_string(), "USD".to_string());
+        oracle.latest.write().unwrap().insert(key.clone(), priced(Utc::now() - chrono::Duration::seconds(181)));
+        assert!(oracle.check().await.unwrap_err().contains("last priced"));
+        oracle.latest.write().unwrap().insert(key, priced(Utc::now()));
+        assert!(oracle.check().await.is_ok());
+    }
+}
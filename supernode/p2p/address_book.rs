@@ -0,0 +1,135 @@
+use crate::p2p::bandwidth::BandwidthConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+// `p2p` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct PeerConfig {
+    // host:port addresses always tried on startup
+    pub static_peers: Vec<String>,
+
+    // DNS names resolving to bootstrap nodes
+    pub dns_seeds: Vec<String>,
+    pub seed_port: u16,
+
+    pub address_book_path: PathBuf,
+
+    pub bandwidth: BandwidthConfig,
+}
+
+impl Default for PeerConfig {
+    fn default() -> Self {
+        PeerConfig {
+            static_peers: Vec::new(),
+            dns_seeds: Vec::new(),
+            seed_port: 31400,
+            address_book_path: PathBuf::from("data/peers.json"),
+            bandwidth: BandwidthConfig::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum PeerSource {
+    Static,
+    DnsSeed,
+    Gossip,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerEntry {
+    pub addr: SocketAddr,
+    pub source: PeerSource,
+    pub last_seen: Option<u64>,
+    pub failures: u32,
+
+    // Unix seconds before which the peer is not retried
+    pub retry_at: u64,
+}
+
+// Backoff grows from 30s up to 6h for unreachable peers
+const BASE_BACKOFF_SECS: u64 = 30;
+const MAX_BACKOFF_SECS: u64 = 6 * 3600;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+// Known peers persisted across restarts
+pub struct AddressBook {
+    path: PathBuf,
+    peers: HashMap<SocketAddr, PeerEntry>,
+}
+
+impl AddressBook {
+    pub fn load(path: PathBuf) -> Self {
+        let peers = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Vec<PeerEntry>>(&bytes).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| (p.addr, p))
+            .collect();
+        AddressBook { path, peers }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let peers: Vec<&PeerEntry> = self.peers.values().collect();
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&peers)?)?;
+        fs::rename(tmp, &self.path)
+    }
+
+    pub fn add(&mut self, addr: SocketAddr, source: PeerSource) {
+        self.peers.entry(addr).or_insert(PeerEntry { addr, source, last_seen: None, failures: 0, retry_at: 0 });
+    }
+
+    // Add configured static peers and whatever the DNS seeds resolve to
+    pub async fn bootstrap(&mut self, config: &PeerConfig) {
+        for peer in &config.static_peers {
+            match tokio::net::lookup_host(peer.as_str()).await {
+                Ok(addrs) => addrs.for_each(|addr| self.add(addr, PeerSource::Static)),
+                Err(e) => warn!(%peer, error = %e, "invalid static peer"),
+            }
+        }
+        for seed in &config.dns_seeds {
+            match tokio::net::lookup_host((seed.as_str(), config.seed_port)).await {
+                Ok(addrs) => addrs.for_each(|addr| self.add(addr, PeerSource::DnsSeed)),
+                Err(e) => warn!(%seed, error = %e, "DNS seed did not resolve"),
+            }
+        }
+    }
+
+    pub fn mark_seen(&mut self, addr: SocketAddr) {
+        if let Some(peer) = self.peers.get_mut(&addr) {
+            peer.last_seen = Some(now());
+            peer.failures = 0;
+            peer.retry_at = 0;
+        }
+    }
+
+    pub fn mark_failed(&mut self, addr: SocketAddr) {
+        if let Some(peer) = self.peers.get_mut(&addr) {
+            peer.failures += 1;
+            let backoff = BASE_BACKOFF_SECS.saturating_mul(1 << peer.failures.min(16)).min(MAX_BACKOFF_SECS);
+            peer.retry_at = now() + backoff;
+        }
+    }
+
+    // Peers due for a connection attempt, most recently seen first
+    pub fn candidates(&self, limit: usize) -> Vec<SocketAddr> {
+        let now = now();
+        let mut due: Vec<&PeerEntry> = self.peers.values().filter(|p| p.retry_at <= now).collect();
+        due.sort_by_key(|e| std::cmp::Reverse(e.last_seen));
+        due.into_iter().take(limit).map(|p| p.addr).collect()
+    }
+}
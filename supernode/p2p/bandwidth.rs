@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::{Filter, Rejection, Reply};
+
+// Bandwidth caps in bytes per second, 0 means unlimited
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BandwidthConfig {
+    pub max_upload_bps: u64,
+    pub max_download_bps: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    Gossip,
+    Replication,
+    Handshake,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct Usage {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+#[derive(Serialize)]
+pub struct UsageReport {
+    pub by_peer: HashMap<String, Usage>,
+    pub by_protocol: HashMap<Protocol, Usage>,
+}
+
+// Token bucket refilled at the cap rate with one second of burst
+struct Bucket {
+    rate: u64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(rate: u64) -> Self {
+        Bucket { rate, tokens: rate as f64, refilled: Instant::now() }
+    }
+
+    // Delay needed before `bytes` may be sent
+    fn take(&mut self, bytes: u64) -> Duration {
+        if self.rate == 0 {
+            return Duration::ZERO;
+        }
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.refilled).as_secs_f64() * self.rate as f64).min(self.rate as f64);
+        self.refilled = now;
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate as f64)
+        }
+    }
+}
+
+struct State {
+    by_peer: HashMap<SocketAddr, Usage>,
+    by_protocol: HashMap<Protocol, Usage>,
+    upload: Bucket,
+    download: Bucket,
+}
+
+// Per-peer and per-protocol traffic accounting with node-wide caps
+#[derive(Clone)]
+pub struct BandwidthMeter {
+    state: Arc<Mutex<State>>,
+}
+
+impl BandwidthMeter {
+    pub fn new(config: &BandwidthConfig) -> Self {
+        BandwidthMeter {
+            state: Arc::new(Mutex::new(State {
+                by_peer: HashMap::new(),
+                by_protocol: HashMap::new(),
+                upload: Bucket::new(config.max_upload_bps),
+                download: Bucket::new(config.max_download_bps),
+            })),
+        }
+    }
+
+    // Account for a transfer and wait as long as the cap requires before it proceeds
+    pub async fn throttle(&self, peer: SocketAddr, protocol: Protocol, direction: Direction, bytes: u64) {
+        let delay = {
+            let mut state = self.state.lock().unwrap();
+            let peer_usage = state.by_peer.entry(peer).or_default();
+            match direction {
+                Direction::In => peer_usage.bytes_in += bytes,
+                Direction::Out => peer_usage.bytes_out += bytes,
+            }
+            let protocol_usage = state.by_protocol.entry(protocol).or_default();
+            match direction {
+                Direction::In => protocol_usage.bytes_in += bytes,
+                Direction::Out => protocol_usage.bytes_out += bytes,
+            }
+            match direction {
+                Direction::In => state.download.take(bytes),
+                Direction::Out => state.upload.take(bytes),
+            }
+        };
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    pub fn report(&self) -> UsageReport {
+        let state = self.state.lock().unwrap();
+        UsageReport {
+            by_peer: state.by_peer.iter().map(|(addr, u)| (addr.to_string(), u.clone())).collect(),
+            by_protocol: state.by_protocol.clone(),
+        }
+    }
+
+    // GET /v1/network/bandwidth
+    pub fn routes(&self) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let meter = self.clone();
+        warp::path!("v1" / "network" / "bandwidth").and(warp::get()).map(move || warp::reply::json(&meter.report()))
+    }
+}
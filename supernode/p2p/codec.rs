@@ -0,0 +1,90 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Frame layout: magic (2) | version (1) | flags (1) | payload length (4, BE) | payload
+const MAGIC: [u8; 2] = *b"PI";
+const HEADER_LEN: usize = 8;
+
+// Current wire version; decoders accept anything up to this
+pub const PROTOCOL_VERSION: u8 = 1;
+
+const FLAG_ZSTD: u8 = 0b0000_0001;
+
+// Payloads smaller than this are not worth compressing
+const COMPRESS_MIN_BYTES: usize = 256;
+const ZSTD_LEVEL: i32 = 3;
+
+// Frames larger than this are rejected before allocating
+pub const MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum CodecError {
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u8),
+    TooLarge(usize),
+    Compression(std::io::Error),
+    Encoding(postcard::Error),
+}
+
+impl std::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CodecError::Truncated => write!(f, "frame truncated"),
+            CodecError::BadMagic => write!(f, "not a pi-supernode frame"),
+            CodecError::UnsupportedVersion(v) => write!(f, "unsupported protocol version {}", v),
+            CodecError::TooLarge(n) => write!(f, "frame of {} bytes exceeds limit", n),
+            CodecError::Compression(e) => write!(f, "zstd: {}", e),
+            CodecError::Encoding(e) => write!(f, "postcard: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+// Encode a gossip or replication message as a postcard frame, zstd-compressed when large
+pub fn encode<T: Serialize>(message: &T, compress: bool) -> Result<Vec<u8>, CodecError> {
+    let mut payload = postcard::to_allocvec(message).map_err(CodecError::Encoding)?;
+    let mut flags = 0;
+    if compress && payload.len() >= COMPRESS_MIN_BYTES {
+        payload = zstd::encode_all(payload.as_slice(), ZSTD_LEVEL).map_err(CodecError::Compression)?;
+        flags |= FLAG_ZSTD;
+    }
+    if payload.len() > MAX_FRAME_BYTES {
+        return Err(CodecError::TooLarge(payload.len()));
+    }
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(&MAGIC);
+    frame.push(PROTOCOL_VERSION);
+    frame.push(flags);
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+// Decode a frame, returning the sender's protocol version alongside the message
+pub fn decode<T: DeserializeOwned>(frame: &[u8]) -> Result<(u8, T), CodecError> {
+    if frame.len() < HEADER_LEN {
+        return Err(CodecError::Truncated);
+    }
+    if frame[0..2] != MAGIC {
+        return Err(CodecError::BadMagic);
+    }
+    let version = frame[2];
+    if version == 0 || version > PROTOCOL_VERSION {
+        return Err(CodecError::UnsupportedVersion(version));
+    }
+    let flags = frame[3];
+    let len = u32::from_be_bytes([frame[4], frame[5], frame[6], frame[7]]) as usize;
+    if len > MAX_FRAME_BYTES {
+        return Err(CodecError::TooLarge(len));
+    }
+    let payload = frame.get(HEADER_LEN..HEADER_LEN + len).ok_or(CodecError::Truncated)?;
+    let message = if flags & FLAG_ZSTD != 0 {
+        let raw = zstd::decode_all(payload).map_err(CodecError::Compression)?;
+        postcard::from_bytes(&raw).map_err(CodecError::Encoding)?
+    } else {
+        postcard::from_bytes(payload).map_err(CodecError::Encoding)?
+    };
+    Ok((version, message))
+}
@@ -0,0 +1,122 @@
+use crate::p2p::codec::PROTOCOL_VERSION;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+
+// Oldest wire version this build still speaks
+pub const MIN_PROTOCOL_VERSION: u8 = 1;
+
+// Optional protocol feature a node may support
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    ReplicationV2,
+    PqSuites,
+    PolicySync,
+    CompressedFrames,
+}
+
+// Features without which this node will not talk to a peer at all
+pub const REQUIRED: &[Capability] = &[];
+
+// First message on every connection, in both directions
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Hello {
+    pub node_id: String,
+    pub network_id: String,
+    pub software_version: String,
+    pub min_version: u8,
+    pub max_version: u8,
+    pub capabilities: BTreeSet<Capability>,
+    pub required: BTreeSet<Capability>,
+}
+
+impl Hello {
+    pub fn local(node_id: &str, network_id: &str, capabilities: BTreeSet<Capability>) -> Self {
+        Hello {
+            node_id: node_id.to_string(),
+            network_id: network_id.to_string(),
+            software_version: env!("CARGO_PKG_VERSION").to_string(),
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            capabilities,
+            required: REQUIRED.iter().copied().collect(),
+        }
+    }
+}
+
+// What both sides agreed to use for the connection
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Session {
+    pub peer: String,
+    pub version: u8,
+    pub capabilities: BTreeSet<Capability>,
+}
+
+impl Session {
+    pub fn has(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HandshakeError {
+    WrongNetwork { ours: String, theirs: String },
+    NoCommonVersion { ours: (u8, u8), theirs: (u8, u8), peer_software: String },
+    MissingCapability { capability: Capability, missing_on: &'static str },
+}
+
+impl std::fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HandshakeError::WrongNetwork { ours, theirs } => write!(
+                f,
+                "peer is on network `{}` but this node is on `{}`; check network_id in config.yaml or remove the peer",
+                theirs, ours
+            ),
+            HandshakeError::NoCommonVersion { ours, theirs, peer_software } => {
+                let advice = if theirs.1 < ours.0 { "the peer must upgrade" } else { "this node must upgrade" };
+                write!(
+                    f,
+                    "no common protocol version: we speak {}-{}, peer (v{}) speaks {}-{}; {}",
+                    ours.0, ours.1, peer_software, theirs.0, theirs.1, advice
+                )
+            }
+            HandshakeError::MissingCapability { capability, missing_on } => write!(
+                f,
+                "{:?} is required but not supported by the {}; upgrade it or drop the requirement",
+                capability, missing_on
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+// Pick the highest shared version and the intersection of capabilities
+pub fn negotiate(local: &Hello, remote: &Hello) -> Result<Session, HandshakeError> {
+    if local.network_id != remote.network_id {
+        return Err(HandshakeError::WrongNetwork { ours: local.network_id.clone(), theirs: remote.network_id.clone() });
+    }
+    let version = local.max_version.min(remote.max_version);
+    if version < local.min_version.max(remote.min_version) {
+        return Err(HandshakeError::NoCommonVersion {
+            ours: (local.min_version, local.max_version),
+            theirs: (remote.min_version, remote.max_version),
+            peer_software: remote.software_version.clone(),
+        });
+    }
+    if let Some(capability) = local.required.iter().find(|c| !remote.capabilities.contains(c)) {
+        return Err(HandshakeError::MissingCapability { capability: *capability, missing_on: "peer" });
+    }
+    if let Some(capability) = remote.required.iter().find(|c| !local.capabilities.contains(c)) {
+        return Err(HandshakeError::MissingCapability { capability: *capability, missing_on: "local node" });
+    }
+    let capabilities: BTreeSet<Capability> = local.capabilities.intersection(&remote.capabilities).copied().collect();
+    let disabled: Vec<&Capability> = local.capabilities.difference(&capabilities).collect();
+    if !disabled.is_empty() {
+        warn!(peer = %remote.node_id, ?disabled, "peer lacks capabilities, falling back for this connection");
+    }
+    info!(peer = %remote.node_id, version, ?capabilities, "handshake complete");
+    Ok(Session { peer: remote.node_id.clone(), version, capabilities })
+}
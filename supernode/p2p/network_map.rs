@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use warp::{Filter, Rejection, Reply};
+
+// Health summary a peer gossips about itself
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerHealth {
+    pub node_id: String,
+    pub version: String,
+    pub ledger_height: u64,
+    pub ledger_head: String,
+
+    // Peg rate the peer currently sees, as a decimal string
+    pub peg_view: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MapEntry {
+    #[serde(flatten)]
+    pub health: PeerHealth,
+    pub latency_ms: u64,
+    pub reported_at: u64,
+
+    // Ledger head or peg view differs from the majority
+    pub diverged: bool,
+}
+
+#[derive(Serialize)]
+pub struct NetworkMap {
+    pub peers: Vec<MapEntry>,
+    pub majority_head: Option<String>,
+    pub majority_peg: Option<String>,
+    pub versions: HashMap<String, usize>,
+}
+
+// Reports older than this are dropped from the map
+const STALE_AFTER_SECS: u64 = 300;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn majority<'a>(values: impl Iterator<Item = &'a String>) -> Option<String> {
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+    for v in values {
+        *counts.entry(v).or_insert(0) += 1;
+    }
+    counts.into_iter().max_by_key(|(_, c)| *c).map(|(v, _)| v.clone())
+}
+
+// Node id -> (latest health report, round-trip ms, received at in unix seconds)
+type Reports = HashMap<String, (PeerHealth, u64, u64)>;
+
+#[derive(Clone, Default)]
+pub struct NetworkMapStore {
+    reports: Arc<RwLock<Reports>>,
+}
+
+impl NetworkMapStore {
+    // Record a health report received from a peer along with the measured round-trip time
+    pub fn record(&self, health: PeerHealth, latency_ms: u64) {
+        self.reports.write().unwrap().insert(health.node_id.clone(), (health, latency_ms, now()));
+    }
+
+    pub fn map(&self) -> NetworkMap {
+        let cutoff = now().saturating_sub(STALE_AFTER_SECS);
+        let mut reports = self.reports.write().unwrap();
+        reports.retain(|_, (_, _, at)| *at >= cutoff);
+        let majority_head = majority(reports.values().map(|(h, _, _)| &h.ledger_head));
+        let majority_peg = majority(reports.values().map(|(h, _, _)| &h.peg_view));
+        let mut versions = HashMap::new();
+        let peers = reports
+            .values()
+            .map(|(health, latency_ms, at)| {
+                *versions.entry(health.version.clone()).or_insert(0) += 1;
+                let diverged = majority_head.as_ref() != Some(&health.ledger_head) || majority_peg.as_ref() != Some(&health.peg_view);
+                MapEntry { health: health.clone(), latency_ms: *latency_ms, reported_at: *at, diverged }
+            })
+            .collect();
+        NetworkMap { peers, majority_head, majority_peg, versions }
+    }
+
+    // GET /v1/network/map
+    pub fn routes(&self) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let store = self.clone();
+        warp::path!("v1" / "network" / "map").and(warp::get()).map(move || warp::reply::json(&store.map()))
+    }
+}
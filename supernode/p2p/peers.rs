@@ -0,0 +1,423 @@
+use crate::api::auth::Auth;
+use crate::p2p::address_book::AddressBook;
+use crate::p2p::bandwidth::{BandwidthConfig, BandwidthMeter, Direction, Protocol};
+use crate::p2p::codec::{self, MAX_FRAME_BYTES};
+use crate::p2p::handshake::{negotiate, Capability, Hello, Session};
+use crate::p2p::network_map::{NetworkMapStore, PeerHealth};
+use crate::p2p::policy_gossip::{GossipSender, KeyRevocation, PolicyBundle};
+use crate::runtime::scheduler::Scheduler;
+use crate::server::PeerAddr;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use warp::http::StatusCode;
+use warp::hyper::body::{Bytes, HttpBody};
+use warp::{Filter, Rejection, Reply};
+
+// Features this build implements on the wire
+const CAPABILITIES: &[Capability] = &[Capability::CompressedFrames];
+
+const FRAME_CONTENT_TYPE: &str = "application/x-pi-frame";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How often peers are greeted, and how many per round
+const ROUND: Duration = Duration::from_secs(60);
+const MAX_PEERS_PER_ROUND: usize = 16;
+
+// This node's current health, as exchanged with peers
+pub type HealthFn = Arc<dyn Fn() -> PeerHealth + Send + Sync>;
+
+#[derive(Serialize)]
+struct PeerSession {
+    addr: SocketAddr,
+    #[serde(flatten)]
+    session: Session,
+}
+
+// Links to other nodes, spoken as codec frames over each node's HTTP port
+#[derive(Clone)]
+pub struct Peers {
+    local: Arc<Hello>,
+    scheme: &'static str,
+    client: reqwest::Client,
+    sessions: Arc<RwLock<BTreeMap<SocketAddr, Session>>>,
+    health: Option<(HealthFn, NetworkMapStore)>,
+    meter: BandwidthMeter,
+
+    // Keys whose revocation was already verified and relayed, so notices stop spreading
+    revoked: Arc<Mutex<HashSet<[u8; 32]>>>,
+}
+
+impl Peers {
+    pub fn new(node_id: &str, network_id: &str, tls: bool) -> Self {
+        let capabilities: BTreeSet<Capability> = CAPABILITIES.iter().copied().collect();
+        Peers {
+            local: Arc::new(Hello::local(node_id, network_id, capabilities)),
+            scheme: if tls { "https" } else { "http" },
+            client: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().expect("static client config"),
+            sessions: Arc::new(RwLock::new(BTreeMap::new())),
+            health: None,
+            meter: BandwidthMeter::new(&BandwidthConfig::default()),
+            revoked: Arc::default(),
+        }
+    }
+
+    // Account all peer traffic, both directions, to `meter` and hold it to its caps
+    pub fn with_meter(mut self, meter: BandwidthMeter) -> Self {
+        self.meter = meter;
+        self
+    }
+
+    // Exchange health with every peer greeted and keep their reports, with round-trip times, in `map`
+    pub fn with_health(mut self, status: HealthFn, map: NetworkMapStore) -> Self {
+        self.health = Some((status, map));
+        self
+    }
+
+    async fn post<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        addr: SocketAddr,
+        path: &str,
+        protocol: Protocol,
+        message: &Req,
+        compress: bool,
+    ) -> Result<Resp, String> {
+        let body = codec::encode(message, compress).map_err(|e| e.to_string())?;
+        self.meter.throttle(addr, protocol, Direction::Out, body.len() as u64).await;
+        let response = self
+            .client
+            .post(format!("{}://{}{}", self.scheme, addr, path))
+            .header(reqwest::header::CONTENT_TYPE, FRAME_CONTENT_TYPE)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+        self.meter.throttle(addr, protocol, Direction::In, bytes.len() as u64).await;
+        if !status.is_success() {
+            return Err(format!("{} answered {}: {}", addr, status, String::from_utf8_lossy(&bytes)));
+        }
+        codec::decode(&bytes).map(|(_, message)| message).map_err(|e| e.to_string())
+    }
+
+    // Exchange hellos with `addr` and keep the session both sides agreed on
+    pub async fn connect(&self, addr: SocketAddr) -> Result<Session, String> {
+        let remote: Hello = self.post(addr, "/p2p/hello", Protocol::Handshake, &*self.local, false).await?;
+        match negotiate(&self.local, &remote) {
+            Ok(session) => {
+                self.sessions.write().unwrap().insert(addr, session.clone());
+                Ok(session)
+            }
+            Err(e) => {
+                self.sessions.write().unwrap().remove(&addr);
+                warn!(%addr, error = %e, "peer refused");
+                Err(e.to_string())
+            }
+        }
+    }
+
+    // Connect to due candidates, recording who answered so unreachable peers back off
+    pub async fn round(&self, book: &Mutex<AddressBook>) {
+        let candidates = book.lock().unwrap().candidates(MAX_PEERS_PER_ROUND);
+        for addr in candidates {
+            let result = match self.connect(addr).await {
+                Ok(session) => self.exchange_health(addr, &session).await,
+                Err(e) => Err(e),
+            };
+            let mut book = book.lock().unwrap();
+            match result {
+                Ok(_) => book.mark_seen(addr),
+                Err(e) => {
+                    warn!(%addr, error = %e, "handshake failed");
+                    self.sessions.write().unwrap().remove(&addr);
+                    book.mark_failed(addr);
+                }
+            }
+        }
+        if let Err(e) = book.lock().unwrap().save() {
+            warn!(error = %e, "could not save the address book");
+        }
+    }
+
+    async fn exchange_health(&self, addr: SocketAddr, session: &Session) -> Result<(), String> {
+        let Some((status, map)) = &self.health else { return Ok(()) };
+        let started = Instant::now();
+        let theirs: PeerHealth = self.post(addr, "/p2p/health", Protocol::Gossip, &status(), session.has(Capability::CompressedFrames)).await?;
+        map.record(theirs, started.elapsed().as_millis() as u64);
+        Ok(())
+    }
+
+    // Verify a revocation notice and pass it on to every session the first time it is seen
+    pub fn receive_revocation(&self, notice: KeyRevocation) -> Result<bool, String> {
+        if self.revoked.lock().unwrap().contains(&notice.revoked) {
+            return Ok(false);
+        }
+        notice.verify().map_err(|e| format!("bad key revocation signature: {}", e))?;
+        if !self.revoked.lock().unwrap().insert(notice.revoked) {
+            return Ok(false);
+        }
+        warn!(revoked = %hex::encode(notice.revoked), successor = %hex::encode(notice.successor), "peer key revoked");
+        self.broadcast_revocation(&notice);
+        Ok(true)
+    }
+
+    pub fn is_revoked(&self, public_key: &[u8; 32]) -> bool {
+        self.revoked.lock().unwrap().contains(public_key)
+    }
+
+    pub fn sessions(&self) -> Vec<(SocketAddr, Session)> {
+        self.sessions.read().unwrap().iter().map(|(addr, s)| (*addr, s.clone())).collect()
+    }
+
+    // POST /p2p/hello answers with this node's hello, or 409 saying why the peer is incompatible;
+    // POST /p2p/health answers with this node's health; POST /p2p/revocation takes a signed key revocation; GET /admin/peers lists the sessions this node opened
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let local = self.local.clone();
+        let hello = self.exchange("hello", Protocol::Handshake, move |body| {
+            let remote: Hello = match codec::decode(&body) {
+                Ok((_, hello)) => hello,
+                Err(e) => return Some(warp::reply::with_status(e.to_string(), StatusCode::BAD_REQUEST).into_response()),
+            };
+            Some(match negotiate(&local, &remote) {
+                Ok(_) => frame(&*local),
+                Err(e) => warp::reply::with_status(e.to_string(), StatusCode::CONFLICT).into_response(),
+            })
+        });
+
+        let status = self.health.as_ref().map(|(status, _)| status.clone());
+        let health = self.exchange("health", Protocol::Gossip, move |body| {
+            let status = status.as_ref()?;
+            Some(match codec::decode::<PeerHealth>(&body) {
+                Ok(_) => frame(&status()),
+                Err(e) => warp::reply::with_status(e.to_string(), StatusCode::BAD_REQUEST).into_response(),
+            })
+        });
+
+        let peers = self.clone();
+        let revocation = self.exchange("revocation", Protocol::Gossip, move |body| {
+            Some(match codec::decode::<KeyRevocation>(&body).map_err(|e| e.to_string()).and_then(|(_, notice)| peers.receive_revocation(notice)) {
+                Ok(new) => frame(&new),
+                Err(e) => warp::reply::with_status(e, StatusCode::BAD_REQUEST).into_response(),
+            })
+        });
+
+        let peers = self.clone();
+        let list = warp::path!("admin" / "peers").and(warp::get()).and(auth.authorized()).map(move |_| {
+            let sessions: Vec<PeerSession> = peers.sessions().into_iter().map(|(addr, session)| PeerSession { addr, session }).collect();
+            warp::reply::json(&sessions)
+        });
+
+        hello.or(health).or(revocation).or(list)
+    }
+
+    // POST /p2p/<name> carrying a frame; `handler` returning `None` means the route is not served.
+    // Both directions count against the calling peer
+    fn exchange<H>(&self, name: &'static str, protocol: Protocol, handler: H) -> impl Filter<Extract = (warp::reply::Response,), Error = Rejection> + Clone
+    where
+        H: Fn(Bytes) -> Option<warp::reply::Response> + Clone + Send + Sync + 'static,
+    {
+        let meter = self.meter.clone();
+        warp::path("p2p")
+            .and(warp::path(name))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::ext::optional::<PeerAddr>())
+            .and(warp::body::content_length_limit(MAX_FRAME_BYTES as u64))
+            .and(warp::body::bytes())
+            .and_then(move |remote: Option<PeerAddr>, body: Bytes| {
+                let (meter, handler) = (meter.clone(), handler.clone());
+                async move {
+                    let received = body.len() as u64;
+                    let response = handler(body).ok_or_else(warp::reject::not_found)?;
+                    if let Some(PeerAddr(remote)) = remote {
+                        meter.throttle(remote, protocol, Direction::In, received).await;
+                        let sent = response.body().size_hint().exact().unwrap_or(0);
+                        meter.throttle(remote, protocol, Direction::Out, sent).await;
+                    }
+                    Ok::<_, Rejection>(response)
+                }
+            })
+    }
+}
+
+impl GossipSender for Peers {
+    // Policy bundles travel only on sessions that negotiated policy sync, which this build does not advertise
+    fn broadcast(&self, _bundle: &PolicyBundle) {
+        debug!("policy sync is not enabled on any session, bundle not gossiped");
+    }
+
+    fn broadcast_revocation(&self, notice: &KeyRevocation) {
+        self.revoked.lock().unwrap().insert(notice.revoked);
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("no runtime to gossip the key revocation on");
+            return;
+        };
+        for (addr, session) in self.sessions() {
+            let (peers, notice) = (self.clone(), notice.clone());
+            runtime.spawn(async move {
+                let compress = session.has(Capability::CompressedFrames);
+                if let Err(e) = peers.post::<_, bool>(addr, "/p2p/revocation", Protocol::Gossip, &notice, compress).await {
+                    warn!(%addr, error = %e, "could not deliver the key revocation");
+                }
+            });
+        }
+    }
+}
+
+// Greet the peers the address book has due every round, so sessions follow peers that restart or upgrade
+pub fn register(scheduler: &Scheduler, peers: Peers, book: AddressBook) {
+    let book = Arc::new(Mutex::new(book));
+    scheduler.register(
+        "p2p:handshake",
+        ROUND,
+        Duration::ZERO,
+        Arc::new(move || {
+            let (peers, book) = (peers.clone(), book.clone());
+            Box::pin(async move { peers.round(&book).await })
+        }),
+    );
+}
+
+fn frame<T: Serialize>(message: &T) -> warp::reply::Response {
+    match codec::encode(message, false) {
+        Ok(body) => warp::reply::with_header(body, "content-type", FRAME_CONTENT_TYPE).into_response(),
+        Err(e) => warp::reply::with_status(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::AuthConfig;
+    use crate::p2p::address_book::{PeerConfig, PeerSource};
+    use crate::server::tests::spawn;
+
+    #[tokio::test]
+    async fn peers_on_the_same_network_agree_on_a_session() {
+        let remote = Peers::new("remote", "mainnet", false);
+        let (addr, token) = spawn(remote.routes(&Auth::new(&AuthConfig::default()).unwrap())).await;
+
+        let local = Peers::new("local", "mainnet", false);
+        let session = local.connect(addr).await.unwrap();
+        assert_eq!(session.peer, "remote");
+        assert!(session.has(Capability::CompressedFrames));
+        assert_eq!(local.sessions().len(), 1);
+        token.cancel();
+    }
+
+    #[tokio::test]
+    async fn peers_on_another_network_are_refused_with_the_reason() {
+        let remote = Peers::new("remote", "testnet", false);
+        let (addr, token) = spawn(remote.routes(&Auth::new(&AuthConfig::default()).unwrap())).await;
+
+        let local = Peers::new("local", "mainnet", false);
+        let error = local.connect(addr).await.unwrap_err();
+        assert!(error.contains("409"), "{}", error);
+        assert!(error.contains("network"), "{}", error);
+        assert!(local.sessions().is_empty());
+        token.cancel();
+    }
+
+    fn health(node_id: &'static str, head: &'static str) -> HealthFn {
+        Arc::new(move || PeerHealth {
+            node_id: node_id.to_string(),
+            version: "1.0.0".to_string(),
+            ledger_height: 7,
+            ledger_head: head.to_string(),
+            peg_view: "PI/USD=314159/10000000".to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn rounds_put_peer_health_on_the_network_map() {
+        let remote = Peers::new("remote", "mainnet", false).with_health(health("remote", "ab"), NetworkMapStore::default());
+        let (addr, token) = spawn(remote.routes(&Auth::new(&AuthConfig::default()).unwrap())).await;
+
+        let path = std::env::temp_dir().join(format!("peers-map-{}.json", std::process::id()));
+        let mut book = AddressBook::load(path.clone());
+        book.add(addr, PeerSource::Static);
+        let book = Mutex::new(book);
+        let map = NetworkMapStore::default();
+        let local = Peers::new("local", "mainnet", false).with_health(health("local", "ab"), map.clone());
+        local.round(&book).await;
+
+        let peers = map.map().peers;
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].health.node_id, "remote");
+        assert_eq!(map.map().majority_head.as_deref(), Some("ab"));
+        let _ = std::fs::remove_file(path);
+        token.cancel();
+    }
+
+    #[tokio::test]
+    async fn peer_traffic_is_metered_on_both_ends() {
+        let served = BandwidthMeter::new(&BandwidthConfig::default());
+        let remote = Peers::new("remote", "mainnet", false).with_meter(served.clone());
+        let (addr, token) = spawn(remote.routes(&Auth::new(&AuthConfig::default()).unwrap())).await;
+
+        let sent = BandwidthMeter::new(&BandwidthConfig::default());
+        let local = Peers::new("local", "mainnet", false).with_meter(sent.clone());
+        local.connect(addr).await.unwrap();
+
+        let usage = &sent.report().by_peer[&addr.to_string()];
+        assert!(usage.bytes_out > 0 && usage.bytes_in > 0);
+        let handshake = &served.report().by_protocol[&Protocol::Handshake];
+        assert_eq!((handshake.bytes_in, handshake.bytes_out), (usage.bytes_out, usage.bytes_in));
+        token.cancel();
+    }
+
+    #[tokio::test]
+    async fn revocations_are_verified_and_relayed_once() {
+        let remote = Peers::new("remote", "mainnet", false);
+        let (addr, token) = spawn(remote.routes(&Auth::new(&AuthConfig::default()).unwrap())).await;
+        let local = Peers::new("local", "mainnet", false);
+        local.connect(addr).await.unwrap();
+
+        let old = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let successor = ed25519_dalek::SigningKey::from_bytes(&[2; 32]);
+        let notice = KeyRevocation::sign(&old, &successor, 1_700_000_000);
+        let mut forged = notice.clone();
+        forged.at += 1;
+        let error = local.post::<_, bool>(addr, "/p2p/revocation", Protocol::Gossip, &forged, false).await.unwrap_err();
+        assert!(error.contains("signature"), "{}", error);
+
+        local.broadcast_revocation(&notice);
+        for _ in 0..50 {
+            if remote.is_revoked(&notice.revoked) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(remote.is_revoked(&notice.revoked));
+        assert_eq!(remote.receive_revocation(notice), Ok(false));
+        token.cancel();
+    }
+
+    #[tokio::test]
+    async fn rounds_back_off_unreachable_peers_and_persist_the_book() {
+        let remote = Peers::new("remote", "mainnet", false);
+        let (up, token) = spawn(remote.routes(&Auth::new(&AuthConfig::default()).unwrap())).await;
+        let down = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let path = std::env::temp_dir().join(format!("peers-{}.json", std::process::id()));
+        let config = PeerConfig { static_peers: vec![up.to_string(), down.to_string()], ..PeerConfig::default() };
+        let mut book = AddressBook::load(path.clone());
+        book.bootstrap(&config).await;
+        let book = Mutex::new(book);
+
+        let local = Peers::new("local", "mainnet", false);
+        local.round(&book).await;
+        assert_eq!(local.sessions().into_iter().map(|(addr, _)| addr).collect::<Vec<_>>(), vec![up]);
+        assert_eq!(book.lock().unwrap().candidates(16), vec![up]);
+
+        let saved = AddressBook::load(path.clone());
+        assert_eq!(saved.candidates(16), vec![up]);
+        let _ = std::fs::remove_file(path);
+        token.cancel();
+    }
+}
@@ -0,0 +1,183 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tracing::{info, warn};
+
+// Signed bundle of policy rules published by an operator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PolicyBundle {
+    pub version: u64,
+    pub publisher: [u8; 32],
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl PolicyBundle {
+    fn signed_bytes(version: u64, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = b"pi-supernode/policy-bundle/v1".to_vec();
+        bytes.extend_from_slice(&version.to_be_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    pub fn sign(key: &SigningKey, version: u64, payload: Vec<u8>) -> Self {
+        let signature = key.sign(&Self::signed_bytes(version, &payload));
+        PolicyBundle { version, publisher: key.verifying_key().to_bytes(), payload, signature: signature.to_bytes().to_vec() }
+    }
+
+    pub fn verify(&self) -> Result<(), String> {
+        let key = VerifyingKey::from_bytes(&self.publisher).map_err(|e| e.to_string())?;
+        let signature = Signature::from_slice(&self.signature).map_err(|e| e.to_string())?;
+        key.verify(&Self::signed_bytes(self.version, &self.payload), &signature).map_err(|e| e.to_string())
+    }
+}
+
+// Announces that a node key is compromised; signed by the revoked key, which anyone holding it can do but
+// which only ever withdraws trust, and by the successor to bind it to the revocation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyRevocation {
+    pub revoked: [u8; 32],
+    pub successor: [u8; 32],
+    pub at: i64,
+    pub revoked_signature: Vec<u8>,
+    pub successor_signature: Vec<u8>,
+}
+
+impl KeyRevocation {
+    fn signed_bytes(revoked: &[u8; 32], successor: &[u8; 32], at: i64) -> Vec<u8> {
+        let mut bytes = b"pi-supernode/key-revocation/v1".to_vec();
+        bytes.extend_from_slice(revoked);
+        bytes.extend_from_slice(successor);
+        bytes.extend_from_slice(&at.to_be_bytes());
+        bytes
+    }
+
+    pub fn sign(revoked: &SigningKey, successor: &SigningKey, at: i64) -> Self {
+        let (old, new) = (revoked.verifying_key().to_bytes(), successor.verifying_key().to_bytes());
+        let message = Self::signed_bytes(&old, &new, at);
+        KeyRevocation {
+            revoked: old,
+            successor: new,
+            at,
+            revoked_signature: revoked.sign(&message).to_bytes().to_vec(),
+            successor_signature: successor.sign(&message).to_bytes().to_vec(),
+        }
+    }
+
+    pub fn verify(&self) -> Result<(), String> {
+        let message = Self::signed_bytes(&self.revoked, &self.successor, self.at);
+        for (key, signature) in [(&self.revoked, &self.revoked_signature), (&self.successor, &self.successor_signature)] {
+            let key = VerifyingKey::from_bytes(key).map_err(|e| e.to_string())?;
+            let signature = Signature::from_slice(signature).map_err(|e| e.to_string())?;
+            key.verify(&message, &signature).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+// What to do with bundles from a trusted publisher
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustMode {
+    AutoApply,
+    Stage,
+}
+
+// Forwards bundles to connected peers
+pub trait GossipSender: Send + Sync {
+    fn broadcast(&self, bundle: &PolicyBundle);
+    fn broadcast_revocation(&self, notice: &KeyRevocation);
+}
+
+// Applies an accepted bundle to the running node
+pub trait PolicyApplier: Send + Sync {
+    fn apply(&self, bundle: &PolicyBundle) -> Result<(), String>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Received {
+    Applied,
+    Staged,
+    Duplicate,
+}
+
+pub struct PolicyGossip {
+    // Publisher key -> trust mode, from config
+    trust_list: HashMap<[u8; 32], TrustMode>,
+    latest_version: u64,
+    staged: HashMap<u64, PolicyBundle>,
+
+    // Keys whose revocation was already handled, so each notice is forwarded once
+    revoked: HashSet<[u8; 32]>,
+    sender: Box<dyn GossipSender>,
+    applier: Box<dyn PolicyApplier>,
+}
+
+impl PolicyGossip {
+    pub fn new(trust_list: HashMap<[u8; 32], TrustMode>, sender: Box<dyn GossipSender>, applier: Box<dyn PolicyApplier>) -> Self {
+        PolicyGossip { trust_list, latest_version: 0, staged: HashMap::new(), revoked: HashSet::new(), sender, applier }
+    }
+
+    // Publish a locally signed bundle to the network
+    pub fn publish(&mut self, bundle: PolicyBundle) -> Result<Received, String> {
+        self.receive(bundle)
+    }
+
+    // Handle a bundle from a peer: verify, check trust, apply or stage, then forward
+    pub fn receive(&mut self, bundle: PolicyBundle) -> Result<Received, String> {
+        if bundle.version <= self.latest_version || self.staged.contains_key(&bundle.version) {
+            return Ok(Received::Duplicate);
+        }
+        bundle.verify().map_err(|e| format!("bad policy bundle signature: {}", e))?;
+        let mode = *self.trust_list.get(&bundle.publisher).ok_or_else(|| {
+            warn!(publisher = %hex::encode(bundle.publisher), "policy bundle from untrusted publisher");
+            "publisher is not in the trust list".to_string()
+        })?;
+        self.sender.broadcast(&bundle);
+        match mode {
+            TrustMode::AutoApply => {
+                self.applier.apply(&bundle)?;
+                self.latest_version = bundle.version;
+                info!(version = bundle.version, "policy bundle applied");
+                Ok(Received::Applied)
+            }
+            TrustMode::Stage => {
+                info!(version = bundle.version, "policy bundle staged for approval");
+                self.staged.insert(bundle.version, bundle);
+                Ok(Received::Staged)
+            }
+        }
+    }
+
+    // A valid revocation removes the revoked publisher from the trust list and is forwarded once;
+    // the successor is not trusted until an operator adds it
+    pub fn receive_revocation(&mut self, notice: KeyRevocation) -> Result<bool, String> {
+        if self.revoked.contains(&notice.revoked) {
+            return Ok(false);
+        }
+        notice.verify().map_err(|e| format!("bad key revocation signature: {}", e))?;
+        self.revoked.insert(notice.revoked);
+        self.trust_list.remove(&notice.revoked);
+        self.staged.retain(|_, bundle| bundle.publisher != notice.revoked);
+        warn!(revoked = %hex::encode(notice.revoked), successor = %hex::encode(notice.successor), "policy publisher key revoked");
+        self.sender.broadcast_revocation(&notice);
+        Ok(true)
+    }
+
+    pub fn staged(&self) -> Vec<u64> {
+        let mut versions: Vec<u64> = self.staged.keys().copied().collect();
+        versions.sort();
+        versions
+    }
+
+    // Operator approval of a staged bundle
+    pub fn approve(&mut self, version: u64) -> Result<(), String> {
+        let bundle = self.staged.remove(&version).ok_or("no staged bundle with that version")?;
+        if bundle.version <= self.latest_version {
+            return Err("a newer bundle is already applied".to_string());
+        }
+        self.applier.apply(&bundle)?;
+        self.latest_version = bundle.version;
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use crate::api::authz::RoutePattern;
+use crate::api::openapi;
+use crate::tenants::TenantLimits;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// `plans` section of the node config
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PlansConfig {
+    // Plan of tenants that were not given one; such tenants are unrestricted when unset
+    pub default_plan: Option<String>,
+    pub tiers: BTreeMap<String, PlanConfig>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlanConfig {
+    // Routes the plan includes, in authorization-policy syntax; nothing when empty
+    pub endpoints: Vec<EndpointConfig>,
+
+    // Applied where the tenant's own limits leave a gap
+    pub limits: TenantLimits,
+    pub sla: Sla,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointConfig {
+    pub path: String,
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+// Service levels promised to the plan's tenants; published, not enforced
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sla {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub availability_percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p99_latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support: Option<String>,
+}
+
+pub struct Plan {
+    pub name: String,
+    endpoints: Vec<RoutePattern>,
+    pub limits: TenantLimits,
+    pub sla: Sla,
+}
+
+impl Plan {
+    // A tenant's own routes (quota, usage, its API document) are part of every plan
+    pub fn permits(&self, method: &str, path: &str) -> bool {
+        path.starts_with("/v1/tenants/") || self.endpoints.iter().any(|e| e.matches(method, path))
+    }
+
+    // The API document trimmed to the operations this plan includes, with its SLA under `x-sla`
+    pub fn openapi(&self) -> serde_json::Value {
+        let mut document = openapi::document_where(|method, path| self.permits(method, path));
+        if let Some(info) = document.get_mut("info").and_then(|i| i.as_object_mut()) {
+            info.insert("x-plan".to_string(), self.name.clone().into());
+            info.insert("x-sla".to_string(), serde_json::to_value(&self.sla).unwrap_or_default());
+        }
+        document
+    }
+}
+
+// Plan tiers, each a set of endpoints, default limits and an SLA
+#[derive(Default)]
+pub struct Plans {
+    default_plan: Option<String>,
+    tiers: BTreeMap<String, Plan>,
+}
+
+impl Plans {
+    pub fn new(config: PlansConfig) -> Result<Self, String> {
+        let mut tiers = BTreeMap::new();
+        for (name, plan) in config.tiers {
+            let endpoints = plan
+                .endpoints
+                .iter()
+                .map(|e| RoutePattern::compile(&e.path, &e.methods))
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("plan {}: {}", name, e))?;
+            tiers.insert(name.clone(), Plan { name, endpoints, limits: plan.limits, sla: plan.sla });
+        }
+        if let Some(default) = &config.default_plan {
+            if !tiers.contains_key(default) {
+                return Err(format!("plans.default_plan {} is not a configured tier", default));
+            }
+        }
+        Ok(Plans { default_plan: config.default_plan, tiers })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Plan> {
+        self.tiers.get(name)
+    }
+
+    // The plan a tenant is on: its own, else the default
+    pub fn resolve(&self, assigned: Option<&str>) -> Option<&Plan> {
+        assigned.or(self.default_plan.as_deref()).and_then(|name| self.get(name))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.tiers.keys().map(String::as_str).collect()
+    }
+}
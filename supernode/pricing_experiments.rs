@@ -0,0 +1,278 @@
+use crate::api::auth::Auth;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// Spread/fee configuration offered to a share of accounts
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    pub spread_bps: u32,
+    pub fee_bps: u32,
+
+    // Relative share of assignments
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+// Limits that keep an experiment from hurting customers or revenue
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Guardrails {
+    pub max_spread_bps: u32,
+    pub max_fee_bps: u32,
+
+    // A variant converting worse than this share of control is stopped
+    pub min_relative_completion: f64,
+
+    // Quotes per variant before the completion guardrail is evaluated
+    pub min_samples: u64,
+}
+
+impl Default for Guardrails {
+    fn default() -> Self {
+        Guardrails { max_spread_bps: 100, max_fee_bps: 100, min_relative_completion: 0.8, min_samples: 500 }
+    }
+}
+
+// Entry of the `experiments` section of the node config; the first variant is control
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExperimentConfig {
+    pub name: String,
+    pub variants: Vec<Variant>,
+    #[serde(default)]
+    pub guardrails: Guardrails,
+
+    // Tenants or accounts that always get control pricing
+    #[serde(default)]
+    pub opt_out: HashSet<String>,
+
+    // Pairs quoted under the experiment as FROM/TO, e.g. PI/USDC; every pair when empty
+    #[serde(default)]
+    pub pairs: Vec<String>,
+}
+
+impl ExperimentConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.variants.is_empty() {
+            return Err(format!("experiment {} has no variants", self.name));
+        }
+        for v in &self.variants {
+            if v.spread_bps > self.guardrails.max_spread_bps || v.fee_bps > self.guardrails.max_fee_bps {
+                return Err(format!("variant {}/{} exceeds the pricing guardrails", self.name, v.name));
+            }
+        }
+        if self.variants.iter().all(|v| v.weight == 0) {
+            return Err(format!("experiment {} has no weighted variants", self.name));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct VariantStats {
+    pub quotes: u64,
+    pub completions: u64,
+    pub revenue: u128,
+    pub stopped: bool,
+}
+
+impl VariantStats {
+    fn completion_rate(&self) -> f64 {
+        if self.quotes == 0 {
+            0.0
+        } else {
+            self.completions as f64 / self.quotes as f64
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct VariantSummary {
+    pub variant: String,
+    pub quotes: u64,
+    pub completions: u64,
+    pub completion_rate: f64,
+    pub revenue: u128,
+    pub revenue_per_quote: f64,
+
+    // Two-proportion z-test of completion rate against control
+    pub z_score: Option<f64>,
+    pub p_value: Option<f64>,
+
+    pub stopped: bool,
+}
+
+// Standard normal CDF (Abramowitz-Stegun 7.1.26 approximation of erf)
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+fn z_test(control: &VariantStats, variant: &VariantStats) -> Option<(f64, f64)> {
+    if control.quotes == 0 || variant.quotes == 0 {
+        return None;
+    }
+    let pooled = (control.completions + variant.completions) as f64 / (control.quotes + variant.quotes) as f64;
+    let se = (pooled * (1.0 - pooled) * (1.0 / control.quotes as f64 + 1.0 / variant.quotes as f64)).sqrt();
+    if se == 0.0 {
+        return None;
+    }
+    let z = (variant.completion_rate() - control.completion_rate()) / se;
+    Some((z, 2.0 * (1.0 - normal_cdf(z.abs()))))
+}
+
+struct Experiment {
+    config: ExperimentConfig,
+    stats: Vec<VariantStats>,
+}
+
+impl Experiment {
+    // Deterministic, so an account sees the same pricing on every quote
+    fn assign(&self, subject: &str) -> usize {
+        if self.config.opt_out.contains(subject) {
+            return 0;
+        }
+        let total: u64 = self.config.variants.iter().zip(&self.stats).filter(|(_, s)| !s.stopped).map(|(v, _)| v.weight as u64).sum();
+        if total == 0 {
+            return 0;
+        }
+        let digest = Sha256::digest(format!("{}:{}", self.config.name, subject).as_bytes());
+        let mut point = u64::from_be_bytes(digest[..8].try_into().unwrap()) % total;
+        for (i, (variant, stats)) in self.config.variants.iter().zip(&self.stats).enumerate() {
+            if stats.stopped {
+                continue;
+            }
+            if point < variant.weight as u64 {
+                return i;
+            }
+            point -= variant.weight as u64;
+        }
+        0
+    }
+
+    fn enforce_guardrails(&mut self) {
+        let guardrails = &self.config.guardrails;
+        if self.stats[0].quotes < guardrails.min_samples {
+            return;
+        }
+        let control = self.stats[0].completion_rate();
+        for (i, stats) in self.stats.iter_mut().enumerate().skip(1) {
+            if stats.stopped || stats.quotes < guardrails.min_samples {
+                continue;
+            }
+            if stats.completion_rate() < control * guardrails.min_relative_completion {
+                stats.stopped = true;
+                warn!(experiment = %self.config.name, variant = %self.config.variants[i].name, "variant stopped by completion guardrail");
+            }
+        }
+    }
+}
+
+// Runs conversion pricing experiments and tracks their outcomes
+#[derive(Clone, Default)]
+pub struct PricingExperiments {
+    experiments: Arc<Mutex<HashMap<String, Experiment>>>,
+
+    // Names in config order; a quote is priced by the first experiment covering its pair
+    order: Arc<Vec<String>>,
+}
+
+impl PricingExperiments {
+    pub fn new(configs: Vec<ExperimentConfig>) -> Result<Self, String> {
+        let mut experiments = HashMap::new();
+        let order = configs.iter().map(|config| config.name.clone()).collect();
+        for config in configs {
+            config.validate()?;
+            let stats = vec![VariantStats::default(); config.variants.len()];
+            experiments.insert(config.name.clone(), Experiment { config, stats });
+        }
+        Ok(PricingExperiments { experiments: Arc::new(Mutex::new(experiments)), order: Arc::new(order) })
+    }
+
+    // The experiment pricing a `from` -> `to` quote, if any, and the variant `subject` gets, counted as an exposure
+    pub fn price(&self, from: &str, to: &str, subject: &str) -> Option<(String, Variant)> {
+        let pair = format!("{}/{}", from, to);
+        let name = {
+            let experiments = self.experiments.lock().unwrap();
+            self.order.iter().find(|name| experiments.get(*name).is_some_and(|e| e.config.pairs.is_empty() || e.config.pairs.contains(&pair)))?.clone()
+        };
+        self.quote(&name, subject).map(|variant| (name, variant))
+    }
+
+    // Pricing for a quote to `subject` (tenant or account), counted as an exposure
+    pub fn quote(&self, experiment: &str, subject: &str) -> Option<Variant> {
+        let mut experiments = self.experiments.lock().unwrap();
+        let experiment = experiments.get_mut(experiment)?;
+        let i = experiment.assign(subject);
+        experiment.stats[i].quotes += 1;
+        experiment.enforce_guardrails();
+        Some(experiment.config.variants[i].clone())
+    }
+
+    // A quoted conversion was executed; `revenue` is the spread plus fee earned
+    pub fn complete(&self, experiment: &str, subject: &str, revenue: u128) {
+        let mut experiments = self.experiments.lock().unwrap();
+        if let Some(experiment) = experiments.get_mut(experiment) {
+            let i = experiment.assign(subject);
+            experiment.stats[i].completions += 1;
+            experiment.stats[i].revenue += revenue;
+            experiment.enforce_guardrails();
+        }
+    }
+
+    pub fn report(&self, experiment: &str) -> Option<Vec<VariantSummary>> {
+        let experiments = self.experiments.lock().unwrap();
+        let experiment = experiments.get(experiment)?;
+        let control = &experiment.stats[0];
+        let summaries = experiment
+            .config
+            .variants
+            .iter()
+            .zip(&experiment.stats)
+            .enumerate()
+            .map(|(i, (variant, stats))| {
+                let test = if i == 0 { None } else { z_test(control, stats) };
+                VariantSummary {
+                    variant: variant.name.clone(),
+                    quotes: stats.quotes,
+                    completions: stats.completions,
+                    completion_rate: stats.completion_rate(),
+                    revenue: stats.revenue,
+                    revenue_per_quote: if stats.quotes == 0 { 0.0 } else { stats.revenue as f64 / stats.quotes as f64 },
+                    z_score: test.map(|(z, _)| z),
+                    p_value: test.map(|(_, p)| p),
+                    stopped: stats.stopped,
+                }
+            })
+            .collect();
+        Some(summaries)
+    }
+
+    // GET /admin/experiments/{name}
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let experiments = self.clone();
+        warp::path!("admin" / "experiments" / String)
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |name: String, _| match experiments.report(&name) {
+                Some(report) => warp::reply::with_status(warp::reply::json(&report), StatusCode::OK),
+                None => warp::reply::with_status(warp::reply::json(&"unknown experiment"), StatusCode::NOT_FOUND),
+            })
+    }
+}
@@ -0,0 +1,448 @@
+use crate::api::auth::{Auth, Principal, Scope};
+use crate::api::problem::ApiError;
+use crate::api::validation::{validated_json, ValidationConfig};
+use crate::audit::bundle::signed_bytes;
+use crate::converter::{Conversion, ConvertError, ConvertRequest, SlippageLimit, StablecoinConverter};
+use crate::events::bus::{Event, EventBus, Step};
+use crate::ids::{ConversionId, QuoteId};
+use crate::keys::NodeKey;
+use crate::netting::{NettingEngine, QueuedConversion};
+use crate::pricing_experiments::PricingExperiments;
+use crate::runtime::scheduler::Scheduler;
+use crate::storage::entities::{EntityError, EntityStore, Versioned};
+use crate::storage::mvcc::{Store, WriteBatch};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// Domain tag for quote signatures
+pub const QUOTE_CONTEXT: &[u8] = b"pi-supernode/conversion-quote/v1";
+
+// `quotes` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct QuoteConfig {
+    // How long a quoted rate is honored
+    pub ttl_secs: u64,
+
+    // Quotes are dropped from storage this long after they expired, executed or not
+    pub retention_secs: u64,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        QuoteConfig { ttl_secs: 30, retention_secs: 24 * 3600 }
+    }
+}
+
+// Firm price for one conversion, signed by the node so the caller can hold it to the terms
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Quote {
+    pub id: QuoteId,
+    pub subject: String,
+    pub conversion: Conversion,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "hex::serde")]
+    pub signer: [u8; 32],
+    pub signature: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executed_at: Option<DateTime<Utc>>,
+
+    // Pricing experiment the quote was priced under; its variant's fee and spread are in `conversion`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<String>,
+}
+
+impl Quote {
+    // Covers everything the caller is promised: who, what, at which rate and fee, and until when
+    pub fn message(&self) -> Vec<u8> {
+        let c = &self.conversion;
+        let mut payload = Vec::new();
+        for part in [self.id.to_string().as_str(), &self.subject, &c.asset, &c.to_asset] {
+            payload.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            payload.extend_from_slice(part.as_bytes());
+        }
+        for value in [c.amount, c.converted_amount, c.rate.numerator, c.rate.denominator, c.fees.as_ref().map_or(0, |f| f.total)] {
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+        payload.extend_from_slice(self.expires_at.to_rfc3339().as_bytes());
+        signed_bytes(QUOTE_CONTEXT, &payload)
+    }
+}
+
+// A quote turned into a conversion at its locked rate
+#[derive(Debug, Serialize)]
+pub struct ExecutedQuote {
+    pub quote: Quote,
+
+    // Ledger entry of the conversion, also its id in the netting queue when queued
+    pub conversion_id: ConversionId,
+
+    // Set when the conversion was queued for netting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queued: Option<QueuedConversion>,
+}
+
+#[derive(Debug)]
+pub enum QuoteError {
+    NotFound(QuoteId),
+    Expired { id: QuoteId, expired_at: DateTime<Utc> },
+    AlreadyExecuted(QuoteId),
+    Convert(ConvertError),
+    Storage(String),
+}
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuoteError::NotFound(id) => write!(f, "no quote {}", id),
+            QuoteError::Expired { id, expired_at } => write!(f, "quote {} expired at {}; request a new one", id, expired_at.to_rfc3339()),
+            QuoteError::AlreadyExecuted(id) => write!(f, "quote {} was already executed", id),
+            QuoteError::Convert(e) => write!(f, "{}", e),
+            QuoteError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<QuoteError> for ApiError {
+    fn from(error: QuoteError) -> Self {
+        match error {
+            QuoteError::NotFound(_) => ApiError::NotFound(error.to_string()),
+            QuoteError::Expired { .. } => ApiError::Gone(error.to_string()),
+            QuoteError::AlreadyExecuted(_) => ApiError::Conflict(error.to_string()),
+            QuoteError::Convert(e) => ApiError::from(e),
+            QuoteError::Storage(_) => ApiError::Unavailable(error.to_string()),
+        }
+    }
+}
+
+fn storage_error(e: EntityError) -> QuoteError {
+    QuoteError::Storage(e.to_string())
+}
+
+// Two-phase conversions: `quote` locks the current rate and fees for `ttl_secs`, `execute` converts at them
+#[derive(Clone)]
+pub struct QuoteBook {
+    config: Arc<QuoteConfig>,
+    converter: StablecoinConverter,
+    key: NodeKey,
+    quotes: EntityStore<Quote>,
+    netting: Option<NettingEngine>,
+    events: Option<EventBus>,
+    experiments: Option<PricingExperiments>,
+}
+
+impl QuoteBook {
+    pub fn new(config: QuoteConfig, converter: StablecoinConverter, key: NodeKey, store: Store) -> Self {
+        QuoteBook { config: Arc::new(config), converter, key, quotes: EntityStore::new(store, "quotes"), netting: None, events: None, experiments: None }
+    }
+
+    // Queue executed quotes for the next netting cycle
+    pub fn with_netting(mut self, netting: NettingEngine) -> Self {
+        self.netting = Some(netting);
+        self
+    }
+
+    // Publish `ConversionExecuted` for every executed quote, and each quote's steps for its timeline
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    // Price quotes under the pricing experiment covering their pair, and report executions as its completions
+    pub fn with_experiments(mut self, experiments: PricingExperiments) -> Self {
+        self.experiments = Some(experiments);
+        self
+    }
+
+    fn publish(&self, event: Event) {
+        if let Some(events) = &self.events {
+            events.publish(event);
+        }
+    }
+
+    // `slippage` guards the quote itself against a rate that moved since the caller last looked
+    pub fn quote(&self, subject: &str, asset: &str, amount: u128, to_asset: &str, slippage: Option<&SlippageLimit>) -> Result<Quote, QuoteError> {
+        let id = QuoteId::new();
+        let pricing = self.experiments.as_ref().and_then(|experiments| experiments.price(asset, to_asset, subject));
+        let variant = pricing.as_ref().map(|(_, variant)| variant);
+        let mut conversion = self.converter.convert_priced(asset, amount, to_asset, slippage, variant).map_err(QuoteError::Convert)?;
+        conversion.quote_id = Some(id);
+        let issued_at = Utc::now();
+        let mut quote = Quote {
+            id,
+            subject: subject.to_string(),
+            conversion,
+            issued_at,
+            expires_at: issued_at + ChronoDuration::seconds(self.config.ttl_secs as i64),
+            signer: self.key.public(),
+            signature: String::new(),
+            executed_at: None,
+            experiment: pricing.map(|(name, _)| name),
+        };
+        quote.signature = hex::encode(self.key.sign(&quote.message()).to_bytes());
+        self.quotes.create(&quote.id.to_string(), quote.clone()).map_err(storage_error)?;
+        self.publish(Event::step(quote.id.to_string(), Step::StateChange, "quoted"));
+        self.publish(Event::TransactionStep {
+            tx_id: quote.id.to_string(),
+            step: Step::Signature,
+            outcome: "signed".to_string(),
+            detail: Some(format!("node key {}", self.key.key_id())),
+            related: None,
+        });
+        info!(quote = %quote.id, asset, amount, to_asset, converted = quote.conversion.converted_amount, "conversion quoted");
+        Ok(quote)
+    }
+
+    // Other callers' quotes are reported as missing
+    fn owned(&self, id: QuoteId, principal: &Principal) -> Result<Versioned<Quote>, QuoteError> {
+        match self.quotes.get(&id.to_string()) {
+            Ok(entity) if entity.value.subject == principal.subject || principal.has_scope(Scope::Admin) => Ok(entity),
+            Ok(_) | Err(EntityError::NotFound) => Err(QuoteError::NotFound(id)),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    // Any caller's quote, for operators
+    pub fn lookup(&self, id: QuoteId) -> Option<Quote> {
+        self.quotes.get(&id.to_string()).ok().map(|entity| entity.value)
+    }
+
+    pub fn get(&self, id: QuoteId, principal: &Principal) -> Result<Quote, QuoteError> {
+        self.owned(id, principal).map(|entity| entity.value)
+    }
+
+    // Convert at the quoted rate; a quote is executed at most once and never after it expired
+    pub fn execute(&self, id: QuoteId, principal: &Principal) -> Result<ExecutedQuote, QuoteError> {
+        let entity = self.owned(id, principal)?;
+        let mut quote = entity.value;
+        if quote.executed_at.is_some() {
+            return Err(QuoteError::AlreadyExecuted(id));
+        }
+        let now = Utc::now();
+        if now >= quote.expires_at {
+            self.publish(Event::step(id.to_string(), Step::StateChange, "expired"));
+            return Err(QuoteError::Expired { id, expired_at: quote.expires_at });
+        }
+        // Claim the quote first, so two concurrent executions cannot both convert
+        let unexecuted = quote.clone();
+        quote.executed_at = Some(now);
+        let claimed = match self.quotes.update(&id.to_string(), entity.version, quote.clone()) {
+            Ok(claimed) => claimed,
+            Err(EntityError::Conflict { .. }) => return Err(QuoteError::AlreadyExecuted(id)),
+            Err(e) => return Err(storage_error(e)),
+        };
+        // Queued for netting, or accepted on its own, the conversion gets its ledger entry in the same commit
+        let accepted = match &self.netting {
+            Some(netting) => netting.submit(&self.converter, &quote.subject, &quote.conversion).map(|queued| (queued.id, Some(queued))),
+            None => {
+                let id = ConversionId::new();
+                self.converter.accept(id, &quote.subject, &quote.conversion, WriteBatch::default()).map(|_| (id, None))
+            }
+        };
+        let (conversion_id, queued) = match accepted {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Give the quote back so it can be retried before it expires
+                if let Err(revert) = self.quotes.update(&id.to_string(), claimed.version, unexecuted) {
+                    warn!(quote = %id, error = %revert, "could not release quote after a failed execution");
+                }
+                return Err(QuoteError::Convert(e));
+            }
+        };
+        self.publish(Event::TransactionStep {
+            tx_id: id.to_string(),
+            step: Step::StateChange,
+            outcome: "executed".to_string(),
+            detail: None,
+            related: Some(conversion_id.to_string()),
+        });
+        if let (Some(experiments), Some(experiment)) = (&self.experiments, &quote.experiment) {
+            experiments.complete(experiment, &quote.subject, quote.conversion.revenue());
+        }
+        let c = &quote.conversion;
+        self.publish(Event::ConversionExecuted {
+            tx_id: conversion_id.to_string(),
+            from: c.asset.clone(),
+            to: c.to_asset.clone(),
+            amount_in: c.amount.to_string(),
+            amount_out: c.converted_amount.to_string(),
+            fee: c.fees.clone(),
+            tenant: principal.tenant.clone(),
+        });
+        info!(quote = %id, subject = %quote.subject, conversion = %quote.conversion, "quote executed");
+        Ok(ExecutedQuote { quote, conversion_id, queued })
+    }
+
+    // Drop quotes past their retention; returns how many
+    pub fn expire(&self) -> usize {
+        let cutoff = Utc::now() - ChronoDuration::seconds(self.config.retention_secs as i64);
+        let expired = self
+            .quotes
+            .list()
+            .into_iter()
+            .filter(|(_, entity)| entity.value.expires_at < cutoff)
+            .filter(|(id, entity)| self.quotes.delete(id, entity.version).is_ok())
+            .count();
+        self.quotes.purge(Duration::ZERO);
+        expired
+    }
+
+    // POST /v1/quotes, GET /v1/quotes/{id} and POST /v1/quotes/{id}/execute
+    pub fn routes(&self, rules: Arc<ValidationConfig>, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let book = self.clone();
+        let create = warp::path!("v1" / "quotes").and(warp::post()).and(auth.authorized()).and(validated_json(rules)).and_then(
+            move |principal: Principal, request: ConvertRequest| {
+                let result = book.quote(&principal.subject, &request.asset, request.amount, &request.to_asset, request.slippage_limit().as_ref());
+                async move {
+                    let quote = result.map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+                    Ok::<_, Rejection>(warp::reply::with_status(warp::reply::json(&quote), StatusCode::CREATED))
+                }
+            },
+        );
+
+        let book = self.clone();
+        let get = warp::path!("v1" / "quotes" / QuoteId).and(warp::get()).and(auth.authorized()).and_then(
+            move |id: QuoteId, principal: Principal| {
+                let result = book.get(id, &principal);
+                async move { result.map(|quote| warp::reply::json(&quote)).map_err(|e| warp::reject::custom(ApiError::from(e))) }
+            },
+        );
+
+        let book = self.clone();
+        let execute = warp::path!("v1" / "quotes" / QuoteId / "execute").and(warp::post()).and(auth.authorized()).and_then(
+            move |id: QuoteId, principal: Principal| {
+                let result = book.execute(id, &principal);
+                async move { result.map(|executed| warp::reply::json(&executed)).map_err(|e| warp::reject::custom(ApiError::from(e))) }
+            },
+        );
+
+        create.or(get).or(execute)
+    }
+}
+
+// Hourly removal of quotes past their retention
+pub fn register(scheduler: &Scheduler, book: QuoteBook) {
+    scheduler.register(
+        "quotes:expire",
+        Duration::from_secs(3600),
+        Duration::from_secs(60),
+        Arc::new(move || {
+            let book = book.clone();
+            Box::pin(async move {
+                let expired = book.expire();
+                if expired > 0 {
+                    info!(expired, "expired quotes removed");
+                }
+            })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::redemption::stage_credits;
+    use crate::converter::{ConverterConfig, Direction, RateConfig};
+    use crate::pricing_experiments::{ExperimentConfig, Variant};
+    use crate::storage::conversion_ledger::ConversionLedger;
+    use ed25519_dalek::SigningKey;
+
+    // PI -> USDC at 1:10, with the conversion ledger attached and alice holding 10000 PI
+    fn book(ttl_secs: u64) -> (QuoteBook, StablecoinConverter, ConversionLedger) {
+        let store = Store::new();
+        let ledger = ConversionLedger::new(store.clone());
+        let accounts = EntityStore::new(store.clone(), "accounts");
+        let mut batch = WriteBatch::default();
+        stage_credits(&accounts, &mut batch, &[("alice", "PI", 10_000)]).unwrap();
+        accounts.commit(batch).unwrap();
+        let config = ConverterConfig {
+            rates: vec![RateConfig { from: "PI".to_string(), to: "USDC".to_string(), numerator: 1, denominator: 10 }],
+            ..ConverterConfig::default()
+        };
+        let converter = StablecoinConverter::new(&config).unwrap().with_ledger(ledger.clone()).with_accounts(accounts);
+        let key = NodeKey::new(SigningKey::from_bytes(&[7; 32]));
+        let book = QuoteBook::new(QuoteConfig { ttl_secs, retention_secs: 60 }, converter.clone(), key, store);
+        (book, converter, ledger)
+    }
+
+    fn caller(subject: &str, scope: Scope) -> Principal {
+        Principal { subject: subject.to_string(), scopes: vec![scope], credential_id: format!("key:{}", subject), tenant: None }
+    }
+
+    #[test]
+    fn executes_once_and_records_the_conversion() {
+        let (book, _, ledger) = book(30);
+        let alice = caller("alice", Scope::Convert);
+        let quote = book.quote("alice", "PI", 1_000, "USDC", None).unwrap();
+
+        let executed = book.execute(quote.id, &alice).unwrap();
+        assert!(executed.quote.executed_at.is_some());
+        assert!(executed.queued.is_none());
+        let entry = ledger.get(&executed.conversion_id).unwrap();
+        assert_eq!((entry.account.as_str(), entry.amount_in, entry.amount_out), ("alice", 1_000, 100));
+
+        assert!(matches!(book.execute(quote.id, &alice), Err(QuoteError::AlreadyExecuted(id)) if id == quote.id));
+    }
+
+    #[test]
+    fn quotes_are_priced_by_the_first_experiment_covering_the_pair() {
+        let (book, _, ledger) = book(30);
+        let experiment = |name: &str, pairs: &[&str], spread_bps| ExperimentConfig {
+            name: name.to_string(),
+            variants: vec![Variant { name: "control".to_string(), spread_bps, fee_bps: 0, weight: 1 }],
+            guardrails: Default::default(),
+            opt_out: Default::default(),
+            pairs: pairs.iter().map(|p| p.to_string()).collect(),
+        };
+        let experiments = PricingExperiments::new(vec![experiment("usdt", &["PI/USDT"], 50), experiment("spread", &[], 100)]).unwrap();
+        let book = book.with_experiments(experiments.clone());
+
+        let quote = book.quote("alice", "PI", 1_000, "USDC", None).unwrap();
+        assert_eq!(quote.experiment.as_deref(), Some("spread"));
+        assert_eq!((quote.conversion.spread_bps, quote.conversion.converted_amount), (Some(100), 99));
+
+        let executed = book.execute(quote.id, &caller("alice", Scope::Convert)).unwrap();
+        assert_eq!(ledger.get(&executed.conversion_id).unwrap().amount_out, 99);
+        let report = experiments.report("spread").unwrap();
+        assert_eq!((report[0].quotes, report[0].completions, report[0].revenue), (1, 1, 10));
+        assert_eq!(experiments.report("usdt").unwrap()[0].quotes, 0);
+    }
+
+    #[test]
+    fn only_the_quoted_caller_or_an_admin_executes() {
+        let (book, _, _) = book(30);
+        let quote = book.quote("alice", "PI", 1_000, "USDC", None).unwrap();
+        assert!(matches!(book.execute(quote.id, &caller("bob", Scope::Convert)), Err(QuoteError::NotFound(_))));
+        assert!(book.execute(quote.id, &caller("ops", Scope::Admin)).is_ok());
+    }
+
+    #[test]
+    fn refuses_expired_quotes() {
+        let (book, _, _) = book(0);
+        let quote = book.quote("alice", "PI", 1_000, "USDC", None).unwrap();
+        assert!(matches!(book.execute(quote.id, &caller("alice", Scope::Convert)), Err(QuoteError::Expired { .. })));
+    }
+
+    #[test]
+    fn releases_the_quote_when_the_conversion_is_refused() {
+        let (book, converter, ledger) = book(30);
+        let alice = caller("alice", Scope::Convert);
+        let quote = book.quote("alice", "PI", 1_000, "USDC", None).unwrap();
+
+        converter.set_liquidity(Direction::Forward, "USDC", 99);
+        let refused = book.execute(quote.id, &alice);
+        assert!(matches!(refused, Err(QuoteError::Convert(ConvertError::InsufficientLiquidity { available: 99, .. }))));
+        assert!(book.lookup(quote.id).unwrap().executed_at.is_none());
+
+        converter.set_liquidity(Direction::Forward, "USDC", 100);
+        let executed = book.execute(quote.id, &alice).unwrap();
+        assert!(ledger.get(&executed.conversion_id).is_some());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::server::PeerAddr;
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::Script;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::reject::Reject;
+use warp::{Filter, Rejection};
+
+// Token bucket parameters for one client
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Limit {
+    // Bucket size
+    pub burst: u32,
+
+    // Tokens added per second
+    pub refill_per_sec: f64,
+}
+
+// Result of taking a token
+pub enum Verdict {
+    Allowed { remaining: u32 },
+    Limited { retry_after: Duration },
+}
+
+// Storage for token buckets, local or shared across replicas
+#[async_trait]
+pub trait RateLimitBackend: Send + Sync {
+    async fn acquire(&self, client: &str, limit: Limit) -> Result<Verdict, String>;
+}
+
+// Refill and take atomically inside Redis so every replica sees the same bucket
+const TOKEN_BUCKET_LUA: &str = r#"
+local tokens_key = KEYS[1]
+local burst = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local state = redis.call('HMGET', tokens_key, 'tokens', 'ts')
+local tokens = tonumber(state[1]) or burst
+local ts = tonumber(state[2]) or now
+tokens = math.min(burst, tokens + (now - ts) * rate)
+local allowed = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+end
+redis.call('HSET', tokens_key, 'tokens', tokens, 'ts', now)
+redis.call('PEXPIRE', tokens_key, math.ceil(burst / rate * 1000) + 1000)
+return {allowed, tostring(tokens)}
+"#;
+
+// Fleet-wide token buckets kept in Redis
+pub struct RedisBackend {
+    conn: ConnectionManager,
+    script: Script,
+    prefix: String,
+}
+
+impl RedisBackend {
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| e.to_string())?;
+        let conn = ConnectionManager::new(client).await.map_err(|e| e.to_string())?;
+        Ok(RedisBackend { conn, script: Script::new(TOKEN_BUCKET_LUA), prefix: prefix.to_string() })
+    }
+}
+
+#[async_trait]
+impl RateLimitBackend for RedisBackend {
+    async fn acquire(&self, client: &str, limit: Limit) -> Result<Verdict, String> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        let mut conn = self.conn.clone();
+        let (allowed, tokens): (i64, String) = self
+            .script
+            .key(format!("{}:{}", self.prefix, client))
+            .arg(limit.burst)
+            .arg(limit.refill_per_sec)
+            .arg(now)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        let tokens: f64 = tokens.parse().unwrap_or(0.0);
+        if allowed == 1 {
+            Ok(Verdict::Allowed { remaining: tokens as u32 })
+        } else {
+            let wait = (1.0 - tokens) / limit.refill_per_sec;
+            Ok(Verdict::Limited { retry_after: Duration::from_secs_f64(wait.max(0.0)) })
+        }
+    }
+}
+
+// Token buckets held in this process
+#[derive(Default)]
+pub struct InMemoryBackend {
+    buckets: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+#[async_trait]
+impl RateLimitBackend for InMemoryBackend {
+    async fn acquire(&self, client: &str, limit: Limit) -> Result<Verdict, String> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+        let (tokens, refilled) = buckets.entry(client.to_string()).or_insert((limit.burst as f64, now));
+        *tokens = (*tokens + now.duration_since(*refilled).as_secs_f64() * limit.refill_per_sec).min(limit.burst as f64);
+        *refilled = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(Verdict::Allowed { remaining: *tokens as u32 })
+        } else {
+            Ok(Verdict::Limited { retry_after: Duration::from_secs_f64((1.0 - *tokens) / limit.refill_per_sec) })
+        }
+    }
+}
+
+// `rate_limit` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+
+    // Applied per API key, or per IP for anonymous callers
+    pub default: Limit,
+
+    // Overrides by API key
+    pub per_key: HashMap<String, Limit>,
+
+    // Shared buckets across replicas when set
+    pub redis_url: Option<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            default: Limit { burst: 20, refill_per_sec: 5.0 },
+            per_key: HashMap::new(),
+            redis_url: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
+
+impl Reject for RateLimited {}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: Arc<RateLimitConfig>,
+    backend: Arc<dyn RateLimitBackend>,
+}
+
+impl RateLimiter {
+    pub async fn from_config(config: RateLimitConfig) -> Result<Self, String> {
+        let backend: Arc<dyn RateLimitBackend> = match &config.redis_url {
+            Some(url) => Arc::new(RedisBackend::connect(url, "pi-supernode:ratelimit").await?),
+            None => Arc::new(InMemoryBackend::default()),
+        };
+        Ok(RateLimiter { config: Arc::new(config), backend })
+    }
+
+    pub fn new(config: RateLimitConfig, backend: Arc<dyn RateLimitBackend>) -> Self {
+        RateLimiter { config: Arc::new(config), backend }
+    }
+
+    // Reject with 429 once the caller's bucket is empty
+    pub fn limit(&self) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        let limiter = self.clone();
+        warp::header::optional::<String>("x-api-key")
+            .and(warp::ext::optional::<PeerAddr>())
+            .and_then(move |api_key: Option<String>, peer: Option<PeerAddr>| {
+                let limiter = limiter.clone();
+                async move {
+                    if !limiter.config.enabled {
+                        return Ok(());
+                    }
+                    let (client, limit) = match &api_key {
+                        Some(key) => (format!("key:{}", key), *limiter.config.per_key.get(key).unwrap_or(&limiter.config.default)),
+                        None => (format!("ip:{}", peer.map(|p| p.0.ip().to_string()).unwrap_or_default()), limiter.config.default),
+                    };
+                    match limiter.backend.acquire(&client, limit).await {
+                        Ok(Verdict::Limited { retry_after }) => Err(warp::reject::custom(RateLimited { retry_after })),
+                        // Fail open when the backend is unavailable rather than taking the API down
+                        Ok(Verdict::Allowed { .. }) | Err(_) => Ok(()),
+                    }
+                }
+            })
+            .untuple_one()
+    }
+}
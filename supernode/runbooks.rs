@@ -0,0 +1,133 @@
+use crate::alerting::{Alert, AlertChannel, Severity};
+use crate::audit::log::AuditLog;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+// `runbooks` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RunbooksConfig {
+    pub dry_run: bool,
+    pub rules: Vec<RunbookRule>,
+}
+
+impl Default for RunbooksConfig {
+    fn default() -> Self {
+        RunbooksConfig { dry_run: true, rules: Vec::new() }
+    }
+}
+
+// Predefined remediation the node can take on its own
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum Action {
+    RestartJob(String),
+    OpenCircuitBreaker(String),
+    SwitchOracleProvider(String),
+    EnterMaintenanceMode,
+}
+
+// Implemented by the node to carry out actions
+pub trait ActionExecutor: Send + Sync {
+    fn execute(&self, action: &Action) -> Result<(), String>;
+}
+
+// Every triggered action is audited, including dry runs
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub alert_kind: String,
+    pub action: Action,
+    pub dry_run: bool,
+    pub outcome: String,
+}
+
+pub trait AuditSink: Send + Sync {
+    fn record(&self, entry: AuditEntry);
+}
+
+// Alert kind and minimum severity that trigger an action
+#[derive(Clone, Debug, Deserialize)]
+pub struct RunbookRule {
+    pub alert_kind: String,
+    pub min_severity: Severity,
+    pub action: Action,
+}
+
+// Alert channel that executes remediation actions for matching alerts
+pub struct Runbooks {
+    rules: Vec<RunbookRule>,
+    executor: Arc<dyn ActionExecutor>,
+    audit: Arc<dyn AuditSink>,
+
+    // Only record what would have been done
+    dry_run: bool,
+}
+
+impl Runbooks {
+    pub fn new(rules: Vec<RunbookRule>, executor: Arc<dyn ActionExecutor>, audit: Arc<dyn AuditSink>, dry_run: bool) -> Self {
+        Runbooks { rules, executor, audit, dry_run }
+    }
+
+    fn handle(&self, alert: &Alert) {
+        for rule in self.rules.iter().filter(|r| r.alert_kind == alert.kind && alert.severity >= r.min_severity) {
+            let outcome = if self.dry_run {
+                "dry-run: not executed".to_string()
+            } else {
+                match self.executor.execute(&rule.action) {
+                    Ok(()) => "executed".to_string(),
+                    Err(e) => format!("failed: {}", e),
+                }
+            };
+            self.audit.record(AuditEntry {
+                at: Utc::now(),
+                alert_kind: alert.kind.clone(),
+                action: rule.action.clone(),
+                dry_run: self.dry_run,
+                outcome,
+            });
+        }
+    }
+}
+
+impl AlertChannel for Runbooks {
+    fn name(&self) -> &str {
+        "runbooks"
+    }
+
+    fn send(&self, alerts: &[Alert]) {
+        for alert in alerts {
+            self.handle(alert);
+        }
+    }
+}
+
+// In-memory audit sink, used until entries are forwarded to the audit log
+#[derive(Default)]
+pub struct MemoryAudit {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl MemoryAudit {
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+}
+
+impl AuditSink for MemoryAudit {
+    fn record(&self, entry: AuditEntry) {
+        self.entries.lock().unwrap().push(entry);
+    }
+}
+
+impl AuditSink for AuditLog {
+    fn record(&self, entry: AuditEntry) {
+        let mut event = serde_json::to_value(&entry).unwrap_or_default();
+        event["kind"] = "runbook_action".into();
+        if let Err(e) = self.append(event) {
+            warn!(error = %e, alert_kind = %entry.alert_kind, "runbook action not audited");
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::alerting::{Alert, Alerter, Severity};
+use crate::api::auth::Auth;
+use crate::runtime::scheduler::Scheduler;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tracing::{info, warn};
+use warp::{Filter, Rejection, Reply};
+
+// `clock` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ClockConfig {
+    // No checks run when empty
+    pub ntp_servers: Vec<String>,
+    pub check_interval_secs: u64,
+
+    // Offset above which an alert is raised
+    pub alert_offset_ms: i64,
+
+    // Tolerance applied to expiry and replay windows when the clock is healthy
+    pub base_tolerance_ms: u64,
+
+    // Upper bound for automatic widening
+    pub max_tolerance_ms: u64,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        ClockConfig {
+            ntp_servers: vec!["pool.ntp.org:123".to_string()],
+            check_interval_secs: 300,
+            alert_offset_ms: 500,
+            base_tolerance_ms: 2_000,
+            max_tolerance_ms: 30_000,
+        }
+    }
+}
+
+// Audit record of every automatic tolerance change
+#[derive(Clone, Debug, Serialize)]
+pub struct ToleranceChange {
+    pub at: DateTime<Utc>,
+    pub offset_ms: i64,
+    pub from_ms: u64,
+    pub to_ms: u64,
+}
+
+#[derive(Serialize)]
+struct ClockStatus {
+    offset_ms: i64,
+    tolerance_ms: u64,
+    tolerance_changes: Vec<ToleranceChange>,
+}
+
+// Seconds between the NTP epoch (1900) and the Unix epoch
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+fn ntp_to_unix_ms(bytes: &[u8]) -> i64 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64;
+    let frac = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as u64;
+    ((secs.saturating_sub(NTP_UNIX_OFFSET)) * 1000 + ((frac * 1000) >> 32)) as i64
+}
+
+fn unix_ms(t: SystemTime) -> i64 {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64
+}
+
+// Single SNTP exchange, returns local clock offset in milliseconds (positive = local is behind)
+pub async fn query_offset(server: &str) -> Result<i64, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(|e| e.to_string())?;
+    socket.connect(server).await.map_err(|e| e.to_string())?;
+    let mut request = [0u8; 48];
+    request[0] = 0x1b; // LI = 0, version 3, client mode
+    let t1 = SystemTime::now();
+    socket.send(&request).await.map_err(|e| e.to_string())?;
+    let mut response = [0u8; 48];
+    tokio::time::timeout(Duration::from_secs(3), socket.recv(&mut response))
+        .await
+        .map_err(|_| format!("{} timed out", server))?
+        .map_err(|e| e.to_string())?;
+    let t4 = SystemTime::now();
+    let t2 = ntp_to_unix_ms(&response[32..40]);
+    let t3 = ntp_to_unix_ms(&response[40..48]);
+    Ok(((t2 - unix_ms(t1)) + (t3 - unix_ms(t4))) / 2)
+}
+
+// Tracks clock offset and the tolerance window other modules should apply
+#[derive(Clone)]
+pub struct ClockGuard {
+    config: ClockConfig,
+    state: Arc<RwLock<(i64, u64, Vec<ToleranceChange>)>>,
+}
+
+impl ClockGuard {
+    pub fn new(config: ClockConfig) -> Self {
+        let tolerance = config.base_tolerance_ms;
+        ClockGuard { config, state: Arc::new(RwLock::new((0, tolerance, Vec::new()))) }
+    }
+
+    // Tolerance to add to quote expiry, replay and attestation windows
+    pub fn tolerance(&self) -> Duration {
+        Duration::from_millis(self.state.read().unwrap().1)
+    }
+
+    pub fn offset_ms(&self) -> i64 {
+        self.state.read().unwrap().0
+    }
+
+    pub fn tolerance_changes(&self) -> Vec<ToleranceChange> {
+        self.state.read().unwrap().2.clone()
+    }
+
+    // Query the configured servers, alert on skew and adjust tolerance; run from the scheduler
+    pub async fn check(&self, alerter: &Alerter) {
+        let mut offsets = Vec::new();
+        for server in &self.config.ntp_servers {
+            match query_offset(server).await {
+                Ok(offset) => offsets.push(offset),
+                Err(e) => warn!(%server, error = %e, "NTP query failed"),
+            }
+        }
+        if offsets.is_empty() {
+            return;
+        }
+        offsets.sort();
+        let offset = offsets[offsets.len() / 2];
+        if offset.abs() > self.config.alert_offset_ms {
+            alerter.raise(Alert {
+                kind: "clock_skew".to_string(),
+                severity: Severity::Warning,
+                message: format!("local clock is off by {} ms", offset),
+                at: Utc::now(),
+            });
+        }
+
+        // Widen to cover twice the drift, shrink back once the clock recovers
+        let wanted = (self.config.base_tolerance_ms + 2 * offset.unsigned_abs()).min(self.config.max_tolerance_ms);
+        let mut state = self.state.write().unwrap();
+        state.0 = offset;
+        if wanted != state.1 {
+            info!(offset_ms = offset, from_ms = state.1, to_ms = wanted, "clock tolerance adjusted");
+            let change = ToleranceChange { at: Utc::now(), offset_ms: offset, from_ms: state.1, to_ms: wanted };
+            state.2.push(change);
+            state.1 = wanted;
+        }
+    }
+
+    // GET /admin/clock
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let guard = self.clone();
+        warp::path!("admin" / "clock").and(warp::get()).and(auth.authorized()).map(move |_| {
+            let (offset_ms, tolerance_ms, tolerance_changes) = guard.state.read().unwrap().clone();
+            warp::reply::json(&ClockStatus { offset_ms, tolerance_ms, tolerance_changes })
+        })
+    }
+}
+
+pub fn register(scheduler: &Scheduler, guard: ClockGuard, alerter: Alerter) {
+    if guard.config.ntp_servers.is_empty() {
+        return;
+    }
+    scheduler.register(
+        "clock:check",
+        Duration::from_secs(guard.config.check_interval_secs.max(1)),
+        Duration::ZERO,
+        Arc::new(move || {
+            let (guard, alerter) = (guard.clone(), alerter.clone());
+            Box::pin(async move { guard.check(&alerter).await })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::{AlertChannel, Delivery, Route};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Capture(Mutex<Vec<Alert>>);
+
+    impl AlertChannel for Capture {
+        fn name(&self) -> &str {
+            "capture"
+        }
+
+        fn send(&self, alerts: &[Alert]) {
+            self.0.lock().unwrap().extend_from_slice(alerts);
+        }
+    }
+
+    // Answers every SNTP request with a clock `ahead_ms` ahead of the local one
+    async fn ntp_server(ahead_ms: u64) -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut request = [0u8; 48];
+            while let Ok((_, peer)) = socket.recv_from(&mut request).await {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap() + Duration::from_millis(ahead_ms);
+                let mut stamp = ((now.as_secs() + NTP_UNIX_OFFSET) as u32).to_be_bytes().to_vec();
+                stamp.extend_from_slice(&(((now.subsec_nanos() as u64) << 32) / 1_000_000_000).to_be_bytes()[4..]);
+                let mut response = [0u8; 48];
+                response[32..40].copy_from_slice(&stamp);
+                response[40..48].copy_from_slice(&stamp);
+                let _ = socket.send_to(&response, peer).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn alerts_on_skew_and_widens_tolerance() {
+        let capture = Arc::new(Capture::default());
+        let route = Route { channel: capture.clone(), min_severity: Severity::Info, delivery: Delivery::Immediate, quiet_hours: None };
+        let alerter = Alerter::new(vec![route]);
+        let config = ClockConfig { ntp_servers: vec![ntp_server(5_000).await], ..ClockConfig::default() };
+        let guard = ClockGuard::new(config);
+
+        guard.check(&alerter).await;
+        let offset = guard.offset_ms();
+        assert!((4_900..=5_100).contains(&offset), "{}", offset);
+        assert_eq!(capture.0.lock().unwrap()[0].kind, "clock_skew");
+        assert_eq!(guard.tolerance(), Duration::from_millis(2_000 + 2 * offset as u64));
+        assert_eq!(guard.tolerance_changes().len(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use warp::reject::Reject;
+
+// Remaining budget in milliseconds, read from callers and sent on to downstream nodes
+pub const BUDGET_HEADER: &str = "x-request-budget-ms";
+
+tokio::task_local! {
+    static CURRENT: Deadline;
+}
+
+// Point by which the whole inbound request must be answered; every dependency call draws on what is left
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    expires_at: Instant,
+    budget: Duration,
+}
+
+// A dependency did not answer within the remaining budget
+#[derive(Debug)]
+pub struct DeadlineExceeded {
+    pub dependency: &'static str,
+    pub budget: Duration,
+
+    // Time the dependency was given, which may be less than the budget if earlier calls used some up
+    pub allowed: Duration,
+}
+
+impl Reject for DeadlineExceeded {}
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} did not answer within the {}ms left of the {}ms request budget",
+            self.dependency,
+            self.allowed.as_millis(),
+            self.budget.as_millis()
+        )
+    }
+}
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Deadline { expires_at: Instant::now() + budget, budget }
+    }
+
+    // Server budget, shortened when the caller declares less patience in `x-request-budget-ms`
+    pub fn for_request(server_budget: Duration, declared_ms: Option<&str>) -> Self {
+        let declared = declared_ms.and_then(|ms| ms.trim().parse::<u64>().ok()).map(Duration::from_millis);
+        Deadline::after(declared.map_or(server_budget, |d| d.min(server_budget)))
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.expires_at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    // Deadline of the request being served on this task, if any
+    pub fn current() -> Option<Deadline> {
+        CURRENT.try_with(|d| *d).ok()
+    }
+
+    // Run `fut` with this deadline visible to everything it calls through `Deadline::current`
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self, fut).await
+    }
+
+    // Run one dependency call within the remaining budget, capped at the dependency's own timeout
+    pub async fn call<F: Future>(&self, dependency: &'static str, own_timeout: Duration, fut: F) -> Result<F::Output, DeadlineExceeded> {
+        let allowed = self.remaining().min(own_timeout);
+        tokio::time::timeout(allowed, fut).await.map_err(|_| DeadlineExceeded { dependency, budget: self.budget, allowed })
+    }
+
+    // Outbound HTTP call to a peer or oracle: bounded by what is left, and told how much that is
+    pub fn limit(&self, request: reqwest::RequestBuilder, own_timeout: Duration) -> reqwest::RequestBuilder {
+        let allowed = self.remaining().min(own_timeout);
+        request.timeout(allowed).header(BUDGET_HEADER, allowed.as_millis().to_string())
+    }
+}
+
+// Dependency calls outside any request (background jobs) keep just their own timeout
+pub async fn call<F: Future>(dependency: &'static str, own_timeout: Duration, fut: F) -> Result<F::Output, DeadlineExceeded> {
+    let deadline = Deadline::current().unwrap_or_else(|| Deadline::after(own_timeout));
+    deadline.call(dependency, own_timeout, fut).await
+}
@@ -0,0 +1,92 @@
+use crate::audit::log::AuditLog;
+use crate::runtime::scheduler::Scheduler;
+use crate::runtime::shutdown::ShutdownHooks;
+use crate::storage::mvcc::Store;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+use tracing::warn;
+use warp::http::Method;
+use warp::path::FullPath;
+use warp::reject::Reject;
+use warp::{Filter, Rejection};
+
+// `forensic` section of the node config; `--forensic` on the command line turns it on too
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ForensicConfig {
+    pub enabled: bool,
+
+    // POST routes that only read, still served in forensic mode
+    pub read_only_posts: Vec<String>,
+}
+
+impl Default for ForensicConfig {
+    fn default() -> Self {
+        ForensicConfig {
+            enabled: false,
+            read_only_posts: ["/graphql", "/admin/audit/log/verify", "/convert", "/v1/fees/estimate"].map(String::from).to_vec(),
+        }
+    }
+}
+
+// A mutating request reached a node in forensic mode
+#[derive(Debug)]
+pub struct ForensicMode;
+
+impl Reject for ForensicMode {}
+
+impl fmt::Display for ForensicMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the node is in forensic mode and only serves reads")
+    }
+}
+
+// Read-only node for incident investigations: state is served exactly as found and nothing writes to it
+#[derive(Clone)]
+pub struct Forensic {
+    read_only_posts: Arc<Vec<String>>,
+}
+
+impl Forensic {
+    // `None` unless forensic mode is enabled
+    pub fn new(config: &ForensicConfig) -> Option<Self> {
+        config.enabled.then(|| Forensic { read_only_posts: Arc::new(config.read_only_posts.clone()) })
+    }
+
+    // Freeze every writer: storage, the audit log, scheduled jobs and state-persisting shutdown hooks
+    pub fn apply(&self, store: &Store, audit: Option<&AuditLog>, scheduler: &Scheduler, hooks: &mut ShutdownHooks) {
+        store.set_read_only(true);
+        if let Some(audit) = audit {
+            audit.set_read_only(true);
+        }
+        scheduler.freeze();
+        hooks.set_read_only(true);
+        warn!("forensic mode: storage is read-only, background jobs and mutating endpoints are disabled");
+    }
+
+    pub fn allows(&self, method: &Method, path: &str) -> bool {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => true,
+            Method::POST => self.read_only_posts.iter().any(|p| p == path),
+            _ => false,
+        }
+    }
+
+    // Rejects mutating requests before routing; mounted by `Router::forensic`
+    pub fn filter(&self) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        let forensic = self.clone();
+        warp::method()
+            .and(warp::path::full())
+            .and_then(move |method: Method, path: FullPath| {
+                let allowed = forensic.allows(&method, path.as_str());
+                async move {
+                    if !allowed {
+                        return Err(warp::reject::custom(ForensicMode));
+                    }
+                    Ok(())
+                }
+            })
+            .untuple_one()
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::{error, info};
+
+// Stages of a node's life, in the order they happen
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    // Before the listener is bound; a failing hook aborts startup
+    Start,
+
+    // The API is accepting connections
+    Ready,
+
+    // Shutdown began: no new connections, in-flight requests still finishing
+    Draining,
+
+    // Every connection is closed
+    Stopped,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Start => "start",
+            Phase::Ready => "ready",
+            Phase::Draining => "draining",
+            Phase::Stopped => "stopped",
+        }
+    }
+}
+
+type Hook = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> + Send>;
+
+// Async hooks an embedding application runs at each phase, e.g. to open its own pools before the node
+// serves and close them after it stopped. Phases run once each and in order, and the hooks of a phase run
+// one after another: start and ready hooks in registration order, draining and stopped hooks in reverse,
+// so what was set up first is torn down last.
+pub struct Lifecycle {
+    hooks: BTreeMap<Phase, Vec<(String, Hook)>>,
+
+    // Per hook; a hook that takes longer counts as failed
+    timeout: Duration,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Lifecycle { hooks: BTreeMap::new(), timeout: Duration::from_secs(30) }
+    }
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn add<F, Fut>(&mut self, phase: Phase, name: &str, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let hook: Hook = Box::new(move || Box::pin(hook()));
+        self.hooks.entry(phase).or_default().push((name.to_string(), hook));
+    }
+
+    pub fn on_start<F, Fut>(&mut self, name: &str, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.add(Phase::Start, name, hook);
+    }
+
+    pub fn on_ready<F, Fut>(&mut self, name: &str, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.add(Phase::Ready, name, hook);
+    }
+
+    pub fn on_draining<F, Fut>(&mut self, name: &str, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.add(Phase::Draining, name, hook);
+    }
+
+    pub fn on_stopped<F, Fut>(&mut self, name: &str, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.add(Phase::Stopped, name, hook);
+    }
+
+    // Run the hooks of `phase`; start stops at the first failure, later phases run every hook and report all failures
+    pub async fn run(&mut self, phase: Phase) -> Result<(), String> {
+        let mut hooks = self.hooks.remove(&phase).unwrap_or_default();
+        if matches!(phase, Phase::Draining | Phase::Stopped) {
+            hooks.reverse();
+        }
+        let mut failures = Vec::new();
+        for (name, hook) in hooks {
+            let result = match tokio::time::timeout(self.timeout, hook()).await {
+                Ok(result) => result,
+                Err(_) => Err(format!("did not finish within {}s", self.timeout.as_secs_f64())),
+            };
+            match result {
+                Ok(()) => info!(phase = phase.as_str(), hook = %name, "lifecycle hook done"),
+                Err(e) => {
+                    error!(phase = phase.as_str(), hook = %name, error = %e, "lifecycle hook failed");
+                    failures.push(format!("{}: {}", name, e));
+                    if phase == Phase::Start {
+                        break;
+                    }
+                }
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{} hooks failed: {}", phase.as_str(), failures.join("; ")))
+        }
+    }
+}
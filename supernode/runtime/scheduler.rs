@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+pub type JobFn = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>;
+
+struct ScheduledJob {
+    cadence: Duration,
+
+    // Random delay up to this much is added to every run
+    jitter: Duration,
+
+    paused: bool,
+    last_run: Option<DateTime<Utc>>,
+    next_run: DateTime<Utc>,
+    run: JobFn,
+}
+
+// Introspection view of a registered job
+#[derive(Serialize)]
+pub struct JobInfo {
+    pub name: String,
+    pub cadence_secs: u64,
+    pub paused: bool,
+    pub last_run: Option<DateTime<Utc>>,
+    pub next_run: DateTime<Utc>,
+}
+
+// Granularity at which due jobs are checked
+const TICK: Duration = Duration::from_secs(1);
+
+// Runs named periodic maintenance jobs (self-heal, model checkpoint, log compaction, ...)
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    jobs: Arc<Mutex<BTreeMap<String, ScheduledJob>>>,
+
+    // No job runs, on schedule or triggered, including ones registered later (forensic mode)
+    frozen: Arc<AtomicBool>,
+}
+
+fn next_after(now: DateTime<Utc>, cadence: Duration, jitter: Duration) -> DateTime<Utc> {
+    let jitter_ms = if jitter.is_zero() { 0 } else { rand::thread_rng().gen_range(0..jitter.as_millis() as u64) };
+    now + chrono::Duration::from_std(cadence + Duration::from_millis(jitter_ms)).unwrap_or_else(|_| chrono::Duration::zero())
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Register or replace a job
+    pub fn register(&self, name: &str, cadence: Duration, jitter: Duration, run: JobFn) {
+        let job = ScheduledJob {
+            cadence,
+            jitter,
+            paused: false,
+            last_run: None,
+            next_run: next_after(Utc::now(), cadence, jitter),
+            run,
+        };
+        self.jobs.lock().unwrap().insert(name.to_string(), job);
+    }
+
+    pub fn set_cadence(&self, name: &str, cadence: Duration) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get_mut(name) {
+            Some(job) => {
+                job.cadence = cadence;
+                job.next_run = next_after(Utc::now(), cadence, job.jitter);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn pause(&self, name: &str) -> bool {
+        self.set_paused(name, true)
+    }
+
+    pub fn resume(&self, name: &str) -> bool {
+        self.set_paused(name, false)
+    }
+
+    fn set_paused(&self, name: &str, paused: bool) -> bool {
+        match self.jobs.lock().unwrap().get_mut(name) {
+            Some(job) => {
+                job.paused = paused;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn freeze(&self) {
+        self.frozen.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::SeqCst)
+    }
+
+    // Run a job now regardless of its schedule; refused while frozen
+    pub async fn trigger(&self, name: &str) -> bool {
+        if self.is_frozen() {
+            return false;
+        }
+        let run = {
+            let mut jobs = self.jobs.lock().unwrap();
+            match jobs.get_mut(name) {
+                Some(job) => {
+                    job.last_run = Some(Utc::now());
+                    job.run.clone()
+                }
+                None => return false,
+            }
+        };
+        run().await;
+        true
+    }
+
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, job)| JobInfo {
+                name: name.clone(),
+                cadence_secs: job.cadence.as_secs(),
+                paused: job.paused,
+                last_run: job.last_run,
+                next_run: job.next_run,
+            })
+            .collect()
+    }
+
+    // Drive all jobs until cancelled; each due job runs on its own task
+    pub async fn run(self, token: CancellationToken) {
+        let mut ticker = tokio::time::interval(TICK);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => return,
+                _ = ticker.tick() => {}
+            }
+            if self.is_frozen() {
+                continue;
+            }
+            let now = Utc::now();
+            let due: Vec<JobFn> = {
+                let mut jobs = self.jobs.lock().unwrap();
+                jobs.values_mut()
+                    .filter(|job| !job.paused && job.next_run <= now)
+                    .map(|job| {
+                        job.last_run = Some(now);
+                        job.next_run = next_after(now, job.cadence, job.jitter);
+                        job.run.clone()
+                    })
+                    .collect()
+            };
+            for run in due {
+                tokio::spawn(run());
+            }
+        }
+    }
+}
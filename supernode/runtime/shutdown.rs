@@ -0,0 +1,51 @@
+use tracing::{error, info};
+
+type Hook = Box<dyn FnOnce() -> Result<(), String> + Send>;
+
+// Final flushes (model checkpoint, state file, log and span exporters) run once the server has drained
+#[derive(Default)]
+pub struct ShutdownHooks {
+    hooks: Vec<(String, Hook, bool)>,
+
+    // Forensic mode: hooks added with `add_writer` are skipped so state on disk stays as found
+    read_only: bool,
+}
+
+impl ShutdownHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Hooks run in registration order, so register log flushing last
+    pub fn add(&mut self, name: &str, hook: impl FnOnce() -> Result<(), String> + Send + 'static) {
+        self.hooks.push((name.to_string(), Box::new(hook), false));
+    }
+
+    // A hook that persists node state (state file, model checkpoint) rather than flushing telemetry
+    pub fn add_writer(&mut self, name: &str, hook: impl FnOnce() -> Result<(), String> + Send + 'static) {
+        self.hooks.push((name.to_string(), Box::new(hook), true));
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    // Runs every hook even if an earlier one fails; returns whether all succeeded
+    pub fn run(self) -> bool {
+        let mut ok = true;
+        for (name, hook, writes) in self.hooks {
+            if writes && self.read_only {
+                info!(hook = %name, "shutdown hook skipped, node is read-only");
+                continue;
+            }
+            match hook() {
+                Ok(()) => info!(hook = %name, "shutdown hook done"),
+                Err(e) => {
+                    error!(hook = %name, error = %e, "shutdown hook failed");
+                    ok = false;
+                }
+            }
+        }
+        ok
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+// Shared view of which tasks are still running, for health probes
+#[derive(Clone, Default)]
+pub struct TaskLiveness {
+    running: Arc<Mutex<BTreeMap<String, bool>>>,
+}
+
+impl TaskLiveness {
+    // Names of tasks that exited while the node was not shutting down
+    pub fn dead(&self) -> Vec<String> {
+        self.running.lock().unwrap().iter().filter(|(_, alive)| !**alive).map(|(n, _)| n.clone()).collect()
+    }
+}
+
+// Owns every long-running background task so the node can stop them cleanly
+pub struct TaskGroup {
+    token: CancellationToken,
+    handles: Vec<(String, JoinHandle<()>)>,
+    liveness: TaskLiveness,
+}
+
+impl TaskGroup {
+    pub fn new() -> Self {
+        TaskGroup { token: CancellationToken::new(), handles: Vec::new(), liveness: TaskLiveness::default() }
+    }
+
+    // Token handed to tasks; it is cancelled when shutdown starts
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    // Spawn a task that receives a child token and must return once it is cancelled
+    pub fn spawn<F, Fut>(&mut self, name: &str, task: F)
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let running = self.liveness.running.clone();
+        running.lock().unwrap().insert(name.to_string(), true);
+        let fut = task(self.token.child_token());
+        let task_name = name.to_string();
+        let token = self.token.clone();
+        let handle = tokio::spawn(async move {
+            fut.await;
+            let mut running = running.lock().unwrap();
+            if token.is_cancelled() {
+                running.remove(&task_name);
+            } else {
+                running.insert(task_name, false);
+            }
+        });
+        self.handles.push((name.to_string(), handle));
+    }
+
+    pub fn liveness(&self) -> TaskLiveness {
+        self.liveness.clone()
+    }
+
+    // Cancel all tasks and wait for them, aborting any that overrun the grace period
+    pub async fn shutdown(self, grace: Duration) {
+        self.token.cancel();
+        for (name, mut handle) in self.handles {
+            match tokio::time::timeout(grace, &mut handle).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!(task = %name, error = %e, "task ended with error"),
+                Err(_) => {
+                    warn!(task = %name, ?grace, "task did not stop in time, aborting");
+                    handle.abort();
+                }
+            }
+        }
+    }
+}
+
+impl Default for TaskGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Run `tick` on a fixed interval until the token is cancelled
+pub async fn run_every<F, Fut>(token: CancellationToken, period: Duration, mut tick: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut ticker = tokio::time::interval(period);
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => return,
+            _ = ticker.tick() => tick().await,
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use crate::api::compression::{self, CompressionConfig};
+use crate::api::problem::Problem;
+use crate::api::signing::RequestVerifier;
+use crate::runtime::deadline::{Deadline, BUDGET_HEADER};
+use crate::runtime::lifecycle::{Lifecycle, Phase};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tokio_rustls::rustls::server::{AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient};
+use tokio_rustls::rustls::{self, Certificate, PrivateKey, RootCertStore};
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+use warp::{Filter, Rejection, Reply};
+
+// `server` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub address: String,
+    pub port: u16,
+
+    // Tokio worker threads, defaults to the number of cores
+    pub worker_threads: Option<usize>,
+
+    // Total budget of a request; storage, oracle and peer calls made for it share what is left
+    pub request_timeout_secs: u64,
+    pub max_body_bytes: u64,
+
+    // How long in-flight requests may take to finish once shutdown starts
+    pub drain_timeout_secs: u64,
+
+    // Overrides of the timeout and body limit for paths under a prefix; the longest prefix wins
+    pub route_limits: Vec<RouteLimits>,
+
+    pub cors: CorsConfig,
+    pub compression: CompressionConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            address: "127.0.0.1".to_string(),
+            port: 3030,
+            worker_threads: None,
+            request_timeout_secs: 30,
+            max_body_bytes: 64 * 1024,
+            drain_timeout_secs: 30,
+            route_limits: Vec::new(),
+            cors: CorsConfig::default(),
+            compression: CompressionConfig::default(),
+        }
+    }
+}
+
+// `server.cors` section; the default allows no cross-origin browser calls at all
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    // Exact origins such as https://app.example.com; `*` allows any origin and cannot be combined with credentials
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+
+    // Response headers scripts may read
+    pub exposed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: Vec::new(),
+            allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            allowed_headers: vec!["content-type".to_string(), "authorization".to_string(), "x-api-key".to_string()],
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
+}
+
+impl CorsConfig {
+    // `None` when no origin is allowed, so browsers get no CORS headers and block cross-origin calls
+    pub fn filter(&self) -> Result<Option<warp::filters::cors::Cors>, String> {
+        if self.allowed_origins.is_empty() {
+            return Ok(None);
+        }
+        let any_origin = self.allowed_origins.iter().any(|o| o == "*");
+        if any_origin && self.allow_credentials {
+            return Err("server.cors: allow_credentials cannot be combined with the `*` origin".to_string());
+        }
+        for origin in self.allowed_origins.iter().filter(|o| *o != "*") {
+            let uri: warp::http::Uri = origin.parse().map_err(|e| format!("server.cors: invalid origin {}: {}", origin, e))?;
+            if uri.scheme().is_none() || uri.host().is_none() || uri.path() != "/" || origin.ends_with('/') {
+                return Err(format!("server.cors: origin {} must be scheme://host[:port] with no path", origin));
+            }
+        }
+        // warp panics on malformed values, so reject them here with a readable error
+        for method in &self.allowed_methods {
+            method.parse::<warp::http::Method>().map_err(|_| format!("server.cors: invalid method {}", method))?;
+        }
+        for header in self.allowed_headers.iter().chain(&self.exposed_headers) {
+            warp::http::header::HeaderName::from_bytes(header.as_bytes()).map_err(|_| format!("server.cors: invalid header {}", header))?;
+        }
+        let mut builder = warp::cors()
+            .allow_methods(self.allowed_methods.iter().map(String::as_str))
+            .allow_headers(self.allowed_headers.iter().map(String::as_str))
+            .expose_headers(self.exposed_headers.iter().map(String::as_str))
+            .allow_credentials(self.allow_credentials)
+            .max_age(Duration::from_secs(self.max_age_secs));
+        builder = if any_origin { builder.allow_any_origin() } else { builder.allow_origins(self.allowed_origins.iter().map(String::as_str)) };
+        Ok(Some(builder.build()))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RouteLimits {
+    pub path_prefix: String,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub max_body_bytes: Option<u64>,
+}
+
+impl ServerConfig {
+    // Timeout and body limit that apply to `path`
+    pub fn limits_for(&self, path: &str) -> (Duration, u64) {
+        let route = self.route_limits.iter().filter(|r| path.starts_with(&r.path_prefix)).max_by_key(|r| r.path_prefix.len());
+        (
+            Duration::from_secs(route.and_then(|r| r.timeout_secs).unwrap_or(self.request_timeout_secs)),
+            route.and_then(|r| r.max_body_bytes).unwrap_or(self.max_body_bytes),
+        )
+    }
+
+    pub fn socket_addr(&self) -> Result<SocketAddr, String> {
+        format!("{}:{}", self.address, self.port)
+            .parse()
+            .map_err(|e| format!("invalid server address {}:{}: {}", self.address, self.port, e))
+    }
+
+    // Runtime sized from the config, built before anything else starts
+    pub fn runtime(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(threads) = self.worker_threads {
+            builder.worker_threads(threads);
+        }
+        builder.build()
+    }
+}
+
+// `tls` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+
+    // CA bundle for verifying client certificates (mutual TLS)
+    #[serde(default)]
+    pub client_ca_path: Option<PathBuf>,
+
+    // Reject clients without a certificate instead of merely verifying those that send one
+    #[serde(default)]
+    pub client_auth_required: bool,
+}
+
+impl TlsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for path in [&self.cert_path, &self.key_path].into_iter().chain(self.client_ca_path.as_ref()) {
+            if !path.exists() {
+                return Err(format!("TLS file {} does not exist", path.display()));
+            }
+        }
+        if self.client_auth_required && self.client_ca_path.is_none() {
+            return Err("client_auth_required needs client_ca_path".to_string());
+        }
+        Ok(())
+    }
+
+    fn acceptor(&self) -> Result<TlsAcceptor, String> {
+        self.validate()?;
+        let open = |path: &PathBuf| File::open(path).map(BufReader::new).map_err(|e| format!("{}: {}", path.display(), e));
+        let certs = rustls_pemfile::certs(&mut open(&self.cert_path)?)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(Certificate)
+            .collect();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut open(&self.key_path)?)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .map(PrivateKey)
+            .ok_or_else(|| format!("no PKCS#8 key in {}", self.key_path.display()))?;
+        let builder = rustls::ServerConfig::builder().with_safe_defaults();
+        let config = match &self.client_ca_path {
+            None => builder.with_no_client_auth().with_single_cert(certs, key),
+            Some(ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in rustls_pemfile::certs(&mut open(ca)?).map_err(|e| e.to_string())? {
+                    roots.add(&Certificate(cert)).map_err(|e| e.to_string())?;
+                }
+                if self.client_auth_required {
+                    builder.with_client_cert_verifier(Arc::new(AllowAnyAuthenticatedClient::new(roots))).with_single_cert(certs, key)
+                } else {
+                    builder
+                        .with_client_cert_verifier(Arc::new(AllowAnyAnonymousOrAuthenticatedClient::new(roots)))
+                        .with_single_cert(certs, key)
+                }
+            }
+        }
+        .map_err(|e| e.to_string())?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+// Client address, attached to every request for filters via `warp::ext::optional::<PeerAddr>()`
+#[derive(Clone, Copy, Debug)]
+pub struct PeerAddr(pub SocketAddr);
+
+fn problem_response(status: StatusCode, code: &'static str, detail: String) -> Response<Body> {
+    Problem::new(status, code, Some(detail)).into_response()
+}
+
+fn too_large(max_body: u64) -> Response<Body> {
+    problem_response(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large", format!("request body exceeds {} bytes", max_body))
+}
+
+// Bodies without a Content-Length are read up to the limit here, so chunked uploads cannot bypass it;
+// the buffered body gets a Content-Length so `content_length_limit` filters accept it
+async fn limit_body(req: Request<Body>, max_body: u64) -> Result<Request<Body>, Response<Body>> {
+    let declared = req.headers().get(hyper::header::CONTENT_LENGTH).and_then(|v| v.to_str().ok()).and_then(|v| v.parse::<u64>().ok());
+    match declared {
+        Some(len) if len > max_body => Err(too_large(max_body)),
+        Some(_) => Ok(req),
+        None if req.method() == hyper::Method::GET || req.method() == hyper::Method::HEAD => Ok(req),
+        None => {
+            let (mut parts, mut body) = req.into_parts();
+            let mut buffered = Vec::new();
+            while let Some(chunk) = hyper::body::HttpBody::data(&mut body).await {
+                let chunk = chunk.map_err(|e| problem_response(StatusCode::BAD_REQUEST, "invalid_body", e.to_string()))?;
+                if (buffered.len() + chunk.len()) as u64 > max_body {
+                    return Err(too_large(max_body));
+                }
+                buffered.extend_from_slice(&chunk);
+            }
+            parts.headers.insert(hyper::header::CONTENT_LENGTH, hyper::header::HeaderValue::from(buffered.len()));
+            parts.headers.remove(hyper::header::TRANSFER_ENCODING);
+            Ok(Request::from_parts(parts, Body::from(buffered)))
+        }
+    }
+}
+
+// Drive a connection until it ends; on shutdown, finish the request in flight and close instead of keeping it alive
+// Upgrades are enabled so a 101 hands the socket to the route, e.g. GET /ws/events
+macro_rules! serve_until_shutdown {
+    ($io:expr, $service:expr, $shutdown:expr) => {{
+        let connection = Http::new().serve_connection($io, $service).with_upgrades();
+        tokio::pin!(connection);
+        tokio::select! {
+            result = connection.as_mut() => result,
+            _ = $shutdown.cancelled() => {
+                connection.as_mut().graceful_shutdown();
+                connection.await
+            }
+        }
+    }};
+}
+
+// Cancel `token` on SIGTERM or Ctrl-C
+pub fn cancel_on_signal(token: CancellationToken) {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(e) => {
+                    warn!(error = %e, "cannot listen for SIGTERM");
+                    std::future::pending::<()>().await
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => info!("interrupt received, shutting down"),
+            _ = terminate => info!("SIGTERM received, shutting down"),
+        }
+        token.cancel();
+    });
+}
+
+// Serve the API with the configured address, limits and optional TLS until `shutdown` is cancelled,
+// then stop accepting and drain open connections for up to `drain_timeout_secs`
+// Signed requests are verified by `signing` before routing so their bodies can be hashed
+pub async fn serve<F>(
+    routes: F,
+    config: &ServerConfig,
+    tls: Option<&TlsConfig>,
+    signing: Option<Arc<RequestVerifier>>,
+    shutdown: CancellationToken,
+) -> Result<(), String>
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    serve_with_lifecycle(routes, config, tls, signing, shutdown, Lifecycle::new()).await
+}
+
+// `serve`, running the embedder's `lifecycle` hooks: start before binding (a failure aborts), ready once
+// listening, draining when shutdown starts and stopped after the last connection closed
+pub async fn serve_with_lifecycle<F>(
+    routes: F,
+    config: &ServerConfig,
+    tls: Option<&TlsConfig>,
+    signing: Option<Arc<RequestVerifier>>,
+    shutdown: CancellationToken,
+    mut lifecycle: Lifecycle,
+) -> Result<(), String>
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    let addr = config.socket_addr()?;
+    config.compression.validate()?;
+    let acceptor = tls.map(TlsConfig::acceptor).transpose()?;
+    lifecycle.run(Phase::Start).await?;
+    let listener = TcpListener::bind(addr).await.map_err(|e| format!("failed to bind {}: {}", addr, e))?;
+    info!(%addr, tls = acceptor.is_some(), "API server listening");
+    let _ = lifecycle.run(Phase::Ready).await;
+
+    let warp_service = warp::service(routes);
+    let config = Arc::new(config.clone());
+    let mut connections = JoinSet::new();
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok(conn) => conn,
+                Err(e) => {
+                    debug!(error = %e, "accept failed");
+                    continue;
+                }
+            },
+            // Reap finished connections so the set does not grow for the life of the server
+            Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+        };
+        let warp_service = warp_service.clone();
+        let signing = signing.clone();
+        let config = config.clone();
+        let service = service_fn(move |mut req: Request<Body>| {
+            let mut warp_service = warp_service.clone();
+            let signing = signing.clone();
+            let (timeout, max_body) = config.limits_for(req.uri().path());
+            let accept_encoding = req.headers().get(hyper::header::ACCEPT_ENCODING).cloned();
+            req.extensions_mut().insert(PeerAddr(peer));
+            let config = config.clone();
+            async move {
+                let mut req = match limit_body(req, max_body).await {
+                    Ok(req) => req,
+                    Err(response) => return Ok::<_, std::convert::Infallible>(response),
+                };
+                let deadline = Deadline::for_request(timeout, req.headers().get(BUDGET_HEADER).and_then(|v| v.to_str().ok()));
+                req.extensions_mut().insert(deadline);
+                let req = match &signing {
+                    Some(signing) => match signing.verify(req).await {
+                        Ok(req) => req,
+                        Err(response) => return Ok(response),
+                    },
+                    None => req,
+                };
+                match tokio::time::timeout(deadline.remaining(), deadline.scope(warp_service.call(req))).await {
+                    Ok(Ok(response)) => Ok(compression::compress(&config.compression, accept_encoding.as_ref(), response).await),
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Ok(problem_response(
+                        StatusCode::REQUEST_TIMEOUT,
+                        "request_timeout",
+                        format!("request was not answered within {}s", timeout.as_secs_f64()),
+                    )),
+                }
+            }
+        });
+        let acceptor = acceptor.clone();
+        let shutdown = shutdown.clone();
+        connections.spawn(async move {
+            let result = match acceptor {
+                Some(acceptor) => match acceptor.accept(stream).await {
+                    Ok(tls_stream) => serve_until_shutdown!(tls_stream, service, shutdown),
+                    Err(e) => {
+                        debug!(%peer, error = %e, "TLS handshake failed");
+                        return;
+                    }
+                },
+                None => serve_until_shutdown!(stream, service, shutdown),
+            };
+            if let Err(e) = result {
+                debug!(%peer, error = %e, "connection closed with error");
+            }
+        });
+    }
+
+    drop(listener);
+    let _ = lifecycle.run(Phase::Draining).await;
+    let drain = Duration::from_secs(config.drain_timeout_secs);
+    info!(open = connections.len(), ?drain, "API server stopped accepting, draining connections");
+    let drained = tokio::time::timeout(drain, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        warn!(remaining = connections.len(), "drain timeout reached, closing remaining connections");
+        connections.shutdown().await;
+    }
+    info!("API server stopped");
+    let _ = lifecycle.run(Phase::Stopped).await;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    // Serve `routes` on a free local port until the token is cancelled
+    pub(crate) async fn spawn<F>(routes: F) -> (SocketAddr, CancellationToken)
+    where
+        F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+        F::Extract: Reply,
+    {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let config = ServerConfig { port, ..ServerConfig::default() };
+        let addr = config.socket_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        let token = shutdown.clone();
+        tokio::spawn(async move { serve(routes, &config, None, None, token).await });
+        for _ in 0..50 {
+            if TcpStream::connect(addr).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        (addr, shutdown)
+    }
+
+    // Open a WebSocket on `path`; returns the status line and, after a 101, the socket
+    pub(crate) async fn upgrade(addr: SocketAddr, path: &str, headers: &[(&str, &str)]) -> (String, TcpStream) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n",
+            path, addr
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8];
+            if stream.read(&mut byte).await.unwrap() == 0 {
+                break;
+            }
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8(head).unwrap();
+        (head.lines().next().unwrap_or_default().to_string(), stream)
+    }
+
+    // Next unmasked text frame sent by the server
+    pub(crate) async fn read_text(stream: &mut TcpStream) -> String {
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(header[0], 0x81, "expected a final text frame");
+        let len = match header[1] & 0x7f {
+            126 => stream.read_u16().await.unwrap() as usize,
+            127 => stream.read_u64().await.unwrap() as usize,
+            len => len as usize,
+        };
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).await.unwrap();
+        String::from_utf8(payload).unwrap()
+    }
+
+    #[tokio::test]
+    async fn completes_websocket_upgrades() {
+        let routes = warp::path!("echo").and(warp::ws()).map(|ws: warp::ws::Ws| {
+            ws.on_upgrade(|mut socket| async move {
+                use futures::SinkExt;
+                let _ = socket.send(warp::ws::Message::text("upgraded")).await;
+            })
+        });
+        let (addr, shutdown) = spawn(routes).await;
+        let (status, mut stream) = upgrade(addr, "/echo", &[]).await;
+        assert!(status.contains("101"), "{}", status);
+        let message = tokio::time::timeout(Duration::from_secs(5), read_text(&mut stream)).await.expect("no frame after the upgrade");
+        assert_eq!(message, "upgraded");
+        shutdown.cancel();
+    }
+}
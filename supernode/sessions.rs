@@ -0,0 +1,340 @@
+use crate::api::auth::{Auth, AuthError, Principal, Scope, SessionVerifier};
+use crate::key_compromise::SessionInvalidator;
+use crate::runtime::scheduler::Scheduler;
+use crate::storage::entities::{EntityError, EntityStore};
+use crate::storage::mvcc::Store;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use warp::http::{Method, StatusCode};
+use warp::{Filter, Rejection, Reply};
+
+// `sessions` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    // Hex key the session cookies are signed with; a random one per start when unset, which logs everyone out on restart
+    pub secret: Option<String>,
+
+    // Lifetime of a session, and how long it may go unused before it lapses
+    pub ttl_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub cookie_name: String,
+
+    // Only send the cookie over HTTPS; turn off for plain-HTTP development setups
+    pub secure_cookie: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            secret: None,
+            ttl_secs: 8 * 3600,
+            idle_timeout_secs: 1800,
+            cookie_name: "pi_session".to_string(),
+            secure_cookie: true,
+        }
+    }
+}
+
+// Last-seen times are written at most this often per session
+const TOUCH_INTERVAL_SECS: i64 = 60;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+
+    // Credential the session was opened with
+    pub login_credential: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    fn active(&self, now: DateTime<Utc>, idle: ChronoDuration) -> bool {
+        self.revoked_at.is_none() && now < self.expires_at && now - self.last_seen_at < idle
+    }
+}
+
+// Returned by login; the session token itself only travels in the HttpOnly cookie
+#[derive(Serialize)]
+pub struct LoginResponse {
+    pub session_id: String,
+    pub expires_at: DateTime<Utc>,
+
+    // Send back as `x-csrf-token` on every request that changes state
+    pub csrf_token: String,
+}
+
+// Login sessions for the admin UI and explorer: an API key or bearer token is exchanged once for a signed,
+// HttpOnly cookie. Sessions live in storage, so they can be listed and revoked; revoked ones stay listed until
+// they would have expired.
+#[derive(Clone)]
+pub struct SessionManager {
+    config: Arc<SessionConfig>,
+    secret: Arc<Vec<u8>>,
+    sessions: EntityStore<Session>,
+}
+
+impl SessionManager {
+    pub fn new(config: SessionConfig, store: Store) -> Result<Self, String> {
+        let secret = match &config.secret {
+            Some(hex_key) => {
+                let key = hex::decode(hex_key).map_err(|e| format!("sessions.secret is not hex: {}", e))?;
+                if key.len() < 32 {
+                    return Err("sessions.secret must be at least 32 bytes".to_string());
+                }
+                key
+            }
+            None => {
+                warn!("sessions.secret is not set, sessions will not survive a restart");
+                let mut key = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+                key
+            }
+        };
+        Ok(SessionManager { config: Arc::new(config), secret: Arc::new(secret), sessions: EntityStore::new(store, "sessions") })
+    }
+
+    fn mac(&self, purpose: &str, id: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(purpose.as_bytes());
+        mac.update(b".");
+        mac.update(id.as_bytes());
+        mac
+    }
+
+    fn sign(&self, purpose: &str, id: &str) -> String {
+        hex::encode(self.mac(purpose, id).finalize().into_bytes())
+    }
+
+    // Constant-time check of a signature made by `sign`
+    fn signed(&self, purpose: &str, id: &str, signature: &str) -> bool {
+        hex::decode(signature).is_ok_and(|bytes| self.mac(purpose, id).verify_slice(&bytes).is_ok())
+    }
+
+    fn idle(&self) -> ChronoDuration {
+        ChronoDuration::seconds(self.config.idle_timeout_secs as i64)
+    }
+
+    // Open a session for `principal`; returns the cookie token and what the client may see
+    pub fn login(&self, principal: &Principal) -> Result<(String, LoginResponse), EntityError> {
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let id = hex::encode(bytes);
+        let now = Utc::now();
+        let session = Session {
+            subject: principal.subject.clone(),
+            scopes: principal.scopes.clone(),
+            tenant: principal.tenant.clone(),
+            login_credential: principal.credential_id.clone(),
+            created_at: now,
+            expires_at: now + ChronoDuration::seconds(self.config.ttl_secs as i64),
+            last_seen_at: now,
+            revoked_at: None,
+        };
+        self.sessions.create(&id, session.clone())?;
+        info!(subject = %session.subject, session = %id, "session opened");
+        let token = format!("{}.{}", id, self.sign("session", &id));
+        let response = LoginResponse { csrf_token: self.sign("csrf", &id), session_id: id, expires_at: session.expires_at };
+        Ok((token, response))
+    }
+
+    pub fn revoke(&self, id: &str) -> Result<Session, EntityError> {
+        let entity = self.sessions.get(id)?;
+        let mut session = entity.value;
+        if session.revoked_at.is_none() {
+            session.revoked_at = Some(Utc::now());
+            self.sessions.update(id, entity.version, session.clone())?;
+            info!(session = %id, subject = %session.subject, "session revoked");
+        }
+        Ok(session)
+    }
+
+    // Revoke every session still active; returns how many
+    pub fn revoke_all(&self) -> Result<usize, String> {
+        let now = Utc::now();
+        let mut revoked = 0;
+        for (id, entity) in self.sessions.list() {
+            if entity.value.active(now, self.idle()) {
+                self.revoke(&id).map_err(|e| e.to_string())?;
+                revoked += 1;
+            }
+        }
+        Ok(revoked)
+    }
+
+    pub fn list(&self) -> Vec<(String, Session)> {
+        self.sessions.list().into_iter().map(|(id, e)| (id, e.value)).collect()
+    }
+
+    // Drop sessions past their expiry, revoked or not; an expired cookie is refused either way
+    pub fn purge_expired(&self) -> usize {
+        let now = Utc::now();
+        let mut purged = 0;
+        for (id, entity) in self.sessions.list() {
+            if entity.value.expires_at <= now && self.sessions.delete(&id, entity.version).is_ok() {
+                purged += 1;
+            }
+        }
+        purged
+    }
+
+    fn cookie(&self, value: &str, max_age: i64) -> String {
+        let secure = if self.config.secure_cookie { "; Secure" } else { "" };
+        format!("{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}{}", self.config.cookie_name, value, max_age, secure)
+    }
+
+    // POST /admin/session/login and /admin/session/logout, GET /admin/sessions, DELETE /admin/sessions/{id}
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let manager = self.clone();
+        let login = warp::path!("admin" / "session" / "login").and(warp::post()).and(auth.authorized()).map(move |principal: Principal| {
+            if principal.credential_id.starts_with("session:") {
+                let e = "log in with an API key or bearer token, not a session";
+                return warp::reply::with_status(warp::reply::json(&e), StatusCode::BAD_REQUEST).into_response();
+            }
+            match manager.login(&principal) {
+                Ok((token, response)) => {
+                    let cookie = manager.cookie(&token, manager.config.ttl_secs as i64);
+                    warp::reply::with_header(warp::reply::json(&response), "set-cookie", cookie).into_response()
+                }
+                Err(e) => warp::reply::with_status(warp::reply::json(&e.to_string()), StatusCode::SERVICE_UNAVAILABLE).into_response(),
+            }
+        });
+
+        let manager = self.clone();
+        let logout = warp::path!("admin" / "session" / "logout").and(warp::post()).and(auth.authorized()).map(move |principal: Principal| {
+            if let Some(id) = principal.credential_id.strip_prefix("session:") {
+                if let Err(e) = manager.revoke(id) {
+                    warn!(session = %id, error = %e, "logout could not revoke the session");
+                }
+            }
+            warp::reply::with_header(StatusCode::NO_CONTENT, "set-cookie", manager.cookie("", 0))
+        });
+
+        let manager = self.clone();
+        let list = warp::path!("admin" / "sessions")
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |_| warp::reply::json(&manager.list().into_iter().collect::<std::collections::BTreeMap<_, _>>()));
+
+        let manager = self.clone();
+        let revoke = warp::path!("admin" / "sessions" / String).and(warp::delete()).and(auth.authorized()).map(
+            move |id: String, principal: Principal| match manager.revoke(&id) {
+                Ok(session) => {
+                    info!(session = %id, by = %principal.subject, "session revoked by an admin");
+                    warp::reply::with_status(warp::reply::json(&session), StatusCode::OK)
+                }
+                Err(e) => {
+                    let status = match e {
+                        EntityError::NotFound => StatusCode::NOT_FOUND,
+                        EntityError::ReadOnly | EntityError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+                        _ => StatusCode::CONFLICT,
+                    };
+                    warp::reply::with_status(warp::reply::json(&e.to_string()), status)
+                }
+            },
+        );
+
+        login.or(logout).or(list).or(revoke)
+    }
+}
+
+impl SessionVerifier for SessionManager {
+    fn cookie_name(&self) -> &str {
+        &self.config.cookie_name
+    }
+
+    fn verify(&self, token: &str, method: &Method, csrf: Option<&str>) -> Result<Principal, AuthError> {
+        let invalid = |reason: &str| AuthError::Invalid(reason.to_string());
+        let (id, signature) = token.split_once('.').ok_or_else(|| invalid("malformed session cookie"))?;
+        if !self.signed("session", id, signature) {
+            return Err(invalid("session cookie signature does not match"));
+        }
+        let entity = self.sessions.get(id).map_err(|_| invalid("unknown session"))?;
+        let mut session = entity.value;
+        let now = Utc::now();
+        if !session.active(now, self.idle()) {
+            return Err(invalid("session expired or revoked"));
+        }
+        // Cookies ride along on cross-site requests; a state change must also prove it read the login response
+        let safe = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+        if !safe && !csrf.is_some_and(|token| self.signed("csrf", id, token)) {
+            return Err(AuthError::Forbidden);
+        }
+        if now - session.last_seen_at >= ChronoDuration::seconds(TOUCH_INTERVAL_SECS) {
+            session.last_seen_at = now;
+            // A concurrent touch winning is fine
+            let _ = self.sessions.update(id, entity.version, session.clone());
+        }
+        Ok(Principal { subject: session.subject, scopes: session.scopes, credential_id: format!("session:{}", id), tenant: session.tenant })
+    }
+}
+
+// A compromised node key ends every session, so all admin UI users must log in again
+impl SessionInvalidator for SessionManager {
+    fn name(&self) -> &str {
+        "admin_sessions"
+    }
+
+    fn invalidate(&self, _key_id: &str) -> Result<usize, String> {
+        self.revoke_all()
+    }
+}
+
+// Purge expired sessions hourly
+pub fn register_purge(scheduler: &Scheduler, sessions: SessionManager) {
+    scheduler.register(
+        "sessions:purge",
+        Duration::from_secs(3600),
+        Duration::ZERO,
+        Arc::new(move || {
+            let sessions = sessions.clone();
+            Box::pin(async move {
+                let purged = sessions.purge_expired();
+                if purged > 0 {
+                    info!(purged, "expired sessions purged");
+                }
+            })
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig};
+    use crate::api::router::Router;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn login_cookies_authenticate_until_the_node_key_is_compromised() {
+        let config = SessionConfig { secure_cookie: false, ..SessionConfig::default() };
+        let manager = SessionManager::new(config, Store::new()).unwrap();
+        let api_keys = HashMap::from([("k-admin".to_string(), ApiKeyConfig { subject: "ops".to_string(), scopes: vec![Scope::Admin], tenant: None })]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap().with_sessions(Arc::new(manager.clone()));
+        let api = warp::any().and(Router::new().mount("sessions", manager.routes(&auth)).build());
+
+        let login = warp::test::request().method("POST").path("/admin/session/login").header("x-api-key", "k-admin").reply(&api).await;
+        assert_eq!(login.status(), StatusCode::OK);
+        let cookie = login.headers()["set-cookie"].to_str().unwrap().split(';').next().unwrap().to_string();
+
+        let listed = warp::test::request().path("/admin/sessions").header("cookie", &cookie).reply(&api).await;
+        assert_eq!(listed.status(), StatusCode::OK);
+
+        assert_eq!(manager.invalidate("any").unwrap(), 1);
+        let listed = warp::test::request().path("/admin/sessions").header("cookie", &cookie).reply(&api).await;
+        assert_eq!(listed.status(), StatusCode::UNAUTHORIZED);
+    }
+}
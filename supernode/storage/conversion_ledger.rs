@@ -0,0 +1,231 @@
+use crate::amount::units_string;
+use crate::api::auth::{Auth, Principal, Scope};
+use crate::api::problem::ApiError;
+use crate::converter::{Conversion, Direction, RateQuote};
+use crate::fees::FeeCharge;
+use crate::ids::ConversionId;
+use crate::storage::mvcc::{Store, WriteBatch};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use warp::{Filter, Rejection, Reply};
+
+const PREFIX: &str = "conversions/ledger/";
+
+// One conversion as accepted by the node; written once and never changed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConversionEntry {
+    pub id: ConversionId,
+
+    // Subject that requested the conversion
+    pub account: String,
+    pub from_asset: String,
+    pub to_asset: String,
+    pub direction: Direction,
+    #[serde(with = "units_string")]
+    pub amount_in: u128,
+    #[serde(with = "units_string")]
+    pub amount_out: u128,
+    pub rate: RateQuote,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeCharge>,
+    pub at: DateTime<Utc>,
+
+    // Hex SHA-256 of every other field, to tell a stored entry was not edited after the fact
+    pub hash: String,
+}
+
+impl ConversionEntry {
+    pub fn new(id: ConversionId, account: &str, conversion: &Conversion, at: DateTime<Utc>) -> Self {
+        let mut entry = ConversionEntry {
+            id,
+            account: account.to_string(),
+            from_asset: conversion.asset.clone(),
+            to_asset: conversion.to_asset.clone(),
+            direction: conversion.direction,
+            amount_in: conversion.amount,
+            amount_out: conversion.converted_amount,
+            rate: conversion.rate.clone(),
+            fee: conversion.fees.clone(),
+            at,
+            hash: String::new(),
+        };
+        entry.hash = entry.digest();
+        entry
+    }
+
+    fn digest(&self) -> String {
+        let unhashed = ConversionEntry { hash: String::new(), ..self.clone() };
+        hex::encode(Sha256::digest(serde_json::to_vec(&unhashed).unwrap_or_default()))
+    }
+
+    pub fn verify(&self) -> bool {
+        self.digest() == self.hash
+    }
+
+    fn involves(&self, asset: &str) -> bool {
+        self.from_asset == asset || self.to_asset == asset
+    }
+}
+
+// Filters of a ledger query; all optional, `from` inclusive and `to` exclusive
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ConversionQuery {
+    pub account: Option<String>,
+
+    // Matches either side of the conversion
+    pub asset: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+
+    // Page on from the last id of the previous page
+    pub before: Option<String>,
+    pub limit: Option<usize>,
+}
+
+// Durable record of every conversion the node accepted, keyed by conversion id so key order is time order; entries are
+// journaled with the rest of the store, so an accepted conversion survives a crash
+#[derive(Clone)]
+pub struct ConversionLedger {
+    store: Store,
+}
+
+impl ConversionLedger {
+    pub fn new(store: Store) -> Self {
+        ConversionLedger { store }
+    }
+
+    // Add `entry` to `batch`, so it is committed together with whatever accepted the conversion
+    pub fn put(&self, batch: &mut WriteBatch, entry: &ConversionEntry) -> Result<(), String> {
+        batch.put(format!("{}{}", PREFIX, entry.id), serde_json::to_vec(entry).map_err(|e| e.to_string())?);
+        Ok(())
+    }
+
+    // Commit `batch` with `entry` in it
+    pub fn record(&self, mut batch: WriteBatch, entry: &ConversionEntry) -> Result<(), String> {
+        self.put(&mut batch, entry)?;
+        self.store.try_commit(batch).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &ConversionId) -> Option<ConversionEntry> {
+        self.store.get(&format!("{}{}", PREFIX, id)).and_then(|v| serde_json::from_slice(&v).ok())
+    }
+
+    // Newest first
+    pub fn query(&self, query: &ConversionQuery) -> Vec<ConversionEntry> {
+        let before = query.before.as_ref().map(|id| format!("{}{}", PREFIX, id));
+        self.store
+            .read_txn()
+            .scan_prefix(PREFIX)
+            .into_iter()
+            .rev()
+            .filter(|(k, _)| before.as_ref().is_none_or(|b| k < b))
+            .filter_map(|(_, v)| serde_json::from_slice::<ConversionEntry>(&v).ok())
+            .skip_while(|e| query.to.is_some_and(|to| e.at >= to))
+            .take_while(|e| query.from.is_none_or(|from| e.at >= from))
+            .filter(|e| query.account.as_ref().is_none_or(|a| &e.account == a))
+            .filter(|e| query.asset.as_ref().is_none_or(|a| e.involves(a)))
+            .take(query.limit.unwrap_or(100).min(1000))
+            .collect()
+    }
+}
+
+// GET /v1/conversions and GET /v1/conversions/{id}; callers without the admin scope only see their own
+pub fn routes(ledger: ConversionLedger, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let l = ledger.clone();
+    let list = warp::path!("v1" / "conversions").and(warp::get()).and(auth.authorized()).and(warp::query::<ConversionQuery>()).map(
+        move |principal: Principal, mut query: ConversionQuery| {
+            if !principal.has_scope(Scope::Admin) {
+                query.account = Some(principal.subject);
+            }
+            warp::reply::json(&l.query(&query))
+        },
+    );
+
+    let one = warp::path!("v1" / "conversions" / ConversionId).and(warp::get()).and(auth.authorized()).and_then(
+        move |id: ConversionId, principal: Principal| {
+            let entry = ledger.get(&id).filter(|e| e.account == principal.subject || principal.has_scope(Scope::Admin));
+            async move {
+                match entry {
+                    Some(entry) => Ok(warp::reply::json(&entry)),
+                    None => Err(warp::reject::custom(ApiError::NotFound(format!("no conversion {}", id)))),
+                }
+            }
+        },
+    );
+
+    list.or(one)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig};
+    use crate::api::router::Router;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn entry(account: &str, from_asset: &str, to_asset: &str, amount_in: u128) -> ConversionEntry {
+        let rate = RateQuote { from: from_asset.to_string(), to: to_asset.to_string(), numerator: 2, denominator: 1, updated_at: Utc::now(), inverse: false };
+        let mut entry = ConversionEntry {
+            id: ConversionId::new(),
+            account: account.to_string(),
+            from_asset: from_asset.to_string(),
+            to_asset: to_asset.to_string(),
+            direction: Direction::Forward,
+            amount_in,
+            amount_out: amount_in * 2,
+            rate,
+            fee: None,
+            at: Utc::now(),
+            hash: String::new(),
+        };
+        entry.hash = entry.digest();
+        entry
+    }
+
+    #[test]
+    fn recorded_entries_survive_a_crash() {
+        let path = std::env::temp_dir().join(format!("ledger-journal-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let store = Store::new();
+        store.open_journal(&path).unwrap();
+        let recorded = entry("alice", "PI", "USDC", 10);
+        ConversionLedger::new(store.clone()).record(WriteBatch::default(), &recorded).unwrap();
+        // No state snapshot is written, as when the process is killed
+        drop(store);
+
+        let restarted = Store::new();
+        restarted.open_journal(&path).unwrap();
+        let restored = ConversionLedger::new(restarted).get(&recorded.id).unwrap();
+        assert!(restored.verify());
+        assert_eq!(restored.amount_out, 20);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn callers_only_list_their_own_conversions() {
+        let ledger = ConversionLedger::new(Store::new());
+        for e in [entry("alice", "PI", "USDC", 10), entry("bob", "PI", "EURC", 20), entry("alice", "USDC", "PI", 30)] {
+            ledger.record(WriteBatch::default(), &e).unwrap();
+        }
+        let key = |subject: &str, scopes| ApiKeyConfig { subject: subject.to_string(), scopes, tenant: None };
+        let api_keys = HashMap::from([("k-alice".to_string(), key("alice", vec![Scope::Convert])), ("k-admin".to_string(), key("ops", vec![Scope::Admin]))]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let api = warp::any().and(Router::new().mount("conversions", routes(ledger, &auth)).build());
+
+        let list = |path: &'static str, key: &'static str| {
+            let api = api.clone();
+            async move {
+                let response = warp::test::request().path(path).header("x-api-key", key).reply(&api).await;
+                serde_json::from_slice::<Vec<ConversionEntry>>(response.body()).unwrap()
+            }
+        };
+        let own = list("/v1/conversions?account=bob", "k-alice").await;
+        assert_eq!(own.len(), 2);
+        assert!(own.iter().all(|e| e.account == "alice"));
+        assert_eq!(list("/v1/conversions?asset=EURC", "k-admin").await.len(), 1);
+        assert_eq!(list("/v1/conversions?asset=USDC&account=alice", "k-admin").await.len(), 2);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::runtime::scheduler::Scheduler;
+use crate::storage::mvcc::{CommitError, Store, WriteBatch};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tracing::info;
+use warp::reject::Reject;
+use warp::Filter;
+
+// Mutable entity with a version bumped on every write
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub version: u64,
+
+    // Set while the entity sits in the restore window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    #[serde(flatten)]
+    pub value: T,
+}
+
+impl<T> Versioned<T> {
+    // Strong ETag for HTTP responses
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+}
+
+// Stored entities are `Versioned` flattened, but serde buffers flattened fields in a form without u128, which amounts
+// need; so the header is split off and the value read from the remaining fields on its own
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<Versioned<T>, EntityError> {
+    let codec = |e: serde_json::Error| EntityError::Codec(e.to_string());
+    let mut fields: BTreeMap<String, Box<RawValue>> = serde_json::from_slice(bytes).map_err(codec)?;
+    let version = fields.remove("version").ok_or_else(|| EntityError::Codec("missing version".to_string()))?;
+    let version = serde_json::from_str(version.get()).map_err(codec)?;
+    let deleted_at = fields.remove("deleted_at").map(|raw| serde_json::from_str(raw.get())).transpose().map_err(codec)?.flatten();
+    let value = serde_json::from_slice(&serde_json::to_vec(&fields).map_err(codec)?).map_err(codec)?;
+    Ok(Versioned { version, deleted_at, value })
+}
+
+#[derive(Debug)]
+pub enum EntityError {
+    NotFound,
+
+    // The caller edited a stale copy; `current` is the version now stored
+    Conflict { expected: u64, current: u64 },
+
+    AlreadyExists,
+    Codec(String),
+
+    // The node runs in forensic mode
+    ReadOnly,
+
+    // The write could not be journaled, so it was not applied
+    Unavailable(String),
+}
+
+impl Reject for EntityError {}
+
+impl std::fmt::Display for EntityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EntityError::NotFound => write!(f, "entity not found"),
+            EntityError::Conflict { expected, current } => {
+                write!(f, "version conflict: expected {}, current is {}", expected, current)
+            }
+            EntityError::AlreadyExists => write!(f, "entity already exists"),
+            EntityError::Codec(e) => write!(f, "stored entity is unreadable: {}", e),
+            EntityError::ReadOnly => write!(f, "storage is read-only while the node is in forensic mode"),
+            EntityError::Unavailable(e) => write!(f, "storage unavailable: {}", e),
+        }
+    }
+}
+
+// Typed collection (policies, webhooks, account settings) with compare-and-swap updates
+pub struct EntityStore<T> {
+    store: Store,
+    prefix: String,
+
+    // Serializes the read-check-write of updates within this collection
+    write_lock: Arc<Mutex<()>>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for EntityStore<T> {
+    fn clone(&self) -> Self {
+        EntityStore { store: self.store.clone(), prefix: self.prefix.clone(), write_lock: self.write_lock.clone(), _marker: PhantomData }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> EntityStore<T> {
+    pub fn new(store: Store, collection: &str) -> Self {
+        EntityStore { store, prefix: format!("{}/", collection), write_lock: Arc::default(), _marker: PhantomData }
+    }
+
+    fn key(&self, id: &str) -> String {
+        format!("{}{}", self.prefix, id)
+    }
+
+    // Live entity; soft-deleted ones are reported as not found
+    pub fn get(&self, id: &str) -> Result<Versioned<T>, EntityError> {
+        let entity = self.get_any(id)?;
+        if entity.deleted_at.is_some() {
+            return Err(EntityError::NotFound);
+        }
+        Ok(entity)
+    }
+
+    fn get_any(&self, id: &str) -> Result<Versioned<T>, EntityError> {
+        let bytes = self.store.get(&self.key(id)).ok_or(EntityError::NotFound)?;
+        decode(&bytes)
+    }
+
+    // All live entities in the collection
+    pub fn list(&self) -> Vec<(String, Versioned<T>)> {
+        self.store
+            .read_txn()
+            .scan_prefix(&self.prefix)
+            .into_iter()
+            .filter_map(|(key, bytes)| {
+                let entity: Versioned<T> = decode(&bytes).ok()?;
+                entity.deleted_at.is_none().then(|| (key[self.prefix.len()..].to_string(), entity))
+            })
+            .collect()
+    }
+
+    // Soft-deleted entities still within the restore window
+    pub fn deleted(&self) -> Vec<(String, Versioned<T>)> {
+        self.store
+            .read_txn()
+            .scan_prefix(&self.prefix)
+            .into_iter()
+            .filter_map(|(key, bytes)| {
+                let entity: Versioned<T> = decode(&bytes).ok()?;
+                entity.deleted_at.map(|_| (key[self.prefix.len()..].to_string(), entity))
+            })
+            .collect()
+    }
+
+    fn write(&self, id: &str, entity: &Versioned<T>) -> Result<(), EntityError> {
+        let bytes = serde_json::to_vec(entity).map_err(|e| EntityError::Codec(e.to_string()))?;
+        let mut batch = WriteBatch::default();
+        batch.put(self.key(id), bytes);
+        self.commit(batch)
+    }
+
+    // The collection's write lock, for writes staged into a caller's batch; hold it until that batch is committed
+    pub fn lock(&self) -> MutexGuard<'_, ()> {
+        self.write_lock.lock().unwrap()
+    }
+
+    // Put `value` into `batch` as the next version of `id`, or version 1 when there is none; call under `lock`
+    pub fn stage(&self, batch: &mut WriteBatch, id: &str, value: T) -> Result<Versioned<T>, EntityError> {
+        let version = match self.get_any(id) {
+            Ok(current) => current.version + 1,
+            Err(EntityError::NotFound) => 1,
+            Err(e) => return Err(e),
+        };
+        let entity = Versioned { version, deleted_at: None, value };
+        batch.put(self.key(id), serde_json::to_vec(&entity).map_err(|e| EntityError::Codec(e.to_string()))?);
+        Ok(entity)
+    }
+
+    pub fn commit(&self, batch: WriteBatch) -> Result<(), EntityError> {
+        self.store.try_commit(batch).map(|_| ()).map_err(|e| match e {
+            CommitError::ReadOnly => EntityError::ReadOnly,
+            CommitError::Journal(e) => EntityError::Unavailable(e),
+        })
+    }
+
+    pub fn create(&self, id: &str, value: T) -> Result<Versioned<T>, EntityError> {
+        let _guard = self.write_lock.lock().unwrap();
+        if self.store.get(&self.key(id)).is_some() {
+            return Err(EntityError::AlreadyExists);
+        }
+        let entity = Versioned { version: 1, deleted_at: None, value };
+        self.write(id, &entity)?;
+        Ok(entity)
+    }
+
+    // Replace the entity only if it is still at `expected_version`
+    pub fn update(&self, id: &str, expected_version: u64, value: T) -> Result<Versioned<T>, EntityError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let current = self.get(id)?;
+        if current.version != expected_version {
+            return Err(EntityError::Conflict { expected: expected_version, current: current.version });
+        }
+        let entity = Versioned { version: current.version + 1, deleted_at: None, value };
+        self.write(id, &entity)?;
+        Ok(entity)
+    }
+
+    // Tombstone the entity; it can be restored until the purge job removes it
+    pub fn delete(&self, id: &str, expected_version: u64) -> Result<(), EntityError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut entity = self.get(id)?;
+        if entity.version != expected_version {
+            return Err(EntityError::Conflict { expected: expected_version, current: entity.version });
+        }
+        entity.version += 1;
+        entity.deleted_at = Some(Utc::now());
+        self.write(id, &entity)
+    }
+
+    pub fn restore(&self, id: &str) -> Result<Versioned<T>, EntityError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut entity = self.get_any(id)?;
+        if entity.deleted_at.is_none() {
+            return Ok(entity);
+        }
+        entity.version += 1;
+        entity.deleted_at = None;
+        self.write(id, &entity)?;
+        Ok(entity)
+    }
+
+    // Permanently remove entities deleted longer ago than the restore window
+    pub fn purge(&self, restore_window: Duration) -> usize {
+        let _guard = self.write_lock.lock().unwrap();
+        let cutoff = Utc::now() - chrono::Duration::from_std(restore_window).unwrap_or_else(|_| chrono::Duration::zero());
+        let expired: Vec<String> = self
+            .deleted()
+            .into_iter()
+            .filter(|(_, e)| e.deleted_at.is_some_and(|at| at < cutoff))
+            .map(|(id, _)| id)
+            .collect();
+        if expired.is_empty() {
+            return 0;
+        }
+        let mut batch = WriteBatch::default();
+        for id in &expired {
+            batch.delete(self.key(id));
+        }
+        match self.store.try_commit(batch) {
+            Ok(_) => expired.len(),
+            Err(_) => 0,
+        }
+    }
+}
+
+// Daily purge of soft-deleted entities past the restore window
+pub fn register_purge<T>(scheduler: &Scheduler, name: &str, entities: EntityStore<T>, restore_window: Duration)
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    let job = format!("purge:{}", name);
+    let label = job.clone();
+    scheduler.register(
+        &label,
+        Duration::from_secs(24 * 3600),
+        Duration::from_secs(600),
+        Arc::new(move || {
+            let entities = entities.clone();
+            let job = job.clone();
+            Box::pin(async move {
+                let purged = entities.purge(restore_window);
+                if purged > 0 {
+                    info!(%job, purged, "purged soft-deleted entities");
+                }
+            })
+        }),
+    );
+}
+
+#[derive(Debug)]
+pub struct PreconditionRequired;
+
+impl Reject for PreconditionRequired {}
+
+// Parse the `If-Match: "<version>"` header required on mutating admin calls
+pub fn if_match() -> impl Filter<Extract = (u64,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("if-match").and_then(|header: Option<String>| async move {
+        header
+            .as_deref()
+            .map(|h| h.trim().trim_start_matches("W/").trim_matches('"'))
+            .and_then(|v| v.parse::<u64>().ok())
+            .ok_or_else(|| warp::reject::custom(PreconditionRequired))
+    })
+}
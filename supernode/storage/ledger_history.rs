@@ -0,0 +1,162 @@
+use crate::events::bus::{Envelope, Event, EventSink};
+use crate::fees::FeeCharge;
+use crate::ids::ThreatId;
+use crate::storage::mvcc::{Store, WriteBatch};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const TX_PREFIX: &str = "history/tx/";
+const THREAT_PREFIX: &str = "history/threat/";
+
+// Completed ledger operation as kept for queries; amounts stay decimal strings like on the bus
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    pub tx_id: String,
+    pub kind: String,
+    pub asset: String,
+    pub amount: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_asset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_out: Option<String>,
+
+    // Fees taken, and the account they were credited to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeCharge>,
+
+    // Tenant of the caller, when it belongs to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+impl TransactionRecord {
+    // The record a ledger event stands for; `None` for other events
+    pub fn from_envelope(envelope: &Envelope) -> Option<Self> {
+        let record = match &envelope.event {
+            Event::IssuanceCompleted { tx_id, asset, amount, fee, tenant } => TransactionRecord {
+                tx_id: tx_id.clone(),
+                kind: "issuance".to_string(),
+                asset: asset.clone(),
+                amount: amount.clone(),
+                to_asset: None,
+                amount_out: None,
+                fee: fee.clone(),
+                tenant: tenant.clone(),
+                at: envelope.at,
+            },
+            Event::RedemptionCompleted { tx_id, asset, amount, tenant } => TransactionRecord {
+                tx_id: tx_id.clone(),
+                kind: "redemption".to_string(),
+                asset: asset.clone(),
+                amount: amount.clone(),
+                to_asset: None,
+                amount_out: None,
+                fee: None,
+                tenant: tenant.clone(),
+                at: envelope.at,
+            },
+            Event::ConversionExecuted { tx_id, from, to, amount_in, amount_out, fee, tenant } => TransactionRecord {
+                tx_id: tx_id.clone(),
+                kind: "conversion".to_string(),
+                asset: from.clone(),
+                amount: amount_in.clone(),
+                to_asset: Some(to.clone()),
+                amount_out: Some(amount_out.clone()),
+                fee: fee.clone(),
+                tenant: tenant.clone(),
+                at: envelope.at,
+            },
+            _ => return None,
+        };
+        Some(record)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThreatRecord {
+    pub id: String,
+    pub source: String,
+    pub severity: f32,
+    pub detail: String,
+    pub at: DateTime<Utc>,
+}
+
+// Persists ledger and threat events from the bus; ids are ULIDs, so key order is time order
+#[derive(Clone)]
+pub struct LedgerHistory {
+    store: Store,
+}
+
+impl LedgerHistory {
+    pub fn new(store: Store) -> Self {
+        LedgerHistory { store }
+    }
+
+    pub fn record(&self, envelope: &Envelope) {
+        let (key, value) = match (TransactionRecord::from_envelope(envelope), &envelope.event) {
+            (Some(record), _) => (format!("{}{}", TX_PREFIX, record.tx_id), serde_json::to_vec(&record)),
+            (None, Event::ThreatDetected { source, severity, detail }) => {
+                let id = ThreatId::new().to_string();
+                (
+                    format!("{}{}", THREAT_PREFIX, id),
+                    serde_json::to_vec(&ThreatRecord {
+                        id,
+                        source: source.clone(),
+                        severity: *severity,
+                        detail: detail.clone(),
+                        at: envelope.at,
+                    }),
+                )
+            }
+            _ => return,
+        };
+        if let Ok(value) = value {
+            let mut batch = WriteBatch::default();
+            batch.put(key, value);
+            self.store.commit(batch);
+        }
+    }
+
+    pub fn transaction(&self, tx_id: &str) -> Option<TransactionRecord> {
+        self.store.get(&format!("{}{}", TX_PREFIX, tx_id)).and_then(|v| serde_json::from_slice(&v).ok())
+    }
+
+    // Newest first, starting strictly before the `before` id when given
+    pub fn transactions(&self, asset: Option<&str>, before: Option<&str>, limit: usize) -> Vec<TransactionRecord> {
+        page::<TransactionRecord>(&self.store, TX_PREFIX, before, limit, |t| {
+            asset.is_none_or(|a| t.asset == a || t.to_asset.as_deref() == Some(a))
+        })
+    }
+
+    pub fn threats(&self, min_severity: f32, before: Option<&str>, limit: usize) -> Vec<ThreatRecord> {
+        page::<ThreatRecord>(&self.store, THREAT_PREFIX, before, limit, |t| t.severity >= min_severity)
+    }
+}
+
+fn page<T: DeserializeOwned>(store: &Store, prefix: &str, before: Option<&str>, limit: usize, keep: impl Fn(&T) -> bool) -> Vec<T> {
+    let before = before.map(|id| format!("{}{}", prefix, id));
+    store
+        .read_txn()
+        .scan_prefix(prefix)
+        .into_iter()
+        .rev()
+        .filter(|(k, _)| before.as_ref().is_none_or(|b| k < b))
+        .filter_map(|(_, v)| serde_json::from_slice::<T>(&v).ok())
+        .filter(|record| keep(record))
+        .take(limit)
+        .collect()
+}
+
+#[async_trait]
+impl EventSink for LedgerHistory {
+    fn name(&self) -> &str {
+        "ledger_history"
+    }
+
+    async fn deliver(&self, envelope: &Envelope) {
+        self.record(envelope);
+    }
+}
@@ -0,0 +1,556 @@
+use crate::storage::profiler::StorageProfiler;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+use tracing::warn;
+
+// Commit sequence number; every write batch gets the next one
+pub type Seq = u64;
+
+// Each key keeps its versions newest last; `None` is a deletion
+type Versions = Vec<(Seq, Option<Vec<u8>>)>;
+
+pub type Op = (String, Option<Vec<u8>>);
+
+// Committed batch as recorded for replication; each hash covers the previous one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Change {
+    pub seq: Seq,
+    pub ops: Vec<Op>,
+    #[serde(with = "hex::serde")]
+    pub prev_hash: [u8; 32],
+    #[serde(with = "hex::serde")]
+    pub hash: [u8; 32],
+}
+
+impl Change {
+    pub fn compute_hash(prev_hash: &[u8; 32], seq: Seq, ops: &[Op]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(prev_hash);
+        hasher.update(seq.to_be_bytes());
+        for (key, value) in ops {
+            hasher.update((key.len() as u64).to_be_bytes());
+            hasher.update(key.as_bytes());
+            match value {
+                Some(value) => {
+                    hasher.update([1u8]);
+                    hasher.update((value.len() as u64).to_be_bytes());
+                    hasher.update(value);
+                }
+                None => hasher.update([0u8]),
+            }
+        }
+        hasher.finalize().into()
+    }
+}
+
+// Recent changes kept for peers catching up; capacity 0 disables recording
+#[derive(Default)]
+struct ChangeLog {
+    entries: VecDeque<Change>,
+    head: [u8; 32],
+    capacity: usize,
+}
+
+struct Inner {
+    data: RwLock<BTreeMap<String, Versions>>,
+    committed: AtomicU64,
+
+    // Snapshots still open, by seq -> count, so old versions are kept for them
+    open_snapshots: Mutex<HashMap<Seq, usize>>,
+
+    changes: Mutex<ChangeLog>,
+
+    // Set in forensic mode; every write is refused
+    read_only: AtomicBool,
+
+    // Set once at startup when storage profiling is enabled
+    profiler: OnceCell<StorageProfiler>,
+
+    // Set once at startup; every commit is appended and synced here before it becomes visible
+    journal: OnceCell<Mutex<Journal>>,
+}
+
+// A commit the store refused
+#[derive(Debug)]
+pub enum CommitError {
+    ReadOnly,
+
+    // The batch could not be made durable, so it was not applied
+    Journal(String),
+}
+
+impl fmt::Display for CommitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommitError::ReadOnly => write!(f, "store is read-only"),
+            CommitError::Journal(e) => write!(f, "commit not journaled: {}", e),
+        }
+    }
+}
+
+// One committed batch, a line of the journal
+#[derive(Serialize, Deserialize)]
+struct Journaled {
+    seq: Seq,
+    ops: Vec<Op>,
+}
+
+// Append-only file of the batches committed since the last state snapshot, replayed on top of it at startup
+struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    fn append(&mut self, seq: Seq, ops: &[Op]) -> Result<(), String> {
+        let mut line = serde_json::to_vec(&Journaled { seq, ops: ops.to_vec() }).map_err(|e| e.to_string())?;
+        line.push(b'\n');
+        let io = |e: std::io::Error| format!("{}: {}", self.path.display(), e);
+        let len = self.file.metadata().map_err(io)?.len();
+        if let Err(e) = self.file.write_all(&line).and_then(|()| self.file.sync_data()) {
+            // Cut a partial line off so later entries are not appended behind it
+            let _ = self.file.set_len(len);
+            return Err(io(e));
+        }
+        Ok(())
+    }
+}
+
+// Multi-version key-value store giving readers a point-in-time view
+#[derive(Clone)]
+pub struct Store {
+    inner: Arc<Inner>,
+}
+
+// Writes applied atomically under a single sequence number
+#[derive(Default)]
+pub struct WriteBatch {
+    ops: Vec<Op>,
+}
+
+impl WriteBatch {
+    pub fn put(&mut self, key: impl Into<String>, value: Vec<u8>) -> &mut Self {
+        self.ops.push((key.into(), Some(value)));
+        self
+    }
+
+    pub fn delete(&mut self, key: impl Into<String>) -> &mut Self {
+        self.ops.push((key.into(), None));
+        self
+    }
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self::with_change_log(0)
+    }
+
+    // Store that keeps the last `capacity` committed batches for replication
+    pub fn with_change_log(capacity: usize) -> Self {
+        Store {
+            inner: Arc::new(Inner {
+                data: RwLock::new(BTreeMap::new()),
+                committed: AtomicU64::new(0),
+                open_snapshots: Mutex::new(HashMap::new()),
+                changes: Mutex::new(ChangeLog { capacity, ..Default::default() }),
+                read_only: AtomicBool::new(false),
+                profiler: OnceCell::new(),
+                journal: OnceCell::new(),
+            }),
+        }
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        self.inner.read_only.store(read_only, Ordering::SeqCst);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.inner.read_only.load(Ordering::SeqCst)
+    }
+
+    // Time every read and commit from now on; a second profiler is ignored
+    pub fn set_profiler(&self, profiler: StorageProfiler) {
+        let _ = self.inner.profiler.set(profiler);
+    }
+
+    // Writers that cannot report an error (archives, projections) drop the batch on a read-only store
+    pub fn commit(&self, batch: WriteBatch) -> Seq {
+        self.try_commit(batch).unwrap_or_else(|e| {
+            warn!(error = %e, "write dropped");
+            self.inner.committed.load(Ordering::SeqCst)
+        })
+    }
+
+    pub fn try_commit(&self, batch: WriteBatch) -> Result<Seq, CommitError> {
+        if self.is_read_only() {
+            return Err(CommitError::ReadOnly);
+        }
+        // Measured from before the write lock, so time spent waiting behind other writers counts
+        let started = Instant::now();
+        let profiled = self.inner.profiler.get().map(|_| {
+            let keys: Vec<String> = batch.ops.iter().map(|(k, _)| k.clone()).collect();
+            let bytes = batch.ops.iter().map(|(k, v)| k.len() + v.as_ref().map_or(0, Vec::len)).sum::<usize>();
+            (keys, bytes)
+        });
+        let mut data = self.inner.data.write().unwrap();
+        let seq = self.inner.committed.load(Ordering::SeqCst) + 1;
+        if let Some(journal) = self.inner.journal.get() {
+            journal.lock().unwrap().append(seq, &batch.ops).map_err(CommitError::Journal)?;
+        }
+        self.apply(&mut data, seq, batch.ops);
+        drop(data);
+        if let (Some(profiler), Some((keys, bytes))) = (self.inner.profiler.get(), profiled) {
+            profiler.record_commit(&keys, started.elapsed(), bytes);
+        }
+        Ok(seq)
+    }
+
+    // Called with the data write lock held
+    fn apply(&self, data: &mut BTreeMap<String, Versions>, seq: Seq, ops: Vec<Op>) {
+        self.record_change(seq, &ops);
+        for (key, value) in ops {
+            data.entry(key).or_default().push((seq, value));
+        }
+        // Publish only after all versions are in place so readers never see half a batch
+        self.inner.committed.store(seq, Ordering::SeqCst);
+    }
+
+    // Replay the batches journaled at `path` after the loaded state, then journal every commit there; returns how many
+    // were replayed. A torn last line, left by a crash mid-append, is dropped. A read-only store replays but never appends
+    pub fn open_journal(&self, path: &Path) -> Result<usize, String> {
+        let text = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        let mut data = self.inner.data.write().unwrap();
+        let (mut replayed, mut intact) = (0, 0);
+        let mut lines = text.split_inclusive(|b| *b == b'\n').peekable();
+        while let Some(line) = lines.next() {
+            let entry = match serde_json::from_slice::<Journaled>(line) {
+                Ok(entry) if line.ends_with(b"\n") => entry,
+                _ if lines.peek().is_none() => {
+                    warn!(path = %path.display(), "dropping a torn journal entry");
+                    break;
+                }
+                Err(e) => return Err(format!("{}: entry after byte {} is unreadable: {}", path.display(), intact, e)),
+                Ok(entry) => return Err(format!("{}: entry {} is not terminated", path.display(), entry.seq)),
+            };
+            intact += line.len();
+            let committed = self.inner.committed.load(Ordering::SeqCst);
+            if entry.seq <= committed {
+                continue;
+            }
+            if entry.seq != committed + 1 {
+                return Err(format!("{}: expected entry {}, found {}", path.display(), committed + 1, entry.seq));
+            }
+            self.apply(&mut data, entry.seq, entry.ops);
+            replayed += 1;
+        }
+        if self.is_read_only() {
+            return Ok(replayed);
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        file.set_len(intact as u64).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if self.inner.journal.set(Mutex::new(Journal { path: path.to_path_buf(), file })).is_err() {
+            return Err("the store already has a journal".to_string());
+        }
+        Ok(replayed)
+    }
+
+    // Drop journaled batches up to `seq`, once a state snapshot at `seq` or later is safely written
+    pub fn truncate_journal(&self, seq: Seq) -> Result<(), String> {
+        let Some(journal) = self.inner.journal.get() else { return Ok(()) };
+        let mut journal = journal.lock().unwrap();
+        let path = journal.path.clone();
+        let io = |e: std::io::Error| format!("{}: {}", path.display(), e);
+        let text = fs::read(&path).map_err(io)?;
+        let kept: Vec<u8> = text
+            .split_inclusive(|b| *b == b'\n')
+            .filter(|line| serde_json::from_slice::<Journaled>(line).map_or(true, |entry| entry.seq > seq))
+            .flatten()
+            .copied()
+            .collect();
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, kept).and_then(|()| File::open(&tmp)?.sync_all()).map_err(io)?;
+        fs::rename(&tmp, &path).map_err(io)?;
+        journal.file = OpenOptions::new().append(true).open(&path).map_err(io)?;
+        Ok(())
+    }
+
+    // Called with the data write lock held, so changes are chained in commit order
+    fn record_change(&self, seq: Seq, ops: &[Op]) {
+        let mut log = self.inner.changes.lock().unwrap();
+        if log.capacity == 0 {
+            return;
+        }
+        let hash = Change::compute_hash(&log.head, seq, ops);
+        let change = Change { seq, ops: ops.to_vec(), prev_hash: log.head, hash };
+        log.head = hash;
+        if log.entries.len() >= log.capacity {
+            log.entries.pop_front();
+        }
+        log.entries.push_back(change);
+    }
+
+    // Chain hash after `seq`; `None` once that change has been dropped from the log
+    pub fn chain_hash_at(&self, seq: Seq) -> Option<[u8; 32]> {
+        let log = self.inner.changes.lock().unwrap();
+        if seq == 0 {
+            return Some([0u8; 32]);
+        }
+        log.entries.iter().find(|c| c.seq == seq).map(|c| c.hash)
+    }
+
+    // Hash of the latest applied change
+    pub fn chain_head(&self) -> [u8; 32] {
+        self.inner.changes.lock().unwrap().head
+    }
+
+    // Changes committed after `seq`, or `None` if some of them are no longer retained
+    pub fn changes_after(&self, seq: Seq) -> Option<Vec<Change>> {
+        let log = self.inner.changes.lock().unwrap();
+        let committed = self.inner.committed.load(Ordering::SeqCst);
+        let first = log.entries.front().map_or(committed + 1, |c| c.seq);
+        if seq + 1 < first {
+            return None;
+        }
+        Some(log.entries.iter().filter(|c| c.seq > seq).cloned().collect())
+    }
+
+    // Initialize an empty store from a verified snapshot taken at `seq`
+    pub fn load_snapshot(&self, seq: Seq, head: [u8; 32], entries: Vec<(String, Vec<u8>)>) {
+        let mut data = self.inner.data.write().unwrap();
+        data.clear();
+        for (key, value) in entries {
+            data.insert(key, vec![(seq, Some(value))]);
+        }
+        let mut log = self.inner.changes.lock().unwrap();
+        log.entries.clear();
+        log.head = head;
+        self.inner.committed.store(seq, Ordering::SeqCst);
+    }
+
+    // Apply a change fetched from a peer; it must extend the local chain exactly
+    pub fn apply_change(&self, change: Change) -> Result<Seq, String> {
+        if self.is_read_only() {
+            return Err(CommitError::ReadOnly.to_string());
+        }
+        let committed = self.inner.committed.load(Ordering::SeqCst);
+        let head = self.inner.changes.lock().unwrap().head;
+        if change.seq != committed + 1 {
+            return Err(format!("expected change {}, got {}", committed + 1, change.seq));
+        }
+        if change.prev_hash != head || Change::compute_hash(&head, change.seq, &change.ops) != change.hash {
+            return Err(format!("change {} does not continue the hash chain", change.seq));
+        }
+        {
+            // Keep the chain head advancing even when this store does not record changes
+            let mut log = self.inner.changes.lock().unwrap();
+            if log.capacity == 0 {
+                log.head = change.hash;
+            }
+        }
+        self.try_commit(WriteBatch { ops: change.ops }).map_err(|e| e.to_string())
+    }
+
+    // Open a read transaction pinned to the latest committed state
+    pub fn read_txn(&self) -> ReadTxn {
+        let seq = self.inner.committed.load(Ordering::SeqCst);
+        *self.inner.open_snapshots.lock().unwrap().entry(seq).or_insert(0) += 1;
+        ReadTxn { store: self.clone(), seq }
+    }
+
+    // Latest value outside of any transaction
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.read_txn().get(key)
+    }
+
+    // Drop versions no open snapshot can see any more
+    pub fn compact(&self) {
+        let oldest = self
+            .inner
+            .open_snapshots
+            .lock()
+            .unwrap()
+            .keys()
+            .min()
+            .copied()
+            .unwrap_or_else(|| self.inner.committed.load(Ordering::SeqCst));
+        let mut data = self.inner.data.write().unwrap();
+        data.retain(|_, versions| {
+            // Keep the newest version visible at `oldest` and everything after it
+            let visible = versions.iter().rposition(|(seq, _)| *seq <= oldest);
+            if let Some(i) = visible {
+                versions.drain(..i);
+            }
+            !(versions.len() == 1 && versions[0].1.is_none() && versions[0].0 <= oldest)
+        });
+    }
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Consistent point-in-time view; concurrent commits are invisible to it
+pub struct ReadTxn {
+    store: Store,
+    seq: Seq,
+}
+
+fn visible_at(versions: &Versions, seq: Seq) -> Option<Vec<u8>> {
+    versions.iter().rev().find(|(s, _)| *s <= seq).and_then(|(_, v)| v.clone())
+}
+
+impl ReadTxn {
+    pub fn seq(&self) -> Seq {
+        self.seq
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let started = Instant::now();
+        let value = {
+            let data = self.store.inner.data.read().unwrap();
+            data.get(key).and_then(|versions| visible_at(versions, self.seq))
+        };
+        if let Some(profiler) = self.store.inner.profiler.get() {
+            profiler.record_get(key, started.elapsed(), value.as_ref().map_or(0, Vec::len));
+        }
+        value
+    }
+
+    // All live keys under a prefix, as of this snapshot
+    pub fn scan_prefix(&self, prefix: &str) -> Vec<(String, Vec<u8>)> {
+        let started = Instant::now();
+        let entries: Vec<(String, Vec<u8>)> = {
+            let data = self.store.inner.data.read().unwrap();
+            data.range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+                .take_while(|(k, _)| k.starts_with(prefix))
+                .filter_map(|(k, versions)| visible_at(versions, self.seq).map(|v| (k.clone(), v)))
+                .collect()
+        };
+        if let Some(profiler) = self.store.inner.profiler.get() {
+            let bytes = entries.iter().map(|(k, v)| k.len() + v.len()).sum();
+            profiler.record_scan(prefix, started.elapsed(), entries.len(), bytes);
+        }
+        entries
+    }
+}
+
+impl Drop for ReadTxn {
+    fn drop(&mut self) {
+        let mut open = self.store.inner.open_snapshots.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.seq) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.seq);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("journal-{}-{}.jsonl", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn put(store: &Store, key: &str, value: &str) -> Seq {
+        let mut batch = WriteBatch::default();
+        batch.put(key, value.as_bytes().to_vec());
+        store.try_commit(batch).unwrap()
+    }
+
+    #[test]
+    fn a_fresh_data_dir_is_created_for_the_journal() {
+        let dir = std::env::temp_dir().join(format!("journal-dir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = Store::new();
+        assert_eq!(store.open_journal(&dir.join("journal.jsonl")).unwrap(), 0);
+        put(&store, "a", "1");
+        assert!(dir.join("journal.jsonl").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn replays_commits_made_before_a_crash() {
+        let path = journal_path("replay");
+        let store = Store::new();
+        assert_eq!(store.open_journal(&path).unwrap(), 0);
+        put(&store, "a", "1");
+        put(&store, "b", "2");
+        put(&store, "a", "3");
+        // Dropped without a state snapshot, as after a crash
+        drop(store);
+
+        let restarted = Store::new();
+        assert_eq!(restarted.open_journal(&path).unwrap(), 3);
+        assert_eq!(restarted.read_txn().seq(), 3);
+        assert_eq!(restarted.get("a"), Some(b"3".to_vec()));
+        assert_eq!(put(&restarted, "c", "4"), 4);
+        assert_eq!(Store::new().open_journal(&path).unwrap(), 4);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn drops_a_torn_last_entry() {
+        let path = journal_path("torn");
+        let store = Store::new();
+        store.open_journal(&path).unwrap();
+        put(&store, "a", "1");
+        drop(store);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"seq":2,"ops":[["b","#).unwrap();
+
+        let restarted = Store::new();
+        assert_eq!(restarted.open_journal(&path).unwrap(), 1);
+        assert_eq!(restarted.get("b"), None);
+        // The torn bytes are cut off so the next entry starts on its own line
+        assert_eq!(put(&restarted, "b", "2"), 2);
+        let replayed = Store::new();
+        assert_eq!(replayed.open_journal(&path).unwrap(), 2);
+        assert_eq!(replayed.get("b"), Some(b"2".to_vec()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncation_keeps_entries_after_the_snapshot() {
+        let path = journal_path("truncate");
+        let store = Store::new();
+        store.open_journal(&path).unwrap();
+        put(&store, "a", "1");
+        let snapshot = put(&store, "b", "2");
+        put(&store, "c", "3");
+        store.truncate_journal(snapshot).unwrap();
+        put(&store, "d", "4");
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        // A store restored from the snapshot picks up only what followed it
+        let restarted = Store::new();
+        put(&restarted, "a", "1");
+        put(&restarted, "b", "2");
+        assert_eq!(restarted.open_journal(&path).unwrap(), 2);
+        assert_eq!(restarted.get("d"), Some(b"4".to_vec()));
+        fs::remove_file(&path).unwrap();
+    }
+}
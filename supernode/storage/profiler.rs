@@ -0,0 +1,306 @@
+use crate::api::auth::{Auth, Principal};
+use crate::metrics;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+use warp::{Filter, Rejection, Reply};
+
+// `storage_profiling` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct StorageProfilingConfig {
+    pub enabled: bool,
+
+    // Operations slower than this are kept in the slow-query log
+    pub slow_query_ms: u64,
+    pub slow_log_capacity: usize,
+}
+
+impl Default for StorageProfilingConfig {
+    fn default() -> Self {
+        StorageProfilingConfig { enabled: true, slow_query_ms: 50, slow_log_capacity: 200 }
+    }
+}
+
+// Upper bounds of the heatmap columns in microseconds; the last column is everything slower
+const BUCKETS_US: [u64; 12] = [10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 50_000, 250_000];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageOp {
+    Get,
+    Scan,
+    Commit,
+}
+
+impl StorageOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            StorageOp::Get => "get",
+            StorageOp::Scan => "scan",
+            StorageOp::Commit => "commit",
+        }
+    }
+}
+
+// Access pattern of a key: its collection with the id replaced, `netting/pending/abc` -> `netting/pending/*`
+pub fn key_pattern(key: &str) -> String {
+    match key.rfind('/') {
+        Some(i) => format!("{}*", &key[..=i]),
+        None => "*".to_string(),
+    }
+}
+
+// Ids can be session tokens or API keys, so the slow log only shows the pattern and a short hash of the id
+fn sanitize_key(key: &str) -> String {
+    let id = key.rsplit('/').next().unwrap_or(key);
+    format!("{} id:{}", key_pattern(key), &hex::encode(Sha256::digest(id.as_bytes()))[..12])
+}
+
+#[derive(Clone, Debug, Default)]
+struct Histogram {
+    // One count per BUCKETS_US entry plus the overflow column
+    counts: Vec<u64>,
+    total_us: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, micros: u64) {
+        if self.counts.is_empty() {
+            self.counts = vec![0; BUCKETS_US.len() + 1];
+        }
+        let bucket = BUCKETS_US.iter().position(|le| micros <= *le).unwrap_or(BUCKETS_US.len());
+        self.counts[bucket] += 1;
+        self.total_us += micros;
+        self.max_us = self.max_us.max(micros);
+    }
+
+    fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    // Upper bound of the bucket holding the `q` quantile; the overflow column reports the maximum seen
+    fn quantile_us(&self, q: f64) -> u64 {
+        let rank = (self.count() as f64 * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKETS_US.get(i).copied().unwrap_or(self.max_us).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+}
+
+// One heatmap row: latency distribution of an operation on one access pattern
+#[derive(Debug, Serialize)]
+pub struct PatternLatency {
+    pub op: StorageOp,
+    pub pattern: String,
+    pub count: u64,
+    pub mean_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+
+    // Counts per column of `LatencyHeatmap::buckets_us`
+    pub buckets: Vec<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencyHeatmap {
+    pub since: DateTime<Utc>,
+
+    // Column upper bounds; the extra last column of every row counts anything slower
+    pub buckets_us: Vec<u64>,
+
+    // Slowest p99 first, so the degrading pattern is at the top
+    pub rows: Vec<PatternLatency>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SlowQuery {
+    pub at: DateTime<Utc>,
+    pub op: StorageOp,
+    pub elapsed_us: u64,
+
+    // Sanitized key, scan prefix or batch summary; never values
+    pub target: String,
+
+    // Entries returned by a scan or ops in a batch
+    pub rows: usize,
+    pub bytes: usize,
+}
+
+struct Inner {
+    since: DateTime<Utc>,
+    histograms: BTreeMap<(StorageOp, String), Histogram>,
+    slow: VecDeque<SlowQuery>,
+}
+
+// Per-pattern latency of store operations and a bounded log of the slow ones; attach with `Store::set_profiler`
+#[derive(Clone)]
+pub struct StorageProfiler {
+    slow_query: Duration,
+    slow_log_capacity: usize,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl StorageProfiler {
+    // `None` when profiling is disabled
+    pub fn new(config: &StorageProfilingConfig) -> Option<Self> {
+        config.enabled.then(|| StorageProfiler {
+            slow_query: Duration::from_millis(config.slow_query_ms),
+            slow_log_capacity: config.slow_log_capacity,
+            inner: Arc::new(Mutex::new(Inner { since: Utc::now(), histograms: BTreeMap::new(), slow: VecDeque::new() })),
+        })
+    }
+
+    pub fn record_get(&self, key: &str, elapsed: Duration, bytes: usize) {
+        self.record(StorageOp::Get, key_pattern(key), elapsed, || (sanitize_key(key), 1, bytes));
+    }
+
+    pub fn record_scan(&self, prefix: &str, elapsed: Duration, rows: usize, bytes: usize) {
+        // A scan prefix names a collection, not an id, so it is shown as is
+        let pattern = format!("{}*", prefix);
+        self.record(StorageOp::Scan, pattern.clone(), elapsed, || (pattern, rows, bytes));
+    }
+
+    // A batch is attributed to its first key's pattern; the slow log lists every pattern it touched
+    pub fn record_commit(&self, keys: &[String], elapsed: Duration, bytes: usize) {
+        let pattern = keys.first().map_or_else(|| "*".to_string(), |k| key_pattern(k));
+        self.record(StorageOp::Commit, pattern, elapsed, || {
+            let mut patterns: Vec<String> = keys.iter().map(|k| key_pattern(k)).collect();
+            patterns.sort();
+            patterns.dedup();
+            (patterns.join(" "), keys.len(), bytes)
+        });
+    }
+
+    fn record(&self, op: StorageOp, pattern: String, elapsed: Duration, slow: impl FnOnce() -> (String, usize, usize)) {
+        metrics::observe_latency("storage", op.as_str(), elapsed, None);
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let mut inner = self.inner.lock().unwrap();
+        inner.histograms.entry((op, pattern)).or_default().observe(micros);
+        if elapsed < self.slow_query || self.slow_log_capacity == 0 {
+            return;
+        }
+        let (target, rows, bytes) = slow();
+        warn!(op = op.as_str(), elapsed_us = micros, %target, rows, bytes, "slow storage operation");
+        if inner.slow.len() >= self.slow_log_capacity {
+            inner.slow.pop_front();
+        }
+        inner.slow.push_back(SlowQuery { at: Utc::now(), op, elapsed_us: micros, target, rows, bytes });
+    }
+
+    pub fn heatmap(&self) -> LatencyHeatmap {
+        let inner = self.inner.lock().unwrap();
+        let mut rows: Vec<PatternLatency> = inner
+            .histograms
+            .iter()
+            .map(|((op, pattern), h)| {
+                let count = h.count();
+                PatternLatency {
+                    op: *op,
+                    pattern: pattern.clone(),
+                    count,
+                    mean_us: h.total_us / count.max(1),
+                    p50_us: h.quantile_us(0.5),
+                    p95_us: h.quantile_us(0.95),
+                    p99_us: h.quantile_us(0.99),
+                    max_us: h.max_us,
+                    buckets: h.counts.clone(),
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| b.p99_us.cmp(&a.p99_us).then(b.count.cmp(&a.count)));
+        LatencyHeatmap { since: inner.since, buckets_us: BUCKETS_US.to_vec(), rows }
+    }
+
+    // Newest first
+    pub fn slow_queries(&self, limit: usize) -> Vec<SlowQuery> {
+        self.inner.lock().unwrap().slow.iter().rev().take(limit).cloned().collect()
+    }
+
+    // Start a new measurement window, e.g. after a fix was deployed
+    pub fn reset(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.since = Utc::now();
+        inner.histograms.clear();
+        inner.slow.clear();
+    }
+}
+
+#[derive(Deserialize)]
+struct SlowQueryParams {
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+// GET/DELETE /admin/storage/latency and GET /admin/storage/slow-queries
+pub fn routes(profiler: StorageProfiler, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let p = profiler.clone();
+    let heatmap =
+        warp::path!("admin" / "storage" / "latency").and(warp::get()).and(auth.authorized()).map(move |_| warp::reply::json(&p.heatmap()));
+
+    let p = profiler.clone();
+    let reset = warp::path!("admin" / "storage" / "latency").and(warp::delete()).and(auth.authorized()).map(move |principal: Principal| {
+        p.reset();
+        info!(subject = %principal.subject, "storage latency statistics reset");
+        warp::reply::with_status(warp::reply(), warp::http::StatusCode::NO_CONTENT)
+    });
+
+    let slow = warp::path!("admin" / "storage" / "slow-queries")
+        .and(warp::get())
+        .and(auth.authorized())
+        .and(warp::query::<SlowQueryParams>())
+        .map(move |_, params: SlowQueryParams| warp::reply::json(&profiler.slow_queries(params.limit.min(1000))));
+
+    heatmap.or(reset).or(slow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use crate::storage::mvcc::{Store, WriteBatch};
+    use std::collections::HashMap;
+    use warp::http::StatusCode;
+
+    #[tokio::test]
+    async fn store_operations_show_up_in_the_latency_route() {
+        let profiler = StorageProfiler::new(&StorageProfilingConfig::default()).unwrap();
+        let store = Store::new();
+        store.set_profiler(profiler.clone());
+        let mut batch = WriteBatch::default();
+        batch.put("accounts/alice", b"1".to_vec());
+        store.commit(batch);
+        store.read_txn().get("accounts/alice");
+
+        let key = |subject: &str, scopes| ApiKeyConfig { subject: subject.to_string(), scopes, tenant: None };
+        let api_keys = HashMap::from([("k-admin".to_string(), key("ops", vec![Scope::Admin])), ("k-iss".to_string(), key("app", vec![Scope::Issue]))]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let api = warp::any().and(Router::new().mount("storage_latency", routes(profiler, &auth)).build());
+
+        let denied = warp::test::request().path("/admin/storage/latency").header("x-api-key", "k-iss").reply(&api).await;
+        assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+        let response = warp::test::request().path("/admin/storage/latency").header("x-api-key", "k-admin").reply(&api).await;
+        let heatmap: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        let rows: Vec<(&str, &str)> =
+            heatmap["rows"].as_array().unwrap().iter().map(|r| (r["op"].as_str().unwrap(), r["pattern"].as_str().unwrap())).collect();
+        assert!(rows.contains(&("commit", "accounts/*")), "{:?}", rows);
+        assert!(rows.contains(&("get", "accounts/*")), "{:?}", rows);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::api::auth::{Auth, Principal};
+use crate::api::problem::ApiError;
+use crate::runtime::scheduler::Scheduler;
+use crate::storage::mvcc::{Change, Seq, Store};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use warp::{Filter, Rejection, Reply};
+
+// `bootstrap` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct BootstrapConfig {
+    // Hex ed25519 keys of peers whose snapshots are accepted
+    pub trusted_keys: Vec<String>,
+
+    // Where the verified state is written for the node to load on start
+    pub state_path: PathBuf,
+
+    // Commits since the state was last written, replayed on top of it on start so a crash loses nothing
+    pub journal_path: PathBuf,
+
+    // How often the state is rewritten and the journal emptied
+    pub checkpoint_secs: u64,
+
+    // API key with the `sync` scope on the peer, sent as `x-api-key`
+    pub api_key: Option<String>,
+}
+
+impl Default for BootstrapConfig {
+    fn default() -> Self {
+        BootstrapConfig {
+            trusted_keys: Vec::new(),
+            state_path: PathBuf::from("data/state.json.zst"),
+            journal_path: PathBuf::from("data/journal.jsonl"),
+            checkpoint_secs: 300,
+            api_key: None,
+        }
+    }
+}
+
+// Full key-value state at one sequence number, signed by the serving node
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub seq: Seq,
+    #[serde(with = "hex::serde")]
+    pub head: [u8; 32],
+    pub entries: Vec<(String, Vec<u8>)>,
+    #[serde(with = "hex::serde")]
+    pub signer: [u8; 32],
+    pub signature: String,
+}
+
+// Changes after a snapshot; the signature covers the final chain hash
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogSegment {
+    pub changes: Vec<Change>,
+    #[serde(with = "hex::serde")]
+    pub signer: [u8; 32],
+    pub signature: String,
+}
+
+fn snapshot_message(seq: Seq, head: &[u8; 32], entries: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = b"pi-supernode/state-snapshot/v1".to_vec();
+    bytes.extend_from_slice(&seq.to_be_bytes());
+    bytes.extend_from_slice(head);
+    bytes.extend_from_slice(&serde_json::to_vec(entries).unwrap_or_default());
+    bytes
+}
+
+fn segment_message(changes: &[Change]) -> Vec<u8> {
+    let mut bytes = b"pi-supernode/log-segment/v1".to_vec();
+    if let (Some(first), Some(last)) = (changes.first(), changes.last()) {
+        bytes.extend_from_slice(&first.prev_hash);
+        bytes.extend_from_slice(&last.seq.to_be_bytes());
+        bytes.extend_from_slice(&last.hash);
+    }
+    bytes
+}
+
+fn check_signature(trusted: &[[u8; 32]], signer: &[u8; 32], message: &[u8], signature: &str) -> Result<(), String> {
+    if !trusted.contains(signer) {
+        return Err(format!("signer {} is not in bootstrap.trusted_keys", hex::encode(signer)));
+    }
+    let key = VerifyingKey::from_bytes(signer).map_err(|e| e.to_string())?;
+    let signature = Signature::from_slice(&hex::decode(signature).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    key.verify(message, &signature).map_err(|_| "signature does not match".to_string())
+}
+
+// Serves this node's state to peers that are bootstrapping
+#[derive(Clone)]
+pub struct SyncServer {
+    store: Store,
+    key: Arc<SigningKey>,
+}
+
+impl SyncServer {
+    pub fn new(store: Store, key: SigningKey) -> Self {
+        SyncServer { store, key: Arc::new(key) }
+    }
+
+    pub fn snapshot(&self) -> Result<StateSnapshot, String> {
+        let txn = self.store.read_txn();
+        let head = self.store.chain_hash_at(txn.seq()).ok_or("change log does not cover the current state")?;
+        let entries = txn.scan_prefix("");
+        let signature = self.key.sign(&snapshot_message(txn.seq(), &head, &entries));
+        Ok(StateSnapshot {
+            seq: txn.seq(),
+            head,
+            entries,
+            signer: self.key.verifying_key().to_bytes(),
+            signature: hex::encode(signature.to_bytes()),
+        })
+    }
+
+    pub fn log_after(&self, seq: Seq) -> Option<LogSegment> {
+        let changes = self.store.changes_after(seq)?;
+        let signature = self.key.sign(&segment_message(&changes));
+        Some(LogSegment { changes, signer: self.key.verifying_key().to_bytes(), signature: hex::encode(signature.to_bytes()) })
+    }
+
+    // GET /v1/sync/snapshot and GET /v1/sync/log/{after_seq}, for peers holding the `sync` scope
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let server = self.clone();
+        let snapshot = warp::path!("v1" / "sync" / "snapshot").and(warp::get()).and(auth.authorized()).and_then(move |_: Principal| {
+            let snapshot = server.snapshot();
+            async move {
+                match snapshot {
+                    Ok(snapshot) => Ok(warp::reply::json(&snapshot)),
+                    Err(e) => Err(warp::reject::custom(ApiError::Unavailable(e))),
+                }
+            }
+        });
+        let server = self.clone();
+        let log = warp::path!("v1" / "sync" / "log" / Seq).and(warp::get()).and(auth.authorized()).and_then(move |after: Seq, _: Principal| {
+            let segment = server.log_after(after);
+            async move {
+                match segment {
+                    Some(segment) => Ok(warp::reply::json(&segment)),
+                    None => Err(warp::reject::custom(ApiError::Gone(format!("changes after {} are no longer retained", after)))),
+                }
+            }
+        });
+        snapshot.or(log)
+    }
+}
+
+// Fetch, verify and apply a peer's snapshot and the log after it into an empty store
+pub async fn bootstrap(peer: &str, config: &BootstrapConfig) -> Result<Store, String> {
+    let trusted: Vec<[u8; 32]> = config
+        .trusted_keys
+        .iter()
+        .map(|k| hex::decode(k).ok().and_then(|b| b.try_into().ok()).ok_or_else(|| format!("invalid trusted key {}", k)))
+        .collect::<Result<_, _>>()?;
+    if trusted.is_empty() {
+        return Err("bootstrap.trusted_keys is empty, refusing to trust any snapshot".to_string());
+    }
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(300)).build().map_err(|e| e.to_string())?;
+    let base = peer.trim_end_matches('/');
+
+    let api_key = config.api_key.as_deref();
+
+    let snapshot: StateSnapshot = fetch(&client, &format!("{}/v1/sync/snapshot", base), api_key).await?;
+    check_signature(&trusted, &snapshot.signer, &snapshot_message(snapshot.seq, &snapshot.head, &snapshot.entries), &snapshot.signature)
+        .map_err(|e| format!("snapshot rejected: {}", e))?;
+    info!(peer, seq = snapshot.seq, keys = snapshot.entries.len(), "snapshot verified");
+
+    let segment: LogSegment = fetch(&client, &format!("{}/v1/sync/log/{}", base, snapshot.seq), api_key).await?;
+    check_signature(&trusted, &segment.signer, &segment_message(&segment.changes), &segment.signature)
+        .map_err(|e| format!("log segment rejected: {}", e))?;
+
+    let store = Store::new();
+    store.load_snapshot(snapshot.seq, snapshot.head, snapshot.entries);
+    let applied = segment.changes.len();
+    for change in segment.changes {
+        store.apply_change(change)?;
+    }
+    info!(peer, applied, seq = store.read_txn().seq(), "event log applied");
+    Ok(store)
+}
+
+async fn fetch<T: serde::de::DeserializeOwned>(client: &reqwest::Client, url: &str, api_key: Option<&str>) -> Result<T, String> {
+    let mut request = client.get(url);
+    if let Some(key) = api_key {
+        request = request.header("x-api-key", key);
+    }
+    let response = request.send().await.map_err(|e| format!("{}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("{}: {}", url, response.status()));
+    }
+    response.json().await.map_err(|e| format!("{}: {}", url, e))
+}
+
+// Persist the state as a snapshot the node loads on start; returns the seq it was taken at
+pub fn write_state(store: &Store, path: &Path) -> Result<Seq, String> {
+    let txn = store.read_txn();
+    let head = store.chain_hash_at(txn.seq()).unwrap_or_else(|| store.chain_head());
+    let state = (txn.seq(), hex::encode(head), txn.scan_prefix(""));
+    let bytes = zstd::encode_all(serde_json::to_vec(&state).map_err(|e| e.to_string())?.as_slice(), 3).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes).and_then(|()| std::fs::File::open(&tmp)?.sync_all()).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())?;
+    Ok(txn.seq())
+}
+
+// Write the state and drop the journal entries it now holds
+pub fn checkpoint(store: &Store, config: &BootstrapConfig) -> Result<Seq, String> {
+    let seq = write_state(store, &config.state_path)?;
+    store.truncate_journal(seq)?;
+    Ok(seq)
+}
+
+pub fn register_checkpoint(scheduler: &Scheduler, store: Store, config: BootstrapConfig) {
+    scheduler.register(
+        "state:checkpoint",
+        Duration::from_secs(config.checkpoint_secs.max(1)),
+        Duration::ZERO,
+        Arc::new(move || {
+            let (store, config) = (store.clone(), config.clone());
+            Box::pin(async move {
+                if let Err(e) = checkpoint(&store, &config) {
+                    warn!(error = %e, "state checkpoint failed, the journal keeps growing");
+                }
+            })
+        }),
+    );
+}
+
+// Load state written by `write_state`, if any
+pub fn read_state(path: &Path, store: &Store) -> Result<bool, String> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.to_string()),
+    };
+    let json = zstd::decode_all(bytes.as_slice()).map_err(|e| e.to_string())?;
+    let (seq, head, entries): (Seq, String, Vec<(String, Vec<u8>)>) = serde_json::from_slice(&json).map_err(|e| e.to_string())?;
+    let head: [u8; 32] = hex::decode(&head).ok().and_then(|b| b.try_into().ok()).ok_or("invalid chain head in state file")?;
+    store.load_snapshot(seq, head, entries);
+    Ok(true)
+}
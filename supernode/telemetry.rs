@@ -0,0 +1,83 @@
+use opentelemetry::global;
+use opentelemetry::trace::{TraceContextExt, TraceError};
+use opentelemetry::KeyValue;
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use serde::Deserialize;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use warp::http::{HeaderMap, Method};
+use warp::path::FullPath;
+use warp::Filter;
+
+// `telemetry` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    // OTLP gRPC collector, tracing export is off when unset
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+
+    // Share of traces sampled, 0.0 - 1.0
+    pub sample_ratio: f64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig { otlp_endpoint: None, service_name: "pi-supernode".to_string(), sample_ratio: 1.0 }
+    }
+}
+
+// Build the OTLP exporting tracer; the caller adds it as a tracing-subscriber layer
+pub fn init_tracer(config: &TelemetryConfig) -> Result<Option<opentelemetry_sdk::trace::Tracer>, TraceError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let endpoint = match &config.otlp_endpoint {
+        Some(endpoint) => endpoint,
+        None => return Ok(None),
+    };
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_trace_config(
+            trace::config()
+                .with_sampler(trace::Sampler::ParentBased(Box::new(trace::Sampler::TraceIdRatioBased(config.sample_ratio))))
+                .with_resource(Resource::new(vec![KeyValue::new("service.name", config.service_name.clone())])),
+        )
+        .install_batch(runtime::Tokio)?;
+    Ok(Some(tracer))
+}
+
+// Flush pending spans on shutdown
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+fn http_span(method: &Method, path: &str, headers: &HeaderMap) -> Span {
+    let parent = global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(headers)));
+    let span = tracing::info_span!("http_request", http.method = %method, http.target = %path);
+    span.set_parent(parent);
+    span
+}
+
+// Span for an inbound request, parented to the caller's W3C traceparent if present.
+// Handlers run inside it with `.instrument(span)` so crypto, AI and converter
+// calls made on their behalf join the same trace.
+pub fn request_span() -> impl Filter<Extract = (Span,), Error = std::convert::Infallible> + Clone {
+    warp::method().and(warp::path::full()).and(warp::header::headers_cloned()).map(
+        |method: Method, path: FullPath, headers: HeaderMap| http_span(&method, path.as_str(), &headers),
+    )
+}
+
+// Run a whole filter, including its `warp::log` wrappers, inside the request span
+pub fn trace_layer() -> warp::trace::Trace<impl Fn(warp::trace::Info) -> Span + Clone> {
+    warp::trace(|info| http_span(info.method(), info.path(), info.request_headers()))
+}
+
+// Hex trace id of the current span when it is being exported, for metric exemplars
+pub fn current_trace_id() -> Option<String> {
+    let context = Span::current().context();
+    let span_context = context.span().span_context().clone();
+    (span_context.is_valid() && span_context.is_sampled()).then(|| span_context.trace_id().to_string())
+}
@@ -0,0 +1,117 @@
+use crate::api::auth::Auth;
+use serde::Serialize;
+use utoipa::ToSchema;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use warp::{Filter, Rejection, Reply};
+
+// Resources consumed by a single tenant
+#[derive(Clone, Default, Serialize, ToSchema)]
+pub struct TenantUsage {
+    pub api_calls: u64,
+    pub storage_bytes: u64,
+    pub crypto_ops: u64,
+}
+
+// Unit prices for usage-based fees, in the smallest fee asset unit
+#[derive(Clone)]
+pub struct UsagePricing {
+    pub per_api_call: u64,
+    pub per_storage_kib: u64,
+    pub per_crypto_op: u64,
+}
+
+// Fee entry to be appended to the ledger when billing is enabled
+#[derive(Serialize)]
+pub struct UsageFeeEntry {
+    pub tenant: String,
+    pub amount: u64,
+    pub usage: TenantUsage,
+}
+
+#[derive(Clone, Default)]
+pub struct UsageMeter {
+    usage: Arc<Mutex<HashMap<String, TenantUsage>>>,
+
+    // Usage-based fees are optional
+    pricing: Option<UsagePricing>,
+}
+
+impl UsageMeter {
+    pub fn new(pricing: Option<UsagePricing>) -> Self {
+        UsageMeter { usage: Arc::default(), pricing }
+    }
+
+    pub fn record_api_call(&self, tenant: &str) {
+        self.usage.lock().unwrap().entry(tenant.to_string()).or_default().api_calls += 1;
+    }
+
+    pub fn record_storage(&self, tenant: &str, bytes: u64) {
+        self.usage.lock().unwrap().entry(tenant.to_string()).or_default().storage_bytes += bytes;
+    }
+
+    pub fn record_crypto_op(&self, tenant: &str) {
+        self.usage.lock().unwrap().entry(tenant.to_string()).or_default().crypto_ops += 1;
+    }
+
+    pub fn usage(&self, tenant: &str) -> TenantUsage {
+        self.usage.lock().unwrap().get(tenant).cloned().unwrap_or_default()
+    }
+
+    // Close the billing period, returning one fee entry per tenant and resetting counters
+    pub fn settle(&self) -> Vec<UsageFeeEntry> {
+        let pricing = match &self.pricing {
+            Some(pricing) => pricing,
+            None => return Vec::new(),
+        };
+        let drained: Vec<(String, TenantUsage)> = self.usage.lock().unwrap().drain().collect();
+        drained
+            .into_iter()
+            .map(|(tenant, usage)| {
+                let amount = usage.api_calls * pricing.per_api_call
+                    + (usage.storage_bytes / 1024) * pricing.per_storage_kib
+                    + usage.crypto_ops * pricing.per_crypto_op;
+                UsageFeeEntry { tenant, amount, usage }
+            })
+            .collect()
+    }
+
+    // GET /v1/tenants/{tenant}/usage, for that tenant's own keys and admins
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let meter = self.clone();
+        warp::path!("v1" / "tenants" / String / "usage")
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |tenant: String, _| warp::reply::json(&meter.usage(&tenant)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+
+    #[tokio::test]
+    async fn tenants_see_the_calls_their_keys_made() {
+        let meter = UsageMeter::new(None);
+        let key = |subject: &str, scopes, tenant: Option<&str>| ApiKeyConfig { subject: subject.to_string(), scopes, tenant: tenant.map(str::to_string) };
+        let api_keys = HashMap::from([
+            ("k-admin".to_string(), key("ops", vec![Scope::Admin], None)),
+            ("k-acme".to_string(), key("acme-app", vec![Scope::Convert], Some("acme"))),
+        ]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap().with_usage_meter(meter.clone());
+        let api = warp::any().and(Router::new().mount("tenant_usage", meter.routes(&auth)).build());
+        let get = |key: &str, path: &str| warp::test::request().path(path).header("x-api-key", key).reply(&api);
+
+        assert_eq!(get("k-acme", "/v1/tenants/acme/usage").await.status(), 200);
+        assert_eq!(get("k-acme", "/v1/tenants/other/usage").await.status(), 403);
+        let usage = get("k-acme", "/v1/tenants/acme/usage").await;
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(usage.body()).unwrap()["api_calls"], 2);
+
+        let usage = get("k-admin", "/v1/tenants/acme/usage").await;
+        assert_eq!(usage.status(), 200);
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(usage.body()).unwrap()["api_calls"], 2);
+        assert_eq!(meter.usage("other").api_calls, 0);
+    }
+}
@@ -0,0 +1,617 @@
+use crate::amount::{units_string, AnyAmount};
+use crate::api::auth::{key_fingerprint, ApiKeyConfig, Auth, AuthError, Entitlements, KeyStore, Principal, Scope};
+use crate::api::problem::ApiError;
+use crate::api::openapi::document_where;
+use crate::plans::{Plans, Sla};
+use crate::storage::entities::{EntityError, EntityStore, Versioned};
+use crate::storage::mvcc::{Store, WriteBatch};
+use chrono::{DateTime, NaiveDate, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+use warp::http::{Method, StatusCode};
+use warp::{Filter, Rejection, Reply};
+
+// Issuance allowed for one asset, in smallest units
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IssuanceQuota {
+    // Lifetime cap on everything the tenant has issued
+    #[serde(with = "units_string")]
+    pub total: u128,
+
+    // Cap per UTC day
+    #[serde(with = "units_string")]
+    pub daily: u128,
+}
+
+// Limits of one partner app; assets without a quota cannot be issued by it
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TenantLimits {
+    // Authenticated requests per UTC day; unlimited when unset
+    pub daily_requests: Option<u64>,
+
+    // Asset -> quota
+    pub issuance: BTreeMap<String, IssuanceQuota>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tenant {
+    pub limits: TenantLimits,
+    pub created_at: DateTime<Utc>,
+
+    // Plan tier; the configured default plan when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+}
+
+// Key issued to a tenant; only its hash is stored, under the hex SHA-256 of the key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TenantKey {
+    pub id: String,
+    pub tenant: String,
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+// Persisted so lifetime quotas survive restarts
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IssuedTotals {
+    pub issued: Vec<AnyAmount>,
+}
+
+// Counters for the current UTC day, stored per tenant and started over when the day changes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub day: NaiveDate,
+    pub requests: u64,
+    pub issued: Vec<AnyAmount>,
+}
+
+impl DailyUsage {
+    fn new(day: NaiveDate) -> Self {
+        DailyUsage { day, requests: 0, issued: Vec::new() }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuotaReport {
+    pub tenant: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sla: Option<Sla>,
+
+    // The tenant's own limits, with the plan's filling the gaps
+    pub limits: TenantLimits,
+    pub today: DailyUsage,
+    pub issued_total: Vec<AnyAmount>,
+}
+
+#[derive(Debug)]
+pub enum TenantError {
+    UnknownTenant(String),
+    UnknownPlan(String),
+    NoQuota { tenant: String, asset: String },
+    QuotaExceeded { tenant: String, asset: String, window: &'static str, limit: u128 },
+    Storage(EntityError),
+}
+
+impl fmt::Display for TenantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TenantError::UnknownTenant(tenant) => write!(f, "unknown tenant {}", tenant),
+            TenantError::UnknownPlan(plan) => write!(f, "unknown plan {}", plan),
+            TenantError::NoQuota { tenant, asset } => write!(f, "tenant {} has no issuance quota for {}", tenant, asset),
+            TenantError::QuotaExceeded { tenant, asset, window, limit } => {
+                write!(f, "tenant {} would exceed its {} {} issuance quota of {}", tenant, window, asset, limit)
+            }
+            TenantError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn amount_of(amounts: &[AnyAmount], asset: &str) -> u128 {
+    amounts.iter().find(|a| a.asset == asset).map_or(0, |a| a.units)
+}
+
+fn add_to(amounts: &mut Vec<AnyAmount>, amount: &AnyAmount) {
+    match amounts.iter_mut().find(|a| a.asset == amount.asset) {
+        Some(existing) => existing.units = existing.units.saturating_add(amount.units),
+        None => amounts.push(amount.clone()),
+    }
+}
+
+fn subtract_from(amounts: &mut [AnyAmount], amount: &AnyAmount) {
+    if let Some(existing) = amounts.iter_mut().find(|a| a.asset == amount.asset) {
+        existing.units = existing.units.saturating_sub(amount.units);
+    }
+}
+
+fn key_hash(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+// Partner apps sharing this node: their keys, limits and usage
+#[derive(Clone)]
+pub struct TenantRegistry {
+    tenants: EntityStore<Tenant>,
+    keys: EntityStore<TenantKey>,
+    totals: EntityStore<IssuedTotals>,
+    daily: EntityStore<DailyUsage>,
+    plans: Arc<Plans>,
+
+    // Serializes quota check-and-reserve so concurrent issuances cannot both squeeze under a limit
+    issuance_lock: Arc<Mutex<()>>,
+}
+
+impl TenantRegistry {
+    pub fn new(store: Store) -> Self {
+        TenantRegistry {
+            tenants: EntityStore::new(store.clone(), "tenants"),
+            keys: EntityStore::new(store.clone(), "tenant_keys"),
+            totals: EntityStore::new(store.clone(), "tenant_issued"),
+            daily: EntityStore::new(store, "tenant_daily"),
+            plans: Arc::default(),
+            issuance_lock: Arc::default(),
+        }
+    }
+
+    pub fn with_plans(mut self, plans: Plans) -> Self {
+        self.plans = Arc::new(plans);
+        self
+    }
+
+    // Limits in force for `tenant`: its own, then its plan's for any the tenant does not set
+    fn limits(&self, tenant: &Tenant) -> TenantLimits {
+        let mut limits = tenant.limits.clone();
+        if let Some(plan) = self.plans.resolve(tenant.plan.as_deref()) {
+            limits.daily_requests = limits.daily_requests.or(plan.limits.daily_requests);
+            for (asset, quota) in &plan.limits.issuance {
+                limits.issuance.entry(asset.clone()).or_insert_with(|| quota.clone());
+            }
+        }
+        limits
+    }
+
+    // Create the tenant or replace its limits
+    pub fn upsert(&self, tenant: &str, limits: TenantLimits) -> Result<Tenant, TenantError> {
+        let result = match self.tenants.get(tenant) {
+            Ok(existing) => {
+                let Tenant { created_at, plan, .. } = existing.value;
+                self.tenants.update(tenant, existing.version, Tenant { limits, created_at, plan })
+            }
+            Err(EntityError::NotFound) => self.tenants.create(tenant, Tenant { limits, created_at: Utc::now(), plan: None }),
+            Err(e) => Err(e),
+        };
+        result.map(|v| v.value).map_err(TenantError::Storage)
+    }
+
+    // Move the tenant to `plan`, or back to the default plan with `None`
+    pub fn set_plan(&self, tenant: &str, plan: Option<String>) -> Result<Tenant, TenantError> {
+        if let Some(name) = &plan {
+            if self.plans.get(name).is_none() {
+                return Err(TenantError::UnknownPlan(name.clone()));
+            }
+        }
+        let existing = self.tenants.get(tenant).map_err(|e| match e {
+            EntityError::NotFound => TenantError::UnknownTenant(tenant.to_string()),
+            e => TenantError::Storage(e),
+        })?;
+        let record = Tenant { plan, ..existing.value };
+        self.tenants.update(tenant, existing.version, record).map(|v| v.value).map_err(TenantError::Storage)
+    }
+
+    pub fn get(&self, tenant: &str) -> Result<Tenant, TenantError> {
+        self.tenants.get(tenant).map(|v| v.value).map_err(|e| match e {
+            EntityError::NotFound => TenantError::UnknownTenant(tenant.to_string()),
+            e => TenantError::Storage(e),
+        })
+    }
+
+    pub fn list(&self) -> Vec<(String, Tenant)> {
+        self.tenants.list().into_iter().map(|(name, v)| (name, v.value)).collect()
+    }
+
+    // Returns the secret once; the registry only keeps its hash
+    pub fn create_key(&self, tenant: &str, subject: &str, scopes: Vec<Scope>) -> Result<(String, TenantKey), TenantError> {
+        self.get(tenant)?;
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let key = format!("pk_{}", hex::encode(secret));
+        let record = TenantKey {
+            id: key_fingerprint(&key),
+            tenant: tenant.to_string(),
+            subject: subject.to_string(),
+            scopes,
+            created_at: Utc::now(),
+            revoked_at: None,
+        };
+        self.keys.create(&key_hash(&key), record.clone()).map_err(TenantError::Storage)?;
+        info!(tenant, key_id = %record.id, "tenant key created");
+        Ok((key, record))
+    }
+
+    pub fn keys(&self, tenant: &str) -> Vec<TenantKey> {
+        self.keys.list().into_iter().map(|(_, v)| v.value).filter(|k| k.tenant == tenant).collect()
+    }
+
+    // Revoked keys stop authenticating immediately; the record stays for access reviews
+    pub fn revoke_key(&self, tenant: &str, key_id: &str) -> Result<TenantKey, TenantError> {
+        let (hash, entity) = self
+            .keys
+            .list()
+            .into_iter()
+            .find(|(_, v)| v.value.tenant == tenant && v.value.id == key_id)
+            .ok_or(TenantError::Storage(EntityError::NotFound))?;
+        let mut record = entity.value;
+        record.revoked_at.get_or_insert_with(Utc::now);
+        self.keys.update(&hash, entity.version, record.clone()).map_err(TenantError::Storage)?;
+        info!(tenant, key_id, "tenant key revoked");
+        Ok(record)
+    }
+
+    // The tenant's counters for today, zero when it has none yet
+    fn today(&self, tenant: &str) -> Result<DailyUsage, TenantError> {
+        let today = Utc::now().date_naive();
+        match self.daily.get(tenant) {
+            Ok(usage) if usage.value.day == today => Ok(usage.value),
+            Ok(_) | Err(EntityError::NotFound) => Ok(DailyUsage::new(today)),
+            Err(e) => Err(TenantError::Storage(e)),
+        }
+    }
+
+    // Apply `f` to today's counters, staged into `batch`; the caller holds `self.daily.lock()` until it is committed
+    fn stage_today(&self, batch: &mut WriteBatch, tenant: &str, f: impl FnOnce(&mut DailyUsage)) -> Result<DailyUsage, TenantError> {
+        let mut usage = self.today(tenant)?;
+        f(&mut usage);
+        self.daily.stage(batch, tenant, usage).map(|v| v.value).map_err(TenantError::Storage)
+    }
+
+    fn update_today(&self, tenant: &str, f: impl FnOnce(&mut DailyUsage)) -> Result<DailyUsage, TenantError> {
+        let _guard = self.daily.lock();
+        let mut batch = WriteBatch::default();
+        let usage = self.stage_today(&mut batch, tenant, f)?;
+        self.daily.commit(batch).map_err(TenantError::Storage)?;
+        Ok(usage)
+    }
+
+    fn issued_totals(&self, tenant: &str) -> Result<Option<Versioned<IssuedTotals>>, TenantError> {
+        match self.totals.get(tenant) {
+            Ok(v) => Ok(Some(v)),
+            Err(EntityError::NotFound) => Ok(None),
+            Err(e) => Err(TenantError::Storage(e)),
+        }
+    }
+
+    // Whether `units` more of `asset` fit the lifetime and daily quotas on top of `issued_total`
+    fn within_quota(&self, tenant: &str, asset: &str, units: u128, issued_total: u128) -> Result<(), TenantError> {
+        let quota = self
+            .limits(&self.get(tenant)?)
+            .issuance
+            .get(asset)
+            .cloned()
+            .ok_or_else(|| TenantError::NoQuota { tenant: tenant.to_string(), asset: asset.to_string() })?;
+        let exceeded = |window, limit| TenantError::QuotaExceeded { tenant: tenant.to_string(), asset: asset.to_string(), window, limit };
+        if issued_total.saturating_add(units) > quota.total {
+            return Err(exceeded("total", quota.total));
+        }
+        let issued_today = amount_of(&self.today(tenant)?.issued, asset);
+        if issued_today.saturating_add(units) > quota.daily {
+            return Err(exceeded("daily", quota.daily));
+        }
+        Ok(())
+    }
+
+    // Check the tenant's issuance quota for `amount` and count it as issued; call before issuing, and
+    // `release_issuance` if the issuance then fails
+    pub fn reserve_issuance(&self, tenant: &str, amount: &AnyAmount) -> Result<(), TenantError> {
+        let _guard = self.issuance_lock.lock().unwrap();
+        let totals = self.issued_totals(tenant)?;
+        let issued_total = totals.as_ref().map_or(0, |v| amount_of(&v.value.issued, &amount.asset));
+        self.within_quota(tenant, &amount.asset, amount.units, issued_total)?;
+        self.count_issuance(tenant, totals.map(|v| v.value).unwrap_or_default(), |issued| add_to(issued, amount))
+    }
+
+    // Give back what `reserve_issuance` counted for an issuance that did not happen
+    pub fn release_issuance(&self, tenant: &str, amount: &AnyAmount) -> Result<(), TenantError> {
+        let _guard = self.issuance_lock.lock().unwrap();
+        let totals = self.issued_totals(tenant)?.map(|v| v.value).unwrap_or_default();
+        self.count_issuance(tenant, totals, |issued| subtract_from(issued, amount))
+    }
+
+    // Apply `change` to the lifetime totals and today's counters in one commit; call under `issuance_lock`
+    fn count_issuance(&self, tenant: &str, mut totals: IssuedTotals, change: impl Fn(&mut Vec<AnyAmount>)) -> Result<(), TenantError> {
+        let _daily = self.daily.lock();
+        let _totals = self.totals.lock();
+        let mut batch = WriteBatch::default();
+        change(&mut totals.issued);
+        self.totals.stage(&mut batch, tenant, totals).map_err(TenantError::Storage)?;
+        self.stage_today(&mut batch, tenant, |usage| change(&mut usage.issued))?;
+        self.totals.commit(batch).map_err(TenantError::Storage)
+    }
+
+    // Like `reserve_issuance` but counts nothing; `planned` is issuance of the same asset checked earlier in a batch
+    pub fn check_issuance(&self, tenant: &str, amount: &AnyAmount, planned: u128) -> Result<(), TenantError> {
+        let issued_total = self.issued_totals(tenant)?.map_or(0, |v| amount_of(&v.value.issued, &amount.asset));
+        self.within_quota(tenant, &amount.asset, amount.units.saturating_add(planned), issued_total)
+    }
+
+    pub fn quota(&self, tenant: &str) -> Result<QuotaReport, TenantError> {
+        let record = self.get(tenant)?;
+        let limits = self.limits(&record);
+        let plan = self.plans.resolve(record.plan.as_deref());
+        let issued_total = self.totals.get(tenant).map(|v| v.value.issued).unwrap_or_default();
+        let today = self.today(tenant)?;
+        Ok(QuotaReport {
+            tenant: tenant.to_string(),
+            plan: plan.map(|p| p.name.clone()),
+            sla: plan.map(|p| p.sla.clone()),
+            limits,
+            today,
+            issued_total,
+        })
+    }
+
+    // GET /admin/tenants, PUT /admin/tenants/{tenant}, PUT /admin/tenants/{tenant}/plan, GET/POST /admin/tenants/{tenant}/keys,
+    // DELETE /admin/tenants/{tenant}/keys/{key_id}, and GET /v1/tenants/{tenant}/quota and /openapi.json for the tenant itself
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let registry = self.clone();
+        let list = warp::path!("admin" / "tenants").and(warp::get()).and(auth.authorized()).map(move |_| {
+            let tenants: BTreeMap<String, Tenant> = registry.list().into_iter().collect();
+            warp::reply::json(&tenants)
+        });
+
+        let registry = self.clone();
+        let upsert = warp::path!("admin" / "tenants" / String)
+            .and(warp::put())
+            .and(auth.authorized())
+            .and(warp::body::json())
+            .and_then(move |tenant: String, principal: Principal, limits: TenantLimits| {
+                let result = registry.upsert(&tenant, limits).map(|record| {
+                    info!(subject = %principal.subject, %tenant, "tenant limits set");
+                    reply(&record, StatusCode::OK)
+                });
+                async move { result.map_err(reject) }
+            });
+
+        let registry = self.clone();
+        let set_plan = warp::path!("admin" / "tenants" / String / "plan")
+            .and(warp::put())
+            .and(auth.authorized())
+            .and(warp::body::json())
+            .and_then(move |tenant: String, principal: Principal, request: SetPlanRequest| {
+                let result = registry.set_plan(&tenant, request.plan).map(|record| {
+                    info!(subject = %principal.subject, %tenant, plan = ?record.plan, "tenant plan set");
+                    reply(&record, StatusCode::OK)
+                });
+                async move { result.map_err(reject) }
+            });
+
+        let registry = self.clone();
+        let keys = warp::path!("admin" / "tenants" / String / "keys")
+            .and(warp::get())
+            .and(auth.authorized())
+            .map(move |tenant: String, _| reply(&registry.keys(&tenant), StatusCode::OK));
+
+        let registry = self.clone();
+        let create_key = warp::path!("admin" / "tenants" / String / "keys")
+            .and(warp::post())
+            .and(auth.authorized())
+            .and(warp::body::json())
+            .and_then(move |tenant: String, _, request: CreateKeyRequest| {
+                let result = if request.scopes.contains(&Scope::Admin) {
+                    Err(warp::reject::custom(ApiError::Unprocessable("tenant keys cannot carry the admin scope".to_string())))
+                } else {
+                    registry
+                        .create_key(&tenant, &request.subject, request.scopes)
+                        .map(|(key, record)| reply(&CreatedKey { key, record }, StatusCode::CREATED))
+                        .map_err(reject)
+                };
+                async move { result }
+            });
+
+        let registry = self.clone();
+        let revoke = warp::path!("admin" / "tenants" / String / "keys" / String)
+            .and(warp::delete())
+            .and(auth.authorized())
+            .and_then(move |tenant: String, key_id: String, _| {
+                let result = registry.revoke_key(&tenant, &key_id).map(|record| reply(&record, StatusCode::OK));
+                async move { result.map_err(reject) }
+            });
+
+        let registry = self.clone();
+        let quota = warp::path!("v1" / "tenants" / String / "quota")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and_then(move |tenant: String, _| {
+                let result = registry.quota(&tenant).map(|report| reply(&report, StatusCode::OK));
+                async move { result.map_err(reject) }
+            });
+
+        // Unrestricted tenants see the whole document
+        let registry = self.clone();
+        let openapi = warp::path!("v1" / "tenants" / String / "openapi.json")
+            .and(warp::get())
+            .and(auth.authorized())
+            .and_then(move |tenant: String, _| {
+                let result = registry.get(&tenant).map(|record| match registry.plans.resolve(record.plan.as_deref()) {
+                    Some(plan) => reply(&plan.openapi(), StatusCode::OK),
+                    None => reply(&document_where(|_, _| true), StatusCode::OK),
+                });
+                async move { result.map_err(reject) }
+            });
+
+        list.or(upsert).or(set_plan).or(keys).or(create_key).or(revoke).or(quota).or(openapi)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateKeyRequest {
+    pub subject: String,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetPlanRequest {
+    pub plan: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CreatedKey {
+    // Shown only in this response
+    key: String,
+    #[serde(flatten)]
+    record: TenantKey,
+}
+
+fn reply(body: &impl Serialize, status: StatusCode) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(body), status)
+}
+
+fn reject(error: TenantError) -> Rejection {
+    warp::reject::custom(ApiError::from(error))
+}
+
+impl From<TenantError> for ApiError {
+    fn from(error: TenantError) -> Self {
+        match error {
+            TenantError::UnknownTenant(_) | TenantError::Storage(EntityError::NotFound) => ApiError::NotFound(error.to_string()),
+            TenantError::UnknownPlan(_) => ApiError::Unprocessable(error.to_string()),
+            TenantError::NoQuota { .. } => ApiError::Forbidden(error.to_string()),
+            TenantError::QuotaExceeded { .. } => ApiError::TooManyRequests(error.to_string()),
+            TenantError::Storage(EntityError::Conflict { .. }) => ApiError::Conflict(error.to_string()),
+            TenantError::Storage(EntityError::ReadOnly | EntityError::Unavailable(_)) => ApiError::Unavailable(error.to_string()),
+            TenantError::Storage(_) => ApiError::Internal(error.to_string()),
+        }
+    }
+}
+
+impl KeyStore for TenantRegistry {
+    fn lookup(&self, key: &str) -> Option<ApiKeyConfig> {
+        let record = self.keys.get(&key_hash(key)).ok()?.value;
+        if record.revoked_at.is_some() {
+            return None;
+        }
+        Some(ApiKeyConfig { subject: record.subject, scopes: record.scopes, tenant: Some(record.tenant) })
+    }
+
+    fn admit(&self, principal: &Principal) -> Result<(), AuthError> {
+        let Some(tenant) = &principal.tenant else { return Ok(()) };
+        let limit = match self.get(tenant) {
+            Ok(t) => self.limits(&t).daily_requests,
+            Err(e) => {
+                warn!(%tenant, error = %e, "key belongs to a tenant that is not registered");
+                return Err(AuthError::Forbidden);
+            }
+        };
+        // Counted in the store so restarts do not reset the day; like the rate limiter, fail open when it cannot be written
+        let requests = match self.update_today(tenant, |usage| usage.requests += 1) {
+            Ok(usage) => usage.requests,
+            Err(e) => {
+                warn!(%tenant, error = %e, "tenant request not counted");
+                return Ok(());
+            }
+        };
+        match limit {
+            Some(limit) if requests > limit => Err(AuthError::QuotaExceeded(format!("tenant {} is over its {} requests per day", tenant, limit))),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Tenants may only call the routes their plan includes; keys without a tenant are not affected
+impl Entitlements for TenantRegistry {
+    fn entitled(&self, principal: &Principal, method: &Method, path: &str) -> Result<(), AuthError> {
+        let Some(tenant) = &principal.tenant else { return Ok(()) };
+        let record = self.get(tenant).map_err(|_| AuthError::Forbidden)?;
+        match self.plans.resolve(record.plan.as_deref()) {
+            Some(plan) if !plan.permits(method.as_str(), path) => {
+                Err(AuthError::NotEntitled(format!("{} {} is not included in the {} plan", method, path, plan.name)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::AuthConfig;
+    use crate::api::issuance::Issuances;
+    use crate::api::redemption::{LedgerAccount, RedemptionRequest, Redemptions};
+    use crate::api::router::Router;
+    use crate::api::validation::{AssetLimits, ValidationConfig};
+    use crate::events::bus::EventBus;
+    use crate::fees::{FeeSchedule, FeeScheduleConfig};
+    use ed25519_dalek::{Signer, SigningKey};
+    use serde_json::{json, Value};
+
+    async fn call<F>(api: &F, method: &str, path: &str, key: &str, body: Value) -> (StatusCode, Value)
+    where
+        F: Filter<Error = Rejection> + Clone + 'static,
+        F::Extract: Reply,
+    {
+        let response = warp::test::request().method(method).path(path).header("x-api-key", key).json(&body).reply(api).await;
+        (response.status(), serde_json::from_slice(response.body()).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn tenant_issues_within_its_quota_and_the_holder_redeems() {
+        let store = Store::new();
+        let tenants = TenantRegistry::new(store.clone());
+        let quota = IssuanceQuota { total: 1_000, daily: 1_000 };
+        tenants.upsert("acme", TenantLimits { daily_requests: None, issuance: BTreeMap::from([("PI".to_string(), quota)]) }).unwrap();
+        let (minter, _) = tenants.create_key("acme", "acme-minter", vec![Scope::Issue]).unwrap();
+        let (holder_key, _) = tenants.create_key("acme", "bob", vec![Scope::Redeem]).unwrap();
+
+        let auth = Auth::with_key_store(&AuthConfig::default(), Arc::new(tenants.clone())).unwrap().with_entitlements(Arc::new(tenants.clone()));
+        let rules = Arc::new(ValidationConfig {
+            assets: BTreeMap::from([("PI".to_string(), AssetLimits { min_amount: 1, max_amount: 1_000_000 })]),
+            ..ValidationConfig::default()
+        });
+        let accounts = EntityStore::<LedgerAccount>::new(store, "accounts");
+        let fees = FeeSchedule::new(FeeScheduleConfig::default()).unwrap();
+        let issuances = Issuances::new(accounts.clone(), fees, EventBus::new()).with_tenants(tenants.clone());
+        let redemptions = Redemptions::new(accounts, EventBus::new());
+        let api = warp::any().and(
+            Router::new()
+                .mount("issuance", issuances.routes(&auth, rules.clone()))
+                .mount("redemption", redemptions.routes(&auth, rules))
+                .mount("tenants", tenants.routes(&auth))
+                .build(),
+        );
+
+        let (status, issued) = call(&api, "POST", "/v1/issuance", &minter, json!({ "asset": "PI", "amount": "600", "recipient": "bob" })).await;
+        assert_eq!(status, StatusCode::OK, "{}", issued);
+        let (status, _) = call(&api, "POST", "/v1/issuance", &minter, json!({ "asset": "PI", "amount": "500", "recipient": "bob" })).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+        let signer = SigningKey::from_bytes(&[5; 32]);
+        let key = json!({ "public_key": hex::encode(signer.verifying_key().to_bytes()) });
+        let (status, body) = call(&api, "PUT", "/v1/accounts/bob/key", &holder_key, key).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        let mut request = RedemptionRequest { account: "bob".to_string(), asset: "PI".to_string(), amount: 250, nonce: 0, signature: String::new() };
+        request.signature = hex::encode(signer.sign(&request.signed_bytes()).to_bytes());
+        let body = json!({ "account": "bob", "asset": "PI", "amount": "250", "nonce": 0, "signature": request.signature });
+        let (status, redeemed) = call(&api, "POST", "/v1/redemption", &holder_key, body).await;
+        assert_eq!(status, StatusCode::OK, "{}", redeemed);
+        assert_eq!(redeemed["remaining_balance"], "350");
+
+        let (status, quota) = call(&api, "GET", "/v1/tenants/acme/quota", &minter, Value::Null).await;
+        assert_eq!(status, StatusCode::OK, "{}", quota);
+        assert_eq!(quota["issued_total"][0]["units"], "600", "{}", quota);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::api::auth::Auth;
+use crate::api::router::Route;
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::warn;
+use warp::filters::path::FullPath;
+use warp::http::Method;
+use warp::hyper::body::{to_bytes, Body};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+// `traffic_mirror` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TrafficMirrorConfig {
+    pub enabled: bool,
+
+    // Base URL of the canary deployment, e.g. "http://127.0.0.1:4030"
+    pub canary_url: String,
+
+    // Share of requests mirrored, 0.0 - 1.0
+    pub sample_rate: f64,
+
+    // Path prefixes of the GET routes mirrored; requests are sent without credentials, so keep to public reads
+    pub paths: Vec<String>,
+
+    // Object keys left out of the comparison at any depth, e.g. timestamps each node sets itself
+    pub ignore_fields: Vec<String>,
+}
+
+impl Default for TrafficMirrorConfig {
+    fn default() -> Self {
+        TrafficMirrorConfig {
+            enabled: false,
+            canary_url: String::new(),
+            sample_rate: 0.1,
+            paths: vec!["/rates".to_string()],
+            ignore_fields: vec!["updated_at".to_string()],
+        }
+    }
+}
+
+// `value` without the keys in `ignore`, at any depth
+fn strip(value: Value, ignore: &[String]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(map.into_iter().filter(|(k, _)| !ignore.contains(k)).map(|(k, v)| (k, strip(v, ignore))).collect()),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| strip(v, ignore)).collect()),
+        other => other,
+    }
+}
+
+// Read-only request handler the live responses are compared against
+#[async_trait]
+pub trait ReadHandler: Send + Sync {
+    async fn handle(&self, path: &str, query: &Value) -> Value;
+}
+
+// Canary reached over HTTP; failures come back as `{"error": ...}` and so count as divergences
+pub struct HttpCanary {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl HttpCanary {
+    pub fn new(base_url: &str) -> Self {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default();
+        HttpCanary { base_url: base_url.trim_end_matches('/').to_string(), client }
+    }
+}
+
+#[async_trait]
+impl ReadHandler for HttpCanary {
+    async fn handle(&self, path: &str, query: &Value) -> Value {
+        let params: Vec<(&String, &Value)> = query.as_object().map(|q| q.iter().collect()).unwrap_or_default();
+        let params: Vec<(&str, String)> =
+            params.into_iter().map(|(k, v)| (k.as_str(), v.as_str().map_or_else(|| v.to_string(), str::to_string))).collect();
+        let response = self.client.get(format!("{}{}", self.base_url, path)).query(&params).send().await;
+        match response {
+            Ok(response) => response.json().await.unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() })),
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        }
+    }
+}
+
+// Canary response that did not match the live one
+#[derive(Clone, Serialize)]
+pub struct Divergence {
+    pub path: String,
+    pub query: Value,
+    pub live: Value,
+    pub canary: Value,
+}
+
+#[derive(Serialize)]
+pub struct MirrorReport {
+    pub mirrored: u64,
+    pub diverged: u64,
+    pub recent: Vec<Divergence>,
+}
+
+// Recent divergences kept for inspection
+const RECENT_DIVERGENCES: usize = 100;
+
+// Shadows a share of successful JSON reads to the canary and records where its answers differ
+#[derive(Clone)]
+pub struct TrafficMirror {
+    canary: Arc<dyn ReadHandler>,
+
+    // Share of requests mirrored, 0.0 - 1.0
+    sample_rate: f64,
+    paths: Arc<Vec<String>>,
+    ignore_fields: Arc<Vec<String>>,
+
+    mirrored: Arc<AtomicU64>,
+    diverged: Arc<AtomicU64>,
+    recent: Arc<Mutex<VecDeque<Divergence>>>,
+}
+
+impl TrafficMirror {
+    pub fn new(canary: Arc<dyn ReadHandler>, sample_rate: f64, paths: Vec<String>) -> Self {
+        TrafficMirror {
+            canary,
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            paths: Arc::new(paths),
+            ignore_fields: Arc::default(),
+            mirrored: Arc::default(),
+            diverged: Arc::default(),
+            recent: Arc::default(),
+        }
+    }
+
+    // Leave `fields` out of every comparison
+    pub fn with_ignored_fields(mut self, fields: Vec<String>) -> Self {
+        self.ignore_fields = Arc::new(fields);
+        self
+    }
+
+    // `None` unless mirroring is enabled
+    pub fn from_config(config: &TrafficMirrorConfig) -> Result<Option<Self>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        if config.canary_url.is_empty() {
+            return Err("traffic_mirror.canary_url must be set when mirroring is enabled".to_string());
+        }
+        let canary = Arc::new(HttpCanary::new(&config.canary_url));
+        Ok(Some(TrafficMirror::new(canary, config.sample_rate, config.paths.clone()).with_ignored_fields(config.ignore_fields.clone())))
+    }
+
+    fn mirrors(&self, method: &Method, path: &str) -> bool {
+        method == Method::GET && self.paths.iter().any(|prefix| path.starts_with(prefix.as_str()))
+    }
+
+    // Compare `live` against the canary's answer in the background
+    pub fn mirror(&self, path: &str, query: &Value, live: Value) {
+        let mirror = self.clone();
+        let path = path.to_string();
+        let query = query.clone();
+        tokio::spawn(async move {
+            let canary = strip(mirror.canary.handle(&path, &query).await, &mirror.ignore_fields);
+            let live = strip(live, &mirror.ignore_fields);
+            mirror.mirrored.fetch_add(1, Ordering::Relaxed);
+            if canary != live {
+                mirror.diverged.fetch_add(1, Ordering::Relaxed);
+                warn!(path, "canary response diverged from live");
+                let mut recent = mirror.recent.lock().unwrap();
+                if recent.len() >= RECENT_DIVERGENCES {
+                    recent.pop_front();
+                }
+                recent.push_back(Divergence { path, query, live, canary });
+            }
+        });
+    }
+
+    // Clients always get the live response; only sampled 2xx JSON bodies are buffered and mirrored
+    async fn observe(&self, method: Method, path: FullPath, query: BTreeMap<String, String>, response: Response) -> Response {
+        if !self.mirrors(&method, path.as_str()) || !response.status().is_success() || !rand::thread_rng().gen_bool(self.sample_rate) {
+            return response;
+        }
+        let (parts, body) = response.into_parts();
+        let bytes = match to_bytes(body).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!(path = path.as_str(), error = %e, "live response could not be buffered for mirroring");
+                return Response::from_parts(parts, Body::empty());
+            }
+        };
+        if let Ok(live) = serde_json::from_slice(&bytes) {
+            self.mirror(path.as_str(), &serde_json::to_value(query).unwrap_or_default(), live);
+        }
+        Response::from_parts(parts, Body::from(bytes))
+    }
+
+    // Wraps the mounted routes; installed by `Router::mirror`
+    pub fn layer(&self, routes: Route) -> Route {
+        let mirror = self.clone();
+        let query = warp::query::<BTreeMap<String, String>>().or(warp::any().map(BTreeMap::new)).unify();
+        warp::method()
+            .and(warp::path::full())
+            .and(query)
+            .and(routes)
+            .and_then(move |method: Method, path: FullPath, query: BTreeMap<String, String>, response: Response| {
+                let mirror = mirror.clone();
+                async move { Ok::<_, Rejection>(mirror.observe(method, path, query, response).await) }
+            })
+            .boxed()
+    }
+
+    pub fn report(&self) -> MirrorReport {
+        MirrorReport {
+            mirrored: self.mirrored.load(Ordering::Relaxed),
+            diverged: self.diverged.load(Ordering::Relaxed),
+            recent: self.recent.lock().unwrap().iter().cloned().collect(),
+        }
+    }
+
+    // GET /admin/mirror
+    pub fn routes(&self, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let mirror = self.clone();
+        warp::path!("admin" / "mirror").and(warp::get()).and(auth.authorized()).map(move |_| warp::reply::json(&mirror.report()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::router::Router;
+
+    // Answers like the live route except for pairs it prices differently
+    struct Canary;
+
+    #[async_trait]
+    impl ReadHandler for Canary {
+        async fn handle(&self, path: &str, _query: &Value) -> Value {
+            match path {
+                "/rates/PI/USDC" => serde_json::json!({ "rate": "0.31", "updated_at": "canary" }),
+                _ => serde_json::json!({ "rate": "1", "updated_at": "canary" }),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn sampled_reads_are_compared_without_changing_the_response() {
+        let mirror = TrafficMirror::new(Arc::new(Canary), 1.0, vec!["/rates".to_string()]).with_ignored_fields(vec!["updated_at".to_string()]);
+        let rates = warp::path!("rates" / String / String).map(|_, _| warp::reply::json(&serde_json::json!({ "rate": "1", "updated_at": "live" })));
+        let other = warp::path!("status").map(|| warp::reply::json(&serde_json::json!({ "rate": "2" })));
+        let routes = Router::new().mount("rates", rates).mount("status", other).mirror(Some(mirror.clone())).build();
+
+        for path in ["/rates/PI/USDT", "/rates/PI/USDC?at=now", "/status"] {
+            let response = warp::test::request().path(path).reply(&routes).await;
+            assert_eq!(response.status(), 200);
+            assert!(!String::from_utf8_lossy(response.body()).contains("canary"));
+        }
+        let missing = warp::test::request().path("/nowhere").reply(&routes).await;
+        assert_eq!(missing.status(), 404);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let report = mirror.report();
+        assert_eq!((report.mirrored, report.diverged), (2, 1));
+        assert_eq!(report.recent[0].path, "/rates/PI/USDC");
+        assert_eq!(report.recent[0].query, serde_json::json!({ "at": "now" }));
+        assert_eq!(report.recent[0].canary, serde_json::json!({ "rate": "0.31" }));
+    }
+}
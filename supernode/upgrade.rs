@@ -0,0 +1,221 @@
+use crate::ai::persistence::MODEL_FORMAT_VERSION;
+use crate::audit::bundle::BUNDLE_FORMAT_VERSION;
+use crate::p2p::codec::PROTOCOL_VERSION;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+// Top-level config sections this version reads; anything else is silently ignored by serde
+const KNOWN_SECTIONS: &[&str] = &[
+    "node_name",
+    "node_type",
+    "network_id",
+    "logging",
+    "telemetry",
+    "self_heal",
+    "model",
+    "p2p",
+    "clock",
+    "webhooks",
+    "auth",
+    "rate_limit",
+    "server",
+    "tls",
+    "policy_guard",
+    "experiments",
+    "bootstrap",
+    "graphql",
+    "metrics_history",
+    "validation",
+    "upgrade",
+    "caches",
+    "audit_log",
+    "alert_correlation",
+    "converter",
+    "forensic",
+    "key_compromise",
+    "sessions",
+    "event_log",
+    "oracle",
+    "plans",
+    "netting",
+    "calendars",
+    "assets",
+    "fees",
+    "storage_profiling",
+    "quotes",
+    "console",
+    "jobs",
+    "alerting",
+    "runbooks",
+];
+
+// Settings earlier versions read, and what replaces them
+const DEPRECATED: &[(&str, &str)] = &[
+    ("rpc_port", "no longer read; the API listens on server.port"),
+    ("ws_port", "no longer read; the event stream is served on server.port at /ws/events"),
+];
+
+// (component, stored version, what the operator must do before this version can use it)
+const MIGRATIONS: &[(&str, u32, &str)] = &[];
+
+// On-disk formats this build writes
+fn current_formats() -> BTreeMap<String, u32> {
+    BTreeMap::from([
+        ("model_snapshot".to_string(), MODEL_FORMAT_VERSION),
+        ("audit_bundle".to_string(), BUNDLE_FORMAT_VERSION),
+        ("wire_protocol".to_string(), PROTOCOL_VERSION as u32),
+    ])
+}
+
+// `upgrade` section of the node config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct UpgradeConfig {
+    // Versions the node last ran with, rewritten after every successful start
+    pub manifest_path: PathBuf,
+
+    // Refuse to start while required migrations are outstanding
+    pub block_on_required: bool,
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        UpgradeConfig { manifest_path: PathBuf::from("data/manifest.json"), block_on_required: true }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub node_version: String,
+    pub formats: BTreeMap<String, u32>,
+}
+
+impl Manifest {
+    pub fn current() -> Self {
+        Manifest { node_version: env!("CARGO_PKG_VERSION").to_string(), formats: current_formats() }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub subject: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpgradeReport {
+    pub from_version: Option<String>,
+    pub to_version: String,
+
+    // Must be done before this version runs
+    pub required: Vec<Finding>,
+
+    // Safe to run with, but should be cleaned up
+    pub warnings: Vec<Finding>,
+}
+
+impl UpgradeReport {
+    pub fn blocking(&self) -> bool {
+        !self.required.is_empty()
+    }
+}
+
+fn finding(subject: &str, message: impl Into<String>) -> Finding {
+    Finding { subject: subject.to_string(), message: message.into() }
+}
+
+fn check_formats(stored: &Manifest, report: &mut UpgradeReport) {
+    for (component, current) in current_formats() {
+        let Some(&previous) = stored.formats.get(&component) else { continue };
+        if previous == current {
+            continue;
+        }
+        if previous > current {
+            report.required.push(finding(
+                &component,
+                format!("format {} was written by a newer node ({}); downgrading to {} is not supported", previous, stored.node_version, current),
+            ));
+            continue;
+        }
+        match MIGRATIONS.iter().find(|(c, from, _)| *c == component && *from == previous) {
+            Some((_, _, action)) => report.required.push(finding(&component, format!("format {} -> {}: {}", previous, current, action))),
+            None if component == "model_snapshot" => report.warnings.push(finding(
+                &component,
+                format!("checkpoint format {} is unreadable by {}; the engine will start from defaults and retrain", previous, current),
+            )),
+            None if component == "audit_bundle" => report.warnings.push(finding(
+                &component,
+                format!("bundles are now written in format {}; bundles in format {} still verify", current, previous),
+            )),
+            None if component == "wire_protocol" => report.warnings.push(finding(
+                &component,
+                format!("protocol {} -> {}; peers on {} still connect but lose newer capabilities", previous, current, previous),
+            )),
+            None => report.required.push(finding(&component, format!("no migration from format {} to {}; export and re-import the data", previous, current))),
+        }
+    }
+}
+
+fn check_config(config: &serde_yaml::Value, report: &mut UpgradeReport) {
+    let Some(map) = config.as_mapping() else { return };
+    for key in map.keys().filter_map(|k| k.as_str()) {
+        if let Some((_, advice)) = DEPRECATED.iter().find(|(name, _)| *name == key) {
+            report.warnings.push(finding(key, *advice));
+        } else if !KNOWN_SECTIONS.contains(&key) {
+            report.warnings.push(finding(key, "unknown setting, ignored; check for a typo or a removed option"));
+        }
+    }
+}
+
+// Compare what the node last ran with against this build; never writes anything
+pub fn advise(config_path: &Path, manifest_path: &Path) -> Result<UpgradeReport, String> {
+    let stored: Option<Manifest> = match std::fs::read(manifest_path) {
+        Ok(bytes) => Some(serde_json::from_slice(&bytes).map_err(|e| format!("{} is corrupt: {}", manifest_path.display(), e))?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut report = UpgradeReport {
+        from_version: stored.as_ref().map(|m| m.node_version.clone()),
+        to_version: env!("CARGO_PKG_VERSION").to_string(),
+        required: Vec::new(),
+        warnings: Vec::new(),
+    };
+    if let Some(stored) = &stored {
+        check_formats(stored, &mut report);
+    }
+    let text = std::fs::read_to_string(config_path).map_err(|e| format!("failed to read {}: {}", config_path.display(), e))?;
+    let config: serde_yaml::Value = serde_yaml::from_str(&text).map_err(|e| format!("invalid config {}: {}", config_path.display(), e))?;
+    check_config(&config, &mut report);
+    Ok(report)
+}
+
+// Run on startup; Err means the node must not start
+pub fn on_startup(config_path: &Path, config: &UpgradeConfig) -> Result<(), String> {
+    let report = advise(config_path, &config.manifest_path)?;
+    for w in &report.warnings {
+        warn!(subject = %w.subject, "{}", w.message);
+    }
+    if report.blocking() {
+        let steps: Vec<String> = report.required.iter().map(|f| format!("{}: {}", f.subject, f.message)).collect();
+        if config.block_on_required {
+            return Err(format!("upgrade needs attention before starting: {}", steps.join("; ")));
+        }
+        warn!(steps = %steps.join("; "), "starting despite required migrations (upgrade.block_on_required is off)");
+    }
+    if report.from_version.as_deref() != Some(report.to_version.as_str()) {
+        info!(from = ?report.from_version, to = %report.to_version, "node version changed");
+    }
+    record(&config.manifest_path)
+}
+
+pub fn record(manifest_path: &Path) -> Result<(), String> {
+    if let Some(dir) = manifest_path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let bytes = serde_json::to_vec_pretty(&Manifest::current()).map_err(|e| e.to_string())?;
+    let tmp = manifest_path.with_extension("tmp");
+    std::fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, manifest_path).map_err(|e| e.to_string())
+}
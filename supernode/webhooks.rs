@@ -0,0 +1,408 @@
+use crate::api::auth::{Auth, Principal};
+use crate::events::bus::{Envelope, Event, EventSink, Step};
+use crate::events::log::{EventLog, LoggedEvent};
+use crate::job_queue::{JobKind, JobQueue};
+use crate::runtime::scheduler::Scheduler;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// One operator-defined webhook from the `webhooks` config section
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConfig {
+    // Name used by the replay API; hooks without one are addressed by their position in the list, from 0
+    #[serde(default)]
+    pub id: Option<String>,
+    pub url: String,
+
+    // Shared secret for the X-Pi-Signature HMAC
+    pub secret: String,
+
+    // Event topics to deliver, all when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+// Retry schedule: 1s, 2s, 4s, 8s, 16s before handing off to the job queue
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+// Queued deliveries are retried this often, and marked failed after this many tries
+const QUEUE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_QUEUED_ATTEMPTS: u32 = 10;
+
+// Payload of a WebhookRetry job
+#[derive(Serialize, Deserialize)]
+pub struct PendingDelivery {
+    pub url: String,
+    pub body: String,
+}
+
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Time range and topics to re-deliver; all topics the hook subscribes to when `events` is empty
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplayRequest {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReplayAccepted {
+    pub webhook: String,
+    pub events: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_event: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_event: Option<String>,
+}
+
+// Posts signed JSON alerts for selected events to operator URLs
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    hooks: Arc<Vec<WebhookConfig>>,
+    client: reqwest::Client,
+
+    // Deliveries that exhaust inline retries are queued for later
+    queue: Option<JobQueue>,
+
+    // Deliveries of transaction events are recorded here for the transaction timeline
+    log: Option<EventLog>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(hooks: Vec<WebhookConfig>, queue: Option<JobQueue>) -> Self {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default();
+        WebhookDispatcher { hooks: Arc::new(hooks), client, queue, log: None }
+    }
+
+    // Written to the log directly rather than published, so delivery steps never trigger deliveries themselves
+    pub fn with_log(mut self, log: EventLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    fn record_delivery(&self, envelope: &Envelope, hook: &WebhookConfig, outcome: &str) {
+        let (Some(log), Some(tx_id)) = (&self.log, envelope.event.tx_id()) else { return };
+        let step = Event::TransactionStep {
+            tx_id: tx_id.to_string(),
+            step: Step::WebhookDelivery,
+            outcome: outcome.to_string(),
+            detail: Some(format!("{} to {}", envelope.event.topic(), hook.id.as_deref().unwrap_or(&hook.url))),
+            related: None,
+        };
+        log.record(&Envelope { at: Utc::now(), event: step });
+    }
+
+    fn hook(&self, id: &str) -> Option<&WebhookConfig> {
+        self.hooks.iter().enumerate().find(|(i, h)| h.id.as_deref().map_or_else(|| i.to_string() == id, |own| own == id)).map(|(_, h)| h)
+    }
+
+    fn subscribed(hook: &WebhookConfig, topic: &str) -> bool {
+        hook.events.is_empty() || hook.events.iter().any(|e| e == topic)
+    }
+
+    async fn post(&self, hook: &WebhookConfig, body: &str, replay: bool) -> Result<(), String> {
+        let timestamp = chrono::Utc::now().timestamp();
+        let mut request = self
+            .client
+            .post(&hook.url)
+            .header("content-type", "application/json")
+            .header("x-pi-timestamp", timestamp.to_string())
+            .header("x-pi-signature", sign(&hook.secret, timestamp, body.as_bytes()));
+        if replay {
+            request = request.header("x-replay", "true");
+        }
+        let response = request.body(body.to_string()).send().await.map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("status {}", response.status()))
+        }
+    }
+
+    // Inline retries with backoff; false once they are exhausted
+    async fn post_with_retry(&self, hook: &WebhookConfig, body: &str, replay: bool) -> bool {
+        let mut backoff = BASE_BACKOFF;
+        for attempt in 1..=MAX_ATTEMPTS {
+            match self.post(hook, body, replay).await {
+                Ok(()) => {
+                    debug!(url = %hook.url, attempt, replay, "webhook delivered");
+                    return true;
+                }
+                Err(e) => warn!(url = %hook.url, attempt, replay, error = %e, "webhook delivery failed"),
+            }
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+        false
+    }
+
+    async fn deliver_with_retry(&self, hook: &WebhookConfig, envelope: &Envelope, body: &str) {
+        if self.post_with_retry(hook, body, false).await {
+            self.record_delivery(envelope, hook, "delivered");
+            return;
+        }
+        self.record_delivery(envelope, hook, if self.queue.is_some() { "queued_for_retry" } else { "failed" });
+        if let Some(queue) = &self.queue {
+            let pending = PendingDelivery { url: hook.url.clone(), body: body.to_string() };
+            if let Ok(payload) = serde_json::to_string(&pending) {
+                let _ = queue.enqueue(JobKind::WebhookRetry, payload, 1, 300);
+            }
+        }
+    }
+
+    // One more try for every queued delivery that is due; a hook removed from the config since fails its deliveries
+    pub async fn retry_queued(&self) {
+        let Some(queue) = &self.queue else { return };
+        let mut leased = Vec::new();
+        while let Ok(Some(job)) = queue.lease_kind(JobKind::WebhookRetry) {
+            leased.push(job);
+        }
+        for job in leased {
+            let pending: Option<PendingDelivery> = serde_json::from_str(&job.payload).ok();
+            let hook = pending.as_ref().and_then(|p| self.hooks.iter().find(|h| h.url == p.url));
+            let delivered = match (hook, &pending) {
+                (Some(hook), Some(pending)) => match self.post(hook, &pending.body, false).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        warn!(url = %hook.url, attempt = job.attempts, error = %e, "queued webhook delivery failed");
+                        false
+                    }
+                },
+                _ => {
+                    warn!(job = %job.id, "queued webhook delivery has no configured hook");
+                    let _ = queue.fail(job.id);
+                    continue;
+                }
+            };
+            let result = if delivered {
+                queue.ack(job.id)
+            } else if job.attempts >= MAX_QUEUED_ATTEMPTS {
+                queue.fail(job.id)
+            } else {
+                // Left in flight, it is leased again once the visibility timeout passes
+                Ok(())
+            };
+            if let Err(e) = result {
+                warn!(job = %job.id, error = %e, "could not update the queued webhook delivery");
+            }
+        }
+    }
+
+    // Re-deliver logged events in publish order with `x-replay: true`; stops at the first event that cannot be
+    // delivered so the integrator never sees a gap, and the replay can be resumed from that event's time
+    async fn replay(&self, hook: &WebhookConfig, events: Vec<LoggedEvent>) {
+        let total = events.len();
+        for (done, event) in events.into_iter().enumerate() {
+            let body = match serde_json::to_string(&event) {
+                Ok(body) => body,
+                Err(_) => continue,
+            };
+            if !self.post_with_retry(hook, &body, true).await {
+                warn!(url = %hook.url, delivered = done, total, stopped_at = %event.id, at = %event.envelope.at, "webhook replay stopped");
+                return;
+            }
+        }
+        info!(url = %hook.url, delivered = total, "webhook replay finished");
+    }
+
+    // POST /v1/webhooks/{id}/replay; delivery runs in the background after the request is accepted
+    pub fn routes(&self, log: EventLog, auth: &Auth) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let dispatcher = self.clone();
+        warp::path!("v1" / "webhooks" / String / "replay")
+            .and(warp::post())
+            .and(auth.authorized())
+            .and(warp::body::json())
+            .map(move |id: String, principal: Principal, request: ReplayRequest| {
+                let reject = |status, e: String| warp::reply::with_status(warp::reply::json(&e), status);
+                let Some(hook) = dispatcher.hook(&id).cloned() else {
+                    return reject(StatusCode::NOT_FOUND, format!("no webhook {}", id));
+                };
+                if request.from >= request.to {
+                    return reject(StatusCode::UNPROCESSABLE_ENTITY, "from must be before to".to_string());
+                }
+                let events: Vec<LoggedEvent> = log
+                    .range(request.from, request.to, &request.events)
+                    .into_iter()
+                    .filter(|e| Self::subscribed(&hook, e.envelope.event.topic()))
+                    .filter(|e| !matches!(e.envelope.event, Event::TransactionStep { step: Step::WebhookDelivery, .. }))
+                    .collect();
+                let max = log.config().max_replay_events;
+                if events.len() > max {
+                    let e = format!("{} events in range, at most {} per replay; split the range", events.len(), max);
+                    return reject(StatusCode::UNPROCESSABLE_ENTITY, e);
+                }
+                let accepted = ReplayAccepted {
+                    webhook: id,
+                    events: events.len(),
+                    first_event: events.first().map(|e| e.id.to_string()),
+                    last_event: events.last().map(|e| e.id.to_string()),
+                };
+                info!(webhook = %accepted.webhook, by = %principal.subject, events = accepted.events, from = %request.from, to = %request.to, "webhook replay started");
+                let dispatcher = dispatcher.clone();
+                tokio::spawn(async move { dispatcher.replay(&hook, events).await });
+                warp::reply::with_status(warp::reply::json(&accepted), StatusCode::ACCEPTED)
+            })
+    }
+}
+
+// Retry deliveries that exhausted their inline attempts
+pub fn register_retries(scheduler: &Scheduler, dispatcher: WebhookDispatcher) {
+    scheduler.register(
+        "webhooks:retry",
+        QUEUE_RETRY_INTERVAL,
+        Duration::ZERO,
+        Arc::new(move || {
+            let dispatcher = dispatcher.clone();
+            Box::pin(async move { dispatcher.retry_queued().await })
+        }),
+    );
+}
+
+#[async_trait]
+impl EventSink for WebhookDispatcher {
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+
+    async fn deliver(&self, envelope: &Envelope) {
+        let topic = envelope.event.topic();
+        let body = match serde_json::to_string(envelope) {
+            Ok(body) => body,
+            Err(_) => return,
+        };
+        for hook in self.hooks.iter().filter(|h| Self::subscribed(h, topic)) {
+            self.deliver_with_retry(hook, envelope, &body).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{ApiKeyConfig, AuthConfig, Scope};
+    use crate::api::router::Router;
+    use crate::events::bus::EventBus;
+    use crate::events::log::EventLogConfig;
+    use crate::storage::mvcc::Store;
+    use std::collections::HashMap;
+    use crate::server::tests::spawn;
+    use std::sync::Mutex;
+    use tokio_util::sync::CancellationToken;
+    use warp::http::HeaderMap;
+    use warp::hyper::body::Bytes;
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+    // An integrator endpoint that records what it is sent
+    async fn receiver() -> (String, Received, CancellationToken) {
+        let received = Received::default();
+        let recorded = received.clone();
+        let routes = warp::post().and(warp::header::headers_cloned()).and(warp::body::bytes()).map(move |headers, body| {
+            recorded.lock().unwrap().push((headers, body));
+            StatusCode::OK
+        });
+        let (addr, token) = spawn(routes).await;
+        (format!("http://{}/hook", addr), received, token)
+    }
+
+    fn hook(url: &str) -> WebhookConfig {
+        WebhookConfig { id: Some("ops".to_string()), url: url.to_string(), secret: "s3cret".to_string(), events: vec!["issuance_completed".to_string()] }
+    }
+
+    async fn wait_for(received: &Received, count: usize) {
+        for _ in 0..100 {
+            if received.lock().unwrap().len() >= count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn bus_events_reach_subscribed_hooks_signed() {
+        let (url, received, server) = receiver().await;
+        let bus = EventBus::new();
+        let token = CancellationToken::new();
+        let forwarding = bus.clone();
+        let sink = Box::new(WebhookDispatcher::new(vec![hook(&url)], None));
+        let child = token.clone();
+        tokio::spawn(async move { forwarding.forward(sink, child).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        bus.publish(Event::SelfHealTriggered { source: "test".to_string(), rule: "r".to_string() });
+        bus.publish(Event::IssuanceCompleted { tx_id: "tx-1".to_string(), asset: "PI".to_string(), amount: "5".to_string(), fee: None, tenant: None });
+        wait_for(&received, 1).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (headers, body) = &received[0];
+        let timestamp: i64 = headers["x-pi-timestamp"].to_str().unwrap().parse().unwrap();
+        assert_eq!(headers["x-pi-signature"].to_str().unwrap(), sign("s3cret", timestamp, body));
+        assert!(String::from_utf8_lossy(body).contains("tx-1"));
+        token.cancel();
+        server.cancel();
+    }
+
+    #[tokio::test]
+    async fn queued_deliveries_are_retried_and_acked() {
+        let (url, received, server) = receiver().await;
+        let path = std::env::temp_dir().join(format!("webhook-jobs-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let queue = JobQueue::open(path.clone(), 60).unwrap();
+        let pending = PendingDelivery { url: url.clone(), body: "{}".to_string() };
+        queue.enqueue(JobKind::WebhookRetry, serde_json::to_string(&pending).unwrap(), 1, 0).unwrap();
+        queue.enqueue(JobKind::Export, "ledger".to_string(), 9, 0).unwrap();
+
+        let dispatcher = WebhookDispatcher::new(vec![hook(&url)], Some(queue.clone()));
+        dispatcher.retry_queued().await;
+        assert_eq!(received.lock().unwrap().len(), 1);
+        let left: Vec<JobKind> = queue.jobs().into_iter().map(|j| j.kind).collect();
+        assert_eq!(left, vec![JobKind::Export]);
+        let _ = std::fs::remove_file(path);
+        server.cancel();
+    }
+
+    #[tokio::test]
+    async fn replay_route_redelivers_logged_events() {
+        let (url, received, server) = receiver().await;
+        let log = EventLog::new(Store::new(), EventLogConfig::default());
+        let issued = Event::IssuanceCompleted { tx_id: "tx-9".to_string(), asset: "PI".to_string(), amount: "5".to_string(), fee: None, tenant: None };
+        log.record(&Envelope { at: Utc::now(), event: issued });
+
+        let key = |subject: &str, scopes| ApiKeyConfig { subject: subject.to_string(), scopes, tenant: None };
+        let api_keys = HashMap::from([("k-admin".to_string(), key("ops", vec![Scope::Admin])), ("k-iss".to_string(), key("app", vec![Scope::Issue]))]);
+        let auth = Auth::new(&AuthConfig { api_keys, ..AuthConfig::default() }).unwrap();
+        let dispatcher = WebhookDispatcher::new(vec![hook(&url)], None);
+        let api = warp::any().and(Router::new().mount("webhooks", dispatcher.routes(log, &auth)).build());
+
+        let range = serde_json::json!({ "from": Utc::now() - chrono::Duration::hours(1), "to": Utc::now() + chrono::Duration::hours(1) });
+        let request = |api_key| warp::test::request().method("POST").path("/v1/webhooks/ops/replay").header("x-api-key", api_key).json(&range);
+        assert_eq!(request("k-iss").reply(&api).await.status(), StatusCode::FORBIDDEN);
+        let accepted = request("k-admin").reply(&api).await;
+        assert_eq!(accepted.status(), StatusCode::ACCEPTED);
+
+        wait_for(&received, 1).await;
+        let received = received.lock().unwrap();
+        assert_eq!(received[0].0["x-replay"], "true");
+        assert!(String::from_utf8_lossy(&received[0].1).contains("tx-9"));
+        server.cancel();
+    }
+}
@@ -0,0 +1,154 @@
+// Boots the whole node in-process from `node::serve`, with the price oracle pointed at a local mock, and drives
+// issuance -> conversion -> redemption over HTTP as clients would
+use ed25519_dalek::{Signer, SigningKey};
+use pi_supernode::api::redemption::RedemptionRequest;
+use pi_supernode::config::NodeConfig;
+use pi_supernode::node;
+use reqwest::{Client, Method, StatusCode};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use warp::Filter;
+
+const CONFIG: &str = r#"
+node_name: full-node-test
+network_id: testnet
+server: { address: 127.0.0.1, port: {port}, drain_timeout_secs: 1 }
+rate_limit: { enabled: false }
+clock: { ntp_servers: [] }
+metrics_history: { enabled: false }
+auth:
+  api_keys:
+    k-admin: { subject: ops, scopes: [admin] }
+    k-minter: { subject: minter, scopes: [issue] }
+    k-alice: { subject: alice, scopes: [convert, redeem] }
+validation:
+  assets:
+    PI: { min_amount: 1, max_amount: 1000000000000 }
+    USDC: { min_amount: 1, max_amount: 1000000000000 }
+assets:
+  PI: { decimals: 7 }
+  USDC: { decimals: 6, stablecoin: true }
+oracle:
+  enabled: true
+  poll_interval_secs: 1
+  pairs: [{ from: PI, to: USDC, from_decimals: 7, to_decimals: 6 }]
+  sources:
+    - { name: a, url: "{oracle}/a", pointer: /price }
+    - { name: b, url: "{oracle}/b", pointer: /price }
+bootstrap: { state_path: "{data}/state.json.zst", journal_path: "{data}/journal.jsonl" }
+model: { checkpoint_path: "{data}/model.json" }
+p2p: { address_book_path: "{data}/peers.json" }
+audit_log: { enabled: true, dir: "{data}/audit" }
+event_log: { schemas_path: "{data}/event_schemas.json" }
+jobs: { path: "{data}/jobs.json" }
+upgrade: { manifest_path: "{data}/manifest.json" }
+key_compromise: { key_dir: "{data}/keys" }
+"#;
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+// Both sources quote 1 PI at 0.5 USDC
+async fn oracle_mock() -> String {
+    let price = warp::path!(String).map(|_| warp::reply::json(&json!({ "price": 0.5 })));
+    let (addr, server) = warp::serve(price).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    format!("http://{}", addr)
+}
+
+struct Node {
+    base: String,
+    client: Client,
+}
+
+impl Node {
+    async fn call(&self, method: Method, path: &str, key: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let mut request = self.client.request(method, format!("{}{}", self.base, path)).header("x-api-key", key);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.unwrap();
+        let status = response.status();
+        (status, response.json().await.unwrap_or(Value::Null))
+    }
+
+    // Listening and priced by the oracle
+    async fn ready(&self) {
+        for _ in 0..100 {
+            let response = self.client.get(format!("{}/rates/PI/USDC", self.base)).send().await;
+            if response.is_ok_and(|r| r.status() == StatusCode::OK) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("node did not come up with an oracle rate");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn issues_converts_and_redeems_over_http() {
+    let data: PathBuf = std::env::temp_dir().join(format!("pi-supernode-full-node-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data);
+    let port = free_port();
+    let yaml = CONFIG.replace("{port}", &port.to_string()).replace("{oracle}", &oracle_mock().await).replace("{data}", &data.display().to_string());
+    let config: NodeConfig = serde_yaml::from_str(&yaml).unwrap();
+
+    let shutdown = CancellationToken::new();
+    let served = tokio::spawn(node::serve(config, shutdown.clone()));
+    let node = Node { base: format!("http://127.0.0.1:{}", port), client: Client::new() };
+    node.ready().await;
+
+    // 1 PI to alice
+    let issue = json!({ "asset": "PI", "amount": "10000000", "recipient": "alice" });
+    let (status, issued) = node.call(Method::POST, "/v1/issuance", "k-minter", Some(issue)).await;
+    assert_eq!(status, StatusCode::OK, "{}", issued);
+    assert_eq!(issued["credited"], "10000000");
+
+    let holder = SigningKey::from_bytes(&[7; 32]);
+    let key = json!({ "public_key": hex::encode(holder.verifying_key().to_bytes()) });
+    let (status, body) = node.call(Method::PUT, "/v1/accounts/alice/key", "k-alice", Some(key)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // 0.4 PI at the oracle's 0.5 is 0.2 USDC
+    let convert = json!({ "asset": "PI", "to_asset": "USDC", "amount": "4000000" });
+    let (status, converted) = node.call(Method::POST, "/convert", "k-alice", Some(convert)).await;
+    assert_eq!(status, StatusCode::OK, "{}", converted);
+    assert_eq!(converted["converted_amount"], 200_000);
+    assert_eq!(converted["converted_decimal"], "0.200000");
+
+    let mut burn = RedemptionRequest { account: "alice".to_string(), asset: "PI".to_string(), amount: 1_000_000, nonce: 0, signature: String::new() };
+    burn.signature = hex::encode(holder.sign(&burn.signed_bytes()).to_bytes());
+    let body = json!({ "account": "alice", "asset": "PI", "amount": "1000000", "nonce": 0, "signature": burn.signature });
+    let (status, redeemed) = node.call(Method::POST, "/v1/redemption", "k-alice", Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK, "{}", redeemed);
+    assert_eq!(redeemed["remaining_balance"], "5000000");
+
+    // The same authorization cannot be replayed
+    let (status, _) = node.call(Method::POST, "/v1/redemption", "k-alice", Some(body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Supply is what was issued less what was burned; the views follow the event bus, so give delivery a moment
+    let mut supply = Value::Null;
+    for _ in 0..20 {
+        let (_, views) = node.call(Method::GET, "/admin/views", "k-admin", None).await;
+        supply = views["asset_supply"]["PI"].clone();
+        if supply == "9000000" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(supply, "9000000");
+
+    let (status, audit) = node.call(Method::GET, "/admin/audit/log/verify", "k-admin", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", audit);
+    assert_eq!(audit["valid"], true);
+
+    drop(node);
+    shutdown.cancel();
+    served.await.unwrap().unwrap();
+    assert!(data.join("state.json.zst").exists());
+    let _ = std::fs::remove_dir_all(&data);
+}